@@ -1,4 +1,6 @@
 use async_trait::async_trait;
+use dav_server::DavMethod;
+use marble_core::clock::{system_clock, ClockRef};
 use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
@@ -10,6 +12,88 @@ use crate::error::{AuthError, LockError};
 pub trait AuthService: Send + Sync + 'static {
     /// Authenticate a user and return their tenant ID
     async fn authenticate(&self, username: &str, password: &str) -> Result<Uuid, AuthError>;
+
+    /// Authenticate a user and return the principal, including their role
+    ///
+    /// Services without roles authenticate every user as the tenant owner.
+    async fn authenticate_principal(&self, username: &str, password: &str) -> Result<Principal, AuthError> {
+        let tenant_id = self.authenticate(username, password).await?;
+        Ok(Principal {
+            tenant_id,
+            role: Role::Owner,
+        })
+    }
+}
+
+/// Role of an authenticated user within a tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Role {
+    /// Full access to the tenant's files
+    #[default]
+    Owner,
+
+    /// Read-only collaborator
+    Viewer,
+}
+
+impl Role {
+    /// Whether the role may use a method
+    pub fn allows(&self, method: DavMethod) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Viewer => matches!(
+                method,
+                DavMethod::Get | DavMethod::Head | DavMethod::Options | DavMethod::PropFind
+            ),
+        }
+    }
+}
+
+/// An authenticated user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// Tenant the user belongs to
+    pub tenant_id: Uuid,
+
+    /// Role of the user within the tenant
+    pub role: Role,
+}
+
+/// Decides whether a principal may use a method
+///
+/// The default policy is [`Role::allows`]; a custom policy can implement finer
+/// grained access control.
+pub type MethodPolicy = Arc<dyn Fn(&Principal, DavMethod) -> bool + Send + Sync>;
+
+/// The policy letting each principal use the methods of its role
+pub fn default_method_policy() -> MethodPolicy {
+    Arc::new(|principal: &Principal, method| principal.role.allows(method))
+}
+
+/// Scope of a write lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockScope {
+    /// The only lock on the resource
+    #[default]
+    Exclusive,
+
+    /// May coexist with other shared locks
+    Shared,
+}
+
+impl LockScope {
+    /// Name of the scope's `DAV:lockscope` element
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockScope::Exclusive => "exclusive",
+            LockScope::Shared => "shared",
+        }
+    }
+
+    /// Whether a lock of this scope can be granted next to one of `other`
+    pub fn compatible_with(&self, other: LockScope) -> bool {
+        *self == LockScope::Shared && other == LockScope::Shared
+    }
 }
 
 /// Lock information
@@ -23,24 +107,58 @@ pub struct LockInfo {
     
     /// Path that is locked
     pub path: String,
+
+    /// Path the lock was requested on, the collection for recursive locks
+    pub root: String,
+
+    /// Whether the lock covers the descendants of its root (`Depth: infinity`)
+    pub recursive: bool,
     
     /// When the lock expires
     pub expires_at: chrono::DateTime<chrono::Utc>,
+    
+    /// Owner given by the client in the LOCK request, if any
+    pub owner: Option<String>,
+
+    /// Whether the lock is exclusive or shared
+    pub scope: LockScope,
 }
 
 /// Lock manager trait
 #[async_trait]
 pub trait LockManager: Send + Sync + 'static {
-    /// Acquire a lock
+    /// Acquire a lock, or refresh the lock with this token
+    ///
+    /// Fails with [`LockError::ResourceLocked`] if another lock on the path
+    /// conflicts: an exclusive lock conflicts with any lock, shared locks only
+    /// with exclusive ones.
     async fn lock(
         &self,
         tenant_id: &Uuid,
         path: &str,
         timeout: Duration,
         token: &str,
+        owner: Option<&str>,
+        scope: LockScope,
+    ) -> Result<(), LockError>;
+
+    /// Acquire one lock on several paths, all or none
+    ///
+    /// Used for `Depth: infinity` locks: `paths` are a collection, which is the
+    /// lock root, followed by its descendants. Fails with
+    /// [`LockError::ResourceLocked`] without locking anything if any path has a
+    /// conflicting lock.
+    async fn lock_paths(
+        &self,
+        tenant_id: &Uuid,
+        paths: &[String],
+        timeout: Duration,
+        token: &str,
+        owner: Option<&str>,
+        scope: LockScope,
     ) -> Result<(), LockError>;
 
-    /// Release a lock
+    /// Release a lock, on every path it covers
     async fn unlock(
         &self,
         tenant_id: &Uuid,
@@ -48,12 +166,41 @@ pub trait LockManager: Send + Sync + 'static {
         token: &str,
     ) -> Result<(), LockError>;
 
-    /// Check if a resource is locked
+    /// Remove every lock past its expiry time, returning how many were removed
+    ///
+    /// Run periodically so locks of clients that went away do not pile up.
+    async fn reap_expired(&self) -> Result<usize, LockError>;
+
+    /// All active locks on a resource
+    async fn active_locks(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+    ) -> Result<Vec<LockInfo>, LockError>;
+
+    /// All active locks on the descendants of a collection
+    ///
+    /// Locks on the collection itself are not included. Used before removing a
+    /// whole subtree, which must not take locked members along.
+    async fn locks_below(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+    ) -> Result<Vec<LockInfo>, LockError>;
+
+    /// Check if a resource is locked, returning one of its locks
     async fn is_locked(
         &self,
         tenant_id: &Uuid,
         path: &str,
-    ) -> Result<Option<LockInfo>, LockError>;
+    ) -> Result<Option<LockInfo>, LockError> {
+        Ok(self.active_locks(tenant_id, path).await?.into_iter().next())
+    }
+
+    /// Clock the lock expiry times are measured against
+    fn clock(&self) -> ClockRef {
+        system_clock()
+    }
 }
 
 /// Type alias for a reference-counted auth service
@@ -1,4 +1,5 @@
 use base64::Engine;
+use http::HeaderMap;
 use std::sync::Arc;
 use marble_db::auth::{AuthService as DbAuthService, AuthError as DbAuthError};
 use uuid::Uuid;
@@ -29,8 +30,10 @@ impl AuthService for WebDavAuthService {
                 DbAuthError::MissingCredentials => AuthError::MissingCredentials,
                 DbAuthError::InvalidCredentials => AuthError::InvalidCredentials,
                 DbAuthError::UserNotFound => AuthError::UserNotFound,
+                DbAuthError::Database(e) if e.is_unavailable() => AuthError::Unavailable(e.to_string()),
                 DbAuthError::Database(e) => AuthError::Database(format!("Database error: {}", e)),
                 DbAuthError::PasswordVerification(e) => AuthError::PasswordVerification(e),
+                DbAuthError::InvalidResetToken => AuthError::InvalidCredentials,
             })
     }
 }
@@ -61,3 +64,34 @@ pub fn extract_basic_auth(auth_header: Option<&str>) -> Option<(String, String)>
     
     Some((parts[0].to_string(), parts[1].to_string()))
 }
+
+/// Whether a request reached the server over HTTPS
+///
+/// The server does not terminate TLS itself, so this relies on the terminating
+/// proxy reporting the original scheme in `X-Forwarded-Proto` or `Forwarded`.
+/// Requests without either header count as plaintext.
+pub fn is_https_request(headers: &HeaderMap) -> bool {
+    let forwarded_proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|proto| proto.trim().eq_ignore_ascii_case("https"));
+    if let Some(https) = forwarded_proto {
+        return https;
+    }
+    
+    // `Forwarded: for=1.2.3.4;proto=https`, first hop only
+    headers
+        .get(http::header::FORWARDED)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|hop| {
+            hop.split(';').any(|pair| {
+                pair.split_once('=').is_some_and(|(name, value)| {
+                    name.trim().eq_ignore_ascii_case("proto")
+                        && value.trim().trim_matches('"').eq_ignore_ascii_case("https")
+                })
+            })
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,68 @@
+//! Optional features of the server
+//!
+//! Served at `GET /.marble/capabilities` so clients can tell which features
+//! this deployment enables. The handler consults the same descriptor wherever
+//! a feature changes behaviour, so what is advertised is what is served.
+
+use serde::Serialize;
+
+use crate::config::WebDavConfig;
+
+/// Features enabled on this server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// WebDAV class 2 locking with LOCK and UNLOCK
+    pub locks: bool,
+
+    /// Partial GETs with `Range` requests
+    pub ranges: bool,
+
+    /// Full-text search over file contents
+    pub search: bool,
+
+    /// Per-tenant storage quotas, reported in PROPFIND of collections
+    pub quotas: bool,
+
+    /// TOTP codes sent separately from the password in `X-Marble-OTP`
+    pub two_factor: bool,
+
+    /// Markdown rendered as HTML for clients accepting `text/html`
+    pub render_markdown: bool,
+
+    /// Gzip compression of text responses
+    pub compression: bool,
+
+    /// Cached reads served while the database is unavailable
+    pub degraded_reads: bool,
+}
+
+impl Capabilities {
+    /// Capabilities of a server running with this configuration
+    pub fn from_config(config: &WebDavConfig) -> Self {
+        Self {
+            locks: !config.disable_locks,
+            ranges: config.ranges,
+            search: !config.disable_search,
+            quotas: !config.disable_quotas,
+            two_factor: !config.disable_two_factor,
+            render_markdown: config.render_markdown,
+            compression: config.compression_min_size.is_some(),
+            degraded_reads: config.degraded_cache_bytes.is_some(),
+        }
+    }
+
+    /// Value of the `DAV` header: class 2 requires locking
+    pub fn dav_compliance(&self) -> &'static str {
+        if self.locks {
+            "1, 2"
+        } else {
+            "1"
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::from_config(&WebDavConfig::default())
+    }
+}
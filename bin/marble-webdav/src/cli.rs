@@ -0,0 +1,165 @@
+//! Command line interface
+//!
+//! Without a subcommand the binary starts the WebDAV server. Maintenance
+//! subcommands run a single task against the configured database and storage
+//! and exit.
+
+use std::io::BufRead;
+
+use clap::{Parser, Subcommand};
+use marble_db::models::User;
+use marble_db::repositories::{Repository, SqlxUserRepository, UserRepository};
+use marble_storage::{
+    collect_garbage, create_hash_storage, find_dangling_refs, index_unindexed, scrub, tombstone_refs,
+    ContentHasher, StorageConfig,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Marble WebDAV server and maintenance tool
+#[derive(Debug, Parser)]
+#[command(name = "marble-webdav", version)]
+pub struct Cli {
+    /// Maintenance command to run instead of starting the server
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Maintenance commands
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Apply pending database migrations
+    Migrate,
+
+    /// Delete stored content that no file references
+    Gc {
+        /// Only report unreferenced content
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Verify that all referenced content exists and matches its hash
+    Scrub,
+
+    /// List live files whose content is missing from storage
+    Dangling {
+        /// Soft-delete the affected files
+        #[arg(long)]
+        tombstone: bool,
+    },
+
+    /// Index markdown files missing from the search index
+    Reindex,
+
+    /// Manage users
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+}
+
+/// User management commands
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum UserCommand {
+    /// Create a user
+    Add {
+        /// Username of the new user
+        name: String,
+
+        /// Password of the new user, read from stdin when omitted
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+/// Run a maintenance command
+pub async fn run(command: Command, db_pool: Arc<PgPool>) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Migrate => {
+            marble_db::run_migrations(&db_pool).await?;
+            println!("Migrations applied");
+        }
+        Command::Gc { dry_run } => {
+            let content_hasher = content_hasher_from_env()?;
+            let report = collect_garbage(&db_pool, &content_hasher, dry_run).await?;
+            for hash in &report.unreferenced {
+                println!("unreferenced {}", hash);
+            }
+            println!(
+                "Scanned {} objects, {} unreferenced, {} deleted",
+                report.scanned,
+                report.unreferenced.len(),
+                report.deleted
+            );
+        }
+        Command::Scrub => {
+            let content_hasher = content_hasher_from_env()?;
+            let report = scrub(&db_pool, &content_hasher).await?;
+            for hash in &report.missing {
+                println!("missing {}", hash);
+            }
+            for hash in &report.corrupt {
+                println!("corrupt {}", hash);
+            }
+            println!(
+                "Checked {} hashes, {} missing, {} corrupt",
+                report.checked,
+                report.missing.len(),
+                report.corrupt.len()
+            );
+            if !report.missing.is_empty() || !report.corrupt.is_empty() {
+                return Err("scrub found damaged content".into());
+            }
+        }
+        Command::Dangling { tombstone } => {
+            let content_hasher = content_hasher_from_env()?;
+            let dangling = find_dangling_refs(&db_pool, content_hasher.operator()).await?;
+            for (file_id, hash) in &dangling {
+                println!("dangling file {} {}", file_id, hash);
+            }
+            if tombstone {
+                let deleted = tombstone_refs(&db_pool, &dangling).await?;
+                println!("Found {} dangling files, {} tombstoned", dangling.len(), deleted);
+            } else {
+                println!("Found {} dangling files", dangling.len());
+            }
+        }
+        Command::Reindex => {
+            let content_hasher = content_hasher_from_env()?;
+            let indexed = index_unindexed(&db_pool, &content_hasher).await?;
+            println!("Indexed {} files", indexed);
+        }
+        Command::User { command: UserCommand::Add { name, password } } => {
+            let password = match password {
+                Some(password) => password,
+                None => read_password()?,
+            };
+
+            // Hashed with Argon2id by the repository on insert
+            let user_repository = SqlxUserRepository::new(db_pool);
+            let user = user_repository.create(&User::new(name, password)).await?;
+            println!("Created user {} ({})", user.username, user.uuid);
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the content hasher for the storage configured in the environment
+fn content_hasher_from_env() -> Result<ContentHasher, Box<dyn std::error::Error>> {
+    let config = StorageConfig::from_env();
+    config.validate()?;
+    Ok(ContentHasher::for_config(create_hash_storage(&config)?, &config))
+}
+
+/// Read a password from the first line of stdin
+fn read_password() -> Result<String, Box<dyn std::error::Error>> {
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        return Err("password must not be empty".into());
+    }
+    Ok(password)
+}
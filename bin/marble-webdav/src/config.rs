@@ -0,0 +1,233 @@
+//! WebDAV server configuration
+//!
+//! This module provides configuration for the WebDAV server, loaded from
+//! environment variables with defaults.
+
+use std::env;
+use std::time::Duration;
+
+use marble_storage::api::ListOrder;
+
+use crate::etag::EtagPolicy;
+
+/// How collections report `getcontenttype` in PROPFIND
+///
+/// Directories are stored with an internal marker type that clients do not
+/// understand; some expect the property to be absent, others expect the
+/// Apache convention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirectoryContentType {
+    /// Omit `getcontenttype` for collections
+    #[default]
+    Omit,
+
+    /// Report `httpd/unix-directory`
+    UnixDirectory,
+}
+
+impl DirectoryContentType {
+    /// Parse a convention name (`omit` or `unix-directory`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "omit" => Some(DirectoryContentType::Omit),
+            "unix-directory" | "httpd/unix-directory" => Some(DirectoryContentType::UnixDirectory),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for the WebDAV server
+#[derive(Debug, Clone, Default)]
+pub struct WebDavConfig {
+    /// Order of directory entries in PROPFIND responses
+    pub list_order: ListOrder,
+
+    /// Convert request paths to Unicode NFC so macOS (NFD) and other clients
+    /// address the same files
+    pub unicode_nfc: bool,
+
+    /// Apply pending database migrations on startup instead of refusing to start
+    pub auto_migrate: bool,
+
+    /// How long responses recorded under an `Idempotency-Key` are replayed,
+    /// [`DEFAULT_IDEMPOTENCY_WINDOW`](crate::idempotency::DEFAULT_IDEMPOTENCY_WINDOW) if unset
+    pub idempotency_window: Option<Duration>,
+
+    /// Whether ETags are strong or weak, for deployments behind compressing proxies
+    pub etag_policy: EtagPolicy,
+
+    /// How collections report their content type in PROPFIND
+    pub directory_content_type: DirectoryContentType,
+
+    /// Time allowed for listing the children of a collection in PROPFIND;
+    /// when exceeded the children listed so far are returned and the rest is
+    /// marked as truncated instead of timing out
+    pub propfind_budget: Option<Duration>,
+
+    /// Maximum number of children listed per collection in PROPFIND; larger
+    /// listings are cut short and marked as truncated
+    pub propfind_max_children: Option<usize>,
+
+    /// Maximum number of requests one tenant may have in flight; further
+    /// requests are rejected with `503 Service Unavailable`
+    pub max_concurrent_per_tenant: Option<usize>,
+
+    /// Answer GETs for markdown files with rendered HTML when the client
+    /// accepts `text/html`
+    pub render_markdown: bool,
+
+    /// Gzip `text/*` and `application/xml` responses of at least this many
+    /// bytes for clients sending `Accept-Encoding: gzip`; off if unset
+    pub compression_min_size: Option<u16>,
+
+    /// Stream GETs of files of at least this many bytes from storage instead of
+    /// reading them into memory,
+    /// [`DEFAULT_STREAM_MIN_SIZE`](crate::operations::get::DEFAULT_STREAM_MIN_SIZE) if unset
+    pub stream_min_size: Option<usize>,
+
+    /// Serve cached reads and refuse writes while the database is
+    /// unavailable, caching up to this many bytes of served files; off if unset
+    pub degraded_cache_bytes: Option<usize>,
+
+    /// Refuse Basic credentials on requests not forwarded as HTTPS, so they
+    /// are never accepted in plaintext
+    pub require_tls_for_auth: bool,
+
+    /// Name the owner of the conflicting lock in `423 Locked` responses; off by
+    /// default since owners often carry e-mail addresses
+    pub expose_lock_owner: bool,
+
+    /// Refuse LOCK and UNLOCK and advertise only WebDAV class 1
+    pub disable_locks: bool,
+
+    /// Answer GETs with a single byte `Range` with `206 Partial Content`
+    pub ranges: bool,
+
+    /// Refuse full-text search at `/.marble/search`
+    pub disable_search: bool,
+
+    /// Leave the quota properties out of PROPFIND; quotas set on tenants are
+    /// still enforced by storage
+    pub disable_quotas: bool,
+
+    /// Ignore TOTP codes sent in `X-Marble-OTP`; codes appended to the
+    /// password are still checked
+    pub disable_two_factor: bool,
+
+    /// How often expired locks are removed in the background,
+    /// [`DEFAULT_REAP_INTERVAL`](crate::lock::DEFAULT_REAP_INTERVAL) if unset
+    pub lock_reap_interval: Option<Duration>,
+
+    /// Abort read requests still being handled after this long with
+    /// `504 Gateway Timeout`; off if unset. Writes always run to completion
+    pub request_timeout: Option<Duration>,
+
+    /// How long metadata resolved by HEAD and GET is reused,
+    /// [`DEFAULT_METADATA_CACHE_TTL`](crate::metadata_cache::DEFAULT_METADATA_CACHE_TTL)
+    /// if unset. Bounds how long writes made through other servers go unseen;
+    /// zero turns the cache off
+    pub metadata_cache_ttl: Option<Duration>,
+}
+
+impl WebDavConfig {
+    /// Create a new WebDavConfig from environment variables with default fallbacks
+    pub fn from_env() -> Self {
+        Self {
+            list_order: env::var("WEBDAV_LIST_ORDER")
+                .ok()
+                .and_then(|s| parse_list_order(&s))
+                .unwrap_or_default(),
+            unicode_nfc: env::var("WEBDAV_UNICODE_NFC")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            auto_migrate: env::var("WEBDAV_AUTO_MIGRATE")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            idempotency_window: env::var("WEBDAV_IDEMPOTENCY_WINDOW_SECS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .map(Duration::from_secs),
+            etag_policy: env::var("WEBDAV_ETAG_POLICY")
+                .ok()
+                .and_then(|s| EtagPolicy::parse(&s))
+                .unwrap_or_default(),
+            directory_content_type: env::var("WEBDAV_DIRECTORY_CONTENT_TYPE")
+                .ok()
+                .and_then(|s| DirectoryContentType::parse(&s))
+                .unwrap_or_default(),
+            propfind_budget: env::var("WEBDAV_PROPFIND_BUDGET_MS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .map(Duration::from_millis),
+            propfind_max_children: env::var("WEBDAV_PROPFIND_MAX_CHILDREN")
+                .ok()
+                .and_then(|s| s.trim().parse().ok()),
+            max_concurrent_per_tenant: env::var("WEBDAV_MAX_CONCURRENT_PER_TENANT")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|max| *max > 0),
+            render_markdown: env::var("WEBDAV_RENDER_MARKDOWN")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            compression_min_size: env::var("WEBDAV_COMPRESSION_MIN_SIZE")
+                .ok()
+                .and_then(|s| s.trim().parse().ok()),
+            stream_min_size: env::var("WEBDAV_STREAM_MIN_SIZE")
+                .ok()
+                .and_then(|s| s.trim().parse().ok()),
+            degraded_cache_bytes: env::var("WEBDAV_DEGRADED_CACHE_BYTES")
+                .ok()
+                .and_then(|s| s.trim().parse().ok()),
+            require_tls_for_auth: env::var("WEBDAV_REQUIRE_TLS_FOR_AUTH")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            expose_lock_owner: env::var("WEBDAV_EXPOSE_LOCK_OWNER")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            disable_locks: env::var("WEBDAV_DISABLE_LOCKS")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            ranges: env::var("WEBDAV_RANGES")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            disable_search: env::var("WEBDAV_DISABLE_SEARCH")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            disable_quotas: env::var("WEBDAV_DISABLE_QUOTAS")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            disable_two_factor: env::var("WEBDAV_DISABLE_TWO_FACTOR")
+                .map(|s| parse_flag(&s))
+                .unwrap_or(false),
+            lock_reap_interval: env::var("WEBDAV_LOCK_REAP_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            request_timeout: env::var("WEBDAV_REQUEST_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            metadata_cache_ttl: env::var("WEBDAV_METADATA_CACHE_TTL_MS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .map(Duration::from_millis),
+        }
+    }
+}
+
+/// Parse a boolean flag (`1`, `true` or `yes`)
+fn parse_flag(value: &str) -> bool {
+    matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// Parse a listing order name (`path`, `modified` or `size`)
+fn parse_list_order(value: &str) -> Option<ListOrder> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "path" => Some(ListOrder::Path),
+        "modified" => Some(ListOrder::ModifiedDesc),
+        "size" => Some(ListOrder::SizeDesc),
+        _ => None,
+    }
+}
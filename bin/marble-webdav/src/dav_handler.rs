@@ -1,24 +1,192 @@
-use crate::api::{AuthServiceRef, LockManagerRef};
-use crate::auth::extract_basic_auth;
-use crate::error::{AuthError, Error};
+use crate::api::{default_method_policy, AuthServiceRef, LockManagerRef, MethodPolicy, Principal};
+use crate::auth::{extract_basic_auth, is_https_request};
+use crate::capabilities::Capabilities;
+use crate::config::WebDavConfig;
+use crate::degraded::{is_database_unavailable, DegradedMode};
+use crate::error::{AuthError, Error, LockError};
+use crate::idempotency::{is_idempotency_method, Begin, IdempotencyCache};
+use crate::metadata_cache::MetadataCache;
+use crate::usage_cache::UsageCache;
 use crate::operations;
+use crate::version::BuildInfo;
 use bytes::Bytes;
 use dav_server::DavMethod;
 use http::{HeaderMap, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+use marble_core::clock::ClockRef;
 use marble_storage::api::TenantStorageRef;
+use marble_storage::PathNormalizer;
 use tracing::{info, warn};
 use uuid::Uuid;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 /// Type alias for WebDAV response
 pub type DavResponse = Response<Bytes>;
 
-// Tests module
-#[cfg(test)]
-mod tests {
-    // This is a placeholder for the main dav_handler tests
-    // All test implementations have been moved to the dedicated tests directory
-    // See the tests/ directory for implementation details
+/// Methods advertised in `Allow` for `OPTIONS`
+pub(crate) const ALLOWED_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, PROPFIND, PROPPATCH, MKCOL, DELETE, COPY, MOVE, LOCK, UNLOCK";
+
+/// The methods of [`ALLOWED_METHODS`], in the same order
+const DAV_METHODS: [(DavMethod, &str); 12] = [
+    (DavMethod::Options, "OPTIONS"),
+    (DavMethod::Get, "GET"),
+    (DavMethod::Head, "HEAD"),
+    (DavMethod::Put, "PUT"),
+    (DavMethod::PropFind, "PROPFIND"),
+    (DavMethod::PropPatch, "PROPPATCH"),
+    (DavMethod::MkCol, "MKCOL"),
+    (DavMethod::Delete, "DELETE"),
+    (DavMethod::Copy, "COPY"),
+    (DavMethod::Move, "MOVE"),
+    (DavMethod::Lock, "LOCK"),
+    (DavMethod::Unlock, "UNLOCK"),
+];
+
+/// Prefix of Marble management routes, relative to the tenant root
+const MANAGEMENT_PREFIX: &str = ".marble/";
+
+/// Management route reporting the build, served without authentication
+const VERSION_ROUTE: &str = "version";
+
+/// Management route listing the enabled features, served without authentication
+const CAPABILITIES_ROUTE: &str = "capabilities";
+
+/// Management route checking the content of one file against its hash
+const VERIFY_ROUTE: &str = "verify";
+
+/// Management route searching the content of markdown files
+const SEARCH_ROUTE: &str = "search";
+
+/// Management route returning the properties of a list of paths
+const PROPFIND_BATCH_ROUTE: &str = "propfind-batch";
+
+/// Response to `OPTIONS *`, advertising server-wide capabilities
+fn server_options_response(capabilities: &Capabilities) -> DavResponse {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(&*crate::headers::DAV, capabilities.dav_compliance())
+        .header("MS-Author-Via", "DAV")
+        .header(http::header::ALLOW, ALLOWED_METHODS)
+        .body(Bytes::new())
+        .unwrap()
+}
+
+/// Response to `OPTIONS` on a resource, advertising what the principal may do
+///
+/// `Allow` lists only the methods the method policy grants, so a read-only
+/// collaborator is told up front that writes will be refused.
+fn resource_options_response(
+    capabilities: &Capabilities,
+    method_policy: &MethodPolicy,
+    principal: &Principal,
+) -> DavResponse {
+    let allow = DAV_METHODS
+        .iter()
+        .filter(|(method, _)| method_policy(principal, *method))
+        .filter(|(method, _)| capabilities.locks || !matches!(method, DavMethod::Lock | DavMethod::Unlock))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(", ");
+    
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(&*crate::headers::DAV, capabilities.dav_compliance())
+        .header("MS-Author-Via", "DAV")
+        .header(http::header::ALLOW, allow)
+        .body(Bytes::new())
+        .unwrap()
+}
+
+/// Response to `GET /.marble/version`, the build information as JSON
+fn version_response() -> DavResponse {
+    // Serializing a struct of strings cannot fail
+    let body = serde_json::to_vec(&BuildInfo::current()).unwrap();
+    
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body))
+        .unwrap()
+}
+
+/// Response to `GET /.marble/capabilities`, the enabled features as JSON
+fn capabilities_response(capabilities: &Capabilities) -> DavResponse {
+    // Serializing a struct of flags cannot fail
+    let body = serde_json::to_vec(capabilities).unwrap();
+    
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body))
+        .unwrap()
+}
+
+/// Split a request target into its path and query string
+fn split_query(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (target, None),
+    }
+}
+
+/// The raw, still percent-encoded value of a query parameter
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Whether a method can change files
+fn is_modifying_method(method: DavMethod) -> bool {
+    matches!(
+        method,
+        DavMethod::Put
+            | DavMethod::Delete
+            | DavMethod::Move
+            | DavMethod::Copy
+            | DavMethod::MkCol
+            | DavMethod::PropPatch
+    )
+}
+
+/// Bounds the number of in-flight requests of each tenant
+///
+/// One tenant issuing many parallel requests would otherwise starve the others.
+struct TenantLimiter {
+    /// Permits per tenant
+    max_in_flight: usize,
+
+    /// Semaphore of each tenant seen so far
+    semaphores: Mutex<HashMap<Uuid, Arc<Semaphore>>>,
+}
+
+impl TenantLimiter {
+    fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a permit for a request, held until the permit is dropped
+    fn try_acquire(&self, tenant_id: Uuid) -> Result<OwnedSemaphorePermit, Error> {
+        let semaphore = self
+            .semaphores
+            .lock()
+            .unwrap()
+            .entry(tenant_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_in_flight)))
+            .clone();
+
+        semaphore
+            .try_acquire_owned()
+            .map_err(|_| Error::TooManyRequests(tenant_id))
+    }
 }
 
 /// Marble WebDAV handler integrating with TenantStorage
@@ -31,6 +199,36 @@ pub struct MarbleDavHandler {
 
     /// Lock manager for WebDAV locks
     lock_manager: LockManagerRef,
+
+    /// Server configuration
+    config: WebDavConfig,
+
+    /// Normalizer shared with the storage layer
+    path_normalizer: PathNormalizer,
+
+    /// Responses recorded under idempotency keys
+    idempotency: IdempotencyCache,
+
+    /// Per-tenant bound on in-flight requests, unlimited if `None`
+    limiter: Option<TenantLimiter>,
+
+    /// Metadata resolved by HEAD and GET, reused by the next request
+    metadata_cache: MetadataCache,
+
+    /// Storage usage reported in quota properties
+    usage_cache: UsageCache,
+
+    /// Methods each principal may use
+    method_policy: MethodPolicy,
+
+    /// Read-only fallback while the database is unavailable, off if `None`
+    degraded: Option<DegradedMode>,
+
+    /// Optional features enabled by the configuration
+    capabilities: Capabilities,
+
+    /// Time source for the lock timeouts reported to clients and for cache expiry, the lock manager's
+    clock: ClockRef,
 }
 
 impl MarbleDavHandler {
@@ -40,17 +238,60 @@ impl MarbleDavHandler {
         auth_service: AuthServiceRef,
         lock_manager: LockManagerRef,
     ) -> Self {
+        let clock = lock_manager.clock();
         Self {
             tenant_storage,
             auth_service,
             lock_manager,
+            config: WebDavConfig::default(),
+            path_normalizer: PathNormalizer::new(),
+            idempotency: IdempotencyCache::default(),
+            limiter: None,
+            metadata_cache: MetadataCache::default().with_clock(clock.clone()),
+            usage_cache: UsageCache::default(),
+            method_policy: default_method_policy(),
+            degraded: None,
+            capabilities: Capabilities::default(),
+            clock,
         }
     }
     
+    /// Decide with a custom policy which methods a principal may use
+    pub fn with_method_policy(mut self, method_policy: MethodPolicy) -> Self {
+        self.method_policy = method_policy;
+        self
+    }
+    
+    /// Use the given server configuration
+    pub fn with_config(mut self, config: WebDavConfig) -> Self {
+        self.path_normalizer = PathNormalizer::new().with_unicode_nfc(config.unicode_nfc);
+        if let Some(window) = config.idempotency_window {
+            self.idempotency = IdempotencyCache::new(window);
+        }
+        if let Some(ttl) = config.metadata_cache_ttl {
+            self.metadata_cache = MetadataCache::new(ttl).with_clock(self.clock.clone());
+        }
+        self.limiter = config.max_concurrent_per_tenant.map(TenantLimiter::new);
+        self.degraded = config.degraded_cache_bytes.map(DegradedMode::new);
+        self.capabilities = Capabilities::from_config(&config);
+        self.config = config;
+        self
+    }
+    
+    /// Optional features this handler serves
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+    
     // Helper methods for tests
     #[cfg(test)]
     pub(crate) async fn handle_get(&self, tenant_id: Uuid, path: &str) -> Result<DavResponse, Error> {
-        operations::handle_get(&self.tenant_storage, tenant_id, path).await
+        operations::handle_get(&self.tenant_storage, tenant_id, path, &HeaderMap::new(), &self.config, &self.metadata_cache).await
+    }
+    
+    #[cfg(test)]
+    pub(crate) async fn handle_head(&self, tenant_id: Uuid, path: &str) -> Result<DavResponse, Error> {
+        operations::handle_head(&self.tenant_storage, tenant_id, path, &self.config, &self.metadata_cache).await
     }
     
     #[cfg(test)]
@@ -61,7 +302,7 @@ impl MarbleDavHandler {
         headers: HeaderMap,
         body: Bytes,
     ) -> Result<DavResponse, Error> {
-        operations::handle_put(&self.tenant_storage, tenant_id, path, headers, body).await
+        operations::handle_put(&self.tenant_storage, &self.lock_manager, tenant_id, path, headers, body, |p| self.normalize_path(p)).await
     }
     
     #[cfg(test)]
@@ -71,7 +312,14 @@ impl MarbleDavHandler {
         path: &str,
         body: Bytes,
     ) -> Result<DavResponse, Error> {
-        operations::handle_propfind(&self.tenant_storage, tenant_id, path, body).await
+        operations::handle_propfind(
+            &self.tenant_storage,
+            tenant_id,
+            path,
+            body,
+            &self.config,
+            &self.usage_cache
+        ).await
     }
     
     #[cfg(test)]
@@ -81,7 +329,7 @@ impl MarbleDavHandler {
     
     #[cfg(test)]
     pub(crate) async fn handle_delete(&self, tenant_id: Uuid, path: &str) -> Result<DavResponse, Error> {
-        operations::handle_delete(&self.tenant_storage, &self.lock_manager, tenant_id, path).await
+        operations::handle_delete(&self.tenant_storage, &self.lock_manager, tenant_id, path, HeaderMap::new(), |p| self.normalize_path(p)).await
     }
     
     #[cfg(test)]
@@ -107,29 +355,8 @@ impl MarbleDavHandler {
         ).await
     }
     
-    #[cfg(test)]
-    pub(crate) async fn handle_lock(&self, tenant_id: Uuid, path: &str, headers: HeaderMap, body: Bytes) -> Result<DavResponse, Error> {
-        operations::handle_lock(
-            &self.lock_manager,
-            tenant_id,
-            path,
-            headers,
-            body
-        ).await
-    }
-    
-    #[cfg(test)]
-    pub(crate) async fn handle_unlock(&self, tenant_id: Uuid, path: &str, headers: HeaderMap) -> Result<DavResponse, Error> {
-        operations::handle_unlock(
-            &self.lock_manager,
-            tenant_id,
-            path,
-            headers
-        ).await
-    }
-
-    /// Authenticate a request and return the tenant ID
-    async fn authenticate(&self, headers: &HeaderMap) -> Result<Uuid, Error> {
+    /// Authenticate a request and return the principal
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, Error> {
         // Extract Authorization header
         let auth_header = headers
             .get(http::header::AUTHORIZATION)
@@ -138,35 +365,77 @@ impl MarbleDavHandler {
         // If missing, return error
         let auth_header = auth_header.ok_or(Error::Auth(AuthError::MissingCredentials))?;
 
+        // Credentials sent in plaintext are already exposed; refuse rather than accept them
+        if self.config.require_tls_for_auth && !is_https_request(headers) {
+            return Err(Error::Forbidden(
+                "Basic authentication requires HTTPS; connect over https://".to_string(),
+            ));
+        }
+
         // Extract credentials
-        let (username, password) = extract_basic_auth(Some(auth_header))
+        let (username, mut password) = extract_basic_auth(Some(auth_header))
             .ok_or(Error::Auth(AuthError::MissingCredentials))?;
 
+        // Clients that cannot append a TOTP code to the password send it separately
+        if self.capabilities.two_factor {
+            if let Some(code) = headers.get(&*crate::headers::MARBLE_OTP).and_then(|h| h.to_str().ok()) {
+                password.push_str(code.trim());
+            }
+        }
+
         // Authenticate with auth service
-        let tenant_id = self
-            .auth_service
-            .authenticate(&username, &password)
+        self.auth_service
+            .authenticate_principal(&username, &password)
             .await
-            .map_err(Error::Auth)?;
-
-        Ok(tenant_id)
+            .map_err(Error::Auth)
     }
 
-    /// Normalize a WebDAV path to a storage path
-    fn normalize_path(&self, path: &str) -> String {
-        // Remove leading slash if present
-        let path = path.trim_start_matches('/');
+    /// Authenticate a request, counting a database outage towards degraded mode
+    ///
+    /// An outage is reported as unavailable rather than as bad credentials.
+    /// While degraded, `serve_cached` requests from credentials that
+    /// authenticated before the outage keep their principal.
+    async fn authenticate_tracked(&self, headers: &HeaderMap, serve_cached: bool) -> Result<Principal, Error> {
+        let Some(degraded) = &self.degraded else {
+            return self.authenticate(headers).await;
+        };
         
-        // Handle empty path as root
-        if path.is_empty() {
-            return ".".to_string();
+        match self.authenticate(headers).await {
+            Ok(principal) => {
+                degraded.remember_principal(headers, &principal);
+                Ok(principal)
+            }
+            Err(error) if is_database_unavailable(&error) => {
+                degraded.record_failure();
+                if serve_cached && degraded.is_degraded() {
+                    if let Some(principal) = degraded.remembered_principal(headers) {
+                        return Ok(principal);
+                    }
+                }
+                Err(error)
+            }
+            Err(error) => Err(error),
+        }
+    }
+    
+    /// Normalize a WebDAV path to a storage path
+    ///
+    /// Storage paths are relative to the tenant root, with `.` for the root.
+    ///
+    /// Request targets are percent-encoded. Each segment is decoded on its own,
+    /// so an encoded slash cannot add a segment; such paths are rejected.
+    /// Invalid UTF-8 is replaced rather than rejected.
+    fn normalize_path(&self, path: &str) -> Result<String, Error> {
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            let segment = percent_decode_str(segment).decode_utf8_lossy();
+            if segment.contains('/') {
+                return Err(Error::WebDav(format!("Encoded slash in path: {}", path)));
+            }
+            segments.push(segment);
         }
         
-        // Replace percent-encoded characters
-        // Note: This is a simplification, a real implementation would use proper URL decoding
-        let path = path.replace("%20", " ");
-        
-        path
+        Ok(self.path_normalizer.to_relative(&segments.join("/")))
     }
     
     /// Helper to create a basic response
@@ -178,6 +447,9 @@ impl MarbleDavHandler {
     }
     
     /// Dispatch WebDAV method to appropriate handler
+    ///
+    /// `path` is the request target and may carry a query string, which only
+    /// management routes read.
     pub async fn handle(
         &self,
         method: DavMethod,
@@ -187,50 +459,344 @@ impl MarbleDavHandler {
     ) -> Result<DavResponse, Error> {
         info!("Handling {:?} request for path: {}", method, path);
         
-        // Extract credentials and get tenant ID
-        let tenant_id = self.authenticate(&headers).await?;
+        let (path, query) = split_query(path);
+        
+        // `OPTIONS *` asks about the server, not a resource, so it needs no tenant
+        if method == DavMethod::Options && path == "*" {
+            return Ok(server_options_response(&self.capabilities));
+        }
         
         // Normalize path
-        let normalized_path = self.normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
+        
+        // The build version and features are public so they can be checked without credentials
+        if method == DavMethod::Get {
+            match normalized_path.strip_prefix(MANAGEMENT_PREFIX) {
+                Some(VERSION_ROUTE) => return Ok(version_response()),
+                Some(CAPABILITIES_ROUTE) => return Ok(capabilities_response(&self.capabilities)),
+                _ => {}
+            }
+        }
+        
+        // Extract credentials and get tenant ID
+        let principal = self.authenticate_tracked(&headers, method == DavMethod::Get).await?;
+        let tenant_id = principal.tenant_id;
+        
+        if !(self.method_policy)(&principal, method) {
+            return Err(Error::Forbidden(format!("Method {:?} not allowed for this user", method)));
+        }
+        
+        if matches!(method, DavMethod::Lock | DavMethod::Unlock) && !self.capabilities.locks {
+            return Err(Error::MethodNotAllowed(format!("Method {:?} not allowed: locking is disabled", method)));
+        }
+        
+        if method == DavMethod::Options {
+            return Ok(resource_options_response(&self.capabilities, &self.method_policy, &principal));
+        }
         
+        // Held until the request completes
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.try_acquire(tenant_id)?),
+            None => None,
+        };
+        
+        // Management routes are not part of the tenant's file tree
+        if let Some(route) = normalized_path.strip_prefix(MANAGEMENT_PREFIX) {
+            return self.handle_management(method, tenant_id, route, query).await;
+        }
+        
+        // Retried mutations with a known idempotency key get the recorded response
+        let idempotency_key = if is_idempotency_method(method) {
+            headers
+                .get(&*crate::headers::IDEMPOTENCY_KEY)
+                .and_then(|h| h.to_str().ok())
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+        } else {
+            None
+        };
+        
+        // Held until the response is recorded; released if the request fails
+        let reservation = match &idempotency_key {
+            Some(key) => match self.idempotency.begin(tenant_id, key, method, &normalized_path).await? {
+                Begin::Replay(response) => {
+                    info!("Replaying recorded response for idempotency key {}", key);
+                    return Ok(response);
+                }
+                Begin::Execute(reservation) => Some(reservation),
+            },
+            None => None,
+        };
+        
+        // Writes cannot succeed while the database is down
+        if let Some(degraded) = &self.degraded {
+            if is_modifying_method(method) && degraded.rejects_writes() {
+                return Err(Error::ReadOnly);
+            }
+        }
+        
+        let mut result = self.dispatch(method, tenant_id, &normalized_path, headers, body).await;
+        
+        if let Some(degraded) = &self.degraded {
+            self.track_degraded(degraded, method, tenant_id, &normalized_path, &mut result);
+        }
+        
+        // Lock owners are only named in 423 responses when the deployment allows it
+        if !self.config.expose_lock_owner {
+            if let Err(Error::Lock(LockError::TokenNotSubmitted { owner, .. })) = &mut result {
+                *owner = None;
+            }
+        }
+        
+        // Cached metadata and usage may be stale after any modification
+        if is_modifying_method(method) {
+            self.metadata_cache.invalidate_tenant(tenant_id).await;
+            self.usage_cache.invalidate_tenant(tenant_id).await;
+        }
+        
+        if let (Some(reservation), Ok(response)) = (reservation, &result) {
+            reservation.complete(response);
+        }
+        
+        result
+    }
+    
+    /// Update the database health from a request's outcome and keep or serve cached reads
+    fn track_degraded(
+        &self,
+        degraded: &DegradedMode,
+        method: DavMethod,
+        tenant_id: Uuid,
+        normalized_path: &str,
+        result: &mut Result<DavResponse, Error>,
+    ) {
+        match result {
+            Err(error) if is_database_unavailable(error) => {
+                degraded.record_failure();
+                if method == DavMethod::Get && degraded.is_degraded() {
+                    if let Some(response) = degraded.cached_read(tenant_id, normalized_path) {
+                        info!("Serving cached read of {} while the database is unavailable", normalized_path);
+                        *result = Ok(response);
+                    }
+                } else if is_modifying_method(method) && degraded.is_degraded() {
+                    *result = Err(Error::ReadOnly);
+                }
+            }
+            Err(_) => {}
+            Ok(response) => {
+                degraded.record_success();
+                if method == DavMethod::Get {
+                    degraded.cache_read(tenant_id, normalized_path, response);
+                } else if is_modifying_method(method) {
+                    degraded.invalidate_tenant(tenant_id);
+                }
+            }
+        }
+    }
+    
+    /// Handle a POST request
+    ///
+    /// POST is not a WebDAV method; it is only accepted by management routes
+    /// that act on many resources at once. Routes that change resources are
+    /// authorized like DELETE, `propfind-batch` like PROPFIND.
+    pub async fn handle_post(
+        &self,
+        path: &str,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<DavResponse, Error> {
+        info!("Handling POST request for path: {}", path);
+        
+        let (path, _query) = split_query(path);
+        
+        let principal = self.authenticate_tracked(&headers, false).await?;
+        let tenant_id = principal.tenant_id;
+        
+        let normalized_path = self.normalize_path(path)?;
+        let route = normalized_path
+            .strip_prefix(MANAGEMENT_PREFIX)
+            .ok_or_else(|| Error::MethodNotAllowed("POST is only allowed on management routes".to_string()))?;
+        
+        // Read-only routes are allowed to anyone who may PROPFIND
+        let implied_method = if route == PROPFIND_BATCH_ROUTE {
+            DavMethod::PropFind
+        } else {
+            DavMethod::Delete
+        };
+        if !(self.method_policy)(&principal, implied_method) {
+            return Err(Error::Forbidden("POST not allowed for this user".to_string()));
+        }
+        
+        // Routes that modify data are refused like any other write while degraded
+        if let Some(degraded) = &self.degraded {
+            if is_modifying_method(implied_method) && degraded.rejects_writes() {
+                return Err(Error::ReadOnly);
+            }
+        }
+        
+        // Held until the request completes
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.try_acquire(tenant_id)?),
+            None => None,
+        };
+        
+        match route {
+            PROPFIND_BATCH_ROUTE => {
+                operations::handle_propfind_batch(
+                    &self.tenant_storage,
+                    tenant_id,
+                    &body,
+                    self.config.etag_policy,
+                    |path| self.normalize_path(path),
+                )
+                .await
+            }
+            "batch-delete" => {
+                let result = operations::handle_batch_delete(
+                    &self.tenant_storage,
+                    &self.lock_manager,
+                    tenant_id,
+                    &headers,
+                    &body,
+                    |path| self.normalize_path(path),
+                )
+                .await;
+                self.metadata_cache.invalidate_tenant(tenant_id).await;
+                self.usage_cache.invalidate_tenant(tenant_id).await;
+                result
+            }
+            _ => Err(Error::Storage(marble_storage::StorageError::NotFound(format!(
+                "{}{}",
+                MANAGEMENT_PREFIX, route
+            )))),
+        }
+    }
+    
+    /// Handle a management route for an authenticated tenant
+    async fn handle_management(
+        &self,
+        method: DavMethod,
+        tenant_id: Uuid,
+        route: &str,
+        query: Option<&str>,
+    ) -> Result<DavResponse, Error> {
+        if route == VERIFY_ROUTE {
+            if method != DavMethod::Get {
+                return Err(Error::MethodNotAllowed(format!(
+                    "Method {:?} not allowed on management route",
+                    method
+                )));
+            }
+            let path = query_param(query, "path")
+                .ok_or_else(|| Error::WebDav("Missing path parameter".to_string()))?;
+            return operations::handle_verify(&self.tenant_storage, tenant_id, &self.normalize_path(path)?).await;
+        }
+        
+        if route == SEARCH_ROUTE && self.capabilities.search {
+            if method != DavMethod::Get {
+                return Err(Error::MethodNotAllowed(format!(
+                    "Method {:?} not allowed on management route",
+                    method
+                )));
+            }
+            let search = query_param(query, "q").unwrap_or_default().replace('+', " ");
+            let search = percent_decode_str(&search).decode_utf8_lossy();
+            return operations::handle_search(&self.tenant_storage, tenant_id, &search).await;
+        }
+        
+        match (method, route.strip_prefix("blob/")) {
+            (DavMethod::Get, Some(hash)) => {
+                operations::handle_get_blob(&self.tenant_storage, tenant_id, hash).await
+            }
+            (_, Some(_)) => Err(Error::MethodNotAllowed(format!(
+                "Method {:?} not allowed on management route",
+                method
+            ))),
+            (_, None) => Err(Error::Storage(marble_storage::StorageError::NotFound(format!(
+                "{}{}",
+                MANAGEMENT_PREFIX, route
+            )))),
+        }
+    }
+    
+    /// Run a WebDAV method for an authenticated tenant
+    async fn dispatch(
+        &self,
+        method: DavMethod,
+        tenant_id: Uuid,
+        normalized_path: &str,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<DavResponse, Error> {
         // Handle method based on tenant ID and normalized path
         match method {
             // Basic file operations
-            DavMethod::Get => operations::handle_get(&self.tenant_storage, tenant_id, &normalized_path).await,
+            DavMethod::Get => operations::handle_get(
+                &self.tenant_storage,
+                tenant_id,
+                normalized_path,
+                &headers,
+                &self.config,
+                &self.metadata_cache
+            ).await,
+            
+            DavMethod::Head => operations::handle_head(
+                &self.tenant_storage,
+                tenant_id,
+                normalized_path,
+                &self.config,
+                &self.metadata_cache
+            ).await,
             
             DavMethod::Put => operations::handle_put(
                 &self.tenant_storage, 
+                &self.lock_manager,
                 tenant_id, 
-                &normalized_path, 
+                normalized_path, 
                 headers, 
-                body
+                body,
+                |p| self.normalize_path(p)
             ).await,
             
             DavMethod::PropFind => operations::handle_propfind(
                 &self.tenant_storage, 
                 tenant_id, 
-                &normalized_path, 
-                body
+                normalized_path, 
+                body,
+                &self.config,
+                &self.usage_cache
+            ).await,
+            
+            DavMethod::PropPatch => operations::handle_proppatch(
+                &self.tenant_storage,
+                &self.lock_manager,
+                tenant_id,
+                normalized_path,
+                headers,
+                body,
+                |p| self.normalize_path(p)
             ).await,
             
             DavMethod::MkCol => operations::handle_mkcol(
                 &self.tenant_storage, 
                 tenant_id, 
-                &normalized_path
+                normalized_path
             ).await,
             
             DavMethod::Delete => operations::handle_delete(
                 &self.tenant_storage,
                 &self.lock_manager,
                 tenant_id, 
-                &normalized_path
+                normalized_path,
+                headers,
+                |p| self.normalize_path(p)
             ).await,
             
             // Advanced operations (implemented)
             DavMethod::Copy => operations::handle_copy(
                 &self.tenant_storage,
                 tenant_id,
-                &normalized_path,
+                normalized_path,
                 headers,
                 |p| self.normalize_path(p)
             ).await,
@@ -239,16 +805,18 @@ impl MarbleDavHandler {
                 &self.tenant_storage,
                 &self.lock_manager,
                 tenant_id,
-                &normalized_path,
+                normalized_path,
                 headers,
                 |p| self.normalize_path(p)
             ).await,
             
             // Lock operations
             DavMethod::Lock => operations::handle_lock(
+                &self.tenant_storage,
                 &self.lock_manager,
+                &self.clock,
                 tenant_id,
-                &normalized_path,
+                normalized_path,
                 headers,
                 body
             ).await,
@@ -256,7 +824,7 @@ impl MarbleDavHandler {
             DavMethod::Unlock => operations::handle_unlock(
                 &self.lock_manager,
                 tenant_id,
-                &normalized_path,
+                normalized_path,
                 headers
             ).await,
             
@@ -268,3 +836,11 @@ impl MarbleDavHandler {
         }
     }
 }
+
+// Tests module
+#[cfg(test)]
+mod tests {
+    // This is a placeholder for the main dav_handler tests
+    // All test implementations have been moved to the dedicated tests directory
+    // See the tests/ directory for implementation details
+}
@@ -0,0 +1,266 @@
+//! Read-only operation while the database is unavailable
+//!
+//! Every storage operation goes through the metadata database, so a database
+//! outage would otherwise fail every request. When database errors persist for
+//! several requests in a row the server degrades: modifying requests are
+//! refused with `503 Service Unavailable` and GETs that fail are answered from
+//! a cache of recently served files. Reads keep going to storage, so the first
+//! one that succeeds after the database returns ends the degraded state; a
+//! write is let through as a probe once the retry interval has passed since
+//! the last failure.
+//!
+//! Looking up the user needs the database too, so an authentication failure
+//! caused by the outage counts towards degrading and is answered with 503
+//! rather than 401. Users who authenticated before the outage are remembered
+//! by a digest of their credentials, so their GETs can still be answered from
+//! the cache.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use blake2b_simd::Params;
+use bytes::Bytes;
+use http::{HeaderMap, Response, StatusCode};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::api::Principal;
+use crate::dav_handler::DavResponse;
+use crate::error::{AuthError, Error};
+use crate::operations::get::StreamedBody;
+
+/// Consecutive database failures after which the server degrades
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Time after the last failure before a write is let through again
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Most principals remembered for serving cached reads
+const MAX_REMEMBERED_PRINCIPALS: usize = 1024;
+
+/// Whether an error means the database could not be reached
+pub fn is_database_unavailable(error: &Error) -> bool {
+    match error {
+        Error::Storage(e) => e.is_unavailable(),
+        Error::Auth(AuthError::Unavailable(_)) => true,
+        _ => false,
+    }
+}
+
+/// Digest of the credentials a request carries
+fn credentials_digest(headers: &HeaderMap) -> Option<[u8; 32]> {
+    let authorization = headers.get(http::header::AUTHORIZATION)?;
+
+    let mut state = Params::new().hash_length(32).to_state();
+    state.update(authorization.as_bytes());
+    if let Some(code) = headers.get(&*crate::headers::MARBLE_OTP) {
+        state.update(b"\0");
+        state.update(code.as_bytes());
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(state.finalize().as_bytes());
+    Some(digest)
+}
+
+/// Health of the database as seen by recent requests
+#[derive(Debug, Default)]
+struct Health {
+    /// Database failures since the last success
+    consecutive_failures: u32,
+
+    /// When the last database failure happened
+    last_failure: Option<Instant>,
+}
+
+/// A cached GET response
+struct CachedRead {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+
+    /// Insertion order, for evicting the oldest entry
+    sequence: u64,
+}
+
+/// Recently served files, keyed by tenant and normalized path
+#[derive(Default)]
+struct ReadCache {
+    entries: HashMap<(Uuid, String), CachedRead>,
+
+    /// Total size of the cached bodies
+    bytes: usize,
+
+    /// Sequence number of the next entry
+    next_sequence: u64,
+}
+
+/// Tracks database health and keeps the reads served while it degrades
+pub struct DegradedMode {
+    failure_threshold: u32,
+    retry_after: Duration,
+    health: Mutex<Health>,
+    reads: Mutex<ReadCache>,
+
+    /// Principals of recently authenticated credentials, keyed by their digest
+    principals: Mutex<HashMap<[u8; 32], Principal>>,
+
+    /// Upper bound on the total size of cached bodies
+    max_cached_bytes: usize,
+}
+
+impl DegradedMode {
+    /// Create a tracker caching up to `max_cached_bytes` of file content
+    pub fn new(max_cached_bytes: usize) -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            retry_after: DEFAULT_RETRY_AFTER,
+            health: Mutex::new(Health::default()),
+            reads: Mutex::new(ReadCache::default()),
+            principals: Mutex::new(HashMap::new()),
+            max_cached_bytes,
+        }
+    }
+
+    /// Degrade after `failure_threshold` consecutive database failures
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Refuse writes for `retry_after` after each failure
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Whether the server is currently degraded
+    pub fn is_degraded(&self) -> bool {
+        self.health.lock().unwrap().consecutive_failures >= self.failure_threshold
+    }
+
+    /// Whether modifying requests are refused right now
+    pub fn rejects_writes(&self) -> bool {
+        let health = self.health.lock().unwrap();
+        health.consecutive_failures >= self.failure_threshold
+            && health
+                .last_failure
+                .is_some_and(|at| at.elapsed() < self.retry_after)
+    }
+
+    /// Record a request that reached the database
+    pub fn record_success(&self) {
+        let mut health = self.health.lock().unwrap();
+        if health.consecutive_failures >= self.failure_threshold {
+            info!("Database is available again, leaving read-only mode");
+        }
+        *health = Health::default();
+    }
+
+    /// Record a request that failed because the database is unavailable
+    pub fn record_failure(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.consecutive_failures += 1;
+        health.last_failure = Some(Instant::now());
+        if health.consecutive_failures == self.failure_threshold {
+            warn!(
+                "Database unavailable for {} requests, serving cached reads only",
+                health.consecutive_failures
+            );
+        }
+    }
+
+    /// Remember who a request's credentials belong to
+    ///
+    /// Only a digest of the credentials is kept. When the table is full it is
+    /// cleared rather than growing further.
+    pub fn remember_principal(&self, headers: &HeaderMap, principal: &Principal) {
+        let Some(digest) = credentials_digest(headers) else {
+            return;
+        };
+
+        let mut principals = self.principals.lock().unwrap();
+        if principals.len() >= MAX_REMEMBERED_PRINCIPALS && !principals.contains_key(&digest) {
+            principals.clear();
+        }
+        principals.insert(digest, principal.clone());
+    }
+
+    /// The principal remembered for a request's credentials, if any
+    pub fn remembered_principal(&self, headers: &HeaderMap) -> Option<Principal> {
+        let digest = credentials_digest(headers)?;
+        self.principals.lock().unwrap().get(&digest).cloned()
+    }
+
+    /// Keep a successful GET response for serving while degraded
+    ///
+    /// Negotiated responses (carrying `Vary`), streamed bodies and bodies
+    /// larger than the whole cache are not kept. The oldest entries are
+    /// evicted to make room.
+    pub fn cache_read(&self, tenant_id: Uuid, path: &str, response: &DavResponse) {
+        let size = response.body().len();
+        if response.status() != StatusCode::OK
+            || response.headers().contains_key(http::header::VARY)
+            || response.extensions().get::<StreamedBody>().is_some()
+            || size > self.max_cached_bytes
+        {
+            return;
+        }
+
+        let mut reads = self.reads.lock().unwrap();
+
+        if let Some(previous) = reads.entries.remove(&(tenant_id, path.to_string())) {
+            reads.bytes -= previous.body.len();
+        }
+        while reads.bytes + size > self.max_cached_bytes {
+            let oldest = reads
+                .entries
+                .iter()
+                .min_by_key(|(_, read)| read.sequence)
+                .map(|(key, _)| key.clone());
+            match oldest.and_then(|key| reads.entries.remove(&key)) {
+                Some(evicted) => reads.bytes -= evicted.body.len(),
+                None => break,
+            }
+        }
+
+        reads.bytes += size;
+        let sequence = reads.next_sequence;
+        reads.next_sequence += 1;
+        reads.entries.insert(
+            (tenant_id, path.to_string()),
+            CachedRead {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response.body().clone(),
+                sequence,
+            },
+        );
+    }
+
+    /// The cached response for a file, if any
+    pub fn cached_read(&self, tenant_id: Uuid, path: &str) -> Option<DavResponse> {
+        let reads = self.reads.lock().unwrap();
+        let read = reads.entries.get(&(tenant_id, path.to_string()))?;
+
+        let mut response = Response::builder().status(read.status);
+        for (name, value) in &read.headers {
+            response = response.header(name, value);
+        }
+        response.body(read.body.clone()).ok()
+    }
+
+    /// Drop the cached reads of a tenant after it modified its files
+    pub fn invalidate_tenant(&self, tenant_id: Uuid) {
+        let mut reads = self.reads.lock().unwrap();
+        let ReadCache { entries, bytes, .. } = &mut *reads;
+        entries.retain(|(tenant, _), read| {
+            let keep = *tenant != tenant_id;
+            if !keep {
+                *bytes -= read.body.len();
+            }
+            keep
+        });
+    }
+}
@@ -33,6 +33,30 @@ pub enum Error {
     #[error("Unlock operation failed: {0}")]
     UnlockFailed(String),
 
+    /// The method is not supported on the resource or by this server
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    /// The principal may not perform the request
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// A request precondition does not hold
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    /// The request conflicts with another one in progress
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Modifications are refused while the database is unavailable
+    #[error("Server is read-only while the database is unavailable")]
+    ReadOnly,
+
+    /// The tenant has too many requests in flight
+    #[error("Too many concurrent requests for tenant {0}")]
+    TooManyRequests(uuid::Uuid),
+
     /// Internal server errors
     #[error("Internal server error: {0}")]
     Internal(String),
@@ -92,6 +116,10 @@ pub enum AuthError {
     /// Password verification error
     #[error("Password verification error: {0}")]
     PasswordVerification(String),
+
+    /// The user database could not be reached
+    #[error("Authentication unavailable: {0}")]
+    Unavailable(String),
 }
 
 /// Lock errors
@@ -101,6 +129,16 @@ pub enum LockError {
     #[error("Resource is locked by another user")]
     ResourceLocked,
 
+    /// A locked resource was modified without submitting its lock token
+    #[error("Resource {path} is locked")]
+    TokenNotSubmitted {
+        /// Path of the locked resource
+        path: String,
+
+        /// Owner of the lock, if known and disclosed
+        owner: Option<String>,
+    },
+
     /// Invalid lock token
     #[error("Invalid lock token")]
     InvalidLockToken,
@@ -0,0 +1,76 @@
+//! Entity tags
+//!
+//! ETags are derived from the content hash and recorded size of a file, so
+//! identical content always has the same tag, while a size that disagrees with
+//! the hash, as left behind by a partial write, changes it. Compressing
+//! proxies rewrite bodies but usually keep the ETag, which makes strong tags
+//! claim byte equality that no longer holds. [`EtagPolicy::Weak`] marks every
+//! tag as weak (`W/"..."`) for such setups.
+
+use marble_storage::api::FileMetadata;
+
+/// Whether ETags are emitted as strong or weak validators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EtagPolicy {
+    /// `"<hash>-<size>"`
+    #[default]
+    Strong,
+
+    /// `W/"<hash>-<size>"`
+    Weak,
+}
+
+impl EtagPolicy {
+    /// Parse a policy name (`strong` or `weak`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "strong" => Some(EtagPolicy::Strong),
+            "weak" => Some(EtagPolicy::Weak),
+            _ => None,
+        }
+    }
+
+    /// Format an opaque tag as an ETag
+    pub fn format(&self, opaque_tag: &str) -> String {
+        match self {
+            EtagPolicy::Strong => format!("\"{}\"", opaque_tag),
+            EtagPolicy::Weak => format!("W/\"{}\"", opaque_tag),
+        }
+    }
+
+    /// ETag of a resource, if it has one
+    ///
+    /// Directories have no content hash and therefore no ETag.
+    pub fn etag_for(&self, metadata: &FileMetadata) -> Option<String> {
+        if metadata.is_directory {
+            return None;
+        }
+        metadata
+            .content_hash
+            .as_deref()
+            .map(|hash| self.format(&format!("{}-{}", hash, metadata.size)))
+    }
+}
+
+/// Strip the weak indicator from an entity tag
+fn opaque_tag(etag: &str) -> &str {
+    let etag = etag.trim();
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+/// Weak comparison of two entity tags (RFC 9110 §8.8.3.2)
+///
+/// Tags match if their opaque parts are equal, regardless of either being weak.
+pub fn weak_eq(a: &str, b: &str) -> bool {
+    opaque_tag(a) == opaque_tag(b)
+}
+
+/// Evaluate an `If-None-Match` header value against the current ETag
+///
+/// Returns true if any listed tag matches by weak comparison, or the list is `*`.
+pub fn if_none_match(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || weak_eq(candidate, etag))
+}
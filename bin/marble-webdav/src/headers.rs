@@ -7,4 +7,11 @@ pub static DAV: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("dav"));
 pub static DEPTH: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("depth"));
 pub static LOCK_TOKEN: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("lock-token"));
 pub static TIMEOUT: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("timeout"));
-pub static OVERWRITE: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("overwrite"));
\ No newline at end of file
+pub static OVERWRITE: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("overwrite"));
+
+// Request safety headers
+pub static IDEMPOTENCY_KEY: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("idempotency-key"));
+
+// Marble extension headers
+pub static MARBLE_OTP: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("x-marble-otp"));
+pub static MARBLE_DEDUP: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("x-marble-dedup"));
@@ -0,0 +1,243 @@
+//! Idempotency keys for mutating requests
+//!
+//! A client that retries a PUT, MKCOL or DELETE after a timeout cannot tell
+//! whether the first attempt was applied. When the request carries an
+//! `Idempotency-Key` header, the server records the response under that key
+//! and answers a repeat with the recorded response instead of executing the
+//! operation again. Records expire after a configurable window.
+//!
+//! A key is reserved before the request executes, so a retry that arrives
+//! while the first attempt is still running is refused with `409 Conflict`
+//! rather than executing a second time. The reservation is released if the
+//! request fails or is cancelled. The table is bounded in entries, and
+//! responses with large bodies are not kept.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::{HeaderMap, Response, StatusCode};
+use uuid::Uuid;
+
+use crate::dav_handler::DavResponse;
+use crate::error::Error;
+
+/// Default time a recorded response is kept
+pub const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Default number of keys tracked at once
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Default size of the largest response body that is recorded
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Whether a method honours the `Idempotency-Key` header
+pub fn is_idempotency_method(method: DavMethod) -> bool {
+    matches!(method, DavMethod::Put | DavMethod::MkCol | DavMethod::Delete)
+}
+
+/// A recorded response
+struct RecordedResponse {
+    /// Response status
+    status: StatusCode,
+    /// Response headers
+    headers: HeaderMap,
+    /// Response body
+    body: Bytes,
+}
+
+/// A key that is reserved or answered
+struct IdempotencyRecord {
+    /// Method of the original request
+    method: DavMethod,
+    /// Normalized path of the original request
+    path: String,
+    /// The response, or `None` while the original request is executing
+    response: Option<RecordedResponse>,
+    /// When the key was reserved
+    recorded_at: Instant,
+    /// Reservation number, identifying the request that made the record
+    sequence: u64,
+}
+
+/// Records keyed by tenant and idempotency key
+#[derive(Default)]
+struct Records {
+    entries: HashMap<(Uuid, String), IdempotencyRecord>,
+
+    /// Reservation number of the next record
+    next_sequence: u64,
+}
+
+/// What to do with a request carrying an idempotency key
+pub enum Begin<'a> {
+    /// The request was already answered; send the recorded response
+    Replay(DavResponse),
+
+    /// Execute the request, then complete the reservation with its response
+    Execute(Reservation<'a>),
+}
+
+/// A reserved idempotency key
+///
+/// Dropping the reservation without completing it releases the key, so a
+/// failed or cancelled request can be retried.
+pub struct Reservation<'a> {
+    cache: &'a IdempotencyCache,
+    key: (Uuid, String),
+    sequence: u64,
+}
+
+impl Reservation<'_> {
+    /// Record the response to the reserved request
+    ///
+    /// Responses with bodies above the size limit are not kept, and the key is
+    /// released instead.
+    pub fn complete(self, response: &DavResponse) {
+        if response.body().len() > self.cache.max_body_bytes {
+            return;
+        }
+
+        let mut records = self.cache.records.lock().unwrap();
+        if let Some(record) = records.entries.get_mut(&self.key) {
+            if record.sequence == self.sequence {
+                record.response = Some(RecordedResponse {
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    body: response.body().clone(),
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut records = self.cache.records.lock().unwrap();
+        let pending = records
+            .entries
+            .get(&self.key)
+            .is_some_and(|record| record.sequence == self.sequence && record.response.is_none());
+        if pending {
+            records.entries.remove(&self.key);
+        }
+    }
+}
+
+/// In-memory table of recorded responses, keyed by tenant and idempotency key
+pub struct IdempotencyCache {
+    records: Mutex<Records>,
+    window: Duration,
+
+    /// Upper bound on the number of keys tracked
+    max_entries: usize,
+
+    /// Upper bound on the size of a recorded response body
+    max_body_bytes: usize,
+}
+
+impl IdempotencyCache {
+    /// Create a cache keeping responses for `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            records: Mutex::new(Records::default()),
+            window,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    /// Track at most `max_entries` keys at once
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Record only responses with bodies of at most `max_body_bytes`
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Replay the recorded response for a key, or reserve the key
+    ///
+    /// Reusing a key for a different method or path is a client error, and a
+    /// key whose original request is still executing is a conflict. When the
+    /// table is full of requests in flight, the request is refused.
+    pub async fn begin(
+        &self,
+        tenant_id: Uuid,
+        key: &str,
+        method: DavMethod,
+        path: &str,
+    ) -> Result<Begin<'_>, Error> {
+        let mut records = self.records.lock().unwrap();
+        let window = self.window;
+        records.entries.retain(|_, record| record.recorded_at.elapsed() < window);
+
+        let entry_key = (tenant_id, key.to_string());
+        if let Some(record) = records.entries.get(&entry_key) {
+            if record.method != method || record.path != path {
+                return Err(Error::WebDav(format!(
+                    "Idempotency-Key {} was already used for a different request",
+                    key
+                )));
+            }
+
+            let Some(recorded) = &record.response else {
+                return Err(Error::Conflict(format!(
+                    "Idempotency-Key {} is still being processed",
+                    key
+                )));
+            };
+
+            let mut response = Response::new(recorded.body.clone());
+            *response.status_mut() = recorded.status;
+            *response.headers_mut() = recorded.headers.clone();
+            return Ok(Begin::Replay(response));
+        }
+
+        // Make room by dropping the oldest answered keys; keys in flight stay reserved
+        while records.entries.len() >= self.max_entries {
+            let oldest = records
+                .entries
+                .iter()
+                .filter(|(_, record)| record.response.is_some())
+                .min_by_key(|(_, record)| record.sequence)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => {
+                    records.entries.remove(&oldest);
+                }
+                None => return Err(Error::TooManyRequests(tenant_id)),
+            }
+        }
+
+        let sequence = records.next_sequence;
+        records.next_sequence += 1;
+        records.entries.insert(
+            entry_key.clone(),
+            IdempotencyRecord {
+                method,
+                path: path.to_string(),
+                response: None,
+                recorded_at: Instant::now(),
+                sequence,
+            },
+        );
+
+        Ok(Begin::Execute(Reservation {
+            cache: self,
+            key: entry_key,
+            sequence,
+        }))
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_WINDOW)
+    }
+}
@@ -0,0 +1,277 @@
+//! The WebDAV `If` header (RFC 4918 §10.4)
+//!
+//! The header holds one or more lists of conditions, optionally tagged with the
+//! resource they apply to:
+//!
+//! ```text
+//! If = ( 1*No-tag-list | 1*Tagged-list )
+//! Tagged-list = Resource-Tag 1*List
+//! List = "(" 1*Condition ")"
+//! Condition = ["Not"] ( State-token | "[" entity-tag "]" )
+//! ```
+//!
+//! A list is true if all of its conditions are true, and the header is true if
+//! any list applying to the resource is true. Lock tokens listed in the header
+//! are also how a client proves it holds the lock on a resource.
+
+use crate::error::Error;
+use crate::etag::weak_eq;
+
+/// A parsed `If` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfHeader {
+    /// All lists in header order
+    pub lists: Vec<IfList>,
+}
+
+/// A parenthesized list of conditions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfList {
+    /// Resource the list applies to, `None` for untagged lists
+    pub resource: Option<String>,
+
+    /// Conditions that must all hold
+    pub conditions: Vec<Condition>,
+}
+
+/// A single, possibly negated, condition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    /// Whether the condition is prefixed with `Not`
+    pub negated: bool,
+
+    /// What is tested
+    pub kind: ConditionKind,
+}
+
+/// What a condition tests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionKind {
+    /// The resource is locked with this token, e.g. `urn:uuid:...`
+    StateToken(String),
+
+    /// The resource has this entity tag, e.g. `"abc"` or `W/"abc"`
+    ETag(String),
+}
+
+/// State of a resource that conditions are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct ResourceState<'a> {
+    /// Tokens of the locks on the resource
+    pub lock_tokens: &'a [String],
+
+    /// Current entity tag, `None` if the resource does not exist or has none
+    pub etag: Option<&'a str>,
+}
+
+impl Condition {
+    /// Evaluate the condition against a resource
+    fn holds(&self, state: &ResourceState) -> bool {
+        let matched = match &self.kind {
+            ConditionKind::StateToken(token) => state.lock_tokens.iter().any(|t| t == token),
+            ConditionKind::ETag(etag) => state.etag.is_some_and(|current| weak_eq(etag, current)),
+        };
+        matched != self.negated
+    }
+}
+
+impl IfHeader {
+    /// Parse an `If` header value
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        Parser { input: value, pos: 0 }
+            .parse()
+            .map_err(|reason| Error::WebDav(format!("Invalid If header: {}", reason)))
+    }
+
+    /// Evaluate the header for a resource
+    ///
+    /// `applies` decides whether a resource tag names the resource. Untagged
+    /// lists always apply. A header with no applicable list imposes no
+    /// condition.
+    pub fn evaluate(&self, applies: impl Fn(&str) -> bool, state: &ResourceState) -> bool {
+        let mut applicable = self
+            .lists
+            .iter()
+            .filter(|list| list.resource.as_deref().is_none_or(&applies))
+            .peekable();
+
+        if applicable.peek().is_none() {
+            return true;
+        }
+
+        applicable.any(|list| list.conditions.iter().all(|c| c.holds(state)))
+    }
+
+    /// Whether the header submits a lock token
+    ///
+    /// Any non-negated state token counts, wherever it appears.
+    pub fn submits_token(&self, token: &str) -> bool {
+        self.lists.iter().flat_map(|list| &list.conditions).any(|c| {
+            !c.negated && matches!(&c.kind, ConditionKind::StateToken(t) if t == token)
+        })
+    }
+}
+
+/// Whether a resource tag (an absolute URL or absolute path) names a storage path
+///
+/// The scheme and authority are ignored, and the tag matches if its path ends
+/// with the storage path, so servers mounted below a prefix work too. The tag
+/// path is percent-encoded like a request target, so it goes through the same
+/// `normalize` as the request path; a tag it rejects matches nothing.
+pub fn tag_matches(tag: &str, path: &str, normalize: impl Fn(&str) -> Result<String, Error>) -> bool {
+    let tag_path = match tag.find("://") {
+        Some(scheme_end) => {
+            let rest = &tag[scheme_end + 3..];
+            rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+        }
+        None => tag,
+    };
+    let Ok(tag_path) = normalize(tag_path) else {
+        return false;
+    };
+    let path = path.trim_matches('/');
+
+    if path.is_empty() || path == "." {
+        return tag_path == ".";
+    }
+
+    tag_path == path || tag_path.ends_with(&format!("/{}", path))
+}
+
+/// Recursive descent parser over the header value
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<IfHeader, String> {
+        let mut lists = Vec::new();
+        let mut tagged = None;
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some('<') => {
+                    // All lists must be tagged or all untagged
+                    if tagged == Some(false) {
+                        return Err(self.error("resource tag after untagged list"));
+                    }
+                    tagged = Some(true);
+
+                    let resource = self.delimited('<', '>')?;
+                    self.skip_whitespace();
+                    if self.peek() != Some('(') {
+                        return Err(self.error("resource tag without a list"));
+                    }
+                    while self.peek() == Some('(') {
+                        lists.push(IfList {
+                            resource: Some(resource.clone()),
+                            conditions: self.list()?,
+                        });
+                        self.skip_whitespace();
+                    }
+                }
+                Some('(') => {
+                    if tagged == Some(true) {
+                        return Err(self.error("untagged list after resource tag"));
+                    }
+                    tagged = Some(false);
+
+                    lists.push(IfList {
+                        resource: None,
+                        conditions: self.list()?,
+                    });
+                }
+                Some(_) => return Err(self.error("expected '<' or '('")),
+            }
+        }
+
+        if lists.is_empty() {
+            return Err(self.error("no lists"));
+        }
+
+        Ok(IfHeader { lists })
+    }
+
+    /// `"(" 1*Condition ")"`
+    fn list(&mut self) -> Result<Vec<Condition>, String> {
+        self.expect('(')?;
+        let mut conditions = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(')') => {
+                    self.pos += 1;
+                    break;
+                }
+                None => return Err(self.error("unterminated list")),
+                Some(_) => conditions.push(self.condition()?),
+            }
+        }
+
+        if conditions.is_empty() {
+            return Err(self.error("empty list"));
+        }
+
+        Ok(conditions)
+    }
+
+    /// `["Not"] ( State-token | "[" entity-tag "]" )`
+    fn condition(&mut self) -> Result<Condition, String> {
+        let rest = &self.input[self.pos..];
+        let negated = rest.get(..3).is_some_and(|word| word.eq_ignore_ascii_case("not"));
+        if negated {
+            self.pos += 3;
+            self.skip_whitespace();
+        }
+
+        let kind = match self.peek() {
+            Some('<') => ConditionKind::StateToken(self.delimited('<', '>')?),
+            Some('[') => ConditionKind::ETag(self.delimited('[', ']')?),
+            _ => return Err(self.error("expected state token or entity tag")),
+        };
+
+        Ok(Condition { negated, kind })
+    }
+
+    /// Text between `open` and `close`, trimmed
+    fn delimited(&mut self, open: char, close: char) -> Result<String, String> {
+        self.expect(open)?;
+        let rest = &self.input[self.pos..];
+        let end = rest
+            .find(close)
+            .ok_or_else(|| self.error(&format!("missing '{}'", close)))?;
+        let value = rest[..end].trim().to_string();
+        self.pos += end + close.len_utf8();
+
+        if value.is_empty() {
+            return Err(self.error("empty token"));
+        }
+
+        Ok(value)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += c.len_utf8();
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, reason: &str) -> String {
+        format!("{} at {}", reason, self.pos)
+    }
+}
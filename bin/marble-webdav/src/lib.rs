@@ -3,12 +3,25 @@ pub mod api;
 
 // Implementation modules
 pub mod auth;
+pub mod capabilities;
+pub mod cli;
+pub mod config;
 mod dav_handler;
+pub mod degraded;
 pub mod error;
+pub mod etag;
 pub mod headers;
+pub mod idempotency;
+pub mod if_header;
 pub mod lock;
+pub mod metadata_cache;
 mod operations;
+pub mod render;
 mod server;
+pub mod startup;
+pub mod stats;
+pub mod usage_cache;
+pub mod version;
 
 // Test modules (only compiled in test mode)
 #[cfg(test)]
@@ -17,7 +30,12 @@ pub mod tests;
 // Re-export public API
 pub use api::*;
 pub use error::Error;
-pub use server::create_webdav_server;
+pub use config::WebDavConfig;
+pub use server::{
+    create_webdav_server, create_webdav_server_with_config, create_webdav_server_with_policy,
+    create_webdav_server_with_stats,
+};
+pub use stats::RequestStats;
 
 // Type re-export
 pub use dav_handler::DavResponse;
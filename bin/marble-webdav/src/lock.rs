@@ -1,17 +1,31 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use marble_core::clock::{system_clock, ClockRef};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::runtime::Handle;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
-use crate::api::{LockInfo, LockManager};
+use crate::api::{LockInfo, LockManager, LockManagerRef, LockScope};
 use crate::error::LockError;
 
+/// How often expired locks are reaped if not configured
+pub const DEFAULT_REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Active locks by tenant and path
+type LockTable = HashMap<(Uuid, String), Vec<LockInfo>>;
+
 /// In-memory lock manager implementation
+///
+/// A path holds either one exclusive lock or any number of shared locks.
 pub struct InMemoryLockManager {
-    locks: Arc<RwLock<HashMap<(Uuid, String), LockInfo>>>,
+    locks: Arc<RwLock<LockTable>>,
+    clock: ClockRef,
 }
 
 impl InMemoryLockManager {
@@ -19,15 +33,69 @@ impl InMemoryLockManager {
     pub fn new() -> Self {
         Self {
             locks: Arc::new(RwLock::new(HashMap::new())),
+            clock: system_clock(),
         }
     }
     
-    /// Clean expired locks
-    async fn clean_expired_locks(&self) {
+    /// Use `clock` to timestamp and expire locks
+    pub fn with_clock(mut self, clock: ClockRef) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Clean expired locks, returning how many path locks were removed
+    async fn clean_expired_locks(&self) -> usize {
+        let mut locks = self.locks.write().await;
+        let now = self.clock.now();
+        
+        let mut removed = 0;
+        locks.retain(|_, path_locks| {
+            let before = path_locks.len();
+            path_locks.retain(|lock_info| lock_info.expires_at > now);
+            removed += before - path_locks.len();
+            !path_locks.is_empty()
+        });
+        removed
+    }
+    
+    /// Grant a lock on all paths at once, or on none if any of them conflicts
+    ///
+    /// `template` carries everything but the path of each lock.
+    async fn insert_locks(&self, paths: &[String], template: LockInfo) -> Result<(), LockError> {
+        // Clean expired locks first
+        self.clean_expired_locks().await;
+        
         let mut locks = self.locks.write().await;
-        let now = Utc::now();
         
-        locks.retain(|_, lock_info| lock_info.expires_at > now);
+        // Check for conflicting locks held by someone else
+        let conflict = paths.iter().any(|path| {
+            locks
+                .get(&(template.tenant_id, path.clone()))
+                .into_iter()
+                .flatten()
+                .any(|existing| existing.token != template.token && !template.scope.compatible_with(existing.scope))
+        });
+        if conflict {
+            return Err(LockError::ResourceLocked);
+        }
+        
+        // Create or update locks
+        for path in paths {
+            let path_locks = locks.entry((template.tenant_id, path.clone())).or_default();
+            path_locks.retain(|existing| existing.token != template.token);
+            path_locks.push(LockInfo {
+                path: path.clone(),
+                ..template.clone()
+            });
+        }
+        
+        Ok(())
+    }
+    
+    /// Expiration time of a lock acquired now
+    fn expires_after(&self, timeout: Duration) -> Result<DateTime<Utc>, LockError> {
+        Ok(self.clock.now() + ChronoDuration::from_std(timeout)
+            .map_err(|e| LockError::Internal(format!("Invalid duration: {}", e)))?)
     }
 }
 
@@ -39,35 +107,48 @@ impl LockManager for InMemoryLockManager {
         path: &str,
         timeout: Duration,
         token: &str,
+        owner: Option<&str>,
+        scope: LockScope,
     ) -> Result<(), LockError> {
-        // Clean expired locks first
-        self.clean_expired_locks().await;
-        
-        let mut locks = self.locks.write().await;
-        let key = (*tenant_id, path.to_string());
-        
-        // Check if already locked by someone else
-        if let Some(existing_lock) = locks.get(&key) {
-            if existing_lock.token != token && existing_lock.expires_at > Utc::now() {
-                return Err(LockError::ResourceLocked);
-            }
-        }
-        
-        // Calculate expiration time
-        let expires_at = Utc::now() + ChronoDuration::from_std(timeout)
-            .map_err(|e| LockError::Internal(format!("Invalid duration: {}", e)))?;
-        
-        // Create or update lock
-        let lock_info = LockInfo {
+        let template = LockInfo {
             token: token.to_string(),
             tenant_id: *tenant_id,
             path: path.to_string(),
-            expires_at,
+            root: path.to_string(),
+            recursive: false,
+            expires_at: self.expires_after(timeout)?,
+            owner: owner.map(str::to_string),
+            scope,
         };
         
-        locks.insert(key, lock_info);
+        self.insert_locks(&[path.to_string()], template).await
+    }
+
+    async fn lock_paths(
+        &self,
+        tenant_id: &Uuid,
+        paths: &[String],
+        timeout: Duration,
+        token: &str,
+        owner: Option<&str>,
+        scope: LockScope,
+    ) -> Result<(), LockError> {
+        let Some(root) = paths.first() else {
+            return Ok(());
+        };
         
-        Ok(())
+        let template = LockInfo {
+            token: token.to_string(),
+            tenant_id: *tenant_id,
+            path: root.clone(),
+            root: root.clone(),
+            recursive: true,
+            expires_at: self.expires_after(timeout)?,
+            owner: owner.map(str::to_string),
+            scope,
+        };
+        
+        self.insert_locks(paths, template).await
     }
 
     async fn unlock(
@@ -80,13 +161,18 @@ impl LockManager for InMemoryLockManager {
         let key = (*tenant_id, path.to_string());
         
         // Check if locked and verify token
-        if let Some(lock_info) = locks.get(&key) {
-            if lock_info.token != token {
+        if let Some(path_locks) = locks.get(&key) {
+            if !path_locks.iter().any(|lock_info| lock_info.token == token) {
                 return Err(LockError::InvalidLockToken);
             }
             
-            // Remove lock
-            locks.remove(&key);
+            // Remove the lock from every path it covers
+            locks.retain(|(lock_tenant, _), path_locks| {
+                if lock_tenant == tenant_id {
+                    path_locks.retain(|lock_info| lock_info.token != token);
+                }
+                !path_locks.is_empty()
+            });
             return Ok(());
         }
         
@@ -94,24 +180,78 @@ impl LockManager for InMemoryLockManager {
         Ok(())
     }
 
-    async fn is_locked(
+    async fn reap_expired(&self) -> Result<usize, LockError> {
+        Ok(self.clean_expired_locks().await)
+    }
+    
+    fn clock(&self) -> ClockRef {
+        self.clock.clone()
+    }
+    
+    async fn active_locks(
         &self,
         tenant_id: &Uuid,
         path: &str,
-    ) -> Result<Option<LockInfo>, LockError> {
+    ) -> Result<Vec<LockInfo>, LockError> {
         // Clean expired locks first
         self.clean_expired_locks().await;
         
         let locks = self.locks.read().await;
         let key = (*tenant_id, path.to_string());
         
-        // Check if locked
-        if let Some(lock_info) = locks.get(&key) {
-            // Clone lock info to return
-            return Ok(Some(lock_info.clone()));
-        }
+        Ok(locks.get(&key).cloned().unwrap_or_default())
+    }
+    
+    async fn locks_below(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+    ) -> Result<Vec<LockInfo>, LockError> {
+        self.clean_expired_locks().await;
+        
+        // Paths are relative to the tenant root, which is `.`
+        let prefix = format!("{}/", path);
+        let below = |locked: &str| {
+            if path == "." {
+                locked != "."
+            } else {
+                locked.starts_with(&prefix)
+            }
+        };
         
-        // Not locked
-        Ok(None)
+        let locks = self.locks.read().await;
+        Ok(locks
+            .iter()
+            .filter(|((lock_tenant, locked), _)| lock_tenant == tenant_id && below(locked))
+            .flat_map(|(_, path_locks)| path_locks.iter().cloned())
+            .collect())
     }
 }
+
+/// Periodically reap expired locks in the background
+///
+/// The task holds only a weak reference and ends once the lock manager is
+/// dropped. Returns `None` when called outside a Tokio runtime.
+pub fn spawn_reaper(lock_manager: &LockManagerRef, interval: Duration) -> Option<JoinHandle<()>> {
+    let runtime = Handle::try_current().ok()?;
+    let lock_manager = Arc::downgrade(lock_manager);
+    
+    Some(runtime.spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately; nothing has expired yet
+        ticks.tick().await;
+        
+        loop {
+            ticks.tick().await;
+            let Some(lock_manager) = lock_manager.upgrade() else {
+                break;
+            };
+            match lock_manager.reap_expired().await {
+                Ok(0) => {}
+                Ok(removed) => debug!("Reaped {} expired locks", removed),
+                Err(e) => warn!("Failed to reap expired locks: {}", e),
+            }
+        }
+    }))
+}
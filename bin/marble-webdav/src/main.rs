@@ -1,8 +1,12 @@
 use std::sync::Arc;
 use marble_db::auth::DatabaseAuthService as DbAuthService;
+use clap::Parser;
 use marble_webdav::auth::WebDavAuthService;
+use marble_webdav::cli::{self, Cli};
 use marble_webdav::lock::InMemoryLockManager;
-use marble_webdav::create_webdav_server;
+use marble_webdav::startup;
+use marble_webdav::version::BuildInfo;
+use marble_webdav::{create_webdav_server_with_stats, RequestStats, WebDavConfig};
 use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 
@@ -15,6 +19,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables
     dotenv().ok();
     
+    // Parse command line arguments
+    let cli = Cli::parse();
+    
     // Initialize logging
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::DEBUG)
@@ -22,7 +29,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::subscriber::set_global_default(subscriber)
         .expect("Failed to set tracing subscriber");
 
-    info!("Starting Marble WebDAV Server");
+    info!("Starting {}", BuildInfo::current().banner());
     
     // Configure the server
     let server_addr = std::env::var("WEBDAV_ADDR")
@@ -35,6 +42,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_config = marble_db::config::DatabaseConfig::from_env();
     let db_pool = Arc::new(marble_db::create_pool(db_config).await?);
     
+    // Run a maintenance command instead of the server if one was given
+    if let Some(command) = cli.command {
+        return cli::run(command, db_pool).await;
+    }
+    
+    // Refuse to serve against an out of date schema
+    let config = WebDavConfig::from_env();
+    startup::ensure_schema(&db_pool, config.auto_migrate).await?;
+    
+    // Warn when the connection pool stays saturated
+    tokio::spawn(marble_db::pool_stats::monitor_pool(
+        (*db_pool).clone(),
+        marble_db::pool_stats::PoolMonitorConfig::default(),
+    ));
+    
     // Initialize auth service
     let db_auth_service = Arc::new(DbAuthService::from_pool(db_pool.clone()));
     let auth_service = Arc::new(WebDavAuthService::new(db_auth_service));
@@ -47,10 +69,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let tenant_storage: TenantStorageRef = Arc::new(marble_storage::MockTenantStorage::new());
     
     // Create WebDAV server
-    let app = create_webdav_server(
-        tenant_storage,
+    let stats = Arc::new(RequestStats::new());
+    let app = create_webdav_server_with_stats(
+        tenant_storage.clone(),
         auth_service,
-        lock_manager
+        lock_manager,
+        config,
+        stats.clone()
     );
     
     // Start the server
@@ -61,8 +86,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Start the server (using TcpListener directly since axum 0.8.3 doesn't have Server::bind)
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, router).await?;
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
     
     info!("Marble WebDAV Server - Shutting down");
+    stats.log_summary();
+    
+    // In-flight requests have finished, release caches and connections
+    tenant_storage.shutdown().await;
+    db_pool.close().await;
     Ok(())
 }
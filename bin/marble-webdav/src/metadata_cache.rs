@@ -0,0 +1,104 @@
+//! Short-lived cache of file metadata between HEAD and GET
+//!
+//! Many clients send a HEAD immediately followed by a GET for the same file.
+//! Both need the size and content hash for `Content-Length` and `ETag`, so the
+//! metadata resolved by the first request is kept for a few seconds and reused
+//! by the second. Each entry carries the content hash it was resolved for.
+//! Entries of a tenant are dropped whenever that tenant modifies anything
+//! through this server. Writes made through other servers cannot be seen
+//! here, so entries also expire after a short TTL, which bounds how long such
+//! a write goes unnoticed; a TTL of zero turns the cache off.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use marble_core::clock::{system_clock, ClockRef};
+use marble_storage::api::{FileMetadata, TenantStorageRef};
+use marble_storage::StorageError;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::error::Error;
+
+/// Default time resolved metadata is reused
+pub const DEFAULT_METADATA_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Resolved metadata and the time it was resolved, keyed by tenant and path
+type Entries = HashMap<(Uuid, String), (FileMetadata, DateTime<Utc>)>;
+
+/// Metadata of files resolved by recent requests, keyed by tenant and path
+pub struct MetadataCache {
+    entries: RwLock<Entries>,
+    ttl: Duration,
+    clock: ClockRef,
+}
+
+impl MetadataCache {
+    /// Create a cache keeping metadata for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+            clock: system_clock(),
+        }
+    }
+
+    /// Use `clock` to timestamp and expire entries
+    pub fn with_clock(mut self, clock: ClockRef) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Metadata of a resource, from the cache or storage
+    ///
+    /// Only files are cached; directories are always resolved from storage.
+    pub async fn resolve(
+        &self,
+        tenant_storage: &TenantStorageRef,
+        tenant_id: Uuid,
+        path: &str,
+    ) -> Result<FileMetadata, Error> {
+        let key = (tenant_id, path.to_string());
+        let now = self.clock.now();
+        if let Some((metadata, resolved_at)) = self.entries.read().await.get(&key) {
+            if self.is_fresh(*resolved_at, now) {
+                return Ok(metadata.clone());
+            }
+        }
+
+        if !tenant_storage.exists(&tenant_id, path).await? {
+            return Err(Error::Storage(StorageError::NotFound(path.to_string())));
+        }
+        let metadata = tenant_storage.metadata(&tenant_id, path).await?;
+
+        if !metadata.is_directory && !self.ttl.is_zero() {
+            let mut entries = self.entries.write().await;
+            entries.retain(|_, (_, resolved_at)| self.is_fresh(*resolved_at, now));
+            entries.insert(key, (metadata.clone(), now));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Whether an entry resolved at `resolved_at` is still within the TTL at `now`
+    ///
+    /// An entry stamped after `now`, as when the clock is set back, counts as fresh.
+    fn is_fresh(&self, resolved_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        (now - resolved_at).to_std().map(|age| age < self.ttl).unwrap_or(true)
+    }
+
+    /// Drop all entries of a tenant
+    pub async fn invalidate_tenant(&self, tenant_id: Uuid) {
+        self.entries
+            .write()
+            .await
+            .retain(|(tenant, _), _| *tenant != tenant_id);
+    }
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_METADATA_CACHE_TTL)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::api::LockManagerRef;
+use crate::error::{Error, LockError};
+use crate::dav_handler::DavResponse;
+use crate::operations::utils::parse_path_list;
+use crate::operations::preconditions::{check_preconditions, parse_if_header};
+use bytes::Bytes;
+use http::{HeaderMap, Response, StatusCode};
+use marble_storage::api::TenantStorageRef;
+use std::collections::BTreeMap;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Result reported for a path that was deleted
+const DELETED: &str = "deleted";
+
+/// Result reported for a path that does not exist
+const NOT_FOUND: &str = "not found";
+
+/// Result reported for a path locked without its token in the `If` header
+const LOCKED: &str = "locked";
+
+/// Result reported for a path whose `If` header condition failed
+const PRECONDITION_FAILED: &str = "precondition failed";
+
+/// Handle a batch delete of the paths listed in a JSON array
+///
+/// Each path is checked like a single DELETE: the request's `If` header must
+/// hold for it and submit the token of any lock on it. The deletes that pass
+/// run in one storage call, so they are applied together. Paths that do not
+/// exist, are locked or fail the `If` header are reported without aborting
+/// the others. The response maps each listed path to its result.
+/// Lists longer than `MAX_BATCH_PATHS` are rejected.
+pub async fn handle_batch_delete(
+    tenant_storage: &TenantStorageRef,
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    headers: &HeaderMap,
+    body: &Bytes,
+    normalize: impl Fn(&str) -> Result<String, Error>,
+) -> Result<DavResponse, Error> {
+    let paths = parse_path_list(body, "batch-delete")?;
+    
+    debug!("Batch delete of {} paths for tenant: {}", paths.len(), tenant_id);
+    
+    let if_header = parse_if_header(headers)?;
+    let mut results: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut permitted = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let normalized = normalize(path)?;
+        match check_preconditions(tenant_storage, lock_manager, tenant_id, &normalized, if_header.as_ref(), &normalize).await {
+            Ok(()) => permitted.push((path.as_str(), normalized)),
+            Err(Error::Lock(LockError::TokenNotSubmitted { .. })) => {
+                results.insert(path, LOCKED);
+            }
+            Err(Error::PreconditionFailed(_)) => {
+                results.insert(path, PRECONDITION_FAILED);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    
+    let normalized: Vec<String> = permitted.iter().map(|(_, normalized)| normalized.clone()).collect();
+    let deleted = tenant_storage.delete_many(&tenant_id, &normalized).await?;
+    for ((path, _), deleted) in permitted.into_iter().zip(deleted) {
+        results.insert(path, if deleted { DELETED } else { NOT_FOUND });
+    }
+    
+    let body = serde_json::to_vec(&results)
+        .map_err(|e| Error::Internal(format!("Failed to encode results: {}", e)))?;
+    
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body))
+        .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
+    
+    Ok(response)
+}
@@ -0,0 +1,41 @@
+use crate::error::Error;
+use crate::dav_handler::DavResponse;
+use bytes::Bytes;
+use http::{Response, StatusCode};
+use marble_storage::api::TenantStorageRef;
+use marble_storage::hash::is_valid_hash;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Cache policy for content-addressed blobs, which never change
+const IMMUTABLE_CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+
+/// Handle GET of a blob by its content hash
+///
+/// The tenant must reference the hash from one of its files, so tenants cannot
+/// read each other's content by guessing hashes. Malformed hashes are refused
+/// with `400 Bad Request`.
+pub async fn handle_get_blob(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    hash: &str
+) -> Result<DavResponse, Error> {
+    debug!("Blob request for hash: {} by tenant: {}", hash, tenant_id);
+    
+    if !is_valid_hash(hash) {
+        return Err(Error::WebDav("Malformed blob hash: expected 43 URL-safe base64 characters".to_string()));
+    }
+    
+    let content = tenant_storage.read_by_hash(&tenant_id, hash).await?;
+    
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/octet-stream")
+        .header(http::header::CONTENT_LENGTH, content.len().to_string())
+        .header(http::header::ETAG, format!("\"{}\"", hash))
+        .header(http::header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL)
+        .body(Bytes::from(content))
+        .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
+    
+    Ok(response)
+}
@@ -5,12 +5,12 @@ use crate::operations::utils::get_parent_path;
 use bytes::Bytes;
 use http::{HeaderMap, Response, StatusCode};
 use marble_storage::api::TenantStorageRef;
-use marble_storage::StorageError;
+use marble_storage::{EntryKind, StorageError};
 use tracing::debug;
 use uuid::Uuid;
 
 /// Extract destination path from headers
-pub fn extract_destination(headers: &HeaderMap, normalize_fn: impl Fn(&str) -> String) -> Result<String, Error> {
+pub fn extract_destination(headers: &HeaderMap, normalize_fn: impl Fn(&str) -> Result<String, Error>) -> Result<String, Error> {
     // Extract the Destination header
     let destination = headers
         .get(&*DESTINATION)
@@ -26,7 +26,7 @@ pub fn extract_destination(headers: &HeaderMap, normalize_fn: impl Fn(&str) -> S
     let path = destination_uri.path();
     
     // Normalize the path
-    Ok(normalize_fn(path))
+    normalize_fn(path)
 }
 
 /// Copy a file from source to destination
@@ -86,18 +86,16 @@ pub async fn copy_directory(
     overwrite: bool
 ) -> Result<DavResponse, Error> {
     // Check if destination exists
-    let dest_exists = tenant_storage.exists(&tenant_id, destination).await?;
+    let dest_kind = tenant_storage.entry_kind(&tenant_id, destination).await?;
+    let dest_exists = dest_kind.is_some();
     
     // If destination exists but is not a directory, handle overwrite
-    if dest_exists {
-        let dest_metadata = tenant_storage.metadata(&tenant_id, destination).await?;
-        if !dest_metadata.is_directory {
-            if overwrite {
-                // Delete the file to replace with directory
-                tenant_storage.delete(&tenant_id, destination).await?;
-            } else {
-                return Err(Error::WebDav("Destination exists but is not a directory".to_string()));
-            }
+    if dest_kind == Some(EntryKind::File) {
+        if overwrite {
+            // Delete the file to replace with directory
+            tenant_storage.delete(&tenant_id, destination).await?;
+        } else {
+            return Err(Error::WebDav("Destination exists but is not a directory".to_string()));
         }
     }
     
@@ -121,10 +119,10 @@ pub async fn copy_directory(
             format!("{}/{}", destination, entry)
         };
         
-        // Get metadata to determine if it's a file or directory
-        let entry_metadata = tenant_storage.metadata(&tenant_id, &source_path).await?;
+        // Determine if it's a file or directory
+        let entry_kind = tenant_storage.entry_kind(&tenant_id, &source_path).await?;
         
-        if entry_metadata.is_directory {
+        if entry_kind == Some(EntryKind::Directory) {
             // Recursively copy the directory - use Box::pin to avoid infinite recursion
             Box::pin(copy_directory(tenant_storage, tenant_id, &source_path, &dest_path, overwrite)).await?;
         } else {
@@ -154,15 +152,15 @@ pub async fn handle_copy(
     tenant_id: Uuid, 
     path: &str, 
     headers: HeaderMap,
-    normalize_fn: impl Fn(&str) -> String
+    normalize_fn: impl Fn(&str) -> Result<String, Error>
 ) -> Result<DavResponse, Error> {
     debug!("COPY request for path: {} by tenant: {}", path, tenant_id);
     
-    // Check if source exists
-    let exists = tenant_storage.exists(&tenant_id, path).await?;
-    if !exists {
-        return Err(Error::Storage(StorageError::NotFound(path.to_string())));
-    }
+    // Check if source exists, and whether it is a collection
+    let source_kind = tenant_storage
+        .entry_kind(&tenant_id, path)
+        .await?
+        .ok_or_else(|| Error::Storage(StorageError::NotFound(path.to_string())))?;
     
     // Extract destination from headers
     let destination = extract_destination(&headers, normalize_fn)?;
@@ -179,14 +177,10 @@ pub async fn handle_copy(
         
     // If destination exists and overwrite is false, return 412 Precondition Failed
     if dest_exists && !overwrite {
-        return Err(Error::WebDav("Destination already exists and overwrite is false".to_string()));
+        return Err(Error::PreconditionFailed("Destination already exists and overwrite is false".to_string()));
     }
     
-    // Check if source is a directory
-    let source_metadata = tenant_storage.metadata(&tenant_id, path).await?;
-    let is_directory = source_metadata.is_directory;
-    
-    if is_directory {
+    if source_kind == EntryKind::Directory {
         // Handle directory copy
         copy_directory(tenant_storage, tenant_id, path, &destination, overwrite).await
     } else {
@@ -1,10 +1,11 @@
 use crate::api::LockManagerRef;
-use crate::error::{Error, LockError};
+use crate::error::Error;
 use crate::dav_handler::DavResponse;
+use crate::operations::preconditions::{check_descendant_lock_tokens, check_preconditions, parse_if_header};
 use bytes::Bytes;
-use http::{Response, StatusCode};
+use http::{HeaderMap, Response, StatusCode};
 use marble_storage::api::TenantStorageRef;
-use marble_storage::StorageError;
+use marble_storage::{EntryKind, StorageError};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -13,25 +14,30 @@ pub async fn handle_delete(
     tenant_storage: &TenantStorageRef,
     lock_manager: &LockManagerRef,
     tenant_id: Uuid, 
-    path: &str
+    path: &str,
+    headers: HeaderMap,
+    normalize: impl Fn(&str) -> Result<String, Error>
 ) -> Result<DavResponse, Error> {
     debug!("DELETE request for path: {} by tenant: {}", path, tenant_id);
     
-    // Check if path exists
-    let exists = tenant_storage.exists(&tenant_id, path).await?;
-    if !exists {
-        return Err(Error::Storage(StorageError::NotFound(path.to_string())));
-    }
+    // Check if path exists, and whether it is a collection
+    let kind = tenant_storage
+        .entry_kind(&tenant_id, path)
+        .await?
+        .ok_or_else(|| Error::Storage(StorageError::NotFound(path.to_string())))?;
     
-    // Check if it's locked
-    if let Some(_) = lock_manager.is_locked(&tenant_id, path).await? {
-        // In a full implementation, we would check the lock token from If header
-        // For simplicity, we're just checking if it's locked at all
-        return Err(Error::Lock(LockError::ResourceLocked));
-    }
+    // Check the If header, and that a lock on the resource is held by the client
+    let if_header = parse_if_header(&headers)?;
+    check_preconditions(tenant_storage, lock_manager, tenant_id, path, if_header.as_ref(), normalize).await?;
     
-    // Delete the resource
-    tenant_storage.delete(&tenant_id, path).await?;
+    // Delete the resource, taking the whole subtree along for a collection
+    if kind == EntryKind::Directory {
+        check_descendant_lock_tokens(lock_manager, tenant_id, path, if_header.as_ref()).await?;
+        let deleted = tenant_storage.delete_directory(&tenant_id, path).await?;
+        debug!("Deleted {} files below {}", deleted, path);
+    } else {
+        tenant_storage.delete(&tenant_id, path).await?;
+    }
     
     // Return 204 No Content on success
     let response = Response::builder()
@@ -1,43 +1,279 @@
 use crate::error::Error;
 use crate::dav_handler::DavResponse;
+use crate::capabilities::Capabilities;
+use crate::config::WebDavConfig;
+use crate::etag::if_none_match;
+use crate::operations::utils::http_date;
+use crate::metadata_cache::MetadataCache;
+use crate::render::{accepts_html, is_markdown, markdown_to_html, CONTENT_SECURITY_POLICY};
 use bytes::Bytes;
-use http::{Response, StatusCode};
+use futures::future;
+use futures::stream::{BoxStream, StreamExt};
+use http::{HeaderMap, Response, StatusCode};
 use marble_storage::api::TenantStorageRef;
-use marble_storage::StorageError;
+use marble_storage::StorageResult;
+use std::sync::{Arc, Mutex};
 use tracing::debug;
 use uuid::Uuid;
 
+/// Default size from which GET streams a file instead of reading it whole
+pub const DEFAULT_STREAM_MIN_SIZE: usize = 256 * 1024;
+
+/// Content of a streamed response, in the chunks storage reads
+type ContentStream = BoxStream<'static, StorageResult<Bytes>>;
+
+/// Body of a response streamed from storage instead of held in memory
+///
+/// Carried as an extension of a [`DavResponse`] whose own body is empty; the
+/// server sends the stream in its place.
+#[derive(Clone)]
+pub struct StreamedBody(Arc<Mutex<Option<ContentStream>>>);
+
+impl StreamedBody {
+    fn new(stream: ContentStream) -> Self {
+        Self(Arc::new(Mutex::new(Some(stream))))
+    }
+    
+    /// Take the stream; only the first call gets it
+    pub fn take(&self) -> Option<ContentStream> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+/// A byte range requested with `Range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Bytes `start` through `end`, inclusive, within the content
+    Satisfiable { start: u64, end: u64 },
+    
+    /// A range starting past the end of the content
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header for content of `size` bytes
+///
+/// Only a single `bytes` range is served; multiple ranges, other units and
+/// malformed values yield `None`, and the whole content is sent instead.
+pub fn parse_range(value: &str, size: u64) -> Option<ByteRange> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+    
+    // A suffix range asks for the last bytes of the content
+    if first.is_empty() {
+        let suffix: u64 = last.parse().ok()?;
+        if suffix == 0 || size == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        return Some(ByteRange::Satisfiable { start: size.saturating_sub(suffix), end: size - 1 });
+    }
+    
+    let start: u64 = first.parse().ok()?;
+    let end = match last {
+        "" => None,
+        last => Some(last.parse::<u64>().ok()?),
+    };
+    if end.is_some_and(|end| end < start) {
+        return None;
+    }
+    if start >= size {
+        return Some(ByteRange::Unsatisfiable);
+    }
+    Some(ByteRange::Satisfiable { start, end: end.map_or(size - 1, |end| end.min(size - 1)) })
+}
+
+/// Whether a `Range` applies under the request's `If-Range`
+///
+/// Only entity tags are compared; a date or a changed ETag sends the whole content.
+fn if_range_matches(headers: &HeaderMap, etag: Option<&str>) -> bool {
+    match headers.get(http::header::IF_RANGE) {
+        None => true,
+        Some(if_range) => etag.is_some_and(|etag| {
+            !etag.starts_with("W/") && if_range.to_str().is_ok_and(|if_range| if_range.trim() == etag)
+        }),
+    }
+}
+
+/// Keep only bytes `start` through `end`, inclusive, of a content stream
+fn slice_stream(stream: ContentStream, start: u64, end: u64) -> ContentStream {
+    stream
+        .scan(0u64, move |offset, chunk| {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return future::ready(Some(Some(Err(e)))),
+            };
+            // Stop reading once the range has been sent
+            if *offset > end {
+                return future::ready(None);
+            }
+            
+            let chunk_start = *offset;
+            *offset += chunk.len() as u64;
+            let from = start.saturating_sub(chunk_start).min(chunk.len() as u64) as usize;
+            let to = (end + 1).saturating_sub(chunk_start).min(chunk.len() as u64) as usize;
+            future::ready(Some((from < to).then(|| chunk.slice(from..to)).map(Ok)))
+        })
+        .filter_map(future::ready)
+        .boxed()
+}
+
 /// Handle GET method to retrieve a file
 pub async fn handle_get(
     tenant_storage: &TenantStorageRef,
     tenant_id: Uuid, 
-    path: &str
+    path: &str,
+    headers: &HeaderMap,
+    config: &WebDavConfig,
+    metadata_cache: &MetadataCache
 ) -> Result<DavResponse, Error> {
     debug!("GET request for path: {} by tenant: {}", path, tenant_id);
     
-    // First, check if the file exists
-    if !tenant_storage.exists(&tenant_id, path).await? {
-        return Err(Error::Storage(StorageError::NotFound(path.to_string())));
-    }
-    
-    // Retrieve file metadata to get content type and size
-    let metadata = tenant_storage.metadata(&tenant_id, path).await?;
+    // Retrieve file metadata to get content type and size, possibly resolved by a HEAD
+    let metadata = metadata_cache.resolve(tenant_storage, tenant_id, path).await?;
     
     // If it's a directory, return a 405 Method Not Allowed
     if metadata.is_directory {
-        return Err(Error::WebDav("Cannot GET a directory".to_string()));
+        return Err(Error::MethodNotAllowed("Cannot GET a directory".to_string()));
+    }
+    
+    // Markdown is rendered for clients asking for HTML
+    let capabilities = Capabilities::from_config(config);
+    let negotiated = capabilities.render_markdown && is_markdown(&metadata);
+    if negotiated && accepts_html(headers) {
+        let content = tenant_storage.read(&tenant_id, path).await?;
+        let rendered = markdown_to_html(&content);
+        
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .header(http::header::CONTENT_LENGTH, rendered.len().to_string())
+            .header(http::header::VARY, "Accept")
+            .header(http::header::CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY)
+            .header(http::header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .body(Bytes::from(rendered))
+            .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)));
     }
     
-    // Read the file content
-    let content = tenant_storage.read(&tenant_id, path).await?;
+    let etag = config.etag_policy.etag_for(&metadata);
+    
+    // The client's cached copy is still current
+    if let (Some(etag), Some(header)) = (&etag, headers.get(http::header::IF_NONE_MATCH)) {
+        if header.to_str().map(|h| if_none_match(h, etag)).unwrap_or(false) {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(http::header::ETAG, etag)
+                .body(Bytes::new())
+                .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)));
+        }
+    }
+    
+    // A single byte range, when ranges are enabled and the client's copy is current
+    let range_header = match headers.get(http::header::RANGE) {
+        Some(range) if capabilities.ranges && if_range_matches(headers, etag.as_deref()) => range.to_str().ok(),
+        _ => None,
+    };
+    
+    // Large files are streamed rather than read into memory. Content read
+    // whole is ranged by its own length, which the cached size may not match
+    let stream_min_size = config.stream_min_size.unwrap_or(DEFAULT_STREAM_MIN_SIZE);
+    let (content, stream, size) = if metadata.size >= stream_min_size as u64 {
+        let stream = tenant_storage.read_stream(&tenant_id, path).await?;
+        (Bytes::new(), Some(stream), metadata.size)
+    } else {
+        let content = Bytes::from(tenant_storage.read(&tenant_id, path).await?);
+        let size = content.len() as u64;
+        (content, None, size)
+    };
+    
+    let range = range_header.and_then(|range| parse_range(range, size));
+    let (start, end) = match range {
+        Some(ByteRange::Satisfiable { start, end }) => (start, end),
+        Some(ByteRange::Unsatisfiable) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(http::header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Bytes::new())
+                .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)));
+        }
+        None => (0, size.saturating_sub(1)),
+    };
+    let (content, stream) = match (stream, range) {
+        (Some(stream), Some(_)) => (content, Some(StreamedBody::new(slice_stream(stream, start, end)))),
+        (Some(stream), None) => (content, Some(StreamedBody::new(stream))),
+        (None, Some(_)) => (content.slice(start as usize..=end as usize), None),
+        (None, None) => (content, None),
+    };
+    let length = match (&stream, range) {
+        (Some(_), Some(_)) => end - start + 1,
+        (Some(_), None) => size,
+        (None, _) => content.len() as u64,
+    };
     
     // Build the response with appropriate headers
-    let response = Response::builder()
-        .status(StatusCode::OK)
+    let mut builder = Response::builder()
+        .status(if range.is_some() { StatusCode::PARTIAL_CONTENT } else { StatusCode::OK })
         .header(http::header::CONTENT_TYPE, metadata.content_type)
-        .header(http::header::CONTENT_LENGTH, content.len().to_string())
-        .body(Bytes::from(content))
+        .header(http::header::CONTENT_LENGTH, length.to_string());
+    if range.is_some() {
+        builder = builder.header(http::header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size));
+    }
+    if capabilities.ranges {
+        builder = builder.header(http::header::ACCEPT_RANGES, "bytes");
+    }
+    if let Some(etag) = etag {
+        builder = builder.header(http::header::ETAG, etag);
+    }
+    if let Some(date) = metadata.last_modified.and_then(http_date) {
+        builder = builder.header(http::header::LAST_MODIFIED, date);
+    }
+    if negotiated {
+        builder = builder.header(http::header::VARY, "Accept");
+    }
+    if let Some(stream) = stream {
+        builder = builder.extension(stream);
+    }
+    
+    let response = builder
+        .body(content)
         .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
     
     Ok(response)
 }
+
+/// Handle HEAD method to retrieve the headers of a file without its content
+pub async fn handle_head(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    path: &str,
+    config: &WebDavConfig,
+    metadata_cache: &MetadataCache
+) -> Result<DavResponse, Error> {
+    debug!("HEAD request for path: {} by tenant: {}", path, tenant_id);
+    
+    let metadata = metadata_cache.resolve(tenant_storage, tenant_id, path).await?;
+    
+    if metadata.is_directory {
+        return Err(Error::MethodNotAllowed("Cannot HEAD a directory".to_string()));
+    }
+    
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, metadata.content_type.as_str())
+        .header(http::header::CONTENT_LENGTH, metadata.size.to_string());
+    if Capabilities::from_config(config).ranges {
+        builder = builder.header(http::header::ACCEPT_RANGES, "bytes");
+    }
+    if let Some(etag) = config.etag_policy.etag_for(&metadata) {
+        builder = builder.header(http::header::ETAG, etag);
+    }
+    if let Some(date) = metadata.last_modified.and_then(http_date) {
+        builder = builder.header(http::header::LAST_MODIFIED, date);
+    }
+    
+    builder
+        .body(Bytes::new())
+        .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))
+}
@@ -1,18 +1,26 @@
-use crate::api::LockManagerRef;
-use crate::error::Error;
+use crate::api::{LockInfo, LockManagerRef, LockScope};
+use crate::error::{Error, LockError};
 use crate::dav_handler::DavResponse;
-use crate::operations::utils::{parse_depth, Depth};
+use crate::operations::utils::{parse_depth, xml_escape, Depth};
 
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use http::{HeaderMap, Response, StatusCode};
-use tracing::{debug, warn};
+use marble_core::clock::ClockRef;
+use marble_storage::api::TenantStorageRef;
+use tracing::debug;
 use uuid::Uuid;
 use std::time::Duration;
 use http::header;
 
 /// Handle LOCK WebDAV method
+///
+/// With `Depth: infinity` on a collection, the collection and all its current
+/// descendants are locked under one token, or none of them if any conflicts.
 pub async fn handle_lock(
+    tenant_storage: &TenantStorageRef,
     lock_manager: &LockManagerRef,
+    clock: &ClockRef,
     tenant_id: Uuid,
     path: &str,
     headers: HeaderMap,
@@ -33,32 +41,31 @@ pub async fn handle_lock(
     // Generate a unique lock token
     let token = format!("urn:uuid:{}", Uuid::new_v4());
     
-    // Acquire the lock
-    lock_manager.lock(
-        &tenant_id,
-        path,
-        timeout,
-        &token
-    ).await.map_err(|e| Error::LockFailed(e.to_string()))?;
-    
-    // Recursive locking not supported yet
-    if depth == Depth::Infinity {
-        warn!("Recursive locking (Depth: infinity) requested but not fully implemented");
-        // In a complete implementation, we would lock all descendants here
-    }
+    let recursive = depth == Depth::Infinity
+        && tenant_storage.exists(&tenant_id, path).await?
+        && tenant_storage.metadata(&tenant_id, path).await?.is_directory;
+    
+    // Acquire the lock; a conflicting lock is reported as 423 Locked
+    let result = if recursive {
+        let mut paths = vec![path.to_string()];
+        collect_descendants(tenant_storage, tenant_id, path, &mut paths).await?;
+        debug!("Locking {} and {} descendants", path, paths.len() - 1);
+        
+        lock_manager.lock_paths(&tenant_id, &paths, timeout, &token, owner.as_deref(), lock_scope).await
+    } else {
+        lock_manager.lock(&tenant_id, path, timeout, &token, owner.as_deref(), lock_scope).await
+    };
+    result.map_err(|e| match e {
+        LockError::ResourceLocked => Error::Lock(e),
+        e => Error::LockFailed(e.to_string()),
+    })?;
     
     // Generate the lock token response header
     let lock_token_header = format!("<{}>", token);
     
-    // Create XML response for lockdiscovery
-    let lock_discovery = generate_lock_discovery_xml(
-        &token,
-        &lock_scope,
-        &lock_type,
-        owner.as_deref(),
-        timeout,
-        path,
-    );
+    // Create XML response for lockdiscovery, listing every lock on the resource
+    let locks = lock_manager.active_locks(&tenant_id, path).await?;
+    let lock_discovery = generate_lock_discovery_xml(&locks, &token, &lock_type, clock.now());
     
     // Build response with proper headers - Response builder approach
     let response = Response::builder()
@@ -71,6 +78,30 @@ pub async fn handle_lock(
     Ok(response)
 }
 
+/// Append the paths of all files and directories below a directory
+async fn collect_descendants(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    dir_path: &str,
+    paths: &mut Vec<String>,
+) -> Result<(), Error> {
+    for entry in tenant_storage.list(&tenant_id, dir_path).await? {
+        let entry_path = if dir_path == "." {
+            entry
+        } else {
+            format!("{}/{}", dir_path, entry)
+        };
+        
+        let is_directory = tenant_storage.metadata(&tenant_id, &entry_path).await?.is_directory;
+        paths.push(entry_path.clone());
+        if is_directory {
+            Box::pin(collect_descendants(tenant_storage, tenant_id, &entry_path, paths)).await?;
+        }
+    }
+    
+    Ok(())
+}
+
 /// Parse timeout header value into a Duration
 /// Format: "Second-xxx" or "Infinite"
 fn parse_timeout_header(headers: &HeaderMap) -> Option<Duration> {
@@ -93,10 +124,10 @@ fn parse_timeout_header(headers: &HeaderMap) -> Option<Duration> {
 }
 
 /// Parse LOCK request XML body to extract lock scope, type, and owner information
-fn parse_lock_body(body: &Bytes) -> Result<(String, String, Option<String>), Error> {
+fn parse_lock_body(body: &Bytes) -> Result<(LockScope, String, Option<String>), Error> {
     if body.is_empty() {
         // If body is empty, use default values
-        return Ok(("exclusive".to_string(), "write".to_string(), None));
+        return Ok((LockScope::Exclusive, "write".to_string(), None));
     }
     
     // Parse XML with quick-xml
@@ -106,14 +137,7 @@ fn parse_lock_body(body: &Bytes) -> Result<(String, String, Option<String>), Err
     // This is a simplified parsing approach; in a real implementation you'd use
     // a proper XML parser to extract these values from the lockinfo XML
     
-    // Extract lock scope (exclusive or shared)
-    let lock_scope = if xml_str.contains("<exclusive") {
-        "exclusive".to_string()
-    } else if xml_str.contains("<shared") {
-        "shared".to_string()
-    } else {
-        "exclusive".to_string() // Default to exclusive
-    };
+    let lock_scope = parse_lock_scope(xml_str);
     
     // Extract lock type (write)
     let lock_type = if xml_str.contains("<write") {
@@ -122,39 +146,72 @@ fn parse_lock_body(body: &Bytes) -> Result<(String, String, Option<String>), Err
         "write".to_string() // Default to write
     };
     
-    // Extract owner information (simplified approach)
-    let owner = if xml_str.contains("<owner>") {
-        // This is a placeholder for owner extraction
-        // In a real implementation, you'd properly parse the XML
-        Some("unknown".to_string())
-    } else {
-        None
-    };
+    let owner = parse_lock_owner(xml_str);
     
     Ok((lock_scope, lock_type, owner))
 }
 
-/// Generate lock discovery XML
-fn generate_lock_discovery_xml(
-    token: &str,
-    lock_scope: &str,
-    lock_type: &str,
-    owner: Option<&str>,
-    timeout: Duration,
-    path: &str,
-) -> String {
-    // Calculate timeout string
-    let timeout_str = format!("Second-{}", timeout.as_secs());
-    
-    // Build XML
-    let mut xml = format!(
-        r#"<?xml version="1.0" encoding="utf-8" ?>
+/// Read the scope from the `DAV:lockscope` element of a lockinfo body
+///
+/// Locks are exclusive unless the body asks for `DAV:shared`.
+fn parse_lock_scope(xml_str: &str) -> LockScope {
+    let Ok(document) = roxmltree::Document::parse(xml_str) else {
+        return LockScope::Exclusive;
+    };
+    let shared = document
+        .descendants()
+        .filter(|node| node.has_tag_name(("DAV:", "lockscope")))
+        .flat_map(|node| node.children())
+        .any(|node| node.has_tag_name(("DAV:", "shared")));
+    
+    if shared {
+        LockScope::Shared
+    } else {
+        LockScope::Exclusive
+    }
+}
+
+/// Extract the text of the `DAV:owner` element of a lockinfo body
+///
+/// Markup inside the owner (usually a `DAV:href`) is flattened to its text.
+/// Bodies that are not well-formed XML have no owner.
+fn parse_lock_owner(xml_str: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(xml_str).ok()?;
+    let owner_element = document
+        .descendants()
+        .find(|node| node.has_tag_name(("DAV:", "owner")))?;
+    
+    let owner: String = owner_element
+        .descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect();
+    let owner = owner.trim();
+    
+    (!owner.is_empty()).then(|| owner.to_string())
+}
+
+/// Generate lock discovery XML with an `activelock` for each lock
+///
+/// Only the lock identified by `own_token` names its owner, so that locking a
+/// shared resource does not disclose who else holds it. Remaining timeouts
+/// are counted from `now`.
+fn generate_lock_discovery_xml(locks: &[LockInfo], own_token: &str, lock_type: &str, now: DateTime<Utc>) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8" ?>
 <D:prop xmlns:D="DAV:">
-    <D:lockdiscovery>
+    <D:lockdiscovery>"#);
+    
+    for lock in locks {
+        // Round the remaining time up, so a fresh lock reports its full timeout
+        let remaining_ms = (lock.expires_at - now).num_milliseconds().max(0);
+        let timeout_str = format!("Second-{}", (remaining_ms + 999) / 1000);
+        
+        xml.push_str(&format!(
+            r#"
         <D:activelock>
             <D:lockscope><D:{}/></D:lockscope>
             <D:locktype><D:{}/></D:locktype>
-            <D:depth>0</D:depth>
+            <D:depth>{}</D:depth>
             <D:timeout>{}</D:timeout>
             <D:locktoken>
                 <D:href>{}</D:href>
@@ -162,25 +219,33 @@ fn generate_lock_discovery_xml(
             <D:lockroot>
                 <D:href>{}</D:href>
             </D:lockroot>"#,
-        lock_scope, lock_type, timeout_str, token, path
-    );
-    
-    // Add owner if present
-    if let Some(owner_str) = owner {
-        xml.push_str(&format!(
-            r#"
+            lock.scope.as_str(),
+            lock_type,
+            if lock.recursive { "infinity" } else { "0" },
+            timeout_str,
+            xml_escape(&lock.token),
+            xml_escape(&lock.root)
+        ));
+        
+        // Add owner if present
+        if let Some(owner_str) = lock.owner.as_deref().filter(|_| lock.token == own_token) {
+            xml.push_str(&format!(
+                r#"
             <D:owner>
                 <D:href>{}</D:href>
             </D:owner>"#,
-            owner_str
-        ));
+                xml_escape(owner_str)
+            ));
+        }
+        
+        xml.push_str(r#"
+        </D:activelock>"#);
     }
     
     // Close tags
     xml.push_str(r#"
-        </D:activelock>
     </D:lockdiscovery>
 </D:prop>"#);
     
     xml
-}
\ No newline at end of file
+}
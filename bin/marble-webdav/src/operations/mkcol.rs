@@ -19,7 +19,7 @@ pub async fn handle_mkcol(
     let exists = tenant_storage.exists(&tenant_id, path).await?;
     if exists {
         // Cannot create collection at an existing path
-        return Err(Error::WebDav("Resource already exists".to_string()));
+        return Err(Error::MethodNotAllowed("Resource already exists".to_string()));
     }
     
     // Check if parent directory exists
@@ -27,7 +27,7 @@ pub async fn handle_mkcol(
     if !parent_path.is_empty() && parent_path != "." {
         let parent_exists = tenant_storage.exists(&tenant_id, &parent_path).await?;
         if !parent_exists {
-            return Err(Error::WebDav("Parent directory does not exist".to_string()));
+            return Err(Error::Conflict("Parent directory does not exist".to_string()));
         }
         
         // Verify parent is a directory
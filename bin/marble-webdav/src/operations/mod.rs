@@ -1,21 +1,34 @@
+pub mod batch_delete;
+pub mod blob;
 pub mod get;
 pub mod put;
 pub mod mkcol;
 pub mod delete;
 pub mod propfind;
+pub mod propfind_batch;
+pub mod proppatch;
 pub mod copy;
 pub mod move_op;
 pub mod lock;
+pub mod preconditions;
+pub mod search;
 pub mod unlock;
 pub mod utils;
+pub mod verify;
 
 // Re-export public operations
-pub use get::handle_get;
+pub use batch_delete::handle_batch_delete;
+pub use blob::handle_get_blob;
+pub use get::{handle_get, handle_head};
 pub use put::handle_put;
 pub use mkcol::handle_mkcol;
 pub use delete::handle_delete;
 pub use propfind::handle_propfind;
+pub use propfind_batch::handle_propfind_batch;
+pub use proppatch::handle_proppatch;
+pub use search::handle_search;
 pub use copy::handle_copy;
 pub use move_op::handle_move;
 pub use lock::handle_lock;
 pub use unlock::handle_unlock;
+pub use verify::handle_verify;
@@ -1,11 +1,14 @@
 use crate::api::LockManagerRef;
 use crate::dav_handler::DavResponse;
-use crate::error::{Error, LockError};
+use crate::error::Error;
 use crate::headers::OVERWRITE;
 use crate::operations::copy::{copy_directory, copy_file, extract_destination};
+use crate::operations::preconditions::{check_lock_token, check_preconditions, parse_if_header};
+use crate::operations::utils::get_parent_path;
+use bytes::Bytes;
 use http::{HeaderMap, Response, StatusCode};
 use marble_storage::api::TenantStorageRef;
-use marble_storage::StorageError;
+use marble_storage::{EntryKind, StorageError};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -16,20 +19,19 @@ pub async fn handle_move(
     tenant_id: Uuid, 
     path: &str, 
     headers: HeaderMap,
-    normalize_fn: impl Fn(&str) -> String
+    normalize_fn: impl Fn(&str) -> Result<String, Error>
 ) -> Result<DavResponse, Error> {
     debug!("MOVE request for path: {} by tenant: {}", path, tenant_id);
     
-    // Check if source exists
-    let exists = tenant_storage.exists(&tenant_id, path).await?;
-    if !exists {
-        return Err(Error::Storage(StorageError::NotFound(path.to_string())));
-    }
+    // Check if source exists, and whether it is a collection
+    let source_kind = tenant_storage
+        .entry_kind(&tenant_id, path)
+        .await?
+        .ok_or_else(|| Error::Storage(StorageError::NotFound(path.to_string())))?;
     
-    // Check if source is locked
-    if let Some(_) = lock_manager.is_locked(&tenant_id, path).await? {
-        return Err(Error::Lock(LockError::ResourceLocked));
-    }
+    // Check the If header against the source, and that its lock is held by the client
+    let if_header = parse_if_header(&headers)?;
+    check_preconditions(tenant_storage, lock_manager, tenant_id, path, if_header.as_ref(), &normalize_fn).await?;
     
     // Extract destination from headers
     let destination = extract_destination(&headers, normalize_fn)?;
@@ -46,17 +48,26 @@ pub async fn handle_move(
         
     // If destination exists and overwrite is false, return 412 Precondition Failed
     if dest_exists && !overwrite {
-        return Err(Error::WebDav("Destination already exists and overwrite is false".to_string()));
+        return Err(Error::PreconditionFailed("Destination already exists and overwrite is false".to_string()));
     }
     
-    // Check if destination is locked
-    if let Some(_) = lock_manager.is_locked(&tenant_id, &destination).await? {
-        return Err(Error::Lock(LockError::ResourceLocked));
-    }
+    // Check that a lock on the destination is held by the client
+    check_lock_token(lock_manager, tenant_id, &destination, if_header.as_ref()).await?;
+    
+    let is_directory = source_kind == EntryKind::Directory;
     
-    // Get source metadata to determine if it's a file or directory
-    let source_metadata = tenant_storage.metadata(&tenant_id, path).await?;
-    let is_directory = source_metadata.is_directory;
+    // Fast path: renaming a file within the same directory is a single metadata update
+    if !is_directory && !dest_exists && get_parent_path(path) == get_parent_path(&destination) {
+        debug!("Renaming {} to {} in place", path, destination);
+        tenant_storage.rename(&tenant_id, path, &destination).await?;
+        
+        let response = Response::builder()
+            .status(StatusCode::CREATED)
+            .body(Bytes::new())
+            .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
+        
+        return Ok(response);
+    }
     
     // Implement move as copy + delete
     let response = if is_directory {
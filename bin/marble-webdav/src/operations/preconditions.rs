@@ -0,0 +1,106 @@
+use crate::api::LockManagerRef;
+use crate::error::{Error, LockError};
+use crate::etag::EtagPolicy;
+use crate::if_header::{tag_matches, IfHeader, ResourceState};
+use http::HeaderMap;
+use marble_storage::api::TenantStorageRef;
+use uuid::Uuid;
+
+/// Parse the `If` header of a request, if present
+pub fn parse_if_header(headers: &HeaderMap) -> Result<Option<IfHeader>, Error> {
+    match headers.get("If") {
+        Some(value) => {
+            let value = value
+                .to_str()
+                .map_err(|_| Error::WebDav("Invalid If header: not ASCII".to_string()))?;
+            IfHeader::parse(value).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Check the `If` header and lock of a resource before modifying it
+///
+/// Fails with `412 Precondition Failed` if the header does not hold for the
+/// resource, and with `423 Locked` if the resource is locked and the header
+/// does not submit the lock token. Resource tags in the header are resolved
+/// with `normalize`, as the request path was.
+pub async fn check_preconditions(
+    tenant_storage: &TenantStorageRef,
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    path: &str,
+    if_header: Option<&IfHeader>,
+    normalize: impl Fn(&str) -> Result<String, Error>,
+) -> Result<(), Error> {
+    if let Some(if_header) = if_header {
+        let locks = lock_manager.active_locks(&tenant_id, path).await?;
+        let lock_tokens: Vec<String> = locks.into_iter().map(|lock| lock.token).collect();
+
+        // Entity tags compare weakly, so the configured policy does not matter
+        let etag = if tenant_storage.exists(&tenant_id, path).await? {
+            EtagPolicy::Strong.etag_for(&tenant_storage.metadata(&tenant_id, path).await?)
+        } else {
+            None
+        };
+
+        let state = ResourceState {
+            lock_tokens: &lock_tokens,
+            etag: etag.as_deref(),
+        };
+
+        if !if_header.evaluate(|tag| tag_matches(tag, path, &normalize), &state) {
+            return Err(Error::PreconditionFailed(format!("If header condition failed for {}", path)));
+        }
+    }
+
+    check_lock_token(lock_manager, tenant_id, path, if_header).await
+}
+
+/// Fail with `423 Locked` unless a token is submitted for every lock below a collection
+///
+/// A collection is deleted with its whole subtree, so each locked member must
+/// be unlocked by the request as well. Locks covering the collection itself
+/// are checked by [`check_lock_token`].
+pub async fn check_descendant_lock_tokens(
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    path: &str,
+    if_header: Option<&IfHeader>,
+) -> Result<(), Error> {
+    let locks = lock_manager.locks_below(&tenant_id, path).await?;
+    let unsubmitted = locks
+        .into_iter()
+        .find(|lock| !if_header.is_some_and(|h| h.submits_token(&lock.token)));
+
+    match unsubmitted {
+        Some(lock) => Err(Error::Lock(LockError::TokenNotSubmitted {
+            path: lock.path,
+            owner: lock.owner,
+        })),
+        None => Ok(()),
+    }
+}
+
+/// Fail with `423 Locked` unless the resource is unlocked or a token of its locks is submitted
+pub async fn check_lock_token(
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    path: &str,
+    if_header: Option<&IfHeader>,
+) -> Result<(), Error> {
+    let locks = lock_manager.active_locks(&tenant_id, path).await?;
+    let submitted = locks
+        .iter()
+        .any(|lock| if_header.is_some_and(|h| h.submits_token(&lock.token)));
+
+    match locks.into_iter().next() {
+        Some(lock) if !submitted => {
+            Err(Error::Lock(LockError::TokenNotSubmitted {
+                path: lock.path,
+                owner: lock.owner,
+            }))
+        }
+        _ => Ok(()),
+    }
+}
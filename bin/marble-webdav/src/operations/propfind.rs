@@ -1,23 +1,236 @@
+use crate::capabilities::Capabilities;
+use crate::config::{DirectoryContentType, WebDavConfig};
 use crate::error::Error;
 use crate::dav_handler::DavResponse;
+use crate::etag::EtagPolicy;
+use crate::usage_cache::UsageCache;
+use crate::operations::utils::{http_date, property_element, xml_escape};
 use bytes::Bytes;
 use http::{Response, StatusCode};
-use marble_storage::api::TenantStorageRef;
+use marble_storage::api::tenant::sort_metadata;
+use marble_storage::api::{DeadProperty, FileMetadata, ListOrder, StorageUsage, TenantStorageRef};
 use marble_storage::StorageError;
-use tracing::debug;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use tokio::time::Instant;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
-/// Convert a storage path to a WebDAV href
-fn path_to_href(path: &str) -> String {
+/// Number of children whose metadata is looked up at once under a latency budget
+const BUDGET_BATCH_SIZE: usize = 100;
+
+/// Characters percent-encoded in an href path segment
+///
+/// Everything outside the unreserved and sub-delimiter characters that could
+/// change how the href is parsed; non-ASCII bytes are always encoded.
+const HREF_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Convert a storage path to a WebDAV href, percent-encoded and escaped for XML
+///
+/// Each segment is encoded on its own, so the href decodes back to the same
+/// path segment by segment.
+pub(crate) fn path_to_href(path: &str) -> String {
     if path == "." {
         return "/".to_string();
     }
     
-    // Ensure the path starts with a slash
-    if path.starts_with('/') {
-        path.to_string()
+    let encoded: Vec<String> = path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, HREF_SEGMENT).to_string())
+        .collect();
+    format!("/{}", xml_escape(&encoded.join("/")))
+}
+
+/// Render the getcontentlength property
+///
+/// Collections have no content length and some clients mis-display them when
+/// the property is present, so it is omitted for directories.
+fn content_length_prop(metadata: &FileMetadata) -> String {
+    if metadata.is_directory {
+        String::new()
     } else {
-        format!("/{}", path)
+        format!("<D:getcontentlength>{}</D:getcontentlength>\n", metadata.size)
+    }
+}
+
+/// Render the getcontenttype property
+///
+/// The internal directory marker type is never exposed; collections follow the
+/// configured convention.
+fn content_type_prop(metadata: &FileMetadata, convention: DirectoryContentType) -> String {
+    let content_type = match (metadata.is_directory, convention) {
+        (false, _) => metadata.content_type.as_str(),
+        (true, DirectoryContentType::Omit) => return String::new(),
+        (true, DirectoryContentType::UnixDirectory) => "httpd/unix-directory",
+    };
+    format!("<D:getcontenttype>{}</D:getcontenttype>\n", xml_escape(content_type))
+}
+
+/// Render the getlastmodified property as an HTTP-date, omitted if unknown
+fn last_modified_prop(metadata: &FileMetadata) -> String {
+    metadata
+        .last_modified
+        .and_then(http_date)
+        .map(|date| format!("<D:getlastmodified>{}</D:getlastmodified>\n", date))
+        .unwrap_or_default()
+}
+
+/// Render the getetag property, omitted for resources without an ETag
+fn etag_prop(metadata: &FileMetadata, etag_policy: EtagPolicy) -> String {
+    etag_policy
+        .etag_for(metadata)
+        .map(|etag| format!("<D:getetag>{}</D:getetag>\n", etag))
+        .unwrap_or_default()
+}
+
+/// RFC 4331 quota properties named in a PROPFIND body
+///
+/// The quota properties are expensive and not part of `allprop`, so they are
+/// only reported when a `DAV:prop` element, or the `DAV:include` element of
+/// an `allprop` request, asks for them by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct QuotaRequest {
+    used: bool,
+    available: bool,
+}
+
+impl QuotaRequest {
+    /// Find the quota properties named in a PROPFIND body
+    ///
+    /// An empty or unparsable body names none.
+    fn parse(body: &[u8]) -> Self {
+        let Some(document) = std::str::from_utf8(body)
+            .ok()
+            .and_then(|text| roxmltree::Document::parse(text).ok())
+        else {
+            return Self::default();
+        };
+        
+        let mut request = Self::default();
+        let named = document
+            .root_element()
+            .children()
+            .filter(|node| node.has_tag_name(("DAV:", "prop")) || node.has_tag_name(("DAV:", "include")))
+            .flat_map(|list| list.children().filter(|node| node.is_element()));
+        for property in named {
+            match (property.tag_name().namespace(), property.tag_name().name()) {
+                (Some("DAV:"), "quota-used-bytes") => request.used = true,
+                (Some("DAV:"), "quota-available-bytes") => request.available = true,
+                _ => {}
+            }
+        }
+        request
+    }
+    
+    fn any(&self) -> bool {
+        self.used || self.available
+    }
+}
+
+/// Render the requested RFC 4331 quota properties of a collection
+///
+/// `quota-available-bytes` is omitted for tenants without a quota, which
+/// clients read as unlimited space.
+fn quota_props(request: QuotaRequest, usage: Option<StorageUsage>) -> String {
+    let Some(usage) = usage else {
+        return String::new();
+    };
+    let mut props = String::new();
+    if request.used {
+        props.push_str(&format!("<D:quota-used-bytes>{}</D:quota-used-bytes>\n", usage.used_bytes));
+    }
+    if let (true, Some(available)) = (request.available, usage.available_bytes()) {
+        props.push_str(&format!("<D:quota-available-bytes>{}</D:quota-available-bytes>\n", available));
+    }
+    props
+}
+
+/// Render the dead properties stored with PROPPATCH
+fn dead_props(properties: &[DeadProperty]) -> String {
+    properties
+        .iter()
+        .map(|property| property_element(&property.namespace, &property.name, Some(&property.value)))
+        .collect()
+}
+
+/// Response marking the listing of a collection as truncated
+///
+/// RFC 4918 §16 uses `507 Insufficient Storage` with the
+/// `DAV:number-of-matches-within-limits` condition for results cut short by
+/// a server limit.
+fn truncated_response(path: &str, reason: &str) -> String {
+    format!(
+        "<D:response>\n\
+         <D:href>{}</D:href>\n\
+         <D:status>HTTP/1.1 507 Insufficient Storage</D:status>\n\
+         <D:error><D:number-of-matches-within-limits/></D:error>\n\
+         <D:responsedescription>{}</D:responsedescription>\n\
+         </D:response>\n",
+        path_to_href(path),
+        xml_escape(reason)
+    )
+}
+
+/// List the children of a collection with their metadata until `deadline`
+///
+/// Metadata is looked up in batches, so a slow listing still yields the
+/// children looked up in time. Returns them in `order`, along with whether
+/// the listing was cut short.
+async fn list_within(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    path: &str,
+    order: ListOrder,
+    deadline: Instant,
+) -> Result<(Vec<FileMetadata>, bool), Error> {
+    let mut paths: Vec<String> = match tokio::time::timeout_at(deadline, tenant_storage.list(&tenant_id, path)).await {
+        Ok(entries) => entries?
+            .iter()
+            .map(|entry| child_path(path, entry))
+            .collect(),
+        Err(_) => return Ok((Vec::new(), true)),
+    };
+    paths.sort();
+    
+    let mut entries = Vec::with_capacity(paths.len());
+    let mut truncated = false;
+    for batch in paths.chunks(BUDGET_BATCH_SIZE) {
+        match tokio::time::timeout_at(deadline, tenant_storage.metadata_many(&tenant_id, batch)).await {
+            Ok(metadata) => entries.extend(metadata?.into_iter().flatten()),
+            Err(_) => {
+                truncated = true;
+                break;
+            }
+        }
+    }
+    
+    sort_metadata(&mut entries, order);
+    Ok((entries, truncated))
+}
+
+/// Path of a listed child of a collection, whether listed by name or by path
+fn child_path(dir_path: &str, entry: &str) -> String {
+    let name = entry.rsplit('/').next().unwrap_or(entry);
+    if dir_path == "." || dir_path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir_path.trim_end_matches('/'), name)
     }
 }
 
@@ -26,9 +239,12 @@ pub async fn handle_propfind(
     tenant_storage: &TenantStorageRef,
     tenant_id: Uuid, 
     path: &str, 
-    _body: Bytes
+    body: Bytes,
+    config: &WebDavConfig,
+    usage_cache: &UsageCache
 ) -> Result<DavResponse, Error> {
     debug!("PROPFIND request for path: {} by tenant: {}", path, tenant_id);
+    let etag_policy = config.etag_policy;
     
     // Check if path exists
     let exists = tenant_storage.exists(&tenant_id, path).await?;
@@ -38,6 +254,22 @@ pub async fn handle_propfind(
     
     // Get metadata for the path
     let metadata = tenant_storage.metadata(&tenant_id, path).await?;
+    let properties = tenant_storage.properties(&tenant_id, path).await?;
+    
+    // Quota properties are reported on the requested collection only, so a
+    // listing costs at most one usage lookup
+    let quota_request = QuotaRequest::parse(&body);
+    let usage = if metadata.is_directory && quota_request.any() && Capabilities::from_config(config).quotas {
+        match usage_cache.resolve(tenant_storage, tenant_id).await {
+            Ok(usage) => Some(usage),
+            Err(e) => {
+                warn!("Failed to get storage usage of tenant {}: {}", tenant_id, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
     
     // Parse the PROPFIND request to determine depth
     // Assume depth 1 for now (path and immediate children)
@@ -53,48 +285,60 @@ pub async fn handle_propfind(
          <D:propstat>\n\
          <D:prop>\n\
          <D:resourcetype>{}</D:resourcetype>\n\
-         <D:getcontentlength>{}</D:getcontentlength>\n\
-         <D:getcontenttype>{}</D:getcontenttype>\n\
-         <D:getlastmodified>{}</D:getlastmodified>\n\
+         {}\
+         {}\
+         {}\
+         {}\
+         {}\
+         {}\
          </D:prop>\n\
          <D:status>HTTP/1.1 200 OK</D:status>\n\
          </D:propstat>\n\
          </D:response>\n",
         path_to_href(path),
         if metadata.is_directory { "<D:collection/>" } else { "" },
-        metadata.size,
-        metadata.content_type,
-        metadata.last_modified.map_or("".to_string(), |ts| {
-            // Convert timestamp to RFC822 format
-            // In a real implementation, use a proper date formatting
-            format!("{}", ts)
-        })
+        content_length_prop(&metadata),
+        content_type_prop(&metadata, config.directory_content_type),
+        last_modified_prop(&metadata),
+        etag_prop(&metadata, etag_policy),
+        quota_props(quota_request, usage),
+        dead_props(&properties)
     );
     
     // If it's a directory and depth > 0, add children
     if metadata.is_directory && depth > 0 {
-        // List contents of directory
-        let entries = tenant_storage.list(&tenant_id, path).await?;
+        // List contents of directory with metadata in the requested order
+        // Under a budget, answer with what was listed in time rather than
+        // letting the client time out
+        let (mut entries, over_budget) = match config.propfind_budget {
+            Some(budget) => {
+                let deadline = Instant::now() + budget;
+                list_within(tenant_storage, tenant_id, path, config.list_order, deadline).await?
+            }
+            None => (tenant_storage.list_with_metadata(&tenant_id, path, config.list_order).await?, false),
+        };
+        if over_budget {
+            warn!("Listing {} exceeded the PROPFIND budget; {} children listed", path, entries.len());
+        }
         
-        for entry in entries {
-            // Get metadata for each child
-            let entry_path = if path.ends_with('/') || path == "." {
-                if path == "." {
-                    entry.clone()
-                } else {
-                    format!("{}{}", path, entry)
-                }
-            } else {
-                format!("{}/{}", path, entry)
-            };
-            
-            let entry_metadata = match tenant_storage.metadata(&tenant_id, &entry_path).await {
-                Ok(m) => m,
-                Err(e) => {
-                    debug!("Error getting metadata for {}: {}", entry_path, e);
-                    continue;
-                }
-            };
+        // Cut large collections short rather than building a huge response
+        let capped = config
+            .propfind_max_children
+            .filter(|max| entries.len() > *max);
+        if let Some(max) = capped {
+            debug!("Listing of {} capped at {} of {} children", path, max, entries.len());
+            entries.truncate(max);
+        }
+        
+        // Dead properties of all listed entries in one lookup
+        let entry_paths: Vec<String> = entries.iter().map(|entry| entry.path.clone()).collect();
+        let mut entry_properties = tenant_storage
+            .properties_many(&tenant_id, &entry_paths)
+            .await?
+            .into_iter();
+        
+        for entry_metadata in entries {
+            let properties = entry_properties.next().unwrap_or_default();
             
             // Add child to XML response
             xml_content.push_str(&format!(
@@ -103,18 +347,34 @@ pub async fn handle_propfind(
                  <D:propstat>\n\
                  <D:prop>\n\
                  <D:resourcetype>{}</D:resourcetype>\n\
-                 <D:getcontentlength>{}</D:getcontentlength>\n\
-                 <D:getcontenttype>{}</D:getcontenttype>\n\
-                 <D:getlastmodified>{}</D:getlastmodified>\n\
+                 {}\
+                 {}\
+                 {}\
+                 {}\
+                 {}\
                  </D:prop>\n\
                  <D:status>HTTP/1.1 200 OK</D:status>\n\
                  </D:propstat>\n\
                  </D:response>\n",
-                path_to_href(&entry_path),
+                path_to_href(&entry_metadata.path),
                 if entry_metadata.is_directory { "<D:collection/>" } else { "" },
-                entry_metadata.size,
-                entry_metadata.content_type,
-                entry_metadata.last_modified.map_or("".to_string(), |ts| format!("{}", ts))
+                content_length_prop(&entry_metadata),
+                content_type_prop(&entry_metadata, config.directory_content_type),
+                last_modified_prop(&entry_metadata),
+                etag_prop(&entry_metadata, etag_policy),
+                dead_props(&properties)
+            ));
+        }
+        
+        if let Some(max) = capped {
+            xml_content.push_str(&truncated_response(
+                path,
+                &format!("Listing truncated to the first {} children", max),
+            ));
+        } else if over_budget {
+            xml_content.push_str(&truncated_response(
+                path,
+                "Listing truncated by the server's latency budget",
             ));
         }
     }
@@ -0,0 +1,95 @@
+use crate::error::Error;
+use crate::dav_handler::DavResponse;
+use crate::operations::utils::parse_path_list;
+use crate::etag::EtagPolicy;
+use bytes::Bytes;
+use http::{Response, StatusCode};
+use marble_storage::api::{FileMetadata, TenantStorageRef};
+use serde::Serialize;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Properties of one requested path, or its absence
+#[derive(Debug, Serialize)]
+struct PathProperties<'a> {
+    path: &'a str,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_directory: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+}
+
+impl<'a> PathProperties<'a> {
+    fn found(path: &'a str, metadata: FileMetadata, etag_policy: EtagPolicy) -> Self {
+        let etag = etag_policy.etag_for(&metadata);
+        Self {
+            path,
+            status: StatusCode::OK.as_u16(),
+            is_directory: Some(metadata.is_directory),
+            size: (!metadata.is_directory).then_some(metadata.size),
+            content_type: (!metadata.is_directory).then_some(metadata.content_type),
+            last_modified: metadata.last_modified,
+            etag,
+        }
+    }
+    
+    fn not_found(path: &'a str) -> Self {
+        Self {
+            path,
+            status: StatusCode::NOT_FOUND.as_u16(),
+            is_directory: None,
+            size: None,
+            content_type: None,
+            last_modified: None,
+            etag: None,
+        }
+    }
+}
+
+/// Handle a lookup of the properties of the paths listed in a JSON array
+///
+/// All paths are resolved in one storage call. The response is a JSON array
+/// aligned with the request: each entry carries the path as sent, a status
+/// of `200` or `404`, and the properties of the paths that exist.
+/// Lists longer than `MAX_BATCH_PATHS` are rejected.
+pub async fn handle_propfind_batch(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    body: &Bytes,
+    etag_policy: EtagPolicy,
+    normalize: impl Fn(&str) -> Result<String, Error>,
+) -> Result<DavResponse, Error> {
+    let paths = parse_path_list(body, "propfind-batch")?;
+    
+    debug!("Batch PROPFIND of {} paths for tenant: {}", paths.len(), tenant_id);
+    
+    let normalized = paths.iter().map(|path| normalize(path)).collect::<Result<Vec<String>, Error>>()?;
+    let found = tenant_storage.metadata_many(&tenant_id, &normalized).await?;
+    
+    let results: Vec<PathProperties> = paths
+        .iter()
+        .zip(found)
+        .map(|(path, metadata)| match metadata {
+            Some(metadata) => PathProperties::found(path, metadata, etag_policy),
+            None => PathProperties::not_found(path),
+        })
+        .collect();
+    
+    let body = serde_json::to_vec(&results)
+        .map_err(|e| Error::Internal(format!("Failed to encode results: {}", e)))?;
+    
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body))
+        .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
+    
+    Ok(response)
+}
@@ -0,0 +1,227 @@
+use crate::api::LockManagerRef;
+use crate::error::Error;
+use crate::dav_handler::DavResponse;
+use crate::operations::preconditions::{check_preconditions, parse_if_header};
+use crate::operations::propfind::path_to_href;
+use crate::operations::utils::property_element;
+use bytes::Bytes;
+use http::{HeaderMap, Response, StatusCode};
+use marble_storage::api::{PropertyChange, TenantStorageRef};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Namespace of properties interpreted by Marble rather than stored as is
+pub const MARBLE_NAMESPACE: &str = "marble:";
+
+/// Marble property pinning the content type of a file
+const CONTENT_TYPE_PROPERTY: &str = "content-type";
+
+/// A `set` or `remove` instruction of a PROPPATCH request
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Instruction {
+    Set {
+        namespace: String,
+        name: String,
+        value: String,
+    },
+    Remove {
+        namespace: String,
+        name: String,
+    },
+}
+
+impl Instruction {
+    fn namespace(&self) -> &str {
+        match self {
+            Instruction::Set { namespace, .. } | Instruction::Remove { namespace, .. } => namespace,
+        }
+    }
+    
+    fn name(&self) -> &str {
+        match self {
+            Instruction::Set { name, .. } | Instruction::Remove { name, .. } => name,
+        }
+    }
+    
+    fn is_content_type(&self) -> bool {
+        self.namespace() == MARBLE_NAMESPACE && self.name() == CONTENT_TYPE_PROPERTY
+    }
+}
+
+/// Parse the instructions of a `propertyupdate` body in document order
+///
+/// Errors describe why the body is invalid.
+fn parse_propertyupdate(body: &[u8]) -> Result<Vec<Instruction>, String> {
+    let text = std::str::from_utf8(body).map_err(|_| "not UTF-8".to_string())?;
+    let document = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    
+    let root = document.root_element();
+    if root.tag_name().namespace() != Some("DAV:") || root.tag_name().name() != "propertyupdate" {
+        return Err("expected DAV:propertyupdate".to_string());
+    }
+    
+    let mut instructions = Vec::new();
+    for action in root.children().filter(|node| node.is_element()) {
+        let is_set = match (action.tag_name().namespace(), action.tag_name().name()) {
+            (Some("DAV:"), "set") => true,
+            (Some("DAV:"), "remove") => false,
+            _ => continue,
+        };
+        
+        let props = action
+            .children()
+            .filter(|node| node.is_element() && node.has_tag_name(("DAV:", "prop")));
+        for property in props.flat_map(|prop| prop.children().filter(|node| node.is_element())) {
+            let namespace = property.tag_name().namespace().unwrap_or_default().to_string();
+            let name = property.tag_name().name().to_string();
+            instructions.push(if is_set {
+                // Values are kept as text; markup inside a value is flattened
+                let value = property
+                    .descendants()
+                    .filter(|node| node.is_text())
+                    .filter_map(|node| node.text())
+                    .collect();
+                Instruction::Set { namespace, name, value }
+            } else {
+                Instruction::Remove { namespace, name }
+            });
+        }
+    }
+    
+    if instructions.is_empty() {
+        return Err("no properties to set or remove".to_string());
+    }
+    
+    Ok(instructions)
+}
+
+/// Why an instruction cannot be carried out, if it cannot
+fn instruction_error(instruction: &Instruction, is_directory: bool, is_root: bool) -> Option<StatusCode> {
+    if is_root || instruction.namespace() == "DAV:" || (is_directory && instruction.is_content_type()) {
+        // Live properties are computed by the server, directories have no
+        // content type and the root has nowhere to keep properties
+        return Some(StatusCode::FORBIDDEN);
+    }
+    
+    match instruction {
+        Instruction::Set { value, .. }
+            if instruction.is_content_type() && value.trim().parse::<mime::Mime>().is_err() =>
+        {
+            Some(StatusCode::CONFLICT)
+        }
+        _ => None,
+    }
+}
+
+/// Render a 207 Multi-Status response grouping the properties by status
+fn multistatus_response(path: &str, results: &[(&Instruction, StatusCode)]) -> DavResponse {
+    let mut xml_content = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <D:multistatus xmlns:D=\"DAV:\">\n\
+         <D:response>\n\
+         <D:href>{}</D:href>\n",
+        path_to_href(path)
+    );
+    
+    let mut statuses: Vec<StatusCode> = results.iter().map(|(_, status)| *status).collect();
+    statuses.sort();
+    statuses.dedup();
+    
+    for status in statuses {
+        xml_content.push_str("<D:propstat>\n<D:prop>\n");
+        for (instruction, _) in results.iter().filter(|(_, s)| *s == status) {
+            xml_content.push_str(&property_element(instruction.namespace(), instruction.name(), None));
+        }
+        xml_content.push_str(&format!(
+            "</D:prop>\n\
+             <D:status>HTTP/1.1 {} {}</D:status>\n\
+             </D:propstat>\n",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default()
+        ));
+    }
+    
+    xml_content.push_str("</D:response>\n</D:multistatus>");
+    
+    // Only static header values are set, so building cannot fail
+    Response::builder()
+        .status(StatusCode::MULTI_STATUS)
+        .header(http::header::CONTENT_TYPE, "application/xml")
+        .body(Bytes::from(xml_content))
+        .unwrap()
+}
+
+/// Handle PROPPATCH method to set and remove dead properties
+///
+/// Works on files and collections alike. Instructions are applied all or
+/// nothing: if any property cannot be changed, none is, and the others are
+/// reported as `424 Failed Dependency`. Setting `{marble:}content-type` pins
+/// the content type of a file instead of storing a dead property; removing it
+/// goes back to the guessed type. The pin and the dead properties are stored
+/// together, so a failing store leaves all of them as they were.
+pub async fn handle_proppatch(
+    tenant_storage: &TenantStorageRef,
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    path: &str,
+    headers: HeaderMap,
+    body: Bytes,
+    normalize: impl Fn(&str) -> Result<String, Error>,
+) -> Result<DavResponse, Error> {
+    debug!("PROPPATCH request for path: {} by tenant: {}", path, tenant_id);
+    
+    let if_header = parse_if_header(&headers)?;
+    check_preconditions(tenant_storage, lock_manager, tenant_id, path, if_header.as_ref(), normalize).await?;
+    
+    let instructions = parse_propertyupdate(&body)
+        .map_err(|reason| Error::WebDav(format!("Invalid PROPPATCH body: {}", reason)))?;
+    let metadata = tenant_storage.metadata(&tenant_id, path).await?;
+    let is_root = path.trim_matches('/').is_empty();
+    
+    let errors: Vec<Option<StatusCode>> = instructions
+        .iter()
+        .map(|instruction| instruction_error(instruction, metadata.is_directory, is_root))
+        .collect();
+    
+    if errors.iter().any(Option::is_some) {
+        let results: Vec<(&Instruction, StatusCode)> = instructions
+            .iter()
+            .zip(errors)
+            .map(|(instruction, error)| (instruction, error.unwrap_or(StatusCode::FAILED_DEPENDENCY)))
+            .collect();
+        return Ok(multistatus_response(path, &results));
+    }
+    
+    // The last content type instruction wins, like for any other property
+    let mut content_type = None;
+    let mut changes = Vec::new();
+    for instruction in &instructions {
+        match instruction {
+            Instruction::Set { value, .. } if instruction.is_content_type() => {
+                content_type = Some(Some(value.trim().to_string()));
+            }
+            Instruction::Remove { .. } if instruction.is_content_type() => {
+                content_type = Some(None);
+            }
+            Instruction::Set { namespace, name, value } => changes.push(PropertyChange::Set {
+                namespace: namespace.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            Instruction::Remove { namespace, name } => changes.push(PropertyChange::Remove {
+                namespace: namespace.clone(),
+                name: name.clone(),
+            }),
+        }
+    }
+    
+    tenant_storage
+        .patch_properties(&tenant_id, path, &changes, content_type.as_ref().map(Option::as_deref))
+        .await?;
+    
+    let results: Vec<(&Instruction, StatusCode)> = instructions
+        .iter()
+        .map(|instruction| (instruction, StatusCode::OK))
+        .collect();
+    Ok(multistatus_response(path, &results))
+}
@@ -1,5 +1,8 @@
+use crate::api::LockManagerRef;
 use crate::error::Error;
+use crate::headers::MARBLE_DEDUP;
 use crate::dav_handler::DavResponse;
+use crate::operations::preconditions::{check_preconditions, parse_if_header};
 use crate::operations::utils::get_parent_path;
 use bytes::Bytes;
 use http::{HeaderMap, Response, StatusCode};
@@ -8,21 +11,29 @@ use tracing::debug;
 use uuid::Uuid;
 
 /// Handle PUT method to create or update a file
+///
+/// The `X-Marble-Dedup` response header is `hit` if the content was already
+/// stored, so no new blob was written, and `miss` otherwise.
 pub async fn handle_put(
     tenant_storage: &TenantStorageRef,
+    lock_manager: &LockManagerRef,
     tenant_id: Uuid, 
     path: &str, 
     headers: HeaderMap, 
-    body: Bytes
+    body: Bytes,
+    normalize: impl Fn(&str) -> Result<String, Error>
 ) -> Result<DavResponse, Error> {
     debug!("PUT request for path: {} by tenant: {}", path, tenant_id);
     
+    let if_header = parse_if_header(&headers)?;
+    check_preconditions(tenant_storage, lock_manager, tenant_id, path, if_header.as_ref(), normalize).await?;
+    
     // Check if the path exists and is a directory
     let exists = tenant_storage.exists(&tenant_id, path).await?;
     if exists {
         let metadata = tenant_storage.metadata(&tenant_id, path).await?;
         if metadata.is_directory {
-            return Err(Error::WebDav("Cannot PUT to a directory".to_string()));
+            return Err(Error::MethodNotAllowed("Cannot PUT to a directory".to_string()));
         }
     }
     
@@ -36,20 +47,14 @@ pub async fn handle_put(
         }
     }
     
-    // Get content type from headers or guess from path
+    // Without a Content-Type header, storage keeps a pinned type or guesses from the path
     let content_type = headers
         .get(http::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-        .or_else(|| {
-            // Use mime_guess to determine content type from path
-            mime_guess::from_path(path)
-                .first_raw()
-                .map(|s| s.to_string())
-        });
+        .map(|s| s.to_string());
     
-    // Write the file
-    tenant_storage.write(
+    // Write the file, learning whether the tenant already held its content
+    let dedup = tenant_storage.write(
         &tenant_id, 
         path, 
         body.to_vec(), 
@@ -65,6 +70,7 @@ pub async fn handle_put(
     
     let response = Response::builder()
         .status(status)
+        .header(&*MARBLE_DEDUP, dedup.as_str())
         .body(Bytes::new())
         .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
     
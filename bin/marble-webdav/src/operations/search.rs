@@ -0,0 +1,56 @@
+use crate::error::Error;
+use crate::dav_handler::DavResponse;
+use crate::operations::propfind::path_to_href;
+use bytes::Bytes;
+use http::{Response, StatusCode};
+use marble_storage::api::TenantStorageRef;
+use serde::Serialize;
+use tracing::debug;
+use uuid::Uuid;
+
+/// A file matching a search
+#[derive(Debug, Serialize)]
+struct SearchHit {
+    href: String,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<u64>,
+}
+
+/// Handle a full-text search over the tenant's markdown files
+///
+/// The response is a JSON array of the matching files, best matches first,
+/// each with its href, size and modification time.
+pub async fn handle_search(
+    tenant_storage: &TenantStorageRef,
+    tenant_id: Uuid,
+    query: &str,
+) -> Result<DavResponse, Error> {
+    if query.trim().is_empty() {
+        return Err(Error::WebDav("Missing search query".to_string()));
+    }
+    
+    debug!("Search for {:?} by tenant: {}", query, tenant_id);
+    
+    let hits: Vec<SearchHit> = tenant_storage
+        .search(&tenant_id, query)
+        .await?
+        .into_iter()
+        .map(|metadata| SearchHit {
+            href: path_to_href(&metadata.path),
+            size: metadata.size,
+            last_modified: metadata.last_modified,
+        })
+        .collect();
+    
+    let body = serde_json::to_vec(&hits)
+        .map_err(|e| Error::Internal(format!("Failed to encode results: {}", e)))?;
+    
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body))
+        .map_err(|e| Error::Internal(format!("Failed to build response: {}", e)))?;
+    
+    Ok(response)
+}
@@ -1,6 +1,10 @@
+use crate::error::Error;
 use bytes::Bytes;
 use http::{HeaderMap, Response, StatusCode};
 
+/// Largest number of paths accepted by a batch management route
+pub const MAX_BATCH_PATHS: usize = 1000;
+
 /// Depth value for WebDAV operations
 #[derive(Debug, PartialEq, Eq)]
 pub enum Depth {
@@ -53,4 +57,64 @@ pub fn get_parent_path(path: &str) -> String {
         }
         None => ".".to_string()
     }
-}
\ No newline at end of file
+}
+/// Format a millisecond timestamp as an HTTP-date (RFC 1123), e.g.
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn http_date(millis: u64) -> Option<String> {
+    let millis = i64::try_from(millis).ok()?;
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|date| date.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+/// Escape a string for use as XML text content
+///
+/// Quotes only need escaping inside attribute values, so they are kept as
+/// they are; `property_element` escapes the one attribute it writes.
+pub fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render a property element, self-closing if it has no value
+///
+/// Properties outside `DAV:` declare their namespace as the default namespace
+/// of the element itself, so no prefixes need to be allocated.
+pub fn property_element(namespace: &str, name: &str, value: Option<&str>) -> String {
+    let (open, close) = if namespace == "DAV:" {
+        (format!("D:{}", name), format!("D:{}", name))
+    } else {
+        let namespace = xml_escape(namespace).replace('"', "&quot;");
+        (format!("{} xmlns=\"{}\"", name, namespace), name.to_string())
+    };
+    
+    match value {
+        Some(value) => format!("<{}>{}</{}>\n", open, xml_escape(value), close),
+        None => format!("<{}/>\n", open),
+    }
+}
+
+/// Parse the JSON array of paths sent to a batch management route
+///
+/// Lists longer than `MAX_BATCH_PATHS` are rejected as a bad request before
+/// any path is resolved.
+pub fn parse_path_list(body: &Bytes, route: &str) -> Result<Vec<String>, Error> {
+    let paths: Vec<String> = serde_json::from_slice(body)
+        .map_err(|e| Error::WebDav(format!("Invalid {} body: {}", route, e)))?;
+    if paths.len() > MAX_BATCH_PATHS {
+        return Err(Error::WebDav(format!(
+            "Too many paths in {} body: {} (at most {})",
+            route,
+            paths.len(),
+            MAX_BATCH_PATHS
+        )));
+    }
+    Ok(paths)
+}
@@ -0,0 +1,100 @@
+//! Markdown rendering for content negotiation
+//!
+//! When enabled, a GET for a markdown file from a client that accepts
+//! `text/html` is answered with the rendered document. The stored markdown
+//! stays authoritative; rendering happens on every such request.
+//!
+//! Notes are written by tenants and served from the WebDAV origin, so the
+//! rendered page must not run anything they contain: raw HTML is escaped into
+//! text, links and images with scriptable URLs are dropped, and responses carry
+//! [`CONTENT_SECURITY_POLICY`] as a second line of defence.
+
+use http::HeaderMap;
+use marble_storage::api::FileMetadata;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// `Content-Security-Policy` sent with rendered markdown
+///
+/// Forbids scripts, styles, frames and plugins; only images from the same
+/// origin load. `sandbox` additionally puts the page in a unique origin.
+pub const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src 'self'; sandbox";
+
+/// Whether a file holds markdown, by content type or extension
+pub fn is_markdown(metadata: &FileMetadata) -> bool {
+    let essence = metadata.content_type.split(';').next().unwrap_or("").trim();
+    if essence.eq_ignore_ascii_case("text/markdown") || essence.eq_ignore_ascii_case("text/x-markdown") {
+        return true;
+    }
+
+    let path = metadata.path.to_ascii_lowercase();
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+/// Whether the `Accept` header lists `text/html`
+///
+/// Media ranges with `q=0` are refused, as are wildcards: a client sending
+/// `*/*` gets the raw source.
+pub fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(http::header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or("");
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            media_type.eq_ignore_ascii_case("text/html") && !refused
+        })
+}
+
+/// Render markdown source to an HTML fragment
+///
+/// Raw HTML in the source is shown as text rather than passed through.
+pub fn markdown_to_html(source: &[u8]) -> String {
+    let source = String::from_utf8_lossy(source);
+    let parser = Parser::new_ext(&source, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+        .map(|event| match event {
+            Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            }),
+            event => event,
+        });
+
+    let mut output = String::new();
+    html::push_html(&mut output, parser);
+    output
+}
+
+/// Keep relative, `http`, `https` and `mailto` URLs; replace any other scheme
+///
+/// Schemes such as `javascript:` and `data:` would run or embed content
+/// chosen by the author of the note.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // A scheme is whatever precedes the first `:` unless a `/`, `?` or `#` comes first
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+
+    match scheme {
+        None => url,
+        Some(scheme) if ["http", "https", "mailto"].iter().any(|safe| scheme.trim().eq_ignore_ascii_case(safe)) => url,
+        Some(_) => CowStr::Borrowed("#"),
+    }
+}
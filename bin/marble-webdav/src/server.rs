@@ -1,19 +1,30 @@
 use axum::{
     Router,
-    extract::State,
+    body::HttpBody,
+    extract::{Request, State},
     http::{HeaderMap, Method, StatusCode, Uri},
+    middleware::{self, Next},
     response::IntoResponse,
     routing::any,
 };
 use bytes::Bytes;
 use dav_server::DavMethod;
 use std::sync::Arc;
+use std::time::Duration;
+use tower_http::compression::predicate::{Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::api::{AuthServiceRef, LockManagerRef};
-use crate::dav_handler::MarbleDavHandler;
+use crate::api::{default_method_policy, AuthServiceRef, LockManagerRef, MethodPolicy};
+use crate::config::WebDavConfig;
+use crate::dav_handler::{MarbleDavHandler, ALLOWED_METHODS};
 use crate::headers::DAV;
+use crate::lock;
+use crate::operations::get::StreamedBody;
+use crate::operations::propfind::path_to_href;
+use crate::operations::utils::xml_escape;
+use crate::stats::RequestStats;
 use marble_storage::api::TenantStorageRef;
 
 // WebDAV server state
@@ -48,17 +59,24 @@ async fn handle_webdav(
     method: Method,
     uri: Uri,
     body: Bytes,
-) -> impl IntoResponse {
+) -> axum::response::Response {
     info!("Received {} request for {}", method, uri.path());
     
     // Convert HTTP method to WebDAV method
     let dav_method = convert_method(&method);
     
-    // Extract path from URI
-    let path = uri.path();
+    // Extract the request target; management routes read its query string
+    let path = uri.path_and_query().map_or(uri.path(), |target| target.as_str());
+    
+    // POST has no WebDAV method and is only used by management routes
+    let result = if method == Method::POST {
+        state.dav_handler.handle_post(path, headers.clone(), body).await
+    } else {
+        state.dav_handler.handle(dav_method, path, headers.clone(), body).await
+    };
     
     // Call the WebDAV handler
-    match state.dav_handler.handle(dav_method, path, headers.clone(), body).await {
+    match result {
         Ok(dav_response) => {
             debug!("Successfully handled WebDAV request");
             
@@ -77,21 +95,22 @@ async fn handle_webdav(
             }
             
             if method == Method::OPTIONS && !dav_response.headers().contains_key(&*DAV) {
-                axum_response = axum_response.header(&*DAV, "1, 2");
+                axum_response = axum_response.header(&*DAV, state.dav_handler.capabilities().dav_compliance());
                 axum_response = axum_response.header("MS-Author-Via", "DAV");
             }
             
             // Set Allow header for OPTIONS requests if not set
             if method == Method::OPTIONS && !dav_response.headers().contains_key(http::header::ALLOW) {
-                axum_response = axum_response.header(
-                    http::header::ALLOW, 
-                    "OPTIONS, GET, HEAD, PUT, PROPFIND, PROPPATCH, MKCOL, DELETE, COPY, MOVE, LOCK, UNLOCK"
-                );
+                axum_response = axum_response.header(http::header::ALLOW, ALLOWED_METHODS);
             }
             
-            // Build final response with body
+            // Build final response with body, sending streamed content as it is read
+            let body = match dav_response.extensions().get::<StreamedBody>().and_then(StreamedBody::take) {
+                Some(stream) => axum::body::Body::from_stream(stream),
+                None => axum::body::Body::from(dav_response.into_body()),
+            };
             axum_response
-                .body(axum::body::Body::from(dav_response.into_body()))
+                .body(body)
                 .unwrap_or_else(|_| {
                     (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
                 })
@@ -99,70 +118,216 @@ async fn handle_webdav(
         Err(error) => {
             error!("Error handling WebDAV request: {:?}", error);
             
-            // Map error to appropriate status code and response
-            let (status_code, message) = match &error {
-                crate::error::Error::Auth(auth_error) => match auth_error {
-                    crate::error::AuthError::MissingCredentials => {
-                        let mut response = (StatusCode::UNAUTHORIZED, "Missing credentials").into_response();
-                        response.headers_mut().insert(
-                            http::header::WWW_AUTHENTICATE,
-                            http::HeaderValue::from_static("Basic realm=\"Marble WebDAV\"")
-                        );
-                        return response;
-                    },
-                    crate::error::AuthError::InvalidCredentials => {
-                        let mut response = (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response();
-                        response.headers_mut().insert(
-                            http::header::WWW_AUTHENTICATE,
-                            http::HeaderValue::from_static("Basic realm=\"Marble WebDAV\"")
-                        );
-                        return response;
-                    },
-                    _ => (StatusCode::UNAUTHORIZED, format!("Authentication error: {}", auth_error)),
-                },
-                crate::error::Error::Storage(storage_error) => match storage_error {
-                    marble_storage::StorageError::NotFound(_) => {
-                        (StatusCode::NOT_FOUND, format!("Resource not found: {}", storage_error))
-                    },
-                    _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Storage error: {}", storage_error)),
-                },
-                crate::error::Error::Lock(lock_error) => match lock_error {
-                    crate::error::LockError::ResourceLocked => {
-                        (StatusCode::LOCKED, "Resource is locked".to_string())
-                    },
-                    _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Lock error: {}", lock_error)),
-                },
-                crate::error::Error::WebDav(msg) => {
-                    if msg.contains("already exists") {
-                        (StatusCode::METHOD_NOT_ALLOWED, msg.clone())
-                    } else if msg.contains("Parent directory does not exist") {
-                        (StatusCode::CONFLICT, msg.clone())
-                    } else if msg.contains("Cannot PUT to a directory") || msg.contains("Cannot GET a directory") {
-                        (StatusCode::METHOD_NOT_ALLOWED, msg.clone())
-                    } else {
-                        (StatusCode::BAD_REQUEST, msg.clone())
-                    }
-                },
-                _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", error)),
-            };
-            
-            (status_code, message).into_response()
+            error_response(&error)
         }
     }
 }
 
+/// Map a handler error to an HTTP response
+pub(crate) fn error_response(error: &crate::error::Error) -> axum::response::Response {
+    // Map error to appropriate status code and response
+    let (status_code, message) = match error {
+        crate::error::Error::Auth(auth_error) => match auth_error {
+            crate::error::AuthError::MissingCredentials => {
+                let mut response = (StatusCode::UNAUTHORIZED, "Missing credentials").into_response();
+                response.headers_mut().insert(
+                    http::header::WWW_AUTHENTICATE,
+                    http::HeaderValue::from_static("Basic realm=\"Marble WebDAV\"")
+                );
+                return response;
+            },
+            crate::error::AuthError::InvalidCredentials => {
+                let mut response = (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response();
+                response.headers_mut().insert(
+                    http::header::WWW_AUTHENTICATE,
+                    http::HeaderValue::from_static("Basic realm=\"Marble WebDAV\"")
+                );
+                return response;
+            },
+            crate::error::AuthError::Unavailable(_) => {
+                let mut response = (StatusCode::SERVICE_UNAVAILABLE, error.to_string()).into_response();
+                response.headers_mut().insert(
+                    http::header::RETRY_AFTER,
+                    http::HeaderValue::from_static("5")
+                );
+                return response;
+            },
+            _ => (StatusCode::UNAUTHORIZED, format!("Authentication error: {}", auth_error)),
+        },
+        crate::error::Error::Storage(storage_error) => match storage_error {
+            marble_storage::StorageError::NotFound(_) => {
+                (StatusCode::NOT_FOUND, format!("Resource not found: {}", storage_error))
+            },
+            marble_storage::StorageError::ContentTypeNotAllowed(_) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Upload rejected: {}", storage_error))
+            },
+            marble_storage::StorageError::IsDirectory(path) => {
+                (StatusCode::METHOD_NOT_ALLOWED, format!("Cannot write to a directory: {}", path))
+            },
+            marble_storage::StorageError::Authorization(_) => {
+                (StatusCode::FORBIDDEN, format!("Access denied: {}", storage_error))
+            },
+            marble_storage::StorageError::FileTooLarge(_) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, format!("Upload rejected: {}", storage_error))
+            },
+            marble_storage::StorageError::FileLimitExceeded(_) | marble_storage::StorageError::QuotaExceeded(_) => {
+                (StatusCode::INSUFFICIENT_STORAGE, format!("Upload rejected: {}", storage_error))
+            },
+            marble_storage::StorageError::ParentNotFound(_) => {
+                (StatusCode::CONFLICT, format!("Conflict: {}", storage_error))
+            },
+            marble_storage::StorageError::Closed => {
+                (StatusCode::SERVICE_UNAVAILABLE, format!("Unavailable: {}", storage_error))
+            },
+            _ if storage_error.is_unavailable() => {
+                (StatusCode::SERVICE_UNAVAILABLE, format!("Unavailable: {}", storage_error))
+            },
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Storage error: {}", storage_error)),
+        },
+        crate::error::Error::Lock(lock_error) => match lock_error {
+            crate::error::LockError::TokenNotSubmitted { path, owner } => {
+                return lock_token_submitted_response(path, owner.as_deref());
+            },
+            crate::error::LockError::ResourceLocked => {
+                (StatusCode::LOCKED, "Resource is locked".to_string())
+            },
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Lock error: {}", lock_error)),
+        },
+        crate::error::Error::WebDav(msg) => {
+            (StatusCode::BAD_REQUEST, msg.clone())
+        },
+        crate::error::Error::MethodNotAllowed(msg) => {
+            (StatusCode::METHOD_NOT_ALLOWED, msg.clone())
+        },
+        crate::error::Error::Forbidden(msg) => {
+            (StatusCode::FORBIDDEN, msg.clone())
+        },
+        crate::error::Error::PreconditionFailed(msg) => {
+            (StatusCode::PRECONDITION_FAILED, msg.clone())
+        },
+        crate::error::Error::Conflict(msg) => {
+            (StatusCode::CONFLICT, msg.clone())
+        },
+        crate::error::Error::ReadOnly => {
+            let mut response = (StatusCode::SERVICE_UNAVAILABLE, error.to_string()).into_response();
+            response.headers_mut().insert(
+                http::header::RETRY_AFTER,
+                http::HeaderValue::from_static("5")
+            );
+            return response;
+        },
+        crate::error::Error::TooManyRequests(_) => {
+            let mut response = (StatusCode::SERVICE_UNAVAILABLE, error.to_string()).into_response();
+            response.headers_mut().insert(
+                http::header::RETRY_AFTER,
+                http::HeaderValue::from_static("1")
+            );
+            return response;
+        },
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", error)),
+    };
+    
+    (status_code, message).into_response()
+}
+
+/// Render a `423 Locked` response naming the locked resource (RFC 4918 §16)
+///
+/// The owner is not part of the RFC's precondition element; it is added as a
+/// sibling so clients can tell users who holds the lock.
+fn lock_token_submitted_response(path: &str, owner: Option<&str>) -> axum::response::Response {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <D:error xmlns:D=\"DAV:\">\n\
+         <D:lock-token-submitted>\n\
+         <D:href>{}</D:href>\n\
+         </D:lock-token-submitted>\n",
+        path_to_href(path)
+    );
+    if let Some(owner) = owner {
+        xml.push_str(&format!("<D:owner>{}</D:owner>\n", xml_escape(owner)));
+    }
+    xml.push_str("</D:error>");
+    
+    (
+        StatusCode::LOCKED,
+        [(http::header::CONTENT_TYPE, "application/xml")],
+        xml,
+    ).into_response()
+}
+
 // Create a WebDAV server with Axum
 pub fn create_webdav_server(
     tenant_storage: TenantStorageRef,
     auth_service: AuthServiceRef,
     lock_manager: LockManagerRef,
 ) -> Router {
+    create_webdav_server_with_config(
+        tenant_storage,
+        auth_service,
+        lock_manager,
+        WebDavConfig::default(),
+    )
+}
+
+// Create a WebDAV server with Axum using the given configuration
+pub fn create_webdav_server_with_config(
+    tenant_storage: TenantStorageRef,
+    auth_service: AuthServiceRef,
+    lock_manager: LockManagerRef,
+    config: WebDavConfig,
+) -> Router {
+    create_webdav_server_with_stats(
+        tenant_storage,
+        auth_service,
+        lock_manager,
+        config,
+        Arc::new(RequestStats::new()),
+    )
+}
+
+// Create a WebDAV server counting the requests it serves into `stats`
+pub fn create_webdav_server_with_stats(
+    tenant_storage: TenantStorageRef,
+    auth_service: AuthServiceRef,
+    lock_manager: LockManagerRef,
+    config: WebDavConfig,
+    stats: Arc<RequestStats>,
+) -> Router {
+    create_webdav_server_with_policy(
+        tenant_storage,
+        auth_service,
+        lock_manager,
+        config,
+        stats,
+        default_method_policy(),
+    )
+}
+
+// Create a WebDAV server deciding with `method_policy` which methods each user may use
+pub fn create_webdav_server_with_policy(
+    tenant_storage: TenantStorageRef,
+    auth_service: AuthServiceRef,
+    lock_manager: LockManagerRef,
+    config: WebDavConfig,
+    stats: Arc<RequestStats>,
+    method_policy: MethodPolicy,
+) -> Router {
+    let compression_min_size = config.compression_min_size;
+    let request_timeout = config.request_timeout;
+    
+    // Locks of clients that went away are removed even if nobody asks for them
+    if !config.disable_locks {
+        lock::spawn_reaper(&lock_manager, config.lock_reap_interval.unwrap_or(lock::DEFAULT_REAP_INTERVAL));
+    }
+    
     // Create the WebDAV handler
     let dav_handler = Arc::new(MarbleDavHandler::new(
         tenant_storage,
         auth_service,
         lock_manager,
-    ));
+    )
+    .with_config(config)
+    .with_method_policy(method_policy));
     
     // Create WebDAV state
     let state = Arc::new(WebDavState {
@@ -170,9 +335,106 @@ pub fn create_webdav_server(
     });
     
     // Create Axum router with Axum 0.8.x syntax
-    Router::new()
-        .route("/*path", any(handle_webdav))
+    let mut router = Router::new()
+        .route("/{*path}", any(handle_webdav))
         .route("/", any(handle_webdav))
+        // The asterisk-form target of `OPTIONS *` matches no path route
+        .fallback(handle_webdav);
+    
+    if let Some(timeout) = request_timeout {
+        router = router.layer(middleware::from_fn_with_state(timeout, enforce_request_timeout));
+    }
+    
+    // Outside the timeout so expired requests are counted, inside compression
+    // so body sizes are known
+    router = router.layer(middleware::from_fn_with_state(stats, record_stats));
+    
+    if let Some(min_size) = compression_min_size {
+        router = router.layer(compression_layer(min_size));
+    }
+    
+    router
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
+
+/// Count every answered request into `stats`, whatever layer answered it
+pub(crate) async fn record_stats(
+    State(stats): State<Arc<RequestStats>>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    let bytes_received = request.body().size_hint().exact().unwrap_or(0);
+    
+    let response = next.run(request).await;
+    
+    // Streamed bodies have no exact size, but announce their length
+    let bytes_sent = response.body().size_hint().exact()
+        .or_else(|| {
+            response.headers().get(http::header::CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok())
+                .and_then(|length| length.parse().ok())
+        })
+        .unwrap_or(0);
+    stats.record(response.status(), bytes_received, bytes_sent);
+    
+    response
+}
+
+/// Abort handling of a read request that is still running after `timeout`
+///
+/// The handler future is dropped on expiry, which releases whatever it held
+/// (database connections, tenant permits, buffered bodies) before the client
+/// gets a `504 Gateway Timeout`. Requests that change data run to completion:
+/// cancelling one halfway could leave a write partly applied, and the time
+/// spent receiving an upload says nothing about a stuck handler.
+pub(crate) async fn enforce_request_timeout(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    if !is_read_only(request.method()) {
+        return next.run(request).await;
+    }
+    
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("{} {} timed out after {:?}", method, path, timeout);
+            (StatusCode::GATEWAY_TIMEOUT, "Request timed out").into_response()
+        }
+    }
+}
+
+/// Whether a method only reads, so abandoning it changes nothing
+fn is_read_only(method: &Method) -> bool {
+    matches!(method.as_str(), "GET" | "HEAD" | "OPTIONS" | "PROPFIND")
+}
+
+/// Gzip compression for text responses of at least `min_size` bytes
+///
+/// Only `text/*` and `application/xml` bodies (markdown, PROPFIND listings) are
+/// compressed. Blobs are served as `application/octet-stream` and responses
+/// that already carry a `Content-Encoding` are passed through untouched.
+pub(crate) fn compression_layer(min_size: u16) -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(SizeAbove::new(min_size).and(is_compressible))
+}
+
+/// Whether a response has a content type worth compressing
+fn is_compressible(
+    _status: StatusCode,
+    _version: http::Version,
+    headers: &HeaderMap,
+    _extensions: &http::Extensions,
+) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .map(|content_type| content_type.trim().to_ascii_lowercase())
+        .is_some_and(|content_type| {
+            content_type.starts_with("text/") || content_type.starts_with("application/xml")
+        })
+}
@@ -0,0 +1,54 @@
+//! Startup checks
+//!
+//! The server must not serve requests against a database whose schema is
+//! missing or out of date, otherwise the first request fails with a query
+//! error. Before serving, pending migrations are either applied (when
+//! `WEBDAV_AUTO_MIGRATE` is set) or startup is refused.
+
+use sqlx::PgPool;
+use tracing::info;
+
+/// What to do about the database schema before serving
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaAction {
+    /// The schema is up to date
+    Serve,
+    /// Apply the pending migrations, then serve
+    Migrate(Vec<i64>),
+    /// Refuse to start because migrations are pending
+    Refuse(Vec<i64>),
+}
+
+/// Decide how to proceed given the pending migration versions
+pub fn schema_action(pending: Vec<i64>, auto_migrate: bool) -> SchemaAction {
+    if pending.is_empty() {
+        SchemaAction::Serve
+    } else if auto_migrate {
+        SchemaAction::Migrate(pending)
+    } else {
+        SchemaAction::Refuse(pending)
+    }
+}
+
+/// Make sure the database schema is up to date before serving
+pub async fn ensure_schema(db_pool: &PgPool, auto_migrate: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let pending = marble_db::pending_migrations(db_pool).await?;
+
+    match schema_action(pending, auto_migrate) {
+        SchemaAction::Serve => Ok(()),
+        SchemaAction::Migrate(pending) => {
+            info!("Applying {} pending migrations", pending.len());
+            marble_db::run_migrations(db_pool).await?;
+            Ok(())
+        }
+        SchemaAction::Refuse(pending) => {
+            let versions: Vec<String> = pending.iter().map(|v| v.to_string()).collect();
+            Err(format!(
+                "database schema is out of date, pending migrations: {}; \
+                 run `marble-webdav migrate` or set WEBDAV_AUTO_MIGRATE=1",
+                versions.join(", ")
+            )
+            .into())
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Counters of the requests served during the process lifetime
+//!
+//! Updated for every request reaching the WebDAV handler and logged as one
+//! structured summary at graceful shutdown, so a deploy can be checked by
+//! comparing what the old process served against what was expected.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use http::StatusCode;
+use tracing::info;
+
+/// Running totals of served requests
+#[derive(Debug, Default)]
+pub struct RequestStats {
+    requests: AtomicU64,
+    informational: AtomicU64,
+    success: AtomicU64,
+    redirection: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+}
+
+/// Totals of [`RequestStats`] at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestStatsSnapshot {
+    /// Requests answered
+    pub requests: u64,
+
+    /// Responses with a `1xx` status
+    pub informational: u64,
+
+    /// Responses with a `2xx` status
+    pub success: u64,
+
+    /// Responses with a `3xx` status
+    pub redirection: u64,
+
+    /// Responses with a `4xx` status
+    pub client_errors: u64,
+
+    /// Responses with a `5xx` status
+    pub server_errors: u64,
+
+    /// Request body bytes received
+    pub bytes_received: u64,
+
+    /// Response body bytes sent, where the body size is known up front
+    pub bytes_sent: u64,
+}
+
+impl RequestStats {
+    /// Create counters starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one answered request
+    pub fn record(&self, status: StatusCode, bytes_received: u64, bytes_sent: u64) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let class = match status.as_u16() / 100 {
+            1 => &self.informational,
+            2 => &self.success,
+            3 => &self.redirection,
+            4 => &self.client_errors,
+            _ => &self.server_errors,
+        };
+        class.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes_received, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes_sent, Ordering::Relaxed);
+    }
+
+    /// Current totals
+    pub fn snapshot(&self) -> RequestStatsSnapshot {
+        RequestStatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            informational: self.informational.load(Ordering::Relaxed),
+            success: self.success.load(Ordering::Relaxed),
+            redirection: self.redirection.load(Ordering::Relaxed),
+            client_errors: self.client_errors.load(Ordering::Relaxed),
+            server_errors: self.server_errors.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Log the totals as one structured event, meant for graceful shutdown
+    pub fn log_summary(&self) -> RequestStatsSnapshot {
+        let snapshot = self.snapshot();
+        info!(
+            requests = snapshot.requests,
+            informational = snapshot.informational,
+            success = snapshot.success,
+            redirection = snapshot.redirection,
+            client_errors = snapshot.client_errors,
+            server_errors = snapshot.server_errors,
+            bytes_received = snapshot.bytes_received,
+            bytes_sent = snapshot.bytes_sent,
+            "Requests served"
+        );
+        snapshot
+    }
+}
@@ -2,6 +2,9 @@ use std::sync::Arc;
 use bytes::Bytes;
 use http::{HeaderMap, StatusCode};
 use crate::dav_handler::MarbleDavHandler;
+use marble_storage::api::TenantStorage;
+use crate::config::{DirectoryContentType, WebDavConfig};
+use marble_storage::api::ListOrder;
 use super::{MockTenantStorage, MockAuthService, MockLockManager};
 use uuid::Uuid;
 
@@ -65,6 +68,37 @@ async fn test_get_nonexistent_file() {
     }
 }
 
+#[tokio::test]
+async fn test_head_file() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    // Same headers as GET, without the content
+    let get = handler.handle_get(tenant_id, "notes.md").await.unwrap();
+    let head = handler.handle_head(tenant_id, "notes.md").await.unwrap();
+    assert_eq!(head.status(), StatusCode::OK);
+    for header in [
+        http::header::CONTENT_LENGTH,
+        http::header::CONTENT_TYPE,
+        http::header::LAST_MODIFIED,
+        http::header::ETAG,
+    ] {
+        assert!(head.headers().contains_key(&header), "missing {}", header);
+        assert_eq!(head.headers().get(&header), get.headers().get(&header), "{}", header);
+    }
+    assert_eq!(head.headers()[http::header::CONTENT_LENGTH], "7");
+    assert!(head.body().is_empty());
+    
+    let result = handler.handle_head(tenant_id, "missing.md").await;
+    assert_eq!(crate::server::error_response(&result.unwrap_err()).status(), StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_put_file() {
     // Create test dependencies
@@ -102,6 +136,63 @@ async fn test_put_file() {
     assert_eq!(stored_content, test_content);
 }
 
+#[tokio::test]
+async fn test_put_reports_dedup() {
+    let handler = MarbleDavHandler::new(
+        Arc::new(MockTenantStorage::new()),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let dedup = |response: &crate::DavResponse| response.headers()["x-marble-dedup"].to_str().unwrap().to_string();
+    
+    let response = handler.handle_put(tenant_id, "first.txt", HeaderMap::new(), Bytes::from("same")).await.unwrap();
+    assert_eq!(dedup(&response), "miss");
+    
+    // The same content at another path is already stored
+    let response = handler.handle_put(tenant_id, "docs/second.txt", HeaderMap::new(), Bytes::from("same")).await.unwrap();
+    assert_eq!(dedup(&response), "hit");
+    
+    let response = handler.handle_put(tenant_id, "first.txt", HeaderMap::new(), Bytes::from("different")).await.unwrap();
+    assert_eq!(dedup(&response), "miss");
+}
+
+#[tokio::test]
+async fn test_put_dedup_ignores_other_tenants() {
+    let handler = MarbleDavHandler::new(
+        Arc::new(MockTenantStorage::new()),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let other_tenant = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+    let dedup = |response: &crate::DavResponse| response.headers()["x-marble-dedup"].to_str().unwrap().to_string();
+    
+    handler.handle_put(tenant_id, "secret.txt", HeaderMap::new(), Bytes::from("guessable")).await.unwrap();
+    
+    // Another tenant must not learn that the content exists
+    let response = handler.handle_put(other_tenant, "probe.txt", HeaderMap::new(), Bytes::from("guessable")).await.unwrap();
+    assert_eq!(dedup(&response), "miss");
+}
+
+#[tokio::test]
+async fn test_put_over_quota_is_insufficient_storage() {
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_quota(10));
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    
+    let response = handler.handle_put(tenant_id, "a.txt", HeaderMap::new(), Bytes::from("12345678")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let result = handler.handle_put(tenant_id, "b.txt", HeaderMap::new(), Bytes::from("123")).await;
+    assert_eq!(crate::server::error_response(&result.unwrap_err()).status(), StatusCode::INSUFFICIENT_STORAGE);
+    assert!(!tenant_storage.exists(&tenant_id, "b.txt").await.unwrap());
+}
+
 #[tokio::test]
 async fn test_mkcol_directory() {
     // Create test dependencies
@@ -167,6 +258,30 @@ async fn test_delete_file() {
     assert!(!exists);
 }
 
+#[tokio::test]
+async fn test_delete_directory_removes_subtree() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "notes");
+    tenant_storage.add_file(&tenant_id, "notes/a.md", b"A".to_vec());
+    tenant_storage.add_file(&tenant_id, "notes/sub/b.md", b"B".to_vec());
+    tenant_storage.add_file(&tenant_id, "notesx.md", b"X".to_vec());
+    
+    let response = handler.handle_delete(tenant_id, "notes").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    
+    for path in ["notes", "notes/a.md", "notes/sub", "notes/sub/b.md"] {
+        assert!(!tenant_storage.exists(&tenant_id, path).await.unwrap(), "{} survived", path);
+    }
+    assert!(tenant_storage.exists(&tenant_id, "notesx.md").await.unwrap());
+}
+
 #[tokio::test]
 async fn test_propfind_directory() {
     // Create test dependencies
@@ -208,3 +323,766 @@ async fn test_propfind_directory() {
     assert!(body.contains("file1.txt"));
     assert!(body.contains("file2.txt"));
 }
+
+/// PROPFIND body naming both quota properties
+const QUOTA_PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:propfind xmlns:D="DAV:">
+  <D:prop><D:quota-used-bytes/><D:quota-available-bytes/></D:prop>
+</D:propfind>"#;
+
+#[tokio::test]
+async fn test_propfind_quota_properties() {
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let propfind_docs = |tenant_storage: Arc<MockTenantStorage>| async move {
+        let handler = MarbleDavHandler::new(
+            tenant_storage,
+            Arc::new(MockAuthService::new()),
+            Arc::new(MockLockManager)
+        );
+        let response = handler.handle_propfind(tenant_id, "docs", Bytes::from(QUOTA_PROPFIND)).await.unwrap();
+        String::from_utf8(response.into_body().to_vec()).unwrap()
+    };
+    
+    // Without a quota only the used bytes are reported
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a.txt", vec![b'a'; 100]);
+    tenant_storage.add_file(&tenant_id, "notes/b.md", vec![b'b'; 23]);
+    let body = propfind_docs(tenant_storage).await;
+    assert!(body.contains("<D:quota-used-bytes>123</D:quota-used-bytes>"));
+    assert!(!body.contains("quota-available-bytes"));
+    
+    // Reported once, on the requested collection, counting the whole tenant
+    assert_eq!(body.matches("quota-used-bytes>").count(), 2);
+    
+    // With a quota the remaining space is reported as well
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_quota(1000));
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a.txt", vec![b'a'; 100]);
+    let body = propfind_docs(tenant_storage).await;
+    assert!(body.contains("<D:quota-used-bytes>100</D:quota-used-bytes>"));
+    assert!(body.contains("<D:quota-available-bytes>900</D:quota-available-bytes>"));
+}
+
+#[tokio::test]
+async fn test_propfind_quota_properties_only_by_name() {
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_quota(1000));
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a.txt", vec![b'a'; 100]);
+    
+    // Neither an empty body nor allprop includes them
+    let allprop = r#"<D:propfind xmlns:D="DAV:"><D:allprop/></D:propfind>"#;
+    for body in [Bytes::new(), Bytes::from(allprop)] {
+        let response = handler.handle_propfind(tenant_id, "docs", body).await.unwrap();
+        let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+        assert!(body.contains("a.txt"));
+        assert!(!body.contains("quota-"));
+    }
+    
+    // Only the named property is reported, also when included in allprop
+    let included = r#"<D:propfind xmlns:D="DAV:"><D:allprop/><D:include><D:quota-available-bytes/></D:include></D:propfind>"#;
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::from(included)).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    assert!(body.contains("<D:quota-available-bytes>900</D:quota-available-bytes>"));
+    assert!(!body.contains("quota-used-bytes"));
+}
+
+#[tokio::test]
+async fn test_propfind_usage_cached_until_modified() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a.txt", vec![b'a'; 100]);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password123")).parse().unwrap()
+    );
+    let used_bytes = || async {
+        let response = handler
+            .handle(DavMethod::PropFind, "/docs", headers.clone(), Bytes::from(QUOTA_PROPFIND))
+            .await
+            .unwrap();
+        String::from_utf8(response.into_body().to_vec()).unwrap()
+    };
+    assert!(used_bytes().await.contains("<D:quota-used-bytes>100</D:quota-used-bytes>"));
+    
+    // A change made behind the server's back is not seen while cached
+    tenant_storage.add_file(&tenant_id, "docs/b.txt", vec![b'b'; 20]);
+    assert!(used_bytes().await.contains("<D:quota-used-bytes>100</D:quota-used-bytes>"));
+    
+    // A write through the server drops the cached usage
+    handler.handle(DavMethod::Put, "/docs/c.txt", headers.clone(), Bytes::from_static(b"abc")).await.unwrap();
+    assert!(used_bytes().await.contains("<D:quota-used-bytes>123</D:quota-used-bytes>"));
+}
+
+#[tokio::test]
+async fn test_propfind_quota_properties_disabled() {
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_quota(1000));
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        disable_quotas: true,
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a.txt", vec![b'a'; 100]);
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::from(QUOTA_PROPFIND)).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    assert!(body.contains("a.txt"));
+    assert!(!body.contains("quota-used-bytes"));
+    assert!(!body.contains("quota-available-bytes"));
+}
+
+#[tokio::test]
+async fn test_propfind_list_order() {
+    // Create test dependencies
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let auth_service = Arc::new(MockAuthService::new());
+    let lock_manager = Arc::new(MockLockManager);
+    
+    // Create handler that lists the largest entries first
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        auth_service,
+        lock_manager
+    ).with_config(WebDavConfig {
+        list_order: ListOrder::SizeDesc,
+        ..Default::default()
+    });
+    
+    // Set up test data
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "sorted");
+    tenant_storage.add_file(&tenant_id, "sorted/a.txt", vec![b'a'; 1]);
+    tenant_storage.add_file(&tenant_id, "sorted/b.txt", vec![b'b'; 3]);
+    tenant_storage.add_file(&tenant_id, "sorted/c.txt", vec![b'c'; 2]);
+    
+    // Call PROPFIND method
+    let response = handler.handle_propfind(
+        tenant_id, 
+        "sorted", 
+        Bytes::new()
+    ).await.unwrap();
+    
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    // Entries should appear largest first
+    let b = body.find("/sorted/b.txt").unwrap();
+    let c = body.find("/sorted/c.txt").unwrap();
+    let a = body.find("/sorted/a.txt").unwrap();
+    assert!(b < c && c < a);
+}
+
+#[tokio::test]
+async fn test_propfind_omits_content_length_for_collections() {
+    // Create test dependencies
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let auth_service = Arc::new(MockAuthService::new());
+    let lock_manager = Arc::new(MockLockManager);
+    
+    // Create handler
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        auth_service,
+        lock_manager
+    );
+    
+    // Set up test data
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_directory(&tenant_id, "docs/nested");
+    tenant_storage.add_file(&tenant_id, "docs/file.txt", b"12345".to_vec());
+    
+    let response = handler.handle_propfind(
+        tenant_id, 
+        "docs", 
+        Bytes::new()
+    ).await.unwrap();
+    
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    // Split the multistatus into per-resource responses
+    let response_for = |href: &str| {
+        body.split("<D:response>")
+            .find(|r| r.contains(&format!("<D:href>{}</D:href>", href)))
+            .unwrap()
+            .to_string()
+    };
+    
+    // Collections carry no content length
+    assert!(!response_for("/docs").contains("getcontentlength"));
+    assert!(!response_for("/docs/nested").contains("getcontentlength"));
+    
+    // Files keep it
+    assert!(response_for("/docs/file.txt").contains("<D:getcontentlength>5</D:getcontentlength>"));
+}
+
+#[tokio::test]
+async fn test_propfind_truncated_by_latency_budget() {
+    use std::time::{Duration, Instant};
+    
+    // Storage that takes far longer to list than the budget allows
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_list_delay(Duration::from_secs(5)));
+    let auth_service = Arc::new(MockAuthService::new());
+    let lock_manager = Arc::new(MockLockManager);
+    
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        auth_service,
+        lock_manager
+    ).with_config(WebDavConfig {
+        propfind_budget: Some(Duration::from_millis(50)),
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "slow");
+    tenant_storage.add_file(&tenant_id, "slow/file.txt", b"content".to_vec());
+    
+    let started = Instant::now();
+    let response = handler.handle_propfind(
+        tenant_id, 
+        "slow", 
+        Bytes::new()
+    ).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    
+    // A valid multistatus with the collection itself and a truncation marker
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    assert!(body.starts_with("<?xml"));
+    assert!(body.ends_with("</D:multistatus>"));
+    assert!(body.contains("<D:collection/>"));
+    assert!(body.contains("HTTP/1.1 507 Insufficient Storage"));
+    assert!(body.contains("<D:number-of-matches-within-limits/>"));
+    assert!(!body.contains("file.txt"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_propfind_budget_returns_partial_listing() {
+    use std::time::Duration;
+    use tokio::time::Instant;
+    
+    // Each batch of metadata takes most of the budget, so only the first arrives in time.
+    // The paused clock only moves as the delays and the budget elapse, not with the wall clock.
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_metadata_delay(Duration::from_millis(100)));
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        propfind_budget: Some(Duration::from_millis(150)),
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "big");
+    for i in 0..150 {
+        tenant_storage.add_file(&tenant_id, &format!("big/file{:03}.txt", i), b"content".to_vec());
+    }
+    
+    let started = Instant::now();
+    let response = handler.handle_propfind(tenant_id, "big", Bytes::new()).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(150));
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    // The children listed in time, in order, then the truncation marker
+    assert!(body.contains("file000.txt"));
+    assert!(body.contains("file099.txt"));
+    assert!(!body.contains("file100.txt"));
+    assert_eq!(body.matches("<D:getcontentlength>7</D:getcontentlength>").count(), 100);
+    assert!(body.contains("HTTP/1.1 507 Insufficient Storage"));
+    assert!(body.find("file099.txt").unwrap() < body.find("507 Insufficient Storage").unwrap());
+    
+    // A listing that completes in time is not marked
+    tenant_storage.add_directory(&tenant_id, "small");
+    tenant_storage.add_file(&tenant_id, "small/one.txt", b"content".to_vec());
+    let response = handler.handle_propfind(tenant_id, "small", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    assert!(body.contains("one.txt"));
+    assert!(!body.contains("507 Insufficient Storage"));
+}
+
+#[tokio::test]
+async fn test_otp_header_appended_to_password() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    // Create test dependencies
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let auth_service = Arc::new(MockAuthService::new());
+    let lock_manager = Arc::new(MockLockManager);
+    
+    // Create handler
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        auth_service,
+        lock_manager
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "test.txt", b"content".to_vec());
+    
+    // The mock user's password is "password123"; send the suffix as the code
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password")).parse().unwrap()
+    );
+    assert!(handler.handle(DavMethod::Get, "/test.txt", headers.clone(), Bytes::new()).await.is_err());
+    
+    headers.insert(crate::headers::MARBLE_OTP.clone(), "123".parse().unwrap());
+    let response = handler.handle(DavMethod::Get, "/test.txt", headers, Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_otp_header_ignored_without_two_factor() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        disable_two_factor: true,
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "test.txt", b"content".to_vec());
+    
+    // The code is not appended, so the short password is rejected
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password")).parse().unwrap()
+    );
+    headers.insert(crate::headers::MARBLE_OTP.clone(), "123".parse().unwrap());
+    assert!(handler.handle(DavMethod::Get, "/test.txt", headers, Bytes::new()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_directory_not_allowed() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    let error = handler.handle_get(tenant_id, "docs").await.unwrap_err();
+    assert!(matches!(error, crate::error::Error::MethodNotAllowed(_)));
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn test_propfind_capped_at_max_children() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        propfind_max_children: Some(3),
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "big");
+    for i in 0..5 {
+        tenant_storage.add_file(&tenant_id, &format!("big/file-{}.txt", i), b"content".to_vec());
+    }
+    
+    let response = handler.handle_propfind(tenant_id, "big", Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    // Only the first three children are listed
+    let listed = (0..5).filter(|i| body.contains(&format!("file-{}.txt", i))).count();
+    assert_eq!(listed, 3);
+    
+    // Followed by a truncation marker for the collection
+    assert!(body.ends_with("</D:multistatus>"));
+    assert!(body.contains("HTTP/1.1 507 Insufficient Storage"));
+    assert!(body.contains("<D:number-of-matches-within-limits/>"));
+    assert!(body.contains("Listing truncated to the first 3 children"));
+}
+
+#[tokio::test]
+async fn test_propfind_within_max_children_not_truncated() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        propfind_max_children: Some(3),
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "small");
+    for i in 0..3 {
+        tenant_storage.add_file(&tenant_id, &format!("small/file-{}.txt", i), b"content".to_vec());
+    }
+    
+    let response = handler.handle_propfind(tenant_id, "small", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    assert_eq!((0..3).filter(|i| body.contains(&format!("file-{}.txt", i))).count(), 3);
+    assert!(!body.contains("number-of-matches-within-limits"));
+}
+
+/// PROPFIND `docs` with the given directory content type convention
+async fn propfind_docs(convention: DirectoryContentType) -> String {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        directory_content_type: convention,
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_directory(&tenant_id, "docs/nested");
+    tenant_storage.add_file(&tenant_id, "docs/note.md", b"# Note".to_vec());
+    
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::new()).await.unwrap();
+    String::from_utf8(response.into_body().to_vec()).unwrap()
+}
+
+/// The `<D:response>` element of a resource in a multistatus body
+fn response_for(body: &str, href: &str) -> String {
+    body.split("<D:response>")
+        .find(|r| r.contains(&format!("<D:href>{}</D:href>", href)))
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_propfind_directory_content_type_omitted() {
+    let body = propfind_docs(DirectoryContentType::Omit).await;
+    
+    assert!(!response_for(&body, "/docs").contains("getcontenttype"));
+    assert!(!response_for(&body, "/docs/nested").contains("getcontenttype"));
+    assert!(!body.contains("vnd.marble.directory"));
+    assert!(response_for(&body, "/docs/note.md").contains("<D:getcontenttype>text/markdown</D:getcontenttype>"));
+}
+
+#[tokio::test]
+async fn test_propfind_directory_content_type_unix_directory() {
+    let body = propfind_docs(DirectoryContentType::UnixDirectory).await;
+    
+    assert!(response_for(&body, "/docs").contains("<D:getcontenttype>httpd/unix-directory</D:getcontenttype>"));
+    assert!(response_for(&body, "/docs/nested").contains("<D:getcontenttype>httpd/unix-directory</D:getcontenttype>"));
+    assert!(!body.contains("vnd.marble.directory"));
+    assert!(response_for(&body, "/docs/note.md").contains("<D:getcontenttype>text/markdown</D:getcontenttype>"));
+}
+
+#[test]
+fn test_write_onto_directory_maps_to_method_not_allowed() {
+    let error = crate::error::Error::Storage(marble_storage::StorageError::IsDirectory("/docs".to_string()));
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[test]
+fn test_content_type_not_allowed_maps_to_unsupported_media_type() {
+    let error = crate::error::Error::Storage(marble_storage::StorageError::ContentTypeNotAllowed(
+        "/tool.txt: declared text/plain, detected application/x-msdownload".to_string(),
+    ));
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[test]
+fn test_http_date_formatting() {
+    use crate::operations::utils::http_date;
+    
+    assert_eq!(http_date(784_111_777_000).unwrap(), "Sun, 06 Nov 1994 08:49:37 GMT");
+    // Milliseconds are dropped, not rounded
+    assert_eq!(http_date(784_111_777_999).unwrap(), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(http_date(0).unwrap(), "Thu, 01 Jan 1970 00:00:00 GMT");
+}
+
+#[tokio::test]
+async fn test_last_modified_as_http_date() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/notes.md", b"# Notes".to_vec());
+    
+    let response = handler.handle_get(tenant_id, "docs/notes.md").await.unwrap();
+    let last_modified = response.headers()[http::header::LAST_MODIFIED].to_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc2822(last_modified).is_ok());
+    assert!(last_modified.ends_with(" GMT"));
+    
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    let start = body.find("<D:getlastmodified>").unwrap() + "<D:getlastmodified>".len();
+    let end = start + body[start..].find("</D:getlastmodified>").unwrap();
+    assert!(chrono::DateTime::parse_from_rfc2822(&body[start..end]).is_ok());
+}
+
+#[tokio::test]
+async fn test_last_modified_set_at_write() {
+    use marble_core::MockClock;
+    
+    let clock = Arc::new(MockClock::new("1994-11-06T08:49:37Z".parse().unwrap()));
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_clock(clock.clone()));
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.write(&tenant_id, "notes.md", b"# Notes".to_vec(), None).await.unwrap();
+    
+    // Later lookups report the time of the write, not the time of the lookup
+    clock.advance(chrono::Duration::hours(1));
+    let metadata = tenant_storage.metadata(&tenant_id, "notes.md").await.unwrap();
+    assert_eq!(metadata.last_modified, Some(784_111_777_000));
+    
+    let response = handler.handle_get(tenant_id, "notes.md").await.unwrap();
+    assert_eq!(response.headers()[http::header::LAST_MODIFIED], "Sun, 06 Nov 1994 08:49:37 GMT");
+    
+    // Renaming keeps the time, rewriting moves it to the time of the new write
+    tenant_storage.rename(&tenant_id, "notes.md", "renamed.md").await.unwrap();
+    let metadata = tenant_storage.metadata(&tenant_id, "renamed.md").await.unwrap();
+    assert_eq!(metadata.last_modified, Some(784_111_777_000));
+    
+    tenant_storage.write(&tenant_id, "renamed.md", b"# Rewritten".to_vec(), None).await.unwrap();
+    let metadata = tenant_storage.metadata(&tenant_id, "renamed.md").await.unwrap();
+    assert_eq!(metadata.last_modified, Some(784_115_377_000));
+}
+
+#[tokio::test]
+async fn test_percent_encoded_paths_decoded() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "issue #42.md", b"hash".to_vec());
+    tenant_storage.add_file(&tenant_id, "c++ (notes).md", b"plus".to_vec());
+    tenant_storage.add_file(&tenant_id, "日記.md", b"diary".to_vec());
+    tenant_storage.add_file(&tenant_id, "a b.md", b"space".to_vec());
+    
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password123")).parse().unwrap()
+    );
+    
+    let cases: [(&str, &[u8]); 4] = [
+        ("/issue%20%2342.md", b"hash"),
+        ("/c%2B%2B%20%28notes%29.md", b"plus"),
+        ("/%E6%97%A5%E8%A8%98.md", b"diary"),
+        ("/a%20b.md", b"space"),
+    ];
+    for (path, expected) in cases {
+        let response = handler.handle(DavMethod::Get, path, headers.clone(), Bytes::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "GET {}", path);
+        assert_eq!(response.body().as_ref(), expected, "GET {}", path);
+    }
+    
+    // A literal plus is not a space in a path
+    let result = handler.handle(DavMethod::Get, "/a+b.md", headers, Bytes::new()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_basic_auth_refused_over_plaintext_when_tls_required() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    use crate::auth::is_https_request;
+    use crate::error::Error;
+    use crate::server::error_response;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        require_tls_for_auth: true,
+        ..Default::default()
+    });
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "test.txt", b"content".to_vec());
+    
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password123")).parse().unwrap()
+    );
+    
+    // No forwarded scheme, or a plaintext one, is refused with 403
+    for proto in [None, Some("http")] {
+        let mut headers = headers.clone();
+        if let Some(proto) = proto {
+            headers.insert("x-forwarded-proto", proto.parse().unwrap());
+        }
+        let error = handler.handle(DavMethod::Get, "/test.txt", headers, Bytes::new()).await.unwrap_err();
+        assert!(matches!(error, Error::Forbidden(_)));
+        assert_eq!(error_response(&error).status(), StatusCode::FORBIDDEN);
+    }
+    
+    // Requests forwarded as HTTPS proceed
+    let mut https_headers = headers.clone();
+    https_headers.insert("x-forwarded-proto", "https".parse().unwrap());
+    let response = handler.handle(DavMethod::Get, "/test.txt", https_headers, Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let mut forwarded_headers = headers.clone();
+    forwarded_headers.insert(http::header::FORWARDED, "for=192.0.2.60;proto=https".parse().unwrap());
+    let response = handler.handle(DavMethod::Get, "/test.txt", forwarded_headers.clone(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(is_https_request(&forwarded_headers));
+}
+
+#[tokio::test]
+async fn test_propfind_escapes_xml_special_characters() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/a & b.md", b"content".to_vec());
+    tenant_storage.add_file(&tenant_id, "docs/<tag> \"quoted\".md", b"content".to_vec());
+    
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    let document = roxmltree::Document::parse(&body).expect("PROPFIND response should be well-formed XML");
+    // Hrefs are percent-encoded as well
+    let hrefs: Vec<String> = document
+        .descendants()
+        .filter(|node| node.has_tag_name(("DAV:", "href")))
+        .filter_map(|node| node.text())
+        .map(|href| percent_encoding::percent_decode_str(href).decode_utf8().unwrap().into_owned())
+        .collect();
+    assert!(hrefs.contains(&"/docs/a & b.md".to_string()));
+    assert!(hrefs.contains(&"/docs/<tag> \"quoted\".md".to_string()));
+}
+
+#[tokio::test]
+async fn test_hrefs_round_trip_through_request_paths() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let names = ["issue #42.md", "100% done.md", "a b.md", "日記.md", "r&d?.md"];
+    tenant_storage.create_directory(&tenant_id, "docs").await.unwrap();
+    for name in names {
+        tenant_storage.add_file(&tenant_id, &format!("docs/{}", name), name.as_bytes().to_vec());
+    }
+    
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password123")).parse().unwrap()
+    );
+    let mut propfind_headers = headers.clone();
+    propfind_headers.insert("Depth", "1".parse().unwrap());
+    
+    let response = handler.handle(DavMethod::PropFind, "/docs", propfind_headers, Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    let hrefs: Vec<String> = body
+        .split("<D:href>")
+        .skip(1)
+        .map(|rest| rest[..rest.find("</D:href>").unwrap()].replace("&amp;", "&"))
+        .filter(|href| href.starts_with("/docs/"))
+        .collect();
+    assert_eq!(hrefs.len(), names.len());
+    assert!(hrefs.contains(&"/docs/issue%20%2342.md".to_string()));
+    assert!(hrefs.contains(&"/docs/100%25%20done.md".to_string()));
+    
+    // Every href addresses the file it was listed for
+    for href in hrefs {
+        let response = handler.handle(DavMethod::Get, &href, headers.clone(), Bytes::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "GET {}", href);
+        let name = href.rsplit('/').next().unwrap();
+        let name = percent_encoding::percent_decode_str(name).decode_utf8().unwrap();
+        assert_eq!(response.body().as_ref(), name.as_bytes(), "GET {}", href);
+    }
+}
+
+#[tokio::test]
+async fn test_encoded_slash_in_path_rejected() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    );
+    
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "docs/a.md", b"nested".to_vec());
+    
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode("testuser:password123")).parse().unwrap()
+    );
+    
+    // An encoded slash is part of a name, never a separator
+    let error = handler.handle(DavMethod::Get, "/docs%2Fa.md", headers, Bytes::new()).await.unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use bytes::Bytes;
+use http::StatusCode;
+use crate::dav_handler::MarbleDavHandler;
+use crate::operations::utils::MAX_BATCH_PATHS;
+use marble_storage::api::TenantStorage;
+use super::{auth_headers, basic_auth, setup, MockTenantStorage, MockAuthService};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_batch_delete_reports_per_path_results() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "a.txt", b"a".to_vec());
+    tenant_storage.add_file(&tenant_id, "b.txt", b"b".to_vec());
+    tenant_storage.add_file(&tenant_id, "notes/c.md", b"c".to_vec());
+    tenant_storage.add_file(&tenant_id, "keep.txt", b"keep".to_vec());
+    
+    let body = r#"["/a.txt", "/b.txt", "/missing.txt", "/notes/c.md"]"#;
+    let response = handler.handle_post(
+        "/.marble/batch-delete",
+        auth_headers(),
+        Bytes::from(body)
+    ).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(http::header::CONTENT_TYPE).unwrap(), "application/json");
+    
+    let results: HashMap<String, String> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results["/a.txt"], "deleted");
+    assert_eq!(results["/b.txt"], "deleted");
+    assert_eq!(results["/notes/c.md"], "deleted");
+    assert_eq!(results["/missing.txt"], "not found");
+    
+    assert!(!tenant_storage.exists(&tenant_id, "a.txt").await.unwrap());
+    assert!(!tenant_storage.exists(&tenant_id, "b.txt").await.unwrap());
+    assert!(!tenant_storage.exists(&tenant_id, "notes/c.md").await.unwrap());
+    assert!(tenant_storage.exists(&tenant_id, "keep.txt").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_batch_delete_checks_locks_per_path() {
+    use crate::lock::InMemoryLockManager;
+    use dav_server::DavMethod;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(InMemoryLockManager::new())
+    );
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "locked.txt", b"locked".to_vec());
+    tenant_storage.add_file(&tenant_id, "free.txt", b"free".to_vec());
+    
+    let lock_body = r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+        </D:lockinfo>"#;
+    let response = handler
+        .handle(DavMethod::Lock, "/locked.txt", auth_headers(), Bytes::from(lock_body))
+        .await
+        .unwrap();
+    let token = response.headers()["Lock-Token"].to_str().unwrap().to_string();
+    
+    // Without the token the locked file is kept and the others are deleted
+    let body = r#"["/locked.txt", "/free.txt"]"#;
+    let response = handler.handle_post(
+        "/.marble/batch-delete",
+        auth_headers(),
+        Bytes::from(body)
+    ).await.unwrap();
+    let results: HashMap<String, String> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results["/locked.txt"], "locked");
+    assert_eq!(results["/free.txt"], "deleted");
+    assert!(tenant_storage.exists(&tenant_id, "locked.txt").await.unwrap());
+    assert!(!tenant_storage.exists(&tenant_id, "free.txt").await.unwrap());
+    
+    // A failing If header condition is reported per path
+    let mut headers = auth_headers();
+    headers.insert("If", "([\"no-such-etag\"])".parse().unwrap());
+    let response = handler.handle_post(
+        "/.marble/batch-delete",
+        headers,
+        Bytes::from(r#"["/locked.txt"]"#)
+    ).await.unwrap();
+    let results: HashMap<String, String> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results["/locked.txt"], "precondition failed");
+    assert!(tenant_storage.exists(&tenant_id, "locked.txt").await.unwrap());
+    
+    // Submitting the token deletes it
+    let mut headers = auth_headers();
+    headers.insert("If", format!("({})", token).parse().unwrap());
+    let response = handler.handle_post(
+        "/.marble/batch-delete",
+        headers,
+        Bytes::from(r#"["/locked.txt"]"#)
+    ).await.unwrap();
+    let results: HashMap<String, String> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results["/locked.txt"], "deleted");
+    assert!(!tenant_storage.exists(&tenant_id, "locked.txt").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_batch_delete_rejects_invalid_body() {
+    let (handler, _, _) = setup();
+    
+    let error = handler.handle_post(
+        "/.marble/batch-delete",
+        auth_headers(),
+        Bytes::from("not json")
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_batch_delete_rejects_too_many_paths() {
+    let (handler, _, _) = setup();
+    
+    let paths: Vec<String> = (0..=MAX_BATCH_PATHS).map(|i| format!("/file{}.txt", i)).collect();
+    let error = handler.handle_post(
+        "/.marble/batch-delete",
+        auth_headers(),
+        Bytes::from(serde_json::to_vec(&paths).unwrap())
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_post_outside_management_routes_not_allowed() {
+    let (handler, _, _) = setup();
+    
+    let error = handler.handle_post(
+        "/a.txt",
+        auth_headers(),
+        Bytes::from("[]")
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn test_batch_delete_forbidden_for_viewer() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "a.txt", b"a".to_vec());
+    
+    let error = handler.handle_post(
+        "/.marble/batch-delete",
+        basic_auth("viewer:viewpass"),
+        Bytes::from(r#"["/a.txt"]"#)
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::FORBIDDEN);
+    assert!(tenant_storage.exists(&tenant_id, "a.txt").await.unwrap());
+}
@@ -0,0 +1,71 @@
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use marble_storage::hash::hash_content;
+use super::{auth_headers, setup};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_get_blob_by_hash() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    let hash = hash_content(b"# Notes").unwrap();
+    
+    let response = handler.handle(
+        DavMethod::Get,
+        &format!("/.marble/blob/{}", hash),
+        auth_headers(),
+        Bytes::new()
+    ).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()[http::header::CACHE_CONTROL].to_str().unwrap().contains("immutable"));
+    assert_eq!(response.into_body(), Bytes::from("# Notes"));
+}
+
+#[tokio::test]
+async fn test_get_blob_of_other_tenant_is_forbidden() {
+    let (handler, tenant_storage, _tenant_id) = setup();
+    let other_tenant = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+    tenant_storage.add_file(&other_tenant, "secret.md", b"secret".to_vec());
+    let hash = hash_content(b"secret").unwrap();
+    
+    let error = handler.handle(
+        DavMethod::Get,
+        &format!("/.marble/blob/{}", hash),
+        auth_headers(),
+        Bytes::new()
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_blob_route_rejects_writes() {
+    let (handler, _tenant_storage, _tenant_id) = setup();
+    
+    let error = handler.handle(
+        DavMethod::Put,
+        "/.marble/blob/abc",
+        auth_headers(),
+        Bytes::from("data")
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn test_malformed_blob_hash_is_bad_request() {
+    let (handler, _tenant_storage, _tenant_id) = setup();
+    
+    for target in [
+        "/.marble/blob/11111111-1111-1111-1111-11111111111g",
+        "/.marble/blob/not%20a%20hash",
+        "/.marble/blob/%FF%FE",
+    ] {
+        let error = handler.handle(DavMethod::Get, target, auth_headers(), Bytes::new())
+            .await
+            .unwrap_err();
+        assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST, "{}", target);
+    }
+}
@@ -0,0 +1,46 @@
+use clap::Parser;
+use crate::cli::{Cli, Command, UserCommand};
+
+fn parse(args: &[&str]) -> Option<Command> {
+    let args = std::iter::once("marble-webdav").chain(args.iter().copied());
+    Cli::try_parse_from(args).expect("Failed to parse arguments").command
+}
+
+#[test]
+fn test_no_subcommand_starts_server() {
+    assert_eq!(parse(&[]), None);
+}
+
+#[test]
+fn test_maintenance_subcommands() {
+    assert_eq!(parse(&["migrate"]), Some(Command::Migrate));
+    assert_eq!(parse(&["gc"]), Some(Command::Gc { dry_run: false }));
+    assert_eq!(parse(&["gc", "--dry-run"]), Some(Command::Gc { dry_run: true }));
+    assert_eq!(parse(&["scrub"]), Some(Command::Scrub));
+    assert_eq!(parse(&["dangling"]), Some(Command::Dangling { tombstone: false }));
+    assert_eq!(parse(&["dangling", "--tombstone"]), Some(Command::Dangling { tombstone: true }));
+    assert_eq!(parse(&["reindex"]), Some(Command::Reindex));
+}
+
+#[test]
+fn test_user_add() {
+    assert_eq!(
+        parse(&["user", "add", "alice"]),
+        Some(Command::User {
+            command: UserCommand::Add { name: "alice".to_string(), password: None },
+        })
+    );
+    assert_eq!(
+        parse(&["user", "add", "bob", "--password", "secret"]),
+        Some(Command::User {
+            command: UserCommand::Add { name: "bob".to_string(), password: Some("secret".to_string()) },
+        })
+    );
+}
+
+#[test]
+fn test_invalid_arguments() {
+    assert!(Cli::try_parse_from(["marble-webdav", "user", "add"]).is_err());
+    assert!(Cli::try_parse_from(["marble-webdav", "scrub", "--dry-run"]).is_err());
+    assert!(Cli::try_parse_from(["marble-webdav", "frobnicate"]).is_err());
+}
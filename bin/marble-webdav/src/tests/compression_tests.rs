@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use axum::body::Body;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::{Request, Response, StatusCode};
+use tower::{service_fn, Layer, ServiceExt};
+use crate::dav_handler::MarbleDavHandler;
+use crate::server::compression_layer;
+use super::{auth_headers, setup};
+
+/// The shared fixture with a collection large enough to compress
+fn setup_with_notes() -> Arc<MarbleDavHandler> {
+    let (handler, tenant_storage, tenant_id) = setup();
+    for i in 0..50 {
+        tenant_storage.add_file(&tenant_id, &format!("notes/note-{:03}.md", i), b"# Note".to_vec());
+    }
+    Arc::new(handler)
+}
+
+/// Send a PROPFIND for the notes collection through the compression layer
+async fn propfind(handler: Arc<MarbleDavHandler>, accept_encoding: Option<&str>) -> Response<Body> {
+    let service = compression_layer(1024).layer(service_fn(move |request: Request<Body>| {
+        let handler = handler.clone();
+        async move {
+            let response = handler
+                .handle(DavMethod::PropFind, "/notes", request.headers().clone(), Bytes::new())
+                .await
+                .unwrap();
+            Ok::<_, std::convert::Infallible>(response.map(Body::from))
+        }
+    }));
+    
+    let mut request = Request::builder()
+        .uri("/notes")
+        .header(http::header::AUTHORIZATION, auth_headers()[http::header::AUTHORIZATION].clone())
+        .header("Depth", "1");
+    if let Some(encoding) = accept_encoding {
+        request = request.header(http::header::ACCEPT_ENCODING, encoding);
+    }
+    
+    let response = service.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    response.map(Body::new)
+}
+
+#[tokio::test]
+async fn test_large_propfind_gzipped_when_accepted() {
+    let response = propfind(setup_with_notes(), Some("gzip")).await;
+    
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    assert_eq!(response.headers().get(http::header::CONTENT_ENCODING).unwrap(), "gzip");
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..2], &[0x1f, 0x8b]);
+}
+
+#[tokio::test]
+async fn test_large_propfind_uncompressed_without_accept_encoding() {
+    let response = propfind(setup_with_notes(), None).await;
+    
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    assert!(response.headers().get(http::header::CONTENT_ENCODING).is_none());
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(body.starts_with(b"<?xml"));
+}
+
+#[tokio::test]
+async fn test_blobs_not_compressed() {
+    let service = compression_layer(16).layer(service_fn(|_request: Request<Body>| async {
+        let response = Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/octet-stream")
+            .body(Body::from(vec![b'a'; 4096]))
+            .unwrap();
+        Ok::<_, std::convert::Infallible>(response)
+    }));
+    
+    let request = Request::builder()
+        .header(http::header::ACCEPT_ENCODING, "gzip")
+        .body(Body::empty())
+        .unwrap();
+    let response = service.oneshot(request).await.unwrap();
+    
+    assert!(response.headers().get(http::header::CONTENT_ENCODING).is_none());
+}
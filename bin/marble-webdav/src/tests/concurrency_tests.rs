@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::config::WebDavConfig;
+use crate::dav_handler::MarbleDavHandler;
+use crate::error::Error;
+use crate::server::error_response;
+use super::{basic_auth, MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_in_flight_requests_bounded_per_tenant() {
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_list_delay(Duration::from_millis(200)));
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let other_tenant_id = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_directory(&other_tenant_id, "docs");
+    
+    let handler = MarbleDavHandler::new(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager)
+    ).with_config(WebDavConfig {
+        max_concurrent_per_tenant: Some(2),
+        ..Default::default()
+    });
+    
+    let propfind = |credentials: &str| {
+        handler.handle(DavMethod::PropFind, "/docs", basic_auth(credentials), Bytes::new())
+    };
+    
+    // Three slow listings for one tenant and one for another, all at once
+    let (first, second, third, other) = tokio::join!(
+        propfind("testuser:password123"),
+        propfind("testuser:password123"),
+        propfind("testuser:password123"),
+        propfind("otheruser:password456"),
+    );
+    
+    let results = [first, second, third];
+    let rejected: Vec<&Error> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+    assert_eq!(rejected.len(), 1);
+    assert!(matches!(rejected[0], Error::TooManyRequests(id) if *id == tenant_id));
+    
+    let response = error_response(rejected[0]);
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+    
+    // The other tenant has its own permits
+    assert!(other.is_ok());
+    
+    // Permits are returned when requests complete
+    assert!(propfind("testuser:password123").await.is_ok());
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use http::{HeaderMap, StatusCode};
 use crate::dav_handler::MarbleDavHandler;
+use marble_storage::api::TenantStorage;
 use super::{MockTenantStorage, MockAuthService, MockLockManager};
 use uuid::Uuid;
 
@@ -26,7 +27,7 @@ async fn test_copy_file() {
     // Create headers with Destination
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/destination.txt".parse().unwrap()
     );
     
@@ -71,7 +72,7 @@ async fn test_copy_directory() {
     // Create headers with Destination
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/dest_dir".parse().unwrap()
     );
     
@@ -122,7 +123,7 @@ async fn test_overwrite_existing_file() {
     // Create headers with Destination and Overwrite: T
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/dest.txt".parse().unwrap()
     );
     headers.insert("Overwrite", "T".parse().unwrap());
@@ -160,7 +161,7 @@ async fn test_copy_with_no_overwrite() {
     // Create headers with Destination and Overwrite: F (false)
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/dest.txt".parse().unwrap()
     );
     headers.insert("Overwrite", "F".parse().unwrap());
@@ -168,12 +169,14 @@ async fn test_copy_with_no_overwrite() {
     // Call COPY method - should fail since overwrite is false
     let result = handler.handle_copy(tenant_id, "source.txt", headers).await;
     
-    // Verify error
+    // Verify error, answered with 412 Precondition Failed (RFC 4918 §9.8.5, §9.9.4)
     assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::error::Error::WebDav(msg) if msg.contains("Destination already exists and overwrite is false") => (),
+    let error = result.unwrap_err();
+    match &error {
+        crate::error::Error::PreconditionFailed(msg) if msg.contains("Destination already exists and overwrite is false") => (),
         err => panic!("Unexpected error: {:?}", err),
     }
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::PRECONDITION_FAILED);
     
     // Verify destination file was not changed
     let dest_content = tenant_storage.read(&tenant_id, "dest.txt").await.unwrap();
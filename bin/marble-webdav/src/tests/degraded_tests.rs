@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::config::WebDavConfig;
+use crate::dav_handler::MarbleDavHandler;
+use crate::degraded::{is_database_unavailable, DegradedMode};
+use crate::error::{AuthError, Error};
+use crate::server::error_response;
+use super::{auth_headers, basic_auth, MockTenantStorage, MockAuthService};
+use uuid::Uuid;
+
+fn setup() -> (MarbleDavHandler, Arc<MockTenantStorage>) {
+    let (handler, tenant_storage, _) = setup_with_auth();
+    (handler, tenant_storage)
+}
+
+/// The shared fixture with degraded mode on and two notes to read
+fn setup_with_auth() -> (MarbleDavHandler, Arc<MockTenantStorage>, Arc<MockAuthService>) {
+    let (handler, tenant_storage, auth_service, tenant_id) = super::setup_with_auth();
+    tenant_storage.add_file(&tenant_id, "published.md", b"# Published".to_vec());
+    tenant_storage.add_file(&tenant_id, "unread.md", b"# Unread".to_vec());
+    
+    let handler = handler.with_config(WebDavConfig {
+        degraded_cache_bytes: Some(1024 * 1024),
+        ..Default::default()
+    });
+    (handler, tenant_storage, auth_service)
+}
+
+#[tokio::test]
+async fn test_cached_reads_served_and_writes_rejected_while_database_down() {
+    let (handler, tenant_storage) = setup();
+    
+    // Served once while healthy, so it is cached
+    let response = handler.handle(DavMethod::Get, "/published.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    tenant_storage.set_database_down(true);
+    
+    // Reads fail until the outage is sustained, then come from the cache
+    for _ in 1..crate::degraded::DEFAULT_FAILURE_THRESHOLD {
+        let result = handler.handle(DavMethod::Get, "/unread.md", auth_headers(), Bytes::new()).await;
+        assert!(result.is_err());
+    }
+    let response = handler.handle(DavMethod::Get, "/published.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().as_ref(), b"# Published");
+    
+    // Files never served before are still unavailable
+    let result = handler.handle(DavMethod::Get, "/unread.md", auth_headers(), Bytes::new()).await;
+    assert!(result.is_err());
+    
+    // Writes are refused with 503
+    let error = handler
+        .handle(DavMethod::Put, "/new.md", auth_headers(), Bytes::from_static(b"new"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::ReadOnly));
+    assert_eq!(error_response(&error).status(), StatusCode::SERVICE_UNAVAILABLE);
+    
+    // So are management routes that modify data
+    let error = handler
+        .handle_post("/.marble/batch-delete", auth_headers(), Bytes::from_static(b"[\"/published.md\"]"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::ReadOnly));
+    
+    // The first successful read after the database returns ends the degraded state
+    tenant_storage.set_database_down(false);
+    let response = handler.handle(DavMethod::Get, "/unread.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = handler
+        .handle(DavMethod::Put, "/new.md", auth_headers(), Bytes::from_static(b"new"))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
+
+#[test]
+fn test_read_cache_evicts_oldest_within_budget() {
+    let degraded = DegradedMode::new(10);
+    let tenant_id = Uuid::new_v4();
+    let response = |body: &'static [u8]| {
+        http::Response::builder().status(StatusCode::OK).body(Bytes::from_static(body)).unwrap()
+    };
+    
+    degraded.cache_read(tenant_id, "a", &response(b"123456"));
+    degraded.cache_read(tenant_id, "b", &response(b"1234"));
+    degraded.cache_read(tenant_id, "c", &response(b"12"));
+    assert!(degraded.cached_read(tenant_id, "a").is_none());
+    assert!(degraded.cached_read(tenant_id, "b").is_some());
+    assert!(degraded.cached_read(tenant_id, "c").is_some());
+    
+    // Larger than the whole cache
+    degraded.cache_read(tenant_id, "d", &response(b"12345678901"));
+    assert!(degraded.cached_read(tenant_id, "d").is_none());
+    
+    degraded.invalidate_tenant(tenant_id);
+    assert!(degraded.cached_read(tenant_id, "b").is_none());
+}
+
+#[tokio::test]
+async fn test_auth_outage_is_unavailable_and_serves_remembered_users() {
+    let (handler, tenant_storage, auth_service) = setup_with_auth();
+    
+    let response = handler.handle(DavMethod::Get, "/published.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // The user lookup fails first during an outage; that is a 503, not bad credentials
+    auth_service.set_database_down(true);
+    tenant_storage.set_database_down(true);
+    let error = handler
+        .handle(DavMethod::Get, "/published.md", auth_headers(), Bytes::new())
+        .await
+        .unwrap_err();
+    assert!(is_database_unavailable(&error));
+    let response = error_response(&error);
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+    
+    // Failed authentications count towards degrading
+    for _ in 2..crate::degraded::DEFAULT_FAILURE_THRESHOLD {
+        let _ = handler.handle(DavMethod::Put, "/new.md", auth_headers(), Bytes::from_static(b"new")).await;
+    }
+    
+    // Once degraded, users who authenticated before the outage get cached reads
+    let response = handler.handle(DavMethod::Get, "/published.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().as_ref(), b"# Published");
+    
+    // Credentials never seen before are not let in
+    let error = handler
+        .handle(DavMethod::Get, "/published.md", basic_auth("testuser:guess"), Bytes::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Auth(AuthError::Unavailable(_))));
+    
+    // Writes still need the database
+    let error = handler
+        .handle(DavMethod::Put, "/new.md", auth_headers(), Bytes::from_static(b"new"))
+        .await
+        .unwrap_err();
+    assert_eq!(error_response(&error).status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[test]
+fn test_database_unavailable_is_typed() {
+    use marble_storage::StorageError;
+    
+    assert!(is_database_unavailable(&Error::Storage(StorageError::Unavailable("down".to_string()))));
+    assert!(is_database_unavailable(&Error::Storage(StorageError::Database(sqlx::Error::PoolClosed))));
+    assert!(!is_database_unavailable(&Error::Storage(StorageError::Storage("Database error: unique violation".to_string()))));
+    
+    let unavailable = StorageError::from(marble_db::Error::QueryFailed(sqlx::Error::PoolTimedOut));
+    assert!(matches!(unavailable, StorageError::Unavailable(_)));
+    let rejected = StorageError::from(marble_db::Error::QueryFailed(sqlx::Error::RowNotFound));
+    assert!(!rejected.is_unavailable());
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use http::{HeaderMap, StatusCode};
+use crate::config::WebDavConfig;
+use crate::dav_handler::MarbleDavHandler;
+use crate::etag::{if_none_match, weak_eq, EtagPolicy};
+use crate::metadata_cache::MetadataCache;
+use crate::operations;
+use marble_storage::api::TenantStorageRef;
+use marble_storage::hash::hash_content;
+use super::{setup, MockTenantStorage};
+use uuid::Uuid;
+
+/// The shared fixture with the given ETag policy
+fn setup_with_policy(etag_policy: EtagPolicy) -> (MarbleDavHandler, Arc<MockTenantStorage>, Uuid) {
+    let (handler, tenant_storage, tenant_id) = setup();
+    let handler = handler.with_config(WebDavConfig {
+        etag_policy,
+        ..Default::default()
+    });
+    (handler, tenant_storage, tenant_id)
+}
+
+#[test]
+fn test_etag_formatting_and_comparison() {
+    assert_eq!(EtagPolicy::Strong.format("abc"), "\"abc\"");
+    assert_eq!(EtagPolicy::Weak.format("abc"), "W/\"abc\"");
+    assert_eq!(EtagPolicy::parse("Weak"), Some(EtagPolicy::Weak));
+    assert_eq!(EtagPolicy::parse("other"), None);
+    
+    // Weak comparison ignores the weak indicator on either side
+    assert!(weak_eq("W/\"abc\"", "\"abc\""));
+    assert!(weak_eq("\"abc\"", "W/\"abc\""));
+    assert!(!weak_eq("\"abc\"", "\"abd\""));
+    
+    assert!(if_none_match("\"x\", W/\"abc\"", "W/\"abc\""));
+    assert!(if_none_match("*", "\"abc\""));
+    assert!(!if_none_match("\"x\"", "\"abc\""));
+}
+
+#[test]
+fn test_etag_includes_size() {
+    use marble_storage::api::FileMetadata;
+    
+    let metadata = |size| FileMetadata {
+        path: "/notes.md".to_string(),
+        size,
+        content_type: "text/markdown".to_string(),
+        is_directory: false,
+        last_modified: None,
+        last_accessed: None,
+        content_hash: Some("abc".to_string()),
+    };
+    
+    // Equal hash and size give equal tags
+    let etag = EtagPolicy::Strong.etag_for(&metadata(7)).unwrap();
+    assert_eq!(etag, "\"abc-7\"");
+    assert_eq!(EtagPolicy::Strong.etag_for(&metadata(7)), Some(etag.clone()));
+    
+    // A recorded size that disagrees with the hash changes the tag
+    let truncated = EtagPolicy::Strong.etag_for(&metadata(3)).unwrap();
+    assert_ne!(etag, truncated);
+    assert!(!weak_eq(&etag, &truncated));
+    assert!(!if_none_match(&etag, &truncated));
+}
+
+#[tokio::test]
+async fn test_strong_etag_matches_metadata_hash() {
+    use marble_storage::api::TenantStorage;
+    
+    let (handler, tenant_storage, tenant_id) = setup_with_policy(EtagPolicy::default());
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/notes.md", b"# Notes".to_vec());
+    let hash = tenant_storage.metadata(&tenant_id, "docs/notes.md").await.unwrap().content_hash.unwrap();
+    
+    let response = handler.handle_get(tenant_id, "docs/notes.md").await.unwrap();
+    assert_eq!(
+        response.headers()[http::header::ETAG].to_str().unwrap(),
+        format!("\"{}-7\"", hash)
+    );
+    
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    assert!(body.contains(&format!("<D:getetag>\"{}-7\"</D:getetag>", hash)));
+    // The collection has no content hash and so no ETag
+    assert_eq!(body.matches("<D:getetag>").count(), 1);
+}
+
+#[tokio::test]
+async fn test_weak_etag_on_get() {
+    let (handler, tenant_storage, tenant_id) = setup_with_policy(EtagPolicy::Weak);
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    let hash = hash_content(b"# Notes").unwrap();
+    
+    let response = handler.handle_get(tenant_id, "notes.md").await.unwrap();
+    assert_eq!(
+        response.headers()[http::header::ETAG].to_str().unwrap(),
+        format!("W/\"{}-7\"", hash)
+    );
+}
+
+#[tokio::test]
+async fn test_weak_etag_in_propfind() {
+    let (handler, tenant_storage, tenant_id) = setup_with_policy(EtagPolicy::Weak);
+    tenant_storage.add_directory(&tenant_id, "docs");
+    tenant_storage.add_file(&tenant_id, "docs/notes.md", b"# Notes".to_vec());
+    let hash = hash_content(b"# Notes").unwrap();
+    
+    let response = handler.handle_propfind(tenant_id, "docs", Bytes::new()).await.unwrap();
+    let body = String::from_utf8(response.into_body().to_vec()).unwrap();
+    
+    assert!(body.contains(&format!("<D:getetag>W/\"{}-7\"</D:getetag>", hash)));
+    // Only the file has an ETag, not the collection
+    assert_eq!(body.matches("<D:getetag>").count(), 1);
+}
+
+#[tokio::test]
+async fn test_if_none_match_weak_comparison() {
+    let (_handler, tenant_storage, tenant_id) = setup_with_policy(EtagPolicy::Weak);
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    let hash = hash_content(b"# Notes").unwrap();
+    let storage: TenantStorageRef = tenant_storage;
+    let weak_config = WebDavConfig {
+        etag_policy: EtagPolicy::Weak,
+        ..Default::default()
+    };
+    
+    // Both the weak tag and its strong spelling match under weak comparison
+    for tag in [format!("W/\"{}-7\"", hash), format!("\"{}-7\"", hash)] {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::IF_NONE_MATCH, tag.parse().unwrap());
+        
+        let response = operations::handle_get(&storage, tenant_id, "notes.md", &headers, &weak_config, &MetadataCache::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+    }
+    
+    // A stale tag gets the full response
+    let mut headers = HeaderMap::new();
+    headers.insert(http::header::IF_NONE_MATCH, "W/\"stale\"".parse().unwrap());
+    let response = operations::handle_get(&storage, tenant_id, "notes.md", &headers, &weak_config, &MetadataCache::default())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,168 @@
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::{HeaderMap, StatusCode};
+use crate::error::Error;
+use crate::idempotency::{Begin, IdempotencyCache};
+use crate::server::error_response;
+use marble_storage::api::TenantStorage;
+use super::{auth_headers, setup};
+use uuid::Uuid;
+
+fn headers_with_key(key: Option<&str>) -> HeaderMap {
+    let mut headers = auth_headers();
+    if let Some(key) = key {
+        headers.insert(crate::headers::IDEMPOTENCY_KEY.clone(), key.parse().unwrap());
+    }
+    headers
+}
+
+#[tokio::test]
+async fn test_repeated_put_with_same_key_writes_once() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    let headers = headers_with_key(Some("put-1"));
+    
+    let first = handler.handle(DavMethod::Put, "/notes.md", headers.clone(), Bytes::from("first"))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::CREATED);
+    
+    // The retry is answered from the record instead of overwriting the file
+    let retry = handler.handle(DavMethod::Put, "/notes.md", headers, Bytes::from("second"))
+        .await
+        .unwrap();
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"first");
+    
+    // A new key executes again
+    let update = handler.handle(DavMethod::Put, "/notes.md", headers_with_key(Some("put-2")), Bytes::from("second"))
+        .await
+        .unwrap();
+    assert_eq!(update.status(), StatusCode::NO_CONTENT);
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"second");
+}
+
+#[tokio::test]
+async fn test_put_without_key_is_not_recorded() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    
+    handler.handle(DavMethod::Put, "/notes.md", headers_with_key(None), Bytes::from("first"))
+        .await
+        .unwrap();
+    let second = handler.handle(DavMethod::Put, "/notes.md", headers_with_key(None), Bytes::from("second"))
+        .await
+        .unwrap();
+    
+    assert_eq!(second.status(), StatusCode::NO_CONTENT);
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"second");
+}
+
+#[tokio::test]
+async fn test_key_reused_for_different_request_is_rejected() {
+    let (handler, _tenant_storage, _tenant_id) = setup();
+    let headers = headers_with_key(Some("reused"));
+    
+    handler.handle(DavMethod::Put, "/a.md", headers.clone(), Bytes::from("a"))
+        .await
+        .unwrap();
+    let result = handler.handle(DavMethod::Put, "/b.md", headers, Bytes::from("b")).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_expired_key_executes_again() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    let handler = handler.with_config(crate::config::WebDavConfig {
+        idempotency_window: Some(std::time::Duration::ZERO),
+        ..Default::default()
+    });
+    let headers = headers_with_key(Some("short-lived"));
+    
+    handler.handle(DavMethod::Put, "/notes.md", headers.clone(), Bytes::from("first"))
+        .await
+        .unwrap();
+    let retry = handler.handle(DavMethod::Put, "/notes.md", headers, Bytes::from("second"))
+        .await
+        .unwrap();
+    
+    assert_eq!(retry.status(), StatusCode::NO_CONTENT);
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"second");
+}
+
+fn created_response(body: &'static [u8]) -> crate::dav_handler::DavResponse {
+    let mut response = http::Response::new(Bytes::from_static(body));
+    *response.status_mut() = StatusCode::CREATED;
+    response
+}
+
+#[tokio::test]
+async fn test_key_in_flight_is_a_conflict_until_released() {
+    let cache = IdempotencyCache::default();
+    let tenant_id = Uuid::new_v4();
+    
+    let Ok(Begin::Execute(reservation)) = cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await else {
+        panic!("first request should execute");
+    };
+    
+    // A retry racing the first attempt must not execute it again
+    let error = cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await.err().unwrap();
+    assert!(matches!(error, Error::Conflict(_)));
+    assert_eq!(error_response(&error).status(), StatusCode::CONFLICT);
+    
+    // A request that never completes releases the key
+    drop(reservation);
+    let Ok(Begin::Execute(reservation)) = cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await else {
+        panic!("released key should execute again");
+    };
+    reservation.complete(&created_response(b""));
+    assert!(matches!(cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await, Ok(Begin::Replay(_))));
+}
+
+#[tokio::test]
+async fn test_large_bodies_are_not_recorded() {
+    let cache = IdempotencyCache::default().with_max_body_bytes(4);
+    let tenant_id = Uuid::new_v4();
+    
+    let Ok(Begin::Execute(reservation)) = cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await else {
+        panic!("first request should execute");
+    };
+    reservation.complete(&created_response(b"too large"));
+    
+    assert!(matches!(cache.begin(tenant_id, "k", DavMethod::Put, "a.md").await, Ok(Begin::Execute(_))));
+}
+
+#[tokio::test]
+async fn test_table_is_bounded() {
+    let cache = IdempotencyCache::default().with_max_entries(1);
+    let tenant_id = Uuid::new_v4();
+    
+    let Ok(Begin::Execute(first)) = cache.begin(tenant_id, "first", DavMethod::Put, "a.md").await else {
+        panic!("first request should execute");
+    };
+    
+    // Keys in flight are never evicted
+    let error = cache.begin(tenant_id, "second", DavMethod::Put, "b.md").await.err().unwrap();
+    assert!(matches!(error, Error::TooManyRequests(_)));
+    
+    // Answered keys make room for new ones
+    first.complete(&created_response(b""));
+    assert!(matches!(cache.begin(tenant_id, "second", DavMethod::Put, "b.md").await, Ok(Begin::Execute(_))));
+    assert!(matches!(cache.begin(tenant_id, "first", DavMethod::Put, "a.md").await, Ok(Begin::Execute(_))));
+}
+
+#[tokio::test]
+async fn test_failed_request_releases_key() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    let headers = headers_with_key(Some("retry-after-failure"));
+    
+    // The first attempt fails and records nothing
+    tenant_storage.set_database_down(true);
+    let result = handler.handle(DavMethod::Put, "/notes.md", headers.clone(), Bytes::from("first")).await;
+    assert!(result.is_err());
+    
+    tenant_storage.set_database_down(false);
+    let retry = handler.handle(DavMethod::Put, "/notes.md", headers, Bytes::from("first"))
+        .await
+        .unwrap();
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"first");
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::Bytes;
+use http::{HeaderMap, StatusCode};
+use crate::api::{LockManagerRef, LockScope};
+use crate::error::{Error, LockError};
+use crate::if_header::{tag_matches, Condition, ConditionKind, IfHeader, IfList, ResourceState};
+use crate::lock::InMemoryLockManager;
+use crate::operations;
+use crate::server::error_response;
+use super::normalize;
+use marble_storage::api::TenantStorageRef;
+use marble_storage::hash::hash_content;
+use super::MockTenantStorage;
+use uuid::Uuid;
+
+const TOKEN: &str = "urn:uuid:6f1b7c0e-0000-4000-8000-000000000001";
+
+async fn setup() -> (TenantStorageRef, LockManagerRef, Uuid, String) {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    let etag = format!("\"{}-7\"", hash_content(b"# Notes").unwrap());
+    
+    let lock_manager: LockManagerRef = Arc::new(InMemoryLockManager::new());
+    (tenant_storage, lock_manager, tenant_id, etag)
+}
+
+fn if_headers(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("If", value.parse().unwrap());
+    headers
+}
+
+async fn put(
+    storage: &TenantStorageRef,
+    lock_manager: &LockManagerRef,
+    tenant_id: Uuid,
+    headers: HeaderMap,
+) -> Result<StatusCode, Error> {
+    operations::handle_put(storage, lock_manager, tenant_id, "notes.md", headers, Bytes::from("updated"), normalize)
+        .await
+        .map(|response| response.status())
+}
+
+#[test]
+fn test_parse_tagged_and_negated_conditions() {
+    let header = IfHeader::parse(
+        "<http://example.com/dav/notes.md> (<urn:uuid:a> [\"abc\"]) (Not <DAV:no-lock>)"
+    ).unwrap();
+    
+    let resource = Some("http://example.com/dav/notes.md".to_string());
+    assert_eq!(header.lists, vec![
+        IfList {
+            resource: resource.clone(),
+            conditions: vec![
+                Condition { negated: false, kind: ConditionKind::StateToken("urn:uuid:a".to_string()) },
+                Condition { negated: false, kind: ConditionKind::ETag("\"abc\"".to_string()) },
+            ],
+        },
+        IfList {
+            resource,
+            conditions: vec![
+                Condition { negated: true, kind: ConditionKind::StateToken("DAV:no-lock".to_string()) },
+            ],
+        },
+    ]);
+    
+    assert!(tag_matches("http://example.com/dav/notes.md", "notes.md", normalize));
+    assert!(!tag_matches("http://example.com/dav/other.md", "notes.md", normalize));
+    
+    // Tags are percent-decoded like request paths
+    assert!(tag_matches("/caf%C3%A9.md", "café.md", normalize));
+    assert!(tag_matches("http://example.com/dav/a%2Bb.md", "a+b.md", normalize));
+    assert!(tag_matches("/my%20notes/today.md", "my notes/today.md", normalize));
+    
+    // Mixing tagged and untagged lists, and empty lists, are rejected
+    assert!(IfHeader::parse("(<urn:uuid:a>) <http://x/y> (<urn:uuid:b>)").is_err());
+    assert!(IfHeader::parse("()").is_err());
+    assert!(IfHeader::parse("<urn:uuid:a>").is_err());
+}
+
+#[test]
+fn test_parse_multiple_lists() {
+    // Untagged lists are alternatives for the request's resource
+    let header = IfHeader::parse("(<urn:uuid:a>) (Not [\"x\"] <urn:uuid:b>)\t([W/\"y\"])").unwrap();
+    assert_eq!(header.lists.len(), 3);
+    assert!(header.lists.iter().all(|list| list.resource.is_none()));
+    assert_eq!(header.lists[1].conditions, vec![
+        Condition { negated: true, kind: ConditionKind::ETag("\"x\"".to_string()) },
+        Condition { negated: false, kind: ConditionKind::StateToken("urn:uuid:b".to_string()) },
+    ]);
+    assert_eq!(header.lists[2].conditions[0].kind, ConditionKind::ETag("W/\"y\"".to_string()));
+    
+    // Each resource tag applies to the lists following it
+    let header = IfHeader::parse("</a.md> (<urn:uuid:a>) (<urn:uuid:b>) </b.md> ([\"b\"])").unwrap();
+    let resources: Vec<_> = header.lists.iter().map(|list| list.resource.as_deref()).collect();
+    assert_eq!(resources, vec![Some("/a.md"), Some("/a.md"), Some("/b.md")]);
+    
+    // Only the lists tagged for the resource are evaluated
+    let tokens = vec!["urn:uuid:b".to_string()];
+    let state = ResourceState { lock_tokens: &tokens, etag: Some("\"other\"") };
+    assert!(header.evaluate(|tag| tag_matches(tag, "a.md", normalize), &state));
+    assert!(!header.evaluate(|tag| tag_matches(tag, "b.md", normalize), &state));
+    assert!(header.evaluate(|tag| tag_matches(tag, "c.md", normalize), &state));
+    
+    // Malformed headers are errors rather than panics
+    assert!(IfHeader::parse("(éé)").is_err());
+    assert!(IfHeader::parse("(<urn:uuid:a>").is_err());
+}
+
+#[tokio::test]
+async fn test_combined_lock_and_etag_condition() {
+    let (storage, lock_manager, tenant_id, etag) = setup().await;
+    lock_manager.lock(&tenant_id, "notes.md", Duration::from_secs(60), TOKEN, None, LockScope::Exclusive).await.unwrap();
+    
+    let status = put(&storage, &lock_manager, tenant_id, if_headers(&format!("(<{}> [{}])", TOKEN, etag)))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(storage.read(&tenant_id, "notes.md").await.unwrap(), b"updated");
+}
+
+#[tokio::test]
+async fn test_tagged_condition_with_encoded_path() {
+    let (_, lock_manager, tenant_id, _) = setup().await;
+    let mock = MockTenantStorage::new();
+    mock.add_file(&tenant_id, "café.md", b"# Cafe".to_vec());
+    let storage: TenantStorageRef = Arc::new(mock);
+    lock_manager.lock(&tenant_id, "café.md", Duration::from_secs(60), TOKEN, None, LockScope::Exclusive).await.unwrap();
+    
+    let headers = if_headers(&format!("</caf%C3%A9.md> (<{}>)", TOKEN));
+    let response = operations::handle_put(&storage, &lock_manager, tenant_id, "café.md", headers, Bytes::from("updated"), normalize)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_negated_conditions() {
+    let (storage, lock_manager, tenant_id, etag) = setup().await;
+    
+    // The resource is not locked by this token, so the negation holds
+    let status = put(&storage, &lock_manager, tenant_id, if_headers("(Not <urn:uuid:unknown>)"))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    
+    // The content changed, so the old entity tag no longer matches and its negation holds
+    let status = put(&storage, &lock_manager, tenant_id, if_headers(&format!("(Not [{}])", etag)))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    
+    // Negating the current entity tag fails
+    let current = format!("\"{}-7\"", hash_content(b"updated").unwrap());
+    let result = put(&storage, &lock_manager, tenant_id, if_headers(&format!("(Not [{}])", current))).await;
+    assert!(matches!(result, Err(Error::PreconditionFailed(_))));
+}
+
+#[tokio::test]
+async fn test_failing_etag_is_precondition_failed() {
+    let (storage, lock_manager, tenant_id, _etag) = setup().await;
+    
+    let error = put(&storage, &lock_manager, tenant_id, if_headers("([\"stale\"])")).await.unwrap_err();
+    assert_eq!(error_response(&error).status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(storage.read(&tenant_id, "notes.md").await.unwrap(), b"# Notes");
+    
+    // A second list that holds makes the header true
+    let (storage, lock_manager, tenant_id, etag) = setup().await;
+    let status = put(&storage, &lock_manager, tenant_id, if_headers(&format!("([\"stale\"]) ([{}])", etag)))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_locked_resource_requires_token() {
+    let (storage, lock_manager, tenant_id, etag) = setup().await;
+    lock_manager.lock(&tenant_id, "notes.md", Duration::from_secs(60), TOKEN, None, LockScope::Exclusive).await.unwrap();
+    
+    // A true condition without the lock token is not enough
+    let result = put(&storage, &lock_manager, tenant_id, if_headers(&format!("([{}])", etag))).await;
+    assert!(matches!(result, Err(Error::Lock(LockError::TokenNotSubmitted { .. }))));
+    
+    let result = put(&storage, &lock_manager, tenant_id, HeaderMap::new()).await;
+    assert_eq!(error_response(&result.unwrap_err()).status(), StatusCode::LOCKED);
+    
+    // Deleting with the token succeeds
+    let response = operations::handle_delete(&storage, &lock_manager, tenant_id, "notes.md", if_headers(&format!("(<{}>)", TOKEN)), normalize)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_locked_resource_accepts_put_with_token() {
+    let (storage, lock_manager, tenant_id, _) = setup().await;
+    lock_manager.lock(&tenant_id, "notes.md", Duration::from_secs(60), TOKEN, None, LockScope::Exclusive).await.unwrap();
+    
+    let status = put(&storage, &lock_manager, tenant_id, if_headers(&format!("(<{}>)", TOKEN))).await.unwrap();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_locked_resource_move_requires_token() {
+    let (storage, lock_manager, tenant_id, _) = setup().await;
+    lock_manager.lock(&tenant_id, "notes.md", Duration::from_secs(60), TOKEN, None, LockScope::Exclusive).await.unwrap();
+    
+    let move_headers = |if_value: Option<&str>| {
+        let mut headers = if_value.map(if_headers).unwrap_or_default();
+        headers.insert("Destination", "http://localhost/renamed.md".parse().unwrap());
+        headers
+    };
+    let normalize = |path: &str| Ok(path.trim_start_matches('/').to_string());
+    
+    // Moving without the token is refused and leaves the source in place
+    let result = operations::handle_move(&storage, &lock_manager, tenant_id, "notes.md", move_headers(None), normalize).await;
+    assert_eq!(error_response(&result.unwrap_err()).status(), StatusCode::LOCKED);
+    assert!(storage.exists(&tenant_id, "notes.md").await.unwrap());
+    
+    // The lock owner moves the file with its token
+    let if_value = format!("(<{}>)", TOKEN);
+    let response = operations::handle_move(&storage, &lock_manager, tenant_id, "notes.md", move_headers(Some(&if_value)), normalize)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(!storage.exists(&tenant_id, "notes.md").await.unwrap());
+    assert!(storage.exists(&tenant_id, "renamed.md").await.unwrap());
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::config::WebDavConfig;
+use crate::dav_handler::MarbleDavHandler;
+use crate::lock::InMemoryLockManager;
+use crate::server::error_response;
+use super::{auth_headers, setup_with_locks};
+
+/// The shared fixture with real locks and a file to lock
+fn setup_with_todo(expose_lock_owner: bool) -> MarbleDavHandler {
+    let (handler, tenant_storage, tenant_id) = setup_with_locks(Arc::new(InMemoryLockManager::new()));
+    tenant_storage.add_file(&tenant_id, "notes/todo.txt", b"# Todo".to_vec());
+    
+    handler.with_config(WebDavConfig {
+        expose_lock_owner,
+        ..Default::default()
+    })
+}
+
+/// Lock the file, then return the body of the 423 refusing a PUT without the token
+async fn refused_put_body(handler: &MarbleDavHandler, lock_body: &str) -> String {
+    let response = handler
+        .handle(DavMethod::Lock, "/notes/todo.txt", auth_headers(), Bytes::from(lock_body.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let error = handler
+        .handle(DavMethod::Put, "/notes/todo.txt", auth_headers(), Bytes::from("changed"))
+        .await
+        .unwrap_err();
+    let response = error_response(&error);
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/xml");
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+/// Text of the first element with this DAV: name
+fn dav_element_text(body: &str, name: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(body).expect("423 body should be well-formed XML");
+    document
+        .descendants()
+        .find(|node| node.has_tag_name(("DAV:", name)))
+        .map(|node| node.text().unwrap_or_default().to_string())
+}
+
+#[tokio::test]
+async fn test_locked_response_names_resource_and_owner() {
+    let handler = setup_with_todo(true);
+    
+    let body = refused_put_body(&handler, r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+            <D:owner><D:href>mailto:ann&amp;co@example.com</D:href></D:owner>
+        </D:lockinfo>"#).await;
+    
+    let document = roxmltree::Document::parse(&body).unwrap();
+    assert!(document.root_element().has_tag_name(("DAV:", "error")));
+    assert!(document.descendants().any(|node| node.has_tag_name(("DAV:", "lock-token-submitted"))));
+    assert_eq!(dav_element_text(&body, "href").as_deref(), Some("/notes/todo.txt"));
+    assert_eq!(dav_element_text(&body, "owner").as_deref(), Some("mailto:ann&co@example.com"));
+}
+
+#[tokio::test]
+async fn test_locked_response_omits_missing_or_hidden_owner() {
+    // No owner given at LOCK time
+    let handler = setup_with_todo(true);
+    let body = refused_put_body(&handler, r#"<D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+        </D:lockinfo>"#).await;
+    assert_eq!(dav_element_text(&body, "href").as_deref(), Some("/notes/todo.txt"));
+    assert_eq!(dav_element_text(&body, "owner"), None);
+    
+    // The owner is withheld unless the configuration exposes it
+    let handler = setup_with_todo(false);
+    let body = refused_put_body(&handler, r#"<D:lockinfo xmlns:D="DAV:">
+            <D:owner>Ann</D:owner>
+        </D:lockinfo>"#).await;
+    assert_eq!(dav_element_text(&body, "href").as_deref(), Some("/notes/todo.txt"));
+    assert_eq!(dav_element_text(&body, "owner"), None);
+}
+
+#[tokio::test]
+async fn test_lock_refused_when_locking_disabled() {
+    let (handler, _tenant_storage, _tenant_id) = setup_with_locks(Arc::new(InMemoryLockManager::new()));
+    let handler = handler.with_config(WebDavConfig {
+        disable_locks: true,
+        ..Default::default()
+    });
+    
+    let error = handler
+        .handle(DavMethod::Lock, "/notes/todo.txt", auth_headers(), Bytes::new())
+        .await
+        .unwrap_err();
+    assert_eq!(error_response(&error).status(), StatusCode::METHOD_NOT_ALLOWED);
+}
@@ -1,126 +1,454 @@
-#[cfg(test)]
-mod lock_tests {
-    use crate::operations::{handle_lock, handle_unlock};
-    use crate::api::{AuthServiceRef, LockManagerRef};
-    use crate::lock::InMemoryLockManager;
-    use crate::tests::common::MockTenantStorage;
-    use marble_storage::api::TenantStorageRef;
-    use marble_core::models::user::UserId;
-    use http::{HeaderMap, StatusCode};
-    use bytes::Bytes;
-    use std::sync::Arc;
-    use std::str::FromStr;
-    use uuid::Uuid;
-    
-    // Mock auth service for testing
-    struct MockAuthService;
-    
-    #[async_trait::async_trait]
-    impl crate::api::AuthService for MockAuthService {
-        async fn authenticate(&self, _username: &str, _password: &str) -> Result<Uuid, crate::error::AuthError> {
-            Ok(Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap())
-        }
+use crate::operations::{handle_delete, handle_lock, handle_unlock};
+use crate::api::{AuthServiceRef, LockManagerRef};
+use crate::lock::InMemoryLockManager;
+use marble_core::clock::system_clock;
+use crate::tests::{normalize, MockTenantStorage};
+use marble_storage::api::TenantStorageRef;
+use http::{HeaderMap, StatusCode};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// Mock auth service for testing
+struct MockAuthService;
+
+#[async_trait::async_trait]
+impl crate::api::AuthService for MockAuthService {
+    async fn authenticate(&self, _username: &str, _password: &str) -> Result<Uuid, crate::error::AuthError> {
+        Ok(Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap())
     }
+}
+
+// Setup helper
+fn setup() -> (TenantStorageRef, AuthServiceRef, LockManagerRef, Uuid) {
+    let storage = Arc::new(MockTenantStorage::new());
+    let auth_service: AuthServiceRef = Arc::new(MockAuthService);
+    let lock_manager: LockManagerRef = Arc::new(InMemoryLockManager::new());
+    let tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap();
+    
+    (storage, auth_service, lock_manager, tenant_id)
+}
+
+#[tokio::test]
+async fn test_lock_and_unlock() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
+    
+    // Create a simple lock XML body
+    let lock_body = r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+            <D:owner>Test User</D:owner>
+        </D:lockinfo>"#;
+    
+    // Create headers for lock request
+    let mut lock_headers = HeaderMap::new();
+    lock_headers.insert("Timeout", "Second-3600".parse().unwrap());
+    
+    // Test LOCK operation
+    let lock_response = handle_lock(
+        &storage,
+        &lock_manager,
+        &system_clock(),
+        tenant_id,
+        "test/path.md",
+        lock_headers,
+        Bytes::from(lock_body)
+    ).await.unwrap();
+    
+    // Check response status
+    assert_eq!(lock_response.status(), StatusCode::OK);
+    
+    // Extract lock token from response
+    let lock_token = lock_response.headers()
+        .get("Lock-Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap();
+    
+    // Create headers for unlock request
+    let mut unlock_headers = HeaderMap::new();
+    unlock_headers.insert("Lock-Token", lock_token.parse().unwrap());
+    
+    // Test UNLOCK operation
+    let unlock_response = handle_unlock(
+        &lock_manager,
+        tenant_id,
+        "test/path.md",
+        unlock_headers
+    ).await.unwrap();
+    
+    // Check response status
+    assert_eq!(unlock_response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_lock_conflict() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
+    
+    // Create simple lock XML body
+    let lock_body = r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+            <D:owner>Test User</D:owner>
+        </D:lockinfo>"#;
+    
+    // Create headers for lock request
+    let mut lock_headers = HeaderMap::new();
+    lock_headers.insert("Timeout", "Second-3600".parse().unwrap());
+    
+    // First user locks the resource
+    let lock_response = handle_lock(
+        &storage,
+        &lock_manager,
+        &system_clock(),
+        tenant_id,
+        "test/path.md",
+        lock_headers.clone(),
+        Bytes::from(lock_body)
+    ).await.unwrap();
+    
+    // Check response status
+    assert_eq!(lock_response.status(), StatusCode::OK);
+    
+    // Second user tries to lock the same resource
+    let lock_result = handle_lock(
+        &storage,
+        &lock_manager,
+        &system_clock(),
+        tenant_id,
+        "test/path.md",
+        lock_headers,
+        Bytes::from(lock_body)
+    ).await;
     
-    // Setup helper
-    fn setup() -> (TenantStorageRef, AuthServiceRef, LockManagerRef, Uuid) {
-        let storage = Arc::new(MockTenantStorage::new());
-        let auth_service: AuthServiceRef = Arc::new(MockAuthService);
-        let lock_manager: LockManagerRef = Arc::new(InMemoryLockManager::new());
-        let tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap();
-        
-        (storage, auth_service, lock_manager, tenant_id)
+    // Lock should fail
+    assert!(lock_result.is_err());
+}
+
+#[tokio::test]
+async fn test_lock_other_tenant_no_conflict() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
+    let other_tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000002").unwrap();
+    
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "test/path.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap();
+    
+    // Locks are scoped per tenant, so the same path of another tenant is free
+    let response = handle_lock(&storage, &lock_manager, &system_clock(), other_tenant_id, "test/path.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+fn lock_body(scope: &str) -> Bytes {
+    Bytes::from(format!(r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:{}/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+        </D:lockinfo>"#, scope))
+}
+
+#[tokio::test]
+async fn test_shared_locks_coexist() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
+    
+    let first = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "shared.md", HeaderMap::new(), lock_body("shared"))
+        .await
+        .unwrap();
+    let second = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "shared.md", HeaderMap::new(), lock_body("shared"))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::OK);
+    
+    // Both locks are active and the second response lists them both
+    let locks = lock_manager.active_locks(&tenant_id, "shared.md").await.unwrap();
+    assert_eq!(locks.len(), 2);
+    let body = String::from_utf8(second.body().to_vec()).unwrap();
+    let document = roxmltree::Document::parse(&body).unwrap();
+    let active: Vec<_> = document
+        .descendants()
+        .filter(|node| node.has_tag_name(("DAV:", "activelock")))
+        .collect();
+    assert_eq!(active.len(), 2);
+    assert!(active.iter().all(|lock| lock.descendants().any(|node| node.has_tag_name(("DAV:", "shared")))));
+    for lock in &locks {
+        assert!(body.contains(&lock.token));
     }
+}
+
+#[tokio::test]
+async fn test_shared_and_exclusive_locks_conflict() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
     
-    #[tokio::test]
-    async fn test_lock_and_unlock() {
-        let (_storage, _auth_service, lock_manager, tenant_id) = setup();
-        
-        // Create a simple lock XML body
-        let lock_body = r#"<?xml version="1.0" encoding="utf-8" ?>
-            <D:lockinfo xmlns:D="DAV:">
-                <D:lockscope><D:exclusive/></D:lockscope>
-                <D:locktype><D:write/></D:locktype>
-                <D:owner>Test User</D:owner>
-            </D:lockinfo>"#;
-        
-        // Create headers for lock request
-        let mut lock_headers = HeaderMap::new();
-        lock_headers.insert("Timeout", "Second-3600".parse().unwrap());
-        
-        // Test LOCK operation
-        let lock_response = handle_lock(
-            &lock_manager,
-            tenant_id,
-            "test/path.md",
-            lock_headers,
-            Bytes::from(lock_body)
-        ).await.unwrap();
-        
-        // Check response status
-        assert_eq!(lock_response.status(), StatusCode::OK);
-        
-        // Extract lock token from response
-        let lock_token = lock_response.headers()
-            .get("Lock-Token")
-            .and_then(|v| v.to_str().ok())
-            .unwrap();
-        
-        // Create headers for unlock request
-        let mut unlock_headers = HeaderMap::new();
-        unlock_headers.insert("Lock-Token", lock_token.parse().unwrap());
-        
-        // Test UNLOCK operation
-        let unlock_response = handle_unlock(
-            &lock_manager,
-            tenant_id,
-            "test/path.md",
-            unlock_headers
-        ).await.unwrap();
-        
-        // Check response status
-        assert_eq!(unlock_response.status(), StatusCode::NO_CONTENT);
+    // An exclusive lock cannot join a shared one
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "a.md", HeaderMap::new(), lock_body("shared")).await.unwrap();
+    let error = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "a.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    
+    // Nor can a shared lock join an exclusive one
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "b.md", HeaderMap::new(), lock_body("exclusive")).await.unwrap();
+    let error = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "b.md", HeaderMap::new(), lock_body("shared"))
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    
+    assert_eq!(lock_manager.active_locks(&tenant_id, "a.md").await.unwrap().len(), 1);
+    assert_eq!(lock_manager.active_locks(&tenant_id, "b.md").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_infinity_lock_covers_descendants() {
+    let (_storage, _auth_service, lock_manager, tenant_id) = setup();
+    let mock = MockTenantStorage::new();
+    mock.add_directory(&tenant_id, "docs");
+    mock.add_file(&tenant_id, "docs/a.md", b"a".to_vec());
+    mock.add_file(&tenant_id, "docs/b.md", b"b".to_vec());
+    let storage: TenantStorageRef = Arc::new(mock);
+    
+    let mut headers = HeaderMap::new();
+    headers.insert("Depth", "infinity".parse().unwrap());
+    let response = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs", headers, lock_body("exclusive"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("<D:depth>infinity</D:depth>"));
+    let token = lock_manager.active_locks(&tenant_id, "docs").await.unwrap()[0].token.clone();
+    
+    // Each child carries the collection's lock and cannot be locked on its own
+    for child in ["docs/a.md", "docs/b.md"] {
+        let locks = lock_manager.active_locks(&tenant_id, child).await.unwrap();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].token, token);
+        assert_eq!(locks[0].root, "docs");
     }
+    let error = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs/a.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    
+    // Unlocking the collection releases the whole set
+    let mut unlock_headers = HeaderMap::new();
+    unlock_headers.insert("Lock-Token", format!("<{}>", token).parse().unwrap());
+    handle_unlock(&lock_manager, tenant_id, "docs", unlock_headers).await.unwrap();
+    assert!(lock_manager.active_locks(&tenant_id, "docs/b.md").await.unwrap().is_empty());
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs/a.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_infinity_lock_fails_whole_on_descendant_conflict() {
+    let (_storage, _auth_service, lock_manager, tenant_id) = setup();
+    let mock = MockTenantStorage::new();
+    mock.add_directory(&tenant_id, "docs");
+    mock.add_file(&tenant_id, "docs/a.md", b"a".to_vec());
+    mock.add_file(&tenant_id, "docs/b.md", b"b".to_vec());
+    let storage: TenantStorageRef = Arc::new(mock);
     
-    #[tokio::test]
-    async fn test_lock_conflict() {
-        let (_storage, _auth_service, lock_manager, tenant_id) = setup();
-        let other_tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000002").unwrap();
-        
-        // Create simple lock XML body
-        let lock_body = r#"<?xml version="1.0" encoding="utf-8" ?>
-            <D:lockinfo xmlns:D="DAV:">
-                <D:lockscope><D:exclusive/></D:lockscope>
-                <D:locktype><D:write/></D:locktype>
-                <D:owner>Test User</D:owner>
-            </D:lockinfo>"#;
-        
-        // Create headers for lock request
-        let mut lock_headers = HeaderMap::new();
-        lock_headers.insert("Timeout", "Second-3600".parse().unwrap());
-        
-        // First user locks the resource
-        let lock_response = handle_lock(
-            &lock_manager,
-            tenant_id,
-            "test/path.md",
-            lock_headers.clone(),
-            Bytes::from(lock_body)
-        ).await.unwrap();
-        
-        // Check response status
-        assert_eq!(lock_response.status(), StatusCode::OK);
-        
-        // Second user tries to lock the same resource
-        let lock_result = handle_lock(
-            &lock_manager,
-            other_tenant_id,
-            "test/path.md",
-            lock_headers,
-            Bytes::from(lock_body)
-        ).await;
-        
-        // Lock should fail
-        assert!(lock_result.is_err());
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs/b.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap();
+    
+    let mut headers = HeaderMap::new();
+    headers.insert("Depth", "infinity".parse().unwrap());
+    let error = handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs", headers, lock_body("exclusive"))
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    
+    // Nothing else was locked
+    assert!(lock_manager.active_locks(&tenant_id, "docs").await.unwrap().is_empty());
+    assert!(lock_manager.active_locks(&tenant_id, "docs/a.md").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_collection_requires_descendant_lock_tokens() {
+    let (_storage, _auth_service, lock_manager, tenant_id) = setup();
+    let mock = MockTenantStorage::new();
+    mock.add_directory(&tenant_id, "docs");
+    mock.add_directory(&tenant_id, "docs/sub");
+    mock.add_file(&tenant_id, "docs/a.md", b"a".to_vec());
+    mock.add_file(&tenant_id, "docs/sub/b.md", b"b".to_vec());
+    let storage: TenantStorageRef = Arc::new(mock);
+    
+    // Another client locks a file, and a subcollection with Depth: infinity
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs/a.md", HeaderMap::new(), lock_body("exclusive"))
+        .await
+        .unwrap();
+    let mut depth = HeaderMap::new();
+    depth.insert("Depth", "infinity".parse().unwrap());
+    handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, "docs/sub", depth, lock_body("exclusive"))
+        .await
+        .unwrap();
+    let file_token = lock_manager.active_locks(&tenant_id, "docs/a.md").await.unwrap()[0].token.clone();
+    let sub_token = lock_manager.active_locks(&tenant_id, "docs/sub").await.unwrap()[0].token.clone();
+    
+    // Deleting the unlocked parent without the tokens is refused
+    let error = handle_delete(&storage, &lock_manager, tenant_id, "docs", HeaderMap::new(), normalize)
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    
+    // One of the two tokens is not enough
+    let mut headers = HeaderMap::new();
+    headers.insert("If", format!("</docs/a.md> (<{}>)", file_token).parse().unwrap());
+    let error = handle_delete(&storage, &lock_manager, tenant_id, "docs", headers, normalize)
+        .await
+        .unwrap_err();
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::LOCKED);
+    assert!(storage.exists(&tenant_id, "docs/a.md").await.unwrap());
+    assert!(storage.exists(&tenant_id, "docs/sub/b.md").await.unwrap());
+    
+    // With every token submitted the subtree is deleted
+    let mut headers = HeaderMap::new();
+    headers.insert("If", format!("</docs/a.md> (<{}>) </docs/sub> (<{}>)", file_token, sub_token).parse().unwrap());
+    let response = handle_delete(&storage, &lock_manager, tenant_id, "docs", headers, normalize).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!storage.exists(&tenant_id, "docs/a.md").await.unwrap());
+    assert!(!storage.exists(&tenant_id, "docs/sub/b.md").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_locks_below_excludes_collection_and_siblings() {
+    let (storage, _auth_service, lock_manager, tenant_id) = setup();
+    
+    for path in ["docs", "docs/a.md", "docs2/b.md", "other.md"] {
+        handle_lock(&storage, &lock_manager, &system_clock(), tenant_id, path, HeaderMap::new(), lock_body("exclusive"))
+            .await
+            .unwrap();
     }
+    
+    let below: Vec<String> = lock_manager.locks_below(&tenant_id, "docs").await.unwrap()
+        .into_iter()
+        .map(|lock| lock.path)
+        .collect();
+    assert_eq!(below, vec!["docs/a.md".to_string()]);
+    
+    // Everything is below the root
+    assert_eq!(lock_manager.locks_below(&tenant_id, ".").await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_lock_expires_exactly_at_timeout() {
+    use crate::api::{LockManager, LockScope};
+    use marble_core::clock::MockClock;
+    
+    let clock = Arc::new(MockClock::starting_now());
+    let lock_manager = InMemoryLockManager::new().with_clock(clock.clone());
+    let tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap();
+    
+    lock_manager
+        .lock(&tenant_id, "notes.md", std::time::Duration::from_secs(60), "opaquelocktoken:clock", None, LockScope::Exclusive)
+        .await
+        .unwrap();
+    
+    // Still held just before the deadline
+    clock.advance(chrono::Duration::seconds(60) - chrono::Duration::milliseconds(1));
+    assert_eq!(lock_manager.active_locks(&tenant_id, "notes.md").await.unwrap().len(), 1);
+    
+    // Gone at the deadline, without any real time passing
+    clock.advance(chrono::Duration::milliseconds(1));
+    assert!(lock_manager.active_locks(&tenant_id, "notes.md").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_expired_lock_reaped_and_reacquired() {
+    use crate::api::{LockManager, LockScope};
+    use crate::error::LockError;
+    use marble_core::clock::MockClock;
+    
+    let clock = Arc::new(MockClock::starting_now());
+    let lock_manager = InMemoryLockManager::new().with_clock(clock.clone());
+    let tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap();
+    let timeout = std::time::Duration::from_secs(1);
+    
+    lock_manager
+        .lock(&tenant_id, "notes.md", timeout, "opaquelocktoken:crashed", None, LockScope::Exclusive)
+        .await
+        .unwrap();
+    let result = lock_manager
+        .lock(&tenant_id, "notes.md", timeout, "opaquelocktoken:next", None, LockScope::Exclusive)
+        .await;
+    assert!(matches!(result, Err(LockError::ResourceLocked)));
+    
+    // Nothing expires early
+    assert_eq!(lock_manager.reap_expired().await.unwrap(), 0);
+    
+    clock.advance(chrono::Duration::seconds(2));
+    assert_eq!(lock_manager.reap_expired().await.unwrap(), 1);
+    
+    lock_manager
+        .lock(&tenant_id, "notes.md", timeout, "opaquelocktoken:next", None, LockScope::Exclusive)
+        .await
+        .unwrap();
+    let locks = lock_manager.active_locks(&tenant_id, "notes.md").await.unwrap();
+    assert_eq!(locks.len(), 1);
+    assert_eq!(locks[0].token, "opaquelocktoken:next");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_reaper_task_removes_expired_locks() {
+    use crate::api::LockScope;
+    use crate::lock::spawn_reaper;
+    use marble_core::clock::MockClock;
+    
+    let clock = Arc::new(MockClock::starting_now());
+    let lock_manager: LockManagerRef = Arc::new(InMemoryLockManager::new().with_clock(clock.clone()));
+    let tenant_id = Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap();
+    let reaper = spawn_reaper(&lock_manager, std::time::Duration::from_secs(60)).unwrap();
+    
+    lock_manager
+        .lock(&tenant_id, "notes.md", std::time::Duration::from_secs(1), "opaquelocktoken:crashed", None, LockScope::Exclusive)
+        .await
+        .unwrap();
+    clock.advance(chrono::Duration::seconds(2));
+    
+    // After one interval the task has already removed the lock
+    tokio::time::sleep(std::time::Duration::from_secs(61)).await;
+    assert_eq!(lock_manager.reap_expired().await.unwrap(), 0);
+    
+    // The task ends with the lock manager
+    drop(lock_manager);
+    tokio::time::sleep(std::time::Duration::from_secs(61)).await;
+    assert!(reaper.is_finished());
+}
+
+#[tokio::test]
+async fn test_lock_timeout_reported_by_handler_clock() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use chrono::TimeZone;
+    use dav_server::DavMethod;
+    use marble_core::clock::MockClock;
+    use crate::dav_handler::MarbleDavHandler;
+    
+    // Far from the wall clock, so a timeout counted from it would be off
+    let clock = Arc::new(MockClock::new(chrono::Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap()));
+    let (storage, auth_service, _lock_manager, _tenant_id) = setup();
+    let lock_manager: LockManagerRef = Arc::new(InMemoryLockManager::new().with_clock(clock.clone()));
+    let handler = MarbleDavHandler::new(storage, auth_service, lock_manager);
+    
+    let mut headers = HeaderMap::new();
+    headers.insert(http::header::AUTHORIZATION, format!("Basic {}", STANDARD.encode("user:pass")).parse().unwrap());
+    headers.insert("Timeout", "Second-600".parse().unwrap());
+    
+    clock.advance(chrono::Duration::seconds(30));
+    let response = handler
+        .handle(DavMethod::Lock, "/notes.md", headers, Bytes::from(r#"<?xml version="1.0" encoding="utf-8" ?>
+        <D:lockinfo xmlns:D="DAV:">
+            <D:lockscope><D:exclusive/></D:lockscope>
+            <D:locktype><D:write/></D:locktype>
+        </D:lockinfo>"#))
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("<D:timeout>Second-600</D:timeout>"), "{}", body);
 }
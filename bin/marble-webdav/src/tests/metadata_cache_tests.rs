@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::dav_handler::MarbleDavHandler;
+use super::{auth_headers, setup, MockTenantStorage, MockAuthService};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_head_then_get_resolves_metadata_once() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    let head = handler.handle(DavMethod::Head, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(head.status(), StatusCode::OK);
+    assert_eq!(head.headers()[http::header::CONTENT_LENGTH], "7");
+    assert!(head.body().is_empty());
+    
+    let get = handler.handle(DavMethod::Get, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(get.headers()[http::header::CONTENT_LENGTH], "7");
+    assert_eq!(get.headers()[http::header::ETAG], head.headers()[http::header::ETAG]);
+    assert_eq!(tenant_storage.metadata_count(), 1);
+    
+    // A write drops the cached size
+    handler.handle(DavMethod::Put, "/notes.md", auth_headers(), Bytes::from("# Longer notes"))
+        .await
+        .unwrap();
+    let get = handler.handle(DavMethod::Get, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(get.headers()[http::header::CONTENT_LENGTH], "14");
+    assert_eq!(get.body().as_ref(), b"# Longer notes");
+}
+
+#[tokio::test]
+async fn test_metadata_cache_expires_writes_made_elsewhere() {
+    use std::time::Duration;
+    use marble_core::clock::MockClock;
+    use crate::config::WebDavConfig;
+    use crate::lock::InMemoryLockManager;
+    
+    let clock = Arc::new(MockClock::starting_now());
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(InMemoryLockManager::new().with_clock(clock.clone()))
+    ).with_config(WebDavConfig {
+        metadata_cache_ttl: Some(Duration::from_secs(5)),
+        ..Default::default()
+    });
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    let head = handler.handle(DavMethod::Head, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(head.headers()[http::header::CONTENT_LENGTH], "7");
+    
+    // Another server rewrites the file; this handler is not told
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Longer notes".to_vec());
+    
+    // Served from the cache until the TTL is up
+    clock.advance(chrono::Duration::seconds(4));
+    let head = handler.handle(DavMethod::Head, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(head.headers()[http::header::CONTENT_LENGTH], "7");
+    
+    clock.advance(chrono::Duration::seconds(1));
+    let head = handler.handle(DavMethod::Head, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(head.headers()[http::header::CONTENT_LENGTH], "14");
+}
+
+#[tokio::test]
+async fn test_metadata_cache_disabled_by_zero_ttl() {
+    use std::time::Duration;
+    use crate::config::WebDavConfig;
+    
+    let (handler, tenant_storage, tenant_id) = setup();
+    let handler = handler.with_config(WebDavConfig {
+        metadata_cache_ttl: Some(Duration::ZERO),
+        ..Default::default()
+    });
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    handler.handle(DavMethod::Head, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Longer notes".to_vec());
+    
+    let get = handler.handle(DavMethod::Get, "/notes.md", auth_headers(), Bytes::new()).await.unwrap();
+    assert_eq!(get.headers()[http::header::CONTENT_LENGTH], "14");
+    assert_eq!(tenant_storage.metadata_count(), 2);
+}
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use async_trait::async_trait;
-use crate::api::AuthService;
+use crate::api::{AuthService, Principal, Role};
 use crate::error::AuthError;
 use uuid::Uuid;
 
 /// Mock AuthService for testing
 pub struct MockAuthService {
-    // Map of username -> (password, tenant_id)
-    users: HashMap<String, (String, Uuid)>,
+    // Map of username -> (password, tenant_id, role)
+    users: HashMap<String, (String, Uuid, Role)>,
+    
+    // Simulates the user database being unreachable
+    database_down: AtomicBool,
 }
 
 impl MockAuthService {
@@ -16,19 +20,49 @@ impl MockAuthService {
         // Add a test user
         users.insert(
             "testuser".to_string(),
-            ("password123".to_string(), Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap())
+            ("password123".to_string(), Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap(), Role::Owner)
+        );
+        users.insert(
+            "otheruser".to_string(),
+            ("password456".to_string(), Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap(), Role::Owner)
+        );
+        // A read-only collaborator of the first tenant
+        users.insert(
+            "viewer".to_string(),
+            ("viewpass".to_string(), Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap(), Role::Viewer)
         );
         
-        Self { users }
+        Self { users, database_down: AtomicBool::new(false) }
+    }
+    
+    /// Make every authentication fail as if the user database were unreachable
+    pub fn set_database_down(&self, down: bool) {
+        self.database_down.store(down, Ordering::SeqCst);
+    }
+}
+
+impl Default for MockAuthService {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[async_trait]
 impl AuthService for MockAuthService {
     async fn authenticate(&self, username: &str, password: &str) -> Result<Uuid, AuthError> {
-        if let Some((stored_password, tenant_id)) = self.users.get(username) {
+        self.authenticate_principal(username, password)
+            .await
+            .map(|principal| principal.tenant_id)
+    }
+    
+    async fn authenticate_principal(&self, username: &str, password: &str) -> Result<Principal, AuthError> {
+        if self.database_down.load(Ordering::SeqCst) {
+            return Err(AuthError::Unavailable("pool timed out while waiting for an open connection".to_string()));
+        }
+        
+        if let Some((stored_password, tenant_id, role)) = self.users.get(username) {
             if stored_password == password {
-                return Ok(*tenant_id);
+                return Ok(Principal { tenant_id: *tenant_id, role: *role });
             }
         }
         
@@ -1,6 +1,6 @@
 use std::time::Duration;
 use async_trait::async_trait;
-use crate::api::{LockManager, LockInfo};
+use crate::api::{LockManager, LockInfo, LockScope};
 use crate::error::LockError;
 use uuid::Uuid;
 
@@ -15,6 +15,20 @@ impl LockManager for MockLockManager {
         _path: &str,
         _timeout: Duration,
         _token: &str,
+        _owner: Option<&str>,
+        _scope: LockScope,
+    ) -> Result<(), LockError> {
+        Ok(())  // No-op for tests
+    }
+    
+    async fn lock_paths(
+        &self,
+        _tenant_id: &Uuid,
+        _paths: &[String],
+        _timeout: Duration,
+        _token: &str,
+        _owner: Option<&str>,
+        _scope: LockScope,
     ) -> Result<(), LockError> {
         Ok(())  // No-op for tests
     }
@@ -28,11 +42,23 @@ impl LockManager for MockLockManager {
         Ok(())  // No-op for tests
     }
     
-    async fn is_locked(
+    async fn reap_expired(&self) -> Result<usize, LockError> {
+        Ok(0)  // Nothing ever expires in tests
+    }
+    
+    async fn active_locks(
+        &self,
+        _tenant_id: &Uuid,
+        _path: &str,
+    ) -> Result<Vec<LockInfo>, LockError> {
+        Ok(Vec::new())  // Always unlocked in tests
+    }
+    
+    async fn locks_below(
         &self,
         _tenant_id: &Uuid,
         _path: &str,
-    ) -> Result<Option<LockInfo>, LockError> {
-        Ok(None)  // Always unlocked in tests
+    ) -> Result<Vec<LockInfo>, LockError> {
+        Ok(Vec::new())
     }
 }
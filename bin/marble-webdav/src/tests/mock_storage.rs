@@ -1,10 +1,19 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use async_trait::async_trait;
-use marble_storage::api::{TenantStorage, FileMetadata};
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
+use marble_storage::api::{TenantStorage, DeadProperty, DedupOutcome, FileMetadata, ListOrder, PropertyChange, StorageUsage};
+use marble_storage::api::tenant::{apply_property_changes, sort_metadata};
 use marble_storage::error::StorageResult;
+use marble_core::ClockRef;
 use uuid::Uuid;
 
+/// Size of the chunks `read_stream` yields, small enough that test files span several
+pub const MOCK_READ_CHUNK_SIZE: usize = 4;
+
 /// Mock TenantStorage for testing
 #[derive(Default)]
 pub struct MockTenantStorage {
@@ -13,6 +22,42 @@ pub struct MockTenantStorage {
     
     // Simulates directories with tenant_id -> directory path
     directories: Mutex<HashMap<Uuid, Vec<String>>>,
+    
+    // Simulates aliases with tenant_id -> alias path -> target path
+    aliases: Mutex<HashMap<Uuid, HashMap<String, String>>>,
+    
+    // Number of in-place renames performed
+    renames: AtomicUsize,
+    
+    // Number of metadata lookups performed
+    metadata_calls: AtomicUsize,
+    
+    // Artificial delay before listing a directory
+    list_delay: Option<Duration>,
+    
+    // Artificial delay before each lookup of metadata for many files
+    metadata_delay: Option<Duration>,
+    
+    // Simulates a database outage: every operation fails while set
+    database_down: AtomicBool,
+    
+    // Pinned content types with tenant_id -> path -> content type
+    content_types: Mutex<HashMap<Uuid, HashMap<String, String>>>,
+    
+    // Dead properties with tenant_id -> path -> properties
+    properties: Mutex<HashMap<Uuid, HashMap<String, Vec<DeadProperty>>>>,
+    
+    // Simulates corrupted blobs with content hash -> bytes read back
+    corrupted_blobs: Mutex<HashMap<String, Vec<u8>>>,
+    
+    // Storage quota of every tenant in bytes
+    quota_bytes: Option<usize>,
+    
+    // Modification times in milliseconds with tenant_id -> path -> time, set on write
+    modified: Mutex<HashMap<Uuid, HashMap<String, u64>>>,
+    
+    // Clock stamping modification times; the system clock when unset
+    clock: Option<ClockRef>,
 }
 
 impl MockTenantStorage {
@@ -23,31 +68,127 @@ impl MockTenantStorage {
     // Helper to set up test data
     pub fn add_file(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>) {
         let mut files = self.files.lock().unwrap();
-        let tenant_files = files.entry(*tenant_id).or_insert_with(HashMap::new);
+        let tenant_files = files.entry(*tenant_id).or_default();
         tenant_files.insert(path.to_string(), content);
+        self.touch(tenant_id, path);
         
         // Ensure parent directories exist
         let parent = if path.contains('/') {
             let parts: Vec<&str> = path.split('/').collect();
             let parent = parts[..parts.len()-1].join("/");
-            if parent.is_empty() { "." } else { &parent }
+            if parent.is_empty() { ".".to_string() } else { parent }
         } else {
-            "."
+            ".".to_string()
         };
         
         let mut directories = self.directories.lock().unwrap();
-        let tenant_dirs = directories.entry(*tenant_id).or_insert_with(Vec::new);
+        let tenant_dirs = directories.entry(*tenant_id).or_default();
         
-        if !tenant_dirs.contains(&parent.to_string()) {
-            tenant_dirs.push(parent.to_string());
+        if !tenant_dirs.contains(&parent) {
+            self.touch(tenant_id, &parent);
+            tenant_dirs.push(parent);
+        }
+    }
+    
+    // Stamp modification times with this clock instead of the system clock
+    pub fn with_clock(mut self, clock: ClockRef) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+    
+    // Refuse writes taking a tenant's files above this many bytes
+    pub fn with_quota(mut self, quota_bytes: usize) -> Self {
+        self.quota_bytes = Some(quota_bytes);
+        self
+    }
+    
+    // Simulate slow storage when listing directories
+    pub fn with_list_delay(mut self, delay: Duration) -> Self {
+        self.list_delay = Some(delay);
+        self
+    }
+    
+    // Simulate slow storage when looking up metadata for many files
+    pub fn with_metadata_delay(mut self, delay: Duration) -> Self {
+        self.metadata_delay = Some(delay);
+        self
+    }
+    
+    pub fn rename_count(&self) -> usize {
+        self.renames.load(Ordering::SeqCst)
+    }
+    
+    pub fn metadata_count(&self) -> usize {
+        self.metadata_calls.load(Ordering::SeqCst)
+    }
+    
+    // Take the simulated database down or bring it back
+    pub fn set_database_down(&self, down: bool) {
+        self.database_down.store(down, Ordering::SeqCst);
+    }
+    
+    /// Make reads of a file's blob return other bytes than were hashed
+    pub fn corrupt_blob(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>) {
+        let files = self.files.lock().unwrap();
+        let original = &files[tenant_id][path];
+        let hash = marble_storage::hash::hash_content(original).unwrap();
+        self.corrupted_blobs.lock().unwrap().insert(hash, content);
+    }
+    
+    fn database_error(&self) -> Option<marble_storage::error::StorageError> {
+        self.database_down
+            .load(Ordering::SeqCst)
+            .then(|| marble_storage::error::StorageError::Database(sqlx::Error::PoolTimedOut))
+    }
+    
+    // Record the current time as the modification time of a path
+    fn touch(&self, tenant_id: &Uuid, path: &str) {
+        let now = self.clock.as_ref().map_or_else(chrono::Utc::now, |clock| clock.now());
+        self.modified
+            .lock()
+            .unwrap()
+            .entry(*tenant_id)
+            .or_default()
+            .insert(path.to_string(), now.timestamp_millis().max(0) as u64);
+    }
+    
+    // Modification time recorded for a path
+    fn modified_at(&self, tenant_id: &Uuid, path: &str) -> Option<u64> {
+        self.modified
+            .lock()
+            .unwrap()
+            .get(tenant_id)
+            .and_then(|tenant_modified| tenant_modified.get(path).copied())
+    }
+    
+    // Path an alias follows, or the path itself for other files
+    fn resolve_alias(&self, tenant_id: &Uuid, path: &str) -> String {
+        let aliases = self.aliases.lock().unwrap();
+        aliases
+            .get(tenant_id)
+            .and_then(|tenant_aliases| tenant_aliases.get(path).cloned())
+            .unwrap_or_else(|| path.to_string())
+    }
+    
+    // Key properties are stored under: the alias target, or the directory without a trailing slash
+    fn property_key(&self, tenant_id: &Uuid, path: &str) -> String {
+        let target = self.resolve_alias(tenant_id, path);
+        let is_file = self.files.lock().unwrap()
+            .get(tenant_id)
+            .is_some_and(|tenant_files| tenant_files.contains_key(&target));
+        if is_file || target == "/" {
+            target
+        } else {
+            target.trim_end_matches('/').to_string()
         }
     }
     
     pub fn add_directory(&self, tenant_id: &Uuid, path: &str) {
         let mut directories = self.directories.lock().unwrap();
-        let tenant_dirs = directories.entry(*tenant_id).or_insert_with(Vec::new);
+        let tenant_dirs = directories.entry(*tenant_id).or_default();
         
         if !tenant_dirs.contains(&path.to_string()) {
+            self.touch(tenant_id, path);
             tenant_dirs.push(path.to_string());
         }
     }
@@ -56,10 +197,15 @@ impl MockTenantStorage {
 #[async_trait]
 impl TenantStorage for MockTenantStorage {
     async fn read(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<u8>> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        let target = self.resolve_alias(tenant_id, path);
         let files = self.files.lock().unwrap();
         
         if let Some(tenant_files) = files.get(tenant_id) {
-            if let Some(content) = tenant_files.get(path) {
+            if let Some(content) = tenant_files.get(&target) {
                 return Ok(content.clone());
             }
         }
@@ -67,18 +213,37 @@ impl TenantStorage for MockTenantStorage {
         Err(marble_storage::error::StorageError::NotFound(path.to_string()))
     }
     
+    async fn read_stream(&self, tenant_id: &Uuid, path: &str) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+        let content = self.read(tenant_id, path).await?;
+        let chunks: Vec<StorageResult<Bytes>> = content
+            .chunks(MOCK_READ_CHUNK_SIZE)
+            .map(Bytes::copy_from_slice)
+            .map(Ok)
+            .collect();
+        Ok(stream::iter(chunks).boxed())
+    }
+    
     async fn create_directory(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
         let mut directories = self.directories.lock().unwrap();
-        let tenant_dirs = directories.entry(*tenant_id).or_insert_with(Vec::new);
+        let tenant_dirs = directories.entry(*tenant_id).or_default();
         
         if !tenant_dirs.contains(&path.to_string()) {
+            self.touch(tenant_id, path);
             tenant_dirs.push(path.to_string());
         }
         
         Ok(())
     }
     
-    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, _content_type: Option<&str>) -> StorageResult<()> {
+    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, _content_type: Option<&str>) -> StorageResult<DedupOutcome> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
         // Create parent directories if needed
         if path.contains('/') {
             let parent = path.rsplit_once('/').unwrap().0;
@@ -87,14 +252,56 @@ impl TenantStorage for MockTenantStorage {
             }
         }
         
+        let target = self.resolve_alias(tenant_id, path);
         let mut files = self.files.lock().unwrap();
-        let tenant_files = files.entry(*tenant_id).or_insert_with(HashMap::new);
-        tenant_files.insert(path.to_string(), content);
         
+        let tenant_files = files.entry(*tenant_id).or_default();
+        
+        // Only the tenant's own files count, as in raw storage
+        let referenced = tenant_files.values().any(|existing| *existing == content);
+        if let Some(quota) = self.quota_bytes {
+            let others: usize = tenant_files
+                .iter()
+                .filter(|(file_path, _)| **file_path != target)
+                .map(|(_, file)| file.len())
+                .sum();
+            if others + content.len() > quota {
+                return Err(marble_storage::error::StorageError::QuotaExceeded(quota as i64));
+            }
+        }
+        self.touch(tenant_id, &target);
+        tenant_files.insert(target, content);
+        
+        Ok(DedupOutcome::from_referenced(referenced))
+    }
+    
+    async fn append(&self, tenant_id: &Uuid, path: &str, data: Vec<u8>, content_type: Option<&str>) -> StorageResult<()> {
+        let mut content = self
+            .files
+            .lock()
+            .unwrap()
+            .get(tenant_id)
+            .and_then(|tenant_files| tenant_files.get(path).cloned())
+            .unwrap_or_default();
+        content.extend_from_slice(&data);
+        
+        self.write(tenant_id, path, content, content_type).await?;
+        Ok(())
+    }
+    
+    async fn truncate(&self, tenant_id: &Uuid, path: &str, len: u64) -> StorageResult<()> {
+        let mut content = self.read(tenant_id, path).await?;
+        content.resize(len as usize, 0);
+        
+        self.write(tenant_id, path, content, None).await?;
         Ok(())
     }
     
     async fn exists(&self, tenant_id: &Uuid, path: &str) -> StorageResult<bool> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
         let files = self.files.lock().unwrap();
         let directories = self.directories.lock().unwrap();
         
@@ -116,6 +323,10 @@ impl TenantStorage for MockTenantStorage {
     }
     
     async fn delete(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
         // Check if it exists first
         if !self.exists(tenant_id, path).await? {
             return Err(marble_storage::error::StorageError::NotFound(path.to_string()));
@@ -125,6 +336,12 @@ impl TenantStorage for MockTenantStorage {
         let mut files = self.files.lock().unwrap();
         if let Some(tenant_files) = files.get_mut(tenant_id) {
             if tenant_files.remove(path).is_some() {
+                if let Some(tenant_types) = self.content_types.lock().unwrap().get_mut(tenant_id) {
+                    tenant_types.remove(path);
+                }
+                if let Some(tenant_properties) = self.properties.lock().unwrap().get_mut(tenant_id) {
+                    tenant_properties.remove(path);
+                }
                 return Ok(());
             }
         }
@@ -142,16 +359,138 @@ impl TenantStorage for MockTenantStorage {
         Err(marble_storage::error::StorageError::NotFound(path.to_string()))
     }
     
+    async fn delete_directory(&self, tenant_id: &Uuid, path: &str) -> StorageResult<u64> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let below = |p: &String| p == path || p.starts_with(&prefix);
+        
+        let mut deleted = 0;
+        if let Some(tenant_files) = self.files.lock().unwrap().get_mut(tenant_id) {
+            let before = tenant_files.len();
+            tenant_files.retain(|p, _| !below(p));
+            deleted = (before - tenant_files.len()) as u64;
+        }
+        if let Some(tenant_dirs) = self.directories.lock().unwrap().get_mut(tenant_id) {
+            tenant_dirs.retain(|p| !below(p));
+        }
+        
+        Ok(deleted)
+    }
+    
+    async fn delete_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<bool>> {
+        let mut deleted = Vec::with_capacity(paths.len());
+        for path in paths {
+            let removed = self
+                .files
+                .lock()
+                .unwrap()
+                .get_mut(tenant_id)
+                .is_some_and(|tenant_files| tenant_files.remove(path).is_some());
+            deleted.push(removed);
+        }
+        Ok(deleted)
+    }
+    
+    async fn search(&self, tenant_id: &Uuid, query: &str) -> StorageResult<Vec<FileMetadata>> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        // Markdown files containing every word, in path order
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut paths: Vec<String> = self
+            .files
+            .lock()
+            .unwrap()
+            .get(tenant_id)
+            .map(|tenant_files| {
+                tenant_files
+                    .iter()
+                    .filter(|(path, _)| path.ends_with(".md"))
+                    .filter(|(_, content)| {
+                        let text = String::from_utf8_lossy(content).to_lowercase();
+                        !words.is_empty() && words.iter().all(|word| text.contains(word.as_str()))
+                    })
+                    .map(|(path, _)| path.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        
+        let mut found = Vec::with_capacity(paths.len());
+        for path in paths {
+            found.push(self.metadata(tenant_id, &path).await?);
+        }
+        Ok(found)
+    }
+    
+    async fn create_alias(&self, tenant_id: &Uuid, path: &str, target: &str) -> StorageResult<()> {
+        let target = self.resolve_alias(tenant_id, target);
+        {
+            let mut files = self.files.lock().unwrap();
+            let tenant_files = files.entry(*tenant_id).or_default();
+            if !tenant_files.contains_key(&target) {
+                return Err(marble_storage::error::StorageError::NotFound(target));
+            }
+            if tenant_files.contains_key(path) {
+                return Err(marble_storage::error::StorageError::Validation(format!("Destination already exists: {}", path)));
+            }
+            
+            // The alias is listed like a file but holds no content of its own
+            tenant_files.insert(path.to_string(), Vec::new());
+            self.touch(tenant_id, path);
+        }
+        
+        let mut aliases = self.aliases.lock().unwrap();
+        aliases.entry(*tenant_id).or_default().insert(path.to_string(), target);
+        Ok(())
+    }
+    
+    async fn rename(&self, tenant_id: &Uuid, from: &str, to: &str) -> StorageResult<()> {
+        let mut files = self.files.lock().unwrap();
+        let tenant_files = files.entry(*tenant_id).or_default();
+        
+        if tenant_files.contains_key(to) {
+            return Err(marble_storage::error::StorageError::Validation(format!("Destination already exists: {}", to)));
+        }
+        
+        let content = tenant_files
+            .remove(from)
+            .ok_or_else(|| marble_storage::error::StorageError::NotFound(from.to_string()))?;
+        tenant_files.insert(to.to_string(), content);
+        
+        // A renamed file keeps its modification time
+        if let Some(tenant_modified) = self.modified.lock().unwrap().get_mut(tenant_id) {
+            if let Some(modified) = tenant_modified.remove(from) {
+                tenant_modified.insert(to.to_string(), modified);
+            }
+        }
+        
+        self.renames.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+    
     async fn list(&self, tenant_id: &Uuid, dir_path: &str) -> StorageResult<Vec<String>> {
+        if let Some(delay) = self.list_delay {
+            tokio::time::sleep(delay).await;
+        }
+        
         let files = self.files.lock().unwrap();
         let mut results = Vec::new();
         
-        // Check if directory exists
+        // Check if directory exists, rejecting files listed as directories
         let directories = self.directories.lock().unwrap();
-        if let Some(tenant_dirs) = directories.get(tenant_id) {
-            if !tenant_dirs.contains(&dir_path.to_string()) && dir_path != "." {
-                return Err(marble_storage::error::StorageError::NotFound(dir_path.to_string()));
+        let is_directory = directories
+            .get(tenant_id)
+            .is_some_and(|tenant_dirs| tenant_dirs.contains(&dir_path.to_string()));
+        if !is_directory && dir_path != "." && !dir_path.is_empty() {
+            if files.get(tenant_id).is_some_and(|tenant_files| tenant_files.contains_key(dir_path)) {
+                return Err(marble_storage::error::StorageError::Validation(format!("Not a directory: {}", dir_path)));
             }
+            return Err(marble_storage::error::StorageError::NotFound(dir_path.to_string()));
         }
         
         // Get files in this directory
@@ -203,20 +542,56 @@ impl TenantStorage for MockTenantStorage {
         Ok(results)
     }
     
+    async fn list_with_metadata(&self, tenant_id: &Uuid, dir_path: &str, order: ListOrder) -> StorageResult<Vec<FileMetadata>> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        let entries = self.list(tenant_id, dir_path).await?;
+        
+        let mut results = Vec::new();
+        for entry in entries {
+            let entry_path = if dir_path == "." {
+                entry
+            } else {
+                format!("{}/{}", dir_path, entry)
+            };
+            
+            if let Ok(metadata) = self.metadata(tenant_id, &entry_path).await {
+                results.push(metadata);
+            }
+        }
+        
+        sort_metadata(&mut results, order);
+        Ok(results)
+    }
+    
     async fn metadata(&self, tenant_id: &Uuid, path: &str) -> StorageResult<FileMetadata> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        self.metadata_calls.fetch_add(1, Ordering::SeqCst);
+        
+        let target = self.resolve_alias(tenant_id, path);
+        let pinned = self.content_types.lock().unwrap()
+            .get(tenant_id)
+            .and_then(|types| types.get(&target).cloned());
         let files = self.files.lock().unwrap();
         let directories = self.directories.lock().unwrap();
         
         // Check if it's a file
         if let Some(tenant_files) = files.get(tenant_id) {
-            if let Some(content) = tenant_files.get(path) {
+            if let Some(content) = tenant_files.get(&target) {
                 return Ok(FileMetadata {
                     path: path.to_string(),
                     size: content.len() as u64,
-                    content_type: mime_guess::from_path(path).first_or_octet_stream().to_string(),
+                    content_type: pinned
+                        .unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream().to_string()),
                     is_directory: false,
-                    last_modified: Some(chrono::Utc::now().timestamp_millis() as u64),
-                    content_hash: None,
+                    last_modified: self.modified_at(tenant_id, &target),
+                    last_accessed: None,
+                    content_hash: marble_storage::hash::hash_content(content).ok(),
                 });
             }
         }
@@ -229,7 +604,8 @@ impl TenantStorage for MockTenantStorage {
                     size: 0,
                     content_type: "application/x-directory".to_string(),
                     is_directory: true,
-                    last_modified: Some(chrono::Utc::now().timestamp_millis() as u64),
+                    last_modified: self.modified_at(tenant_id, path),
+                    last_accessed: None,
                     content_hash: None,
                 });
             }
@@ -237,4 +613,130 @@ impl TenantStorage for MockTenantStorage {
         
         Err(marble_storage::error::StorageError::NotFound(path.to_string()))
     }
+    
+    async fn metadata_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<Option<FileMetadata>>> {
+        if let Some(delay) = self.metadata_delay {
+            tokio::time::sleep(delay).await;
+        }
+        
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.metadata(tenant_id, path).await {
+                Ok(metadata) => results.push(Some(metadata)),
+                Err(marble_storage::error::StorageError::NotFound(_)) => results.push(None),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+    
+    async fn usage(&self, tenant_id: &Uuid) -> StorageResult<StorageUsage> {
+        let used_bytes = self.files
+            .lock()
+            .unwrap()
+            .get(tenant_id)
+            .map_or(0, |tenant_files| tenant_files.values().map(|file| file.len() as u64).sum());
+        
+        Ok(StorageUsage {
+            used_bytes,
+            quota_bytes: self.quota_bytes.map(|quota| quota as u64),
+        })
+    }
+    
+    async fn set_content_type_override(&self, tenant_id: &Uuid, path: &str, content_type: Option<&str>) -> StorageResult<()> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        let target = self.resolve_alias(tenant_id, path);
+        let is_file = self.files.lock().unwrap()
+            .get(tenant_id)
+            .is_some_and(|tenant_files| tenant_files.contains_key(&target));
+        if !is_file {
+            return Err(marble_storage::error::StorageError::NotFound(path.to_string()));
+        }
+        
+        let mut content_types = self.content_types.lock().unwrap();
+        let tenant_types = content_types.entry(*tenant_id).or_default();
+        match content_type {
+            Some(content_type) => tenant_types.insert(target, content_type.to_string()),
+            None => tenant_types.remove(&target),
+        };
+        Ok(())
+    }
+    
+    async fn properties(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<DeadProperty>> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        if !self.exists(tenant_id, path).await? {
+            return Err(marble_storage::error::StorageError::NotFound(path.to_string()));
+        }
+        
+        let target = self.property_key(tenant_id, path);
+        let properties = self.properties.lock().unwrap();
+        Ok(properties
+            .get(tenant_id)
+            .and_then(|tenant_properties| tenant_properties.get(&target).cloned())
+            .unwrap_or_default())
+    }
+    
+    async fn patch_properties(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        changes: &[PropertyChange],
+        content_type: Option<Option<&str>>,
+    ) -> StorageResult<()> {
+        if let Some(error) = self.database_error() {
+            return Err(error);
+        }
+        
+        let target = self.resolve_alias(tenant_id, path);
+        let is_file = self.files.lock().unwrap()
+            .get(tenant_id)
+            .is_some_and(|tenant_files| tenant_files.contains_key(&target));
+        if !is_file {
+            if !self.exists(tenant_id, path).await? {
+                return Err(marble_storage::error::StorageError::NotFound(path.to_string()));
+            }
+            if content_type.is_some() {
+                return Err(marble_storage::error::StorageError::Validation(format!("Cannot set the content type of a directory: {}", path)));
+            }
+        }
+        
+        let key = self.property_key(tenant_id, path);
+        let mut properties = self.properties.lock().unwrap();
+        let tenant_properties = properties.entry(*tenant_id).or_default();
+        apply_property_changes(tenant_properties.entry(key).or_default(), changes);
+        
+        if let Some(content_type) = content_type {
+            let mut content_types = self.content_types.lock().unwrap();
+            let tenant_types = content_types.entry(*tenant_id).or_default();
+            match content_type {
+                Some(content_type) => tenant_types.insert(target, content_type.to_string()),
+                None => tenant_types.remove(&target),
+            };
+        }
+        Ok(())
+    }
+    
+    async fn read_by_hash(&self, tenant_id: &Uuid, content_hash: &str) -> StorageResult<Vec<u8>> {
+        let files = self.files.lock().unwrap();
+        if let Some(tenant_files) = files.get(tenant_id) {
+            for content in tenant_files.values() {
+                if marble_storage::hash::hash_content(content)? == content_hash {
+                    if let Some(corrupted) = self.corrupted_blobs.lock().unwrap().get(content_hash) {
+                        return Ok(corrupted.clone());
+                    }
+                    return Ok(content.clone());
+                }
+            }
+        }
+        Err(marble_storage::error::StorageError::Authorization(format!(
+            "Content not referenced by tenant: {}",
+            content_hash
+        )))
+    }
 }
@@ -5,8 +5,84 @@ pub mod basic_operations;
 pub mod copy_operations;
 pub mod move_operations;
 pub mod lock_tests;
+pub mod cli_tests;
+pub mod startup_tests;
+pub mod idempotency_tests;
+pub mod blob_tests;
+pub mod etag_tests;
+pub mod concurrency_tests;
+pub mod render_tests;
+pub mod if_header_tests;
+pub mod metadata_cache_tests;
+pub mod role_tests;
+pub mod batch_delete_tests;
+pub mod compression_tests;
+pub mod server_info_tests;
+pub mod degraded_tests;
+pub mod proppatch_tests;
+pub mod lock_conflict_tests;
+pub mod verify_tests;
+pub mod timeout_tests;
+pub mod propfind_batch_tests;
+pub mod stats_tests;
+pub mod search_tests;
+pub mod streaming_tests;
+pub mod range_tests;
+
+use std::sync::Arc;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::HeaderMap;
+use uuid::Uuid;
+use crate::api::LockManagerRef;
+use crate::dav_handler::MarbleDavHandler;
 
 // Re-export the mocks for use in tests
 pub use mock_storage::MockTenantStorage;
 pub use mock_auth::MockAuthService;
 pub use mock_lock::MockLockManager;
+
+/// Normalize a request path as the handler does, for tests calling operations directly
+pub fn normalize(path: &str) -> Result<String, crate::error::Error> {
+    let decoded = percent_encoding::percent_decode_str(path).decode_utf8_lossy();
+    Ok(marble_storage::PathNormalizer::new().to_relative(&decoded))
+}
+
+/// Basic credentials for `user:password` in a fresh header map
+pub fn basic_auth(credentials: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        http::header::AUTHORIZATION,
+        format!("Basic {}", STANDARD.encode(credentials)).parse().unwrap()
+    );
+    headers
+}
+
+/// Credentials of the default test user, who owns the default test tenant
+pub fn auth_headers() -> HeaderMap {
+    basic_auth("testuser:password123")
+}
+
+/// A handler over empty mock storage, with the storage and the default test tenant for seeding
+pub fn setup() -> (MarbleDavHandler, Arc<MockTenantStorage>, Uuid) {
+    setup_with_locks(Arc::new(MockLockManager))
+}
+
+/// Like [`setup`], over the given lock manager
+pub fn setup_with_locks(lock_manager: LockManagerRef) -> (MarbleDavHandler, Arc<MockTenantStorage>, Uuid) {
+    let (handler, tenant_storage, _auth_service, tenant_id) = fixture(lock_manager);
+    (handler, tenant_storage, tenant_id)
+}
+
+/// Like [`setup`], also returning the auth service to simulate outages with
+pub fn setup_with_auth() -> (MarbleDavHandler, Arc<MockTenantStorage>, Arc<MockAuthService>, Uuid) {
+    fixture(Arc::new(MockLockManager))
+}
+
+/// The mocks wired into a handler, shared by the fixtures above
+fn fixture(lock_manager: LockManagerRef) -> (MarbleDavHandler, Arc<MockTenantStorage>, Arc<MockAuthService>, Uuid) {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let auth_service = Arc::new(MockAuthService::new());
+    let handler = MarbleDavHandler::new(tenant_storage.clone(), auth_service.clone(), lock_manager);
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    (handler, tenant_storage, auth_service, tenant_id)
+}
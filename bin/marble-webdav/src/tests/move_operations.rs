@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use http::{HeaderMap, StatusCode};
 use crate::dav_handler::MarbleDavHandler;
+use marble_storage::api::TenantStorage;
 use super::{MockTenantStorage, MockAuthService, MockLockManager};
 use uuid::Uuid;
 
@@ -26,7 +27,7 @@ async fn test_move_file() {
     // Create headers with Destination
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/moved.txt".parse().unwrap()
     );
     
@@ -71,7 +72,7 @@ async fn test_move_directory() {
     // Create headers with Destination
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/moved_dir".parse().unwrap()
     );
     
@@ -120,7 +121,7 @@ async fn test_move_with_overwrite() {
     // Create headers with Destination and Overwrite: T
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/dest.txt".parse().unwrap()
     );
     headers.insert("Overwrite", "T".parse().unwrap());
@@ -162,7 +163,7 @@ async fn test_move_with_no_overwrite() {
     // Create headers with Destination and Overwrite: F (false)
     let mut headers = HeaderMap::new();
     headers.insert(
-        http::header::DESTINATION, 
+        crate::headers::DESTINATION.clone(), 
         "/dest.txt".parse().unwrap()
     );
     headers.insert("Overwrite", "F".parse().unwrap());
@@ -170,12 +171,14 @@ async fn test_move_with_no_overwrite() {
     // Call MOVE method - should fail since overwrite is false
     let result = handler.handle_move(tenant_id, "source.txt", headers).await;
     
-    // Verify error
+    // Verify error, answered with 412 Precondition Failed (RFC 4918 §9.8.5, §9.9.4)
     assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::error::Error::WebDav(msg) if msg.contains("Destination already exists and overwrite is false") => (),
+    let error = result.unwrap_err();
+    match &error {
+        crate::error::Error::PreconditionFailed(msg) if msg.contains("Destination already exists and overwrite is false") => (),
         err => panic!("Unexpected error: {:?}", err),
     }
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::PRECONDITION_FAILED);
     
     // Verify source file still exists
     let source_exists = tenant_storage.exists(&tenant_id, "source.txt").await.unwrap();
@@ -185,3 +188,51 @@ async fn test_move_with_no_overwrite() {
     let dest_content = tenant_storage.read(&tenant_id, "dest.txt").await.unwrap();
     assert_eq!(dest_content, b"Original destination content".to_vec());
 }
+
+#[tokio::test]
+async fn test_move_rename_in_place() {
+    // Create test dependencies
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let auth_service = Arc::new(MockAuthService::new());
+    let lock_manager = Arc::new(MockLockManager);
+    
+    // Create handler
+    let handler = MarbleDavHandler::new(
+        tenant_storage.clone(),
+        auth_service,
+        lock_manager
+    );
+    
+    // Set up test data
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let test_content = b"Renamed content".to_vec();
+    tenant_storage.add_directory(&tenant_id, "a");
+    tenant_storage.add_file(&tenant_id, "a/x.md", test_content.clone());
+    
+    // Rename within the same directory
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        crate::headers::DESTINATION.clone(), 
+        "/a/y.md".parse().unwrap()
+    );
+    
+    let response = handler.handle_move(tenant_id, "a/x.md", headers).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // The rename went through the single-update fast path
+    assert_eq!(tenant_storage.rename_count(), 1);
+    assert!(!tenant_storage.exists(&tenant_id, "a/x.md").await.unwrap());
+    assert_eq!(tenant_storage.read(&tenant_id, "a/y.md").await.unwrap(), test_content);
+    
+    // Moving into another directory still uses copy + delete
+    tenant_storage.add_directory(&tenant_id, "b");
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        crate::headers::DESTINATION.clone(), 
+        "/b/y.md".parse().unwrap()
+    );
+    
+    handler.handle_move(tenant_id, "a/y.md", headers).await.unwrap();
+    assert_eq!(tenant_storage.rename_count(), 1);
+    assert_eq!(tenant_storage.read(&tenant_id, "b/y.md").await.unwrap(), test_content);
+}
@@ -0,0 +1,93 @@
+use bytes::Bytes;
+use http::StatusCode;
+use serde_json::Value;
+use crate::dav_handler::MarbleDavHandler;
+use crate::operations::utils::MAX_BATCH_PATHS;
+use super::{auth_headers, basic_auth, setup};
+
+/// The shared fixture with a file and a file in a folder
+fn setup_with_files() -> MarbleDavHandler {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "a.txt", b"alpha".to_vec());
+    tenant_storage.add_file(&tenant_id, "notes/b.md", b"# Beta".to_vec());
+    handler
+}
+
+#[tokio::test]
+async fn test_propfind_batch_results_aligned_with_request() {
+    let handler = setup_with_files();
+    
+    let body = r#"["/notes/b.md", "/missing.txt", "/a.txt", "/notes"]"#;
+    let response = handler.handle_post(
+        "/.marble/propfind-batch",
+        auth_headers(),
+        Bytes::from(body)
+    ).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(http::header::CONTENT_TYPE).unwrap(), "application/json");
+    
+    let results: Vec<Value> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results.len(), 4);
+    
+    assert_eq!(results[0]["path"], "/notes/b.md");
+    assert_eq!(results[0]["status"], 200);
+    assert_eq!(results[0]["is_directory"], false);
+    assert_eq!(results[0]["size"], 6);
+    assert!(results[0]["etag"].as_str().unwrap().ends_with("-6\""));
+    
+    assert_eq!(results[1]["path"], "/missing.txt");
+    assert_eq!(results[1]["status"], 404);
+    assert!(results[1].get("size").is_none());
+    
+    assert_eq!(results[2]["path"], "/a.txt");
+    assert_eq!(results[2]["status"], 200);
+    assert_eq!(results[2]["size"], 5);
+    
+    assert_eq!(results[3]["path"], "/notes");
+    assert_eq!(results[3]["status"], 200);
+    assert_eq!(results[3]["is_directory"], true);
+    assert!(results[3].get("etag").is_none());
+}
+
+#[tokio::test]
+async fn test_propfind_batch_allowed_for_viewer() {
+    let handler = setup_with_files();
+    
+    let response = handler.handle_post(
+        "/.marble/propfind-batch",
+        basic_auth("viewer:viewpass"),
+        Bytes::from(r#"["/a.txt"]"#)
+    ).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    let results: Vec<Value> = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(results[0]["status"], 200);
+}
+
+#[tokio::test]
+async fn test_propfind_batch_rejects_invalid_body() {
+    let handler = setup_with_files();
+    
+    let error = handler.handle_post(
+        "/.marble/propfind-batch",
+        auth_headers(),
+        Bytes::from("{\"paths\": 1}")
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_propfind_batch_rejects_too_many_paths() {
+    let handler = setup_with_files();
+    
+    let paths: Vec<String> = (0..=MAX_BATCH_PATHS).map(|i| format!("/file{}.txt", i)).collect();
+    let error = handler.handle_post(
+        "/.marble/propfind-batch",
+        auth_headers(),
+        Bytes::from(serde_json::to_vec(&paths).unwrap())
+    ).await.unwrap_err();
+    
+    assert_eq!(crate::server::error_response(&error).status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,171 @@
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::dav_handler::{DavResponse, MarbleDavHandler};
+use super::{auth_headers, setup};
+
+/// The shared fixture with a file to set properties on
+fn setup_with_todo() -> MarbleDavHandler {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "notes/todo.txt", b"# Todo".to_vec());
+    handler
+}
+
+async fn proppatch(handler: &MarbleDavHandler, path: &str, body: &str) -> DavResponse {
+    handler
+        .handle(DavMethod::PropPatch, path, auth_headers(), Bytes::from(body.to_string()))
+        .await
+        .unwrap()
+}
+
+async fn propfind(handler: &MarbleDavHandler, path: &str) -> String {
+    let response = handler
+        .handle(DavMethod::PropFind, path, auth_headers(), Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    String::from_utf8(response.body().to_vec()).unwrap()
+}
+
+/// Status reported for a property in a PROPPATCH response
+fn property_status(body: &str, namespace: &str, name: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(body).expect("PROPPATCH response should be well-formed XML");
+    let property = document.descendants().find(|node| node.has_tag_name((namespace, name)))?;
+    let propstat = property.ancestors().find(|node| node.has_tag_name(("DAV:", "propstat")))?;
+    propstat
+        .children()
+        .find(|node| node.has_tag_name(("DAV:", "status")))
+        .and_then(|node| node.text())
+        .map(str::to_string)
+}
+
+/// Value of the first property with this name in a PROPFIND response
+fn property_value(body: &str, namespace: &str, name: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(body).expect("PROPFIND response should be well-formed XML");
+    document
+        .descendants()
+        .find(|node| node.has_tag_name((namespace, name)))
+        .map(|node| node.text().unwrap_or_default().to_string())
+}
+
+#[tokio::test]
+async fn test_proppatch_round_trip() {
+    let handler = setup_with_todo();
+    
+    let response = proppatch(&handler, "/notes/todo.txt", r#"<?xml version="1.0" encoding="utf-8"?>
+        <D:propertyupdate xmlns:D="DAV:" xmlns:Z="urn:example:props">
+            <D:set><D:prop><Z:color>blue &amp; green</Z:color><Z:starred>yes</Z:starred></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert_eq!(property_status(&body, "urn:example:props", "color").as_deref(), Some("HTTP/1.1 200 OK"));
+    assert_eq!(property_status(&body, "urn:example:props", "starred").as_deref(), Some("HTTP/1.1 200 OK"));
+    
+    // The file and its listing in the parent both report the properties
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "urn:example:props", "color").as_deref(), Some("blue & green"));
+    let body = propfind(&handler, "/notes").await;
+    assert_eq!(property_value(&body, "urn:example:props", "starred").as_deref(), Some("yes"));
+    
+    // Removing a property leaves the others in place
+    let response = proppatch(&handler, "/notes/todo.txt", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:Z="urn:example:props">
+            <D:remove><D:prop><Z:starred/></D:prop></D:remove>
+        </D:propertyupdate>"#).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "urn:example:props", "starred"), None);
+    assert_eq!(property_value(&body, "urn:example:props", "color").as_deref(), Some("blue & green"));
+}
+
+#[tokio::test]
+async fn test_proppatch_is_all_or_nothing() {
+    let handler = setup_with_todo();
+    
+    // Live properties are protected, so the whole update fails
+    let response = proppatch(&handler, "/notes/todo.txt", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:Z="urn:example:props">
+            <D:set><D:prop><D:getcontentlength>0</D:getcontentlength><Z:color>red</Z:color></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert_eq!(property_status(&body, "DAV:", "getcontentlength").as_deref(), Some("HTTP/1.1 403 Forbidden"));
+    assert_eq!(property_status(&body, "urn:example:props", "color").as_deref(), Some("HTTP/1.1 424 Failed Dependency"));
+    
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "urn:example:props", "color"), None);
+}
+
+#[tokio::test]
+async fn test_proppatch_rejects_invalid_requests() {
+    let handler = setup_with_todo();
+    
+    let result = handler
+        .handle(DavMethod::PropPatch, "/notes/todo.txt", auth_headers(), Bytes::from("<not-xml"))
+        .await;
+    assert_eq!(crate::server::error_response(&result.unwrap_err()).status(), StatusCode::BAD_REQUEST);
+    
+    let result = handler
+        .handle(
+            DavMethod::PropPatch,
+            "/missing.txt",
+            auth_headers(),
+            Bytes::from(r#"<D:propertyupdate xmlns:D="DAV:"><D:set><D:prop><color>red</color></D:prop></D:set></D:propertyupdate>"#),
+        )
+        .await;
+    assert_eq!(crate::server::error_response(&result.unwrap_err()).status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_proppatch_content_type_survives_put() {
+    let handler = setup_with_todo();
+    
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "DAV:", "getcontenttype").as_deref(), Some("text/plain"));
+    
+    let response = proppatch(&handler, "/notes/todo.txt", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:M="marble:">
+            <D:set><D:prop><M:content-type>text/markdown</M:content-type></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "DAV:", "getcontenttype").as_deref(), Some("text/markdown"));
+    
+    // A content-only PUT keeps the pinned type
+    let response = handler
+        .handle(DavMethod::Put, "/notes/todo.txt", auth_headers(), Bytes::from("# Todo\n\n- more"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let body = propfind(&handler, "/notes/todo.txt").await;
+    assert_eq!(property_value(&body, "DAV:", "getcontenttype").as_deref(), Some("text/markdown"));
+    
+    // Invalid types are refused
+    let response = proppatch(&handler, "/notes/todo.txt", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:M="marble:">
+            <D:set><D:prop><M:content-type>not a type</M:content-type></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert_eq!(property_status(&body, "marble:", "content-type").as_deref(), Some("HTTP/1.1 409 Conflict"));
+}
+
+#[tokio::test]
+async fn test_proppatch_collection() {
+    let handler = setup_with_todo();
+    
+    let response = proppatch(&handler, "/notes", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:Z="urn:example:props">
+            <D:set><D:prop><Z:color>green</Z:color></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert_eq!(property_status(&body, "urn:example:props", "color").as_deref(), Some("HTTP/1.1 200 OK"));
+    
+    let body = propfind(&handler, "/notes").await;
+    assert_eq!(property_value(&body, "urn:example:props", "color").as_deref(), Some("green"));
+    
+    // Collections have no content type to pin, so nothing is applied
+    let response = proppatch(&handler, "/notes", r#"<D:propertyupdate xmlns:D="DAV:" xmlns:M="marble:" xmlns:Z="urn:example:props">
+            <D:set><D:prop><M:content-type>text/plain</M:content-type><Z:color>red</Z:color></D:prop></D:set>
+        </D:propertyupdate>"#).await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert_eq!(property_status(&body, "marble:", "content-type").as_deref(), Some("HTTP/1.1 403 Forbidden"));
+    assert_eq!(property_status(&body, "urn:example:props", "color").as_deref(), Some("HTTP/1.1 424 Failed Dependency"));
+    let body = propfind(&handler, "/notes").await;
+    assert_eq!(property_value(&body, "urn:example:props", "color").as_deref(), Some("green"));
+}
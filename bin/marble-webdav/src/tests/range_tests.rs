@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use futures::TryStreamExt;
+use http::{HeaderMap, StatusCode};
+use crate::config::WebDavConfig;
+use crate::dav_handler::{DavResponse, MarbleDavHandler};
+use crate::operations::get::{parse_range, ByteRange, StreamedBody};
+use super::{auth_headers, setup, MockTenantStorage};
+use uuid::Uuid;
+
+const CONTENT: &[u8] = b"0123456789abcdefghij";
+
+/// The shared fixture with a file to take ranges of
+fn setup_with_file(config: WebDavConfig) -> (MarbleDavHandler, Arc<MockTenantStorage>, Uuid) {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "file.txt", CONTENT.to_vec());
+    (handler.with_config(config), tenant_storage, tenant_id)
+}
+
+fn ranges_on() -> WebDavConfig {
+    WebDavConfig {
+        ranges: true,
+        ..Default::default()
+    }
+}
+
+fn range_headers(range: &str) -> HeaderMap {
+    let mut headers = auth_headers();
+    headers.insert(http::header::RANGE, range.parse().unwrap());
+    headers
+}
+
+async fn body_of(response: DavResponse) -> Vec<u8> {
+    match response.extensions().get::<StreamedBody>().and_then(StreamedBody::take) {
+        Some(stream) => stream.try_collect::<Vec<Bytes>>().await.unwrap().concat(),
+        None => response.into_body().to_vec(),
+    }
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range("bytes=0-4", 20), Some(ByteRange::Satisfiable { start: 0, end: 4 }));
+    assert_eq!(parse_range("bytes=15-", 20), Some(ByteRange::Satisfiable { start: 15, end: 19 }));
+    assert_eq!(parse_range("bytes=-5", 20), Some(ByteRange::Satisfiable { start: 15, end: 19 }));
+    assert_eq!(parse_range("bytes=10-100", 20), Some(ByteRange::Satisfiable { start: 10, end: 19 }));
+    assert_eq!(parse_range("bytes=20-", 20), Some(ByteRange::Unsatisfiable));
+    assert_eq!(parse_range("bytes=-0", 20), Some(ByteRange::Unsatisfiable));
+    
+    // Served whole: several ranges, other units and nonsense
+    assert_eq!(parse_range("bytes=0-1,5-6", 20), None);
+    assert_eq!(parse_range("items=0-1", 20), None);
+    assert_eq!(parse_range("bytes=5-2", 20), None);
+    assert_eq!(parse_range("bytes=a-b", 20), None);
+}
+
+#[tokio::test]
+async fn test_get_serves_requested_range() {
+    let (handler, _tenant_storage, _tenant_id) = setup_with_file(ranges_on());
+    
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=5-9"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes 5-9/20");
+    assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "5");
+    assert_eq!(response.headers()[http::header::ACCEPT_RANGES], "bytes");
+    assert_eq!(body_of(response).await, b"56789");
+    
+    // A range past the end cannot be served
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=30-"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes */20");
+}
+
+#[tokio::test]
+async fn test_get_range_of_streamed_file() {
+    // Streamed in chunks of four bytes, so the range spans chunk boundaries
+    let (handler, _tenant_storage, _tenant_id) = setup_with_file(WebDavConfig {
+        stream_min_size: Some(1),
+        ..ranges_on()
+    });
+    
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=3-13"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "11");
+    assert_eq!(body_of(response).await, b"3456789abcd");
+}
+
+#[tokio::test]
+async fn test_range_ignored_when_disabled_or_stale() {
+    // Without range support the whole file is sent
+    let (handler, _tenant_storage, _tenant_id) = setup_with_file(WebDavConfig::default());
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=5-9"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(http::header::ACCEPT_RANGES));
+    assert_eq!(body_of(response).await, CONTENT);
+    
+    // So it is when the client's copy is out of date
+    let (handler, _tenant_storage, _tenant_id) = setup_with_file(ranges_on());
+    let mut headers = range_headers("bytes=5-9");
+    headers.insert(http::header::IF_RANGE, "\"stale\"".parse().unwrap());
+    let response = handler.handle(DavMethod::Get, "/file.txt", headers, Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_of(response).await, CONTENT);
+}
+
+#[tokio::test]
+async fn test_range_of_content_shorter_than_cached_size() {
+    let (handler, tenant_storage, tenant_id) = setup_with_file(WebDavConfig {
+        metadata_cache_ttl: Some(std::time::Duration::from_secs(3600)),
+        ..ranges_on()
+    });
+    
+    // Cache the 20-byte size, then shrink the file behind the cache's back
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=0-1"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    tenant_storage.add_file(&tenant_id, "file.txt", CONTENT[..8].to_vec());
+    
+    // The range is cut to the content actually read
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=5-9"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes 5-7/8");
+    assert_eq!(response.headers()[http::header::CONTENT_LENGTH], "3");
+    assert_eq!(body_of(response).await, b"567");
+    
+    // And a range starting past the content read cannot be served
+    let response = handler.handle(DavMethod::Get, "/file.txt", range_headers("bytes=10-"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes */8");
+}
+
+#[tokio::test]
+async fn test_range_of_empty_file() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "empty.txt", Vec::new());
+    let handler = handler.with_config(ranges_on());
+    
+    let response = handler.handle(DavMethod::Get, "/empty.txt", range_headers("bytes=0-"), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.headers()[http::header::CONTENT_RANGE], "bytes */0");
+}
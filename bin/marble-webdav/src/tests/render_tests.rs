@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use http::{HeaderMap, StatusCode};
+use crate::config::WebDavConfig;
+use crate::metadata_cache::MetadataCache;
+use crate::operations;
+use crate::render::{accepts_html, markdown_to_html, CONTENT_SECURITY_POLICY};
+use marble_storage::api::TenantStorageRef;
+use super::MockTenantStorage;
+use uuid::Uuid;
+
+const SOURCE: &[u8] = b"# Notes\n\nSome *emphasis*.\n";
+
+fn setup() -> (TenantStorageRef, Uuid) {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", SOURCE.to_vec());
+    (tenant_storage, tenant_id)
+}
+
+fn accept(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(http::header::ACCEPT, value.parse().unwrap());
+    headers
+}
+
+#[test]
+fn test_accepts_html() {
+    assert!(accepts_html(&accept("text/html,application/xhtml+xml;q=0.9")));
+    assert!(accepts_html(&accept("TEXT/HTML; q=0.5")));
+    assert!(!accepts_html(&accept("text/html;q=0")));
+    assert!(!accepts_html(&accept("*/*")));
+    assert!(!accepts_html(&HeaderMap::new()));
+}
+
+#[tokio::test]
+async fn test_markdown_rendered_for_html_clients() {
+    let (storage, tenant_id) = setup();
+    let config = WebDavConfig {
+        render_markdown: true,
+        ..Default::default()
+    };
+    
+    let response = operations::handle_get(&storage, tenant_id, "notes.md", &accept("text/html"), &config, &MetadataCache::default())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[http::header::CONTENT_TYPE], "text/html; charset=utf-8");
+    assert_eq!(response.headers()[http::header::VARY], "Accept");
+    assert_eq!(response.headers()[http::header::CONTENT_SECURITY_POLICY], CONTENT_SECURITY_POLICY);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("<h1>Notes</h1>"));
+    assert!(body.contains("<em>emphasis</em>"));
+    
+    // A client not asking for HTML gets the source
+    let response = operations::handle_get(&storage, tenant_id, "notes.md", &HeaderMap::new(), &config, &MetadataCache::default())
+        .await
+        .unwrap();
+    assert_eq!(response.body().as_ref(), SOURCE);
+    assert_eq!(response.headers()[http::header::VARY], "Accept");
+    
+    // Stored content is untouched
+    assert_eq!(storage.read(&tenant_id, "notes.md").await.unwrap(), SOURCE);
+}
+
+#[test]
+fn test_raw_html_escaped() {
+    let rendered = markdown_to_html(b"<script>alert(1)</script>\n\nInline <img src=x onerror=alert(1)> here.\n");
+    
+    assert!(!rendered.contains("<script"));
+    assert!(!rendered.contains("<img"));
+    assert!(rendered.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    assert!(rendered.contains("&lt;img src=x onerror=alert(1)&gt;"));
+}
+
+#[test]
+fn test_scriptable_urls_dropped() {
+    let rendered = markdown_to_html(b"[a](javascript:alert(1)) [b]( JavaScript:alert(1)) ![c](data:text/html,x)\n");
+    assert!(!rendered.to_ascii_lowercase().contains("javascript:"));
+    assert!(!rendered.contains("data:"));
+    assert_eq!(rendered.matches("href=\"#\"").count(), 2);
+    
+    let rendered = markdown_to_html(b"[a](https://example.com) [b](other.md) [c](/notes/x.md?at=1:2) [d](mailto:me@example.com)\n");
+    assert!(rendered.contains("href=\"https://example.com\""));
+    assert!(rendered.contains("href=\"other.md\""));
+    assert!(rendered.contains("href=\"/notes/x.md?at=1:2\""));
+    assert!(rendered.contains("href=\"mailto:me@example.com\""));
+}
+
+#[tokio::test]
+async fn test_markdown_served_raw_when_rendering_disabled() {
+    let (storage, tenant_id) = setup();
+    
+    let response = operations::handle_get(&storage, tenant_id, "notes.md", &accept("text/html"), &WebDavConfig::default(), &MetadataCache::default())
+        .await
+        .unwrap();
+    assert_eq!(response.body().as_ref(), SOURCE);
+    assert!(!response.headers().contains_key(http::header::VARY));
+}
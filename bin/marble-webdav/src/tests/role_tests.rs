@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use http::StatusCode;
+use crate::api::{Principal, Role};
+use crate::dav_handler::MarbleDavHandler;
+use crate::error::Error;
+use crate::server::error_response;
+use marble_storage::api::TenantStorage;
+use super::{auth_headers, basic_auth, setup, MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+/// The shared fixture with a note to read and write
+fn setup_with_note() -> (MarbleDavHandler, Arc<MockTenantStorage>, Uuid) {
+    let (handler, tenant_storage, tenant_id) = setup();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    (handler, tenant_storage, tenant_id)
+}
+
+#[tokio::test]
+async fn test_viewer_can_read() {
+    let (handler, _tenant_storage, _tenant_id) = setup_with_note();
+    
+    let response = handler.handle(DavMethod::Get, "/notes.md", basic_auth("viewer:viewpass"), Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(response.body().as_ref(), b"# Notes");
+    
+    let response = handler.handle(DavMethod::PropFind, "/", basic_auth("viewer:viewpass"), Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+}
+
+#[tokio::test]
+async fn test_viewer_cannot_write() {
+    let (handler, tenant_storage, tenant_id) = setup_with_note();
+    
+    let error = handler.handle(DavMethod::Put, "/notes.md", basic_auth("viewer:viewpass"), Bytes::from("changed"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Forbidden(_)));
+    assert_eq!(error_response(&error).status(), StatusCode::FORBIDDEN);
+    
+    let error = handler.handle(DavMethod::Delete, "/notes.md", basic_auth("viewer:viewpass"), Bytes::new())
+        .await
+        .unwrap_err();
+    assert_eq!(error_response(&error).status(), StatusCode::FORBIDDEN);
+    
+    assert_eq!(tenant_storage.read(&tenant_id, "notes.md").await.unwrap(), b"# Notes");
+    
+    // The owner of the same tenant may write
+    let response = handler.handle(DavMethod::Put, "/notes.md", auth_headers(), Bytes::from("changed"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_custom_method_policy() {
+    let (handler, _tenant_storage, _tenant_id) = setup_with_note();
+    let handler = handler.with_method_policy(Arc::new(|principal: &Principal, method| {
+        principal.role == Role::Owner && method != DavMethod::Delete
+    }));
+    
+    let result = handler.handle(DavMethod::Delete, "/notes.md", auth_headers(), Bytes::new()).await;
+    assert!(matches!(result, Err(Error::Forbidden(_))));
+}
+
+#[tokio::test]
+async fn test_options_allow_follows_role() {
+    let (handler, _tenant_storage, _tenant_id) = setup_with_note();
+    
+    let allow = |response: crate::DavResponse| {
+        response.headers()[http::header::ALLOW].to_str().unwrap().to_string()
+    };
+    
+    let response = handler.handle(DavMethod::Options, "/notes.md", basic_auth("viewer:viewpass"), Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(allow(response), "OPTIONS, GET, HEAD, PROPFIND");
+    
+    let response = handler.handle(DavMethod::Options, "/notes.md", auth_headers(), Bytes::new())
+        .await
+        .unwrap();
+    assert_eq!(allow(response), crate::dav_handler::ALLOWED_METHODS);
+}
+
+#[tokio::test]
+async fn test_server_with_method_policy() {
+    use axum::body::Body;
+    use http::{Method, Request};
+    use tower::ServiceExt;
+    use crate::config::WebDavConfig;
+    use crate::server::create_webdav_server_with_policy;
+    use crate::stats::RequestStats;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    // Owners may do everything but delete
+    let router = create_webdav_server_with_policy(
+        tenant_storage.clone(),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+        WebDavConfig::default(),
+        Arc::new(RequestStats::new()),
+        Arc::new(|principal: &Principal, method| {
+            principal.role.allows(method) && method != DavMethod::Delete
+        }),
+    );
+    
+    let request = |method: Method| {
+        Request::builder()
+            .method(method)
+            .uri("/notes.md")
+            .header(http::header::AUTHORIZATION, auth_headers()[http::header::AUTHORIZATION].clone())
+            .body(Body::empty())
+            .unwrap()
+    };
+    
+    let response = router.clone().oneshot(request(Method::DELETE)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(tenant_storage.exists(&tenant_id, "notes.md").await.unwrap());
+    
+    let response = router.oneshot(request(Method::OPTIONS)).await.unwrap();
+    let allow = response.headers()[http::header::ALLOW].to_str().unwrap();
+    assert!(allow.contains("PUT"));
+    assert!(!allow.contains("DELETE"));
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use axum::body::Body;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+use crate::server::create_webdav_server;
+use super::{MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+/// GET a search URI through the server, returning the status and JSON body
+async fn search(tenant_storage: Arc<MockTenantStorage>, uri: &str) -> (StatusCode, serde_json::Value) {
+    let router = create_webdav_server(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+    );
+    
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .header(
+            http::header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode("testuser:password123"))
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_search_finds_matching_notes() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    let other_tenant = Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap();
+    tenant_storage.add_directory(&tenant_id, "notes");
+    tenant_storage.add_file(&tenant_id, "notes/garden plan.md", b"# Garden\nPlant tomatoes in spring".to_vec());
+    tenant_storage.add_file(&tenant_id, "notes/kitchen.md", b"# Kitchen\nBuy tomatoes and basil".to_vec());
+    tenant_storage.add_file(&tenant_id, "notes/spring.txt", b"spring cleaning".to_vec());
+    tenant_storage.add_file(&other_tenant, "secret.md", b"spring secrets".to_vec());
+    
+    // Only the tenant's own markdown files are searched
+    let (status, body) = search(tenant_storage.clone(), "/.marble/search?q=spring").await;
+    assert_eq!(status, StatusCode::OK);
+    let hits = body.as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["href"], "/notes/garden%20plan.md");
+    
+    // Every word of the query must occur
+    let (_, body) = search(tenant_storage.clone(), "/.marble/search?q=tomatoes+basil").await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    let (_, body) = search(tenant_storage.clone(), "/.marble/search?q=tomatoes").await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    
+    let (status, _) = search(tenant_storage, "/.marble/search").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_search_route_disabled() {
+    use crate::config::WebDavConfig;
+    use crate::server::create_webdav_server_with_config;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"spring".to_vec());
+    let router = create_webdav_server_with_config(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+        WebDavConfig {
+            disable_search: true,
+            ..Default::default()
+        },
+    );
+    
+    // The route is not served, so the path is looked up as a file
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/.marble/search?q=spring")
+        .header(
+            http::header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode("testuser:password123"))
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use axum::body::Body;
+use http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+use crate::server::create_webdav_server;
+use super::{MockTenantStorage, MockAuthService, MockLockManager};
+
+#[tokio::test]
+async fn test_options_asterisk_returns_capabilities_without_auth() {
+    let router = create_webdav_server(
+        Arc::new(MockTenantStorage::new()),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+    );
+    
+    // No Authorization header: the server-wide probe must not require one
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("*")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("dav").unwrap(), "1, 2");
+    assert_eq!(response.headers().get("ms-author-via").unwrap(), "DAV");
+    let allow = response.headers().get(http::header::ALLOW).unwrap().to_str().unwrap();
+    assert!(allow.contains("PROPFIND"));
+    assert!(allow.contains("LOCK"));
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(body.is_empty());
+}
+
+#[tokio::test]
+async fn test_version_endpoint_without_auth() {
+    use crate::version::{BuildInfo, VERSION};
+    
+    let router = create_webdav_server(
+        Arc::new(MockTenantStorage::new()),
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+    );
+    
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/.marble/version")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/json");
+    
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["webdav"], VERSION);
+    assert_eq!(json["storage"], marble_storage::VERSION);
+    assert_eq!(json["git_sha"], BuildInfo::current().git_sha);
+    assert!(BuildInfo::current().banner().contains(VERSION));
+}
+
+#[tokio::test]
+async fn test_capabilities_endpoint_reflects_config() {
+    use crate::config::WebDavConfig;
+    use crate::server::create_webdav_server_with_config;
+    
+    let capabilities = |config: WebDavConfig| async move {
+        let router = create_webdav_server_with_config(
+            Arc::new(MockTenantStorage::new()),
+            Arc::new(MockAuthService::new()),
+            Arc::new(MockLockManager),
+            config,
+        );
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/.marble/capabilities")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("*")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let dav = response.headers()["dav"].to_str().unwrap().to_string();
+        (json, dav)
+    };
+    
+    let (json, dav) = capabilities(WebDavConfig {
+        render_markdown: true,
+        ..Default::default()
+    }).await;
+    assert_eq!(json["locks"], true);
+    assert_eq!(json["ranges"], false);
+    assert_eq!(json["search"], true);
+    assert_eq!(json["quotas"], true);
+    assert_eq!(json["two_factor"], true);
+    assert_eq!(json["render_markdown"], true);
+    assert_eq!(json["compression"], false);
+    assert_eq!(dav, "1, 2");
+    
+    // Without locking the server is class 1 only
+    let (json, dav) = capabilities(WebDavConfig {
+        disable_locks: true,
+        compression_min_size: Some(1024),
+        ..Default::default()
+    }).await;
+    assert_eq!(json["locks"], false);
+    assert_eq!(json["render_markdown"], false);
+    assert_eq!(json["compression"], true);
+    assert_eq!(dav, "1");
+    
+    // Every other feature follows its own setting
+    let (json, _) = capabilities(WebDavConfig {
+        ranges: true,
+        disable_search: true,
+        disable_quotas: true,
+        disable_two_factor: true,
+        ..Default::default()
+    }).await;
+    assert_eq!(json["ranges"], true);
+    assert_eq!(json["search"], false);
+    assert_eq!(json["quotas"], false);
+    assert_eq!(json["two_factor"], false);
+}
@@ -0,0 +1,23 @@
+use crate::startup::{schema_action, SchemaAction};
+
+#[test]
+fn test_up_to_date_schema_serves() {
+    assert_eq!(schema_action(vec![], false), SchemaAction::Serve);
+    assert_eq!(schema_action(vec![], true), SchemaAction::Serve);
+}
+
+#[test]
+fn test_pending_migrations_refuse_without_auto_migrate() {
+    assert_eq!(
+        schema_action(vec![20250404000005, 20250404000006], false),
+        SchemaAction::Refuse(vec![20250404000005, 20250404000006])
+    );
+}
+
+#[test]
+fn test_pending_migrations_applied_with_auto_migrate() {
+    assert_eq!(
+        schema_action(vec![20250404000006], true),
+        SchemaAction::Migrate(vec![20250404000006])
+    );
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use axum::body::Body;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+use crate::config::WebDavConfig;
+use crate::server::create_webdav_server_with_stats;
+use crate::stats::RequestStats;
+use super::{MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+fn request(method: Method, uri: &str, body: &'static str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(
+            http::header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode("testuser:password123"))
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_stats_count_served_requests() {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    let stats = Arc::new(RequestStats::new());
+    let router = create_webdav_server_with_stats(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+        WebDavConfig::default(),
+        stats.clone(),
+    );
+    
+    let response = router.clone().oneshot(request(Method::GET, "/notes.md", "")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response = router.clone().oneshot(request(Method::PUT, "/new.md", "hello")).await.unwrap();
+    assert!(response.status().is_success());
+    
+    let response = router.clone().oneshot(request(Method::GET, "/missing.md", "")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.requests, 3);
+    assert_eq!(snapshot.success, 2);
+    assert_eq!(snapshot.client_errors, 1);
+    assert_eq!(snapshot.server_errors, 0);
+    assert_eq!(snapshot.bytes_received, 5);
+    // The GET body plus the 404 message
+    assert!(snapshot.bytes_sent > 7);
+    
+    // The summary logged at shutdown reports the same totals
+    assert_eq!(stats.log_summary(), snapshot);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_stats_count_timed_out_requests() {
+    use std::time::Duration;
+    
+    let tenant_storage = Arc::new(MockTenantStorage::new().with_list_delay(Duration::from_secs(60)));
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_file(&tenant_id, "notes.md", b"# Notes".to_vec());
+    
+    let stats = Arc::new(RequestStats::new());
+    let router = create_webdav_server_with_stats(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+        WebDavConfig {
+            request_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        },
+        stats.clone(),
+    );
+    
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let response = router.oneshot(request(propfind, "/", "")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.requests, 1);
+    assert_eq!(snapshot.server_errors, 1);
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use axum::body::Body;
+use bytes::Bytes;
+use dav_server::DavMethod;
+use futures::TryStreamExt;
+use http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+use crate::config::WebDavConfig;
+use crate::operations::get::StreamedBody;
+use crate::server::create_webdav_server_with_config;
+use super::mock_storage::MOCK_READ_CHUNK_SIZE;
+use super::{auth_headers, setup, MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+const CONTENT: &[u8] = b"# A note long enough to be streamed";
+
+fn config() -> WebDavConfig {
+    WebDavConfig {
+        stream_min_size: Some(16),
+        ..Default::default()
+    }
+}
+
+/// Add a note long enough to stream and one too short to
+fn add_notes(tenant_storage: &MockTenantStorage, tenant_id: &Uuid) {
+    tenant_storage.add_file(tenant_id, "long.md", CONTENT.to_vec());
+    tenant_storage.add_file(tenant_id, "short.md", b"# Short".to_vec());
+}
+
+#[tokio::test]
+async fn test_get_streams_large_files_in_chunks() {
+    let (handler, tenant_storage, tenant_id) = setup();
+    add_notes(&tenant_storage, &tenant_id);
+    let handler = handler.with_config(config());
+    let headers = auth_headers();
+    
+    // The content is not held in the response but read from storage chunk by chunk
+    let response = handler.handle(DavMethod::Get, "/long.md", headers.clone(), Bytes::new()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.body().is_empty());
+    assert_eq!(response.headers()[http::header::CONTENT_LENGTH], CONTENT.len().to_string());
+    let stream = response.extensions().get::<StreamedBody>().and_then(StreamedBody::take).unwrap();
+    let chunks: Vec<Bytes> = stream.try_collect().await.unwrap();
+    assert_eq!(chunks.len(), CONTENT.len().div_ceil(MOCK_READ_CHUNK_SIZE));
+    assert_eq!(chunks.concat(), CONTENT);
+    
+    // Small files are read whole
+    let response = handler.handle(DavMethod::Get, "/short.md", headers, Bytes::new()).await.unwrap();
+    assert!(response.extensions().get::<StreamedBody>().is_none());
+    assert_eq!(response.body().as_ref(), b"# Short");
+}
+
+#[tokio::test]
+async fn test_server_sends_streamed_content() {
+    let (_handler, tenant_storage, tenant_id) = setup();
+    add_notes(&tenant_storage, &tenant_id);
+    let router = create_webdav_server_with_config(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+        config(),
+    );
+    
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/long.md")
+        .header(http::header::AUTHORIZATION, auth_headers()[http::header::AUTHORIZATION].clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[http::header::CONTENT_LENGTH], CONTENT.len().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body.as_ref(), CONTENT);
+}
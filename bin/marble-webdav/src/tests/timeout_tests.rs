@@ -0,0 +1,67 @@
+use std::time::Duration;
+use axum::body::Body;
+use axum::routing::get;
+use axum::{middleware, Router};
+use http::{Request, StatusCode};
+use tokio::time::Instant;
+use tower::ServiceExt;
+use crate::server::enforce_request_timeout;
+
+/// Router whose handlers take `delay` to answer, behind a `timeout` deadline
+fn slow_router(delay: Duration, timeout: Duration) -> Router {
+    let handler = move || async move {
+        tokio::time::sleep(delay).await;
+        "done"
+    };
+    Router::new()
+        .route("/", get(handler).put(handler))
+        .layer(middleware::from_fn_with_state(timeout, enforce_request_timeout))
+}
+
+async fn body_text(response: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_slow_request_times_out_at_deadline() {
+    let router = slow_router(Duration::from_secs(60), Duration::from_millis(100));
+    
+    let started = Instant::now();
+    let response = router
+        .oneshot(Request::get("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(started.elapsed(), Duration::from_millis(100));
+    assert_eq!(body_text(response).await, "Request timed out");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_fast_request_passes_through() {
+    let router = slow_router(Duration::from_millis(10), Duration::from_millis(100));
+    
+    let response = router
+        .oneshot(Request::get("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(response).await, "done");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_slow_write_runs_to_completion() {
+    let router = slow_router(Duration::from_secs(60), Duration::from_millis(100));
+    
+    let started = Instant::now();
+    let response = router
+        .oneshot(Request::put("/").body(Body::from("content")).unwrap())
+        .await
+        .unwrap();
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(started.elapsed(), Duration::from_secs(60));
+    assert_eq!(body_text(response).await, "done");
+}
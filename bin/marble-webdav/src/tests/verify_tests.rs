@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use axum::body::Body;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{Method, Request, StatusCode};
+use tower::ServiceExt;
+use crate::server::create_webdav_server;
+use marble_storage::hash::hash_content;
+use super::{MockTenantStorage, MockAuthService, MockLockManager};
+use uuid::Uuid;
+
+fn setup() -> (Arc<MockTenantStorage>, Uuid) {
+    let tenant_storage = Arc::new(MockTenantStorage::new());
+    let tenant_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+    tenant_storage.add_directory(&tenant_id, "notes");
+    tenant_storage.add_file(&tenant_id, "notes/a b.md", b"# Intact".to_vec());
+    (tenant_storage, tenant_id)
+}
+
+/// GET a verify URI through the server, returning the status and JSON body
+async fn verify(tenant_storage: Arc<MockTenantStorage>, uri: &str, authenticated: bool) -> (StatusCode, serde_json::Value) {
+    let router = create_webdav_server(
+        tenant_storage,
+        Arc::new(MockAuthService::new()),
+        Arc::new(MockLockManager),
+    );
+    
+    let mut request = Request::builder().method(Method::GET).uri(uri);
+    if authenticated {
+        request = request.header(
+            http::header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode("testuser:password123"))
+        );
+    }
+    let response = router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_verify_intact_file() {
+    let (tenant_storage, _tenant_id) = setup();
+    let hash = hash_content(b"# Intact").unwrap();
+    
+    let (status, json) = verify(tenant_storage, "/.marble/verify?path=/notes/a%20b.md", true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["path"], "notes/a b.md");
+    assert_eq!(json["stored_hash"], hash.as_str());
+    assert_eq!(json["computed_hash"], hash.as_str());
+    assert_eq!(json["ok"], true);
+}
+
+#[tokio::test]
+async fn test_verify_corrupted_blob() {
+    let (tenant_storage, tenant_id) = setup();
+    tenant_storage.corrupt_blob(&tenant_id, "notes/a b.md", b"# Bitrot".to_vec());
+    
+    let (status, json) = verify(tenant_storage, "/.marble/verify?path=notes/a%20b.md", true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["stored_hash"], hash_content(b"# Intact").unwrap().as_str());
+    assert_eq!(json["computed_hash"], hash_content(b"# Bitrot").unwrap().as_str());
+    assert_eq!(json["ok"], false);
+}
+
+#[tokio::test]
+async fn test_verify_requires_auth_and_existing_file() {
+    let (tenant_storage, _tenant_id) = setup();
+    
+    let (status, _) = verify(tenant_storage.clone(), "/.marble/verify?path=notes/a%20b.md", false).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    
+    let (status, _) = verify(tenant_storage.clone(), "/.marble/verify?path=notes/missing.md", true).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    
+    let (status, _) = verify(tenant_storage, "/.marble/verify", true).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,69 @@
+//! Short-lived cache of tenant storage usage
+//!
+//! Quota properties are computed from the sum of a tenant's file sizes, which
+//! costs a scan of the tenant's files. Clients that show free space ask for
+//! them on every PROPFIND of a folder, so the usage of a tenant is kept for a
+//! few seconds and reused. Entries of a tenant are dropped whenever that
+//! tenant modifies anything, and expire on their own so changes made through
+//! other servers are picked up.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use marble_storage::api::{StorageUsage, TenantStorageRef};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::error::Error;
+
+/// Default time a tenant's usage is reused
+pub const DEFAULT_USAGE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Storage usage of recently listed tenants
+pub struct UsageCache {
+    entries: RwLock<HashMap<Uuid, (StorageUsage, Instant)>>,
+    ttl: Duration,
+}
+
+impl UsageCache {
+    /// Create a cache keeping usage for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Storage usage of a tenant, from the cache or storage
+    pub async fn resolve(
+        &self,
+        tenant_storage: &TenantStorageRef,
+        tenant_id: Uuid,
+    ) -> Result<StorageUsage, Error> {
+        if let Some((usage, resolved_at)) = self.entries.read().await.get(&tenant_id) {
+            if resolved_at.elapsed() < self.ttl {
+                return Ok(*usage);
+            }
+        }
+
+        let usage = tenant_storage.usage(&tenant_id).await?;
+
+        let mut entries = self.entries.write().await;
+        let ttl = self.ttl;
+        entries.retain(|_, (_, resolved_at)| resolved_at.elapsed() < ttl);
+        entries.insert(tenant_id, (usage, Instant::now()));
+
+        Ok(usage)
+    }
+
+    /// Drop the entry of a tenant
+    pub async fn invalidate_tenant(&self, tenant_id: Uuid) {
+        self.entries.write().await.remove(&tenant_id);
+    }
+}
+
+impl Default for UsageCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_USAGE_CACHE_TTL)
+    }
+}
@@ -0,0 +1,44 @@
+//! Build information
+//!
+//! Served at `GET /.marble/version` and logged at startup so operators can
+//! tell which build is running.
+
+use serde::Serialize;
+
+/// Version of the WebDAV server crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short git SHA of the build, `unknown` outside a git checkout
+pub const GIT_SHA: &str = env!("MARBLE_GIT_SHA");
+
+/// Versions of the running build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Version of the WebDAV server
+    pub webdav: &'static str,
+
+    /// Version of the storage crate
+    pub storage: &'static str,
+
+    /// Git SHA the server was built from
+    pub git_sha: &'static str,
+}
+
+impl BuildInfo {
+    /// Build information of this binary
+    pub fn current() -> Self {
+        Self {
+            webdav: VERSION,
+            storage: marble_storage::VERSION,
+            git_sha: GIT_SHA,
+        }
+    }
+
+    /// One-line banner for the startup log
+    pub fn banner(&self) -> String {
+        format!(
+            "Marble WebDAV Server {} (storage {}, git {})",
+            self.webdav, self.storage, self.git_sha
+        )
+    }
+}
@@ -4,6 +4,10 @@
 // Export error types from the error module
 pub mod error;
 
+// Injectable source of the current time
+pub mod clock;
+pub use clock::{Clock, ClockRef, MockClock, SystemClock};
+
 /// Placeholder function
 pub fn placeholder() -> &'static str {
     "marble-core placeholder"
@@ -0,0 +1,14 @@
+-- Create password reset tokens table
+-- Only a hash of each token is stored; the token itself is handed to the user
+
+CREATE TABLE password_reset_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(128) UNIQUE NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+-- Create index for invalidating a user's outstanding tokens
+CREATE INDEX idx_password_reset_tokens_user ON password_reset_tokens(user_id);
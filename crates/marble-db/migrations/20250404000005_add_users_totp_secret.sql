@@ -0,0 +1,4 @@
+-- Add optional TOTP two-factor secret to users
+-- NULL means the user has not enrolled
+
+ALTER TABLE users ADD COLUMN totp_secret VARCHAR(64);
@@ -0,0 +1,7 @@
+-- Store the original path spelling separately from the lookup key
+-- `path` is the key used for uniqueness and lookups; in case-insensitive mode
+-- it is lowercased and NFC normalized. `display_path` keeps the user's casing.
+
+ALTER TABLE files ADD COLUMN display_path VARCHAR(1024);
+UPDATE files SET display_path = path;
+ALTER TABLE files ALTER COLUMN display_path SET NOT NULL;
@@ -0,0 +1,13 @@
+-- Create directories table
+-- Tracks empty directories under the implicit directory strategy, so that
+-- no placeholder rows are needed in the files table
+
+CREATE TABLE directories (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    path VARCHAR(1024) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, path)
+);
+
+CREATE INDEX idx_directories_user_path ON directories(user_id, path);
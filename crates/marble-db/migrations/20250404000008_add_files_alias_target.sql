@@ -0,0 +1,5 @@
+-- Let a file row act as a live alias of another file
+-- Alias rows store the target's lookup path in `alias_target` instead of
+-- content of their own; their `content_hash` is empty.
+
+ALTER TABLE files ADD COLUMN alias_target VARCHAR(1024);
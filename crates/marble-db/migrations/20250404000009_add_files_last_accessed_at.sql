@@ -0,0 +1,5 @@
+-- Record when a file was last read
+-- Only written when access tracking is enabled, and then at most once per
+-- throttle window per file, so it is an approximate timestamp.
+
+ALTER TABLE files ADD COLUMN last_accessed_at TIMESTAMPTZ;
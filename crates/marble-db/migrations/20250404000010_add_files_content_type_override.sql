@@ -0,0 +1,5 @@
+-- Let a user pin the content type of a file
+-- When set, writes that do not declare a content type store this one instead
+-- of the type guessed from the path.
+
+ALTER TABLE files ADD COLUMN content_type_override VARCHAR(255);
@@ -0,0 +1,5 @@
+-- Index the change feed order
+-- The feed pages through a user's files by (updated_at, id), with the id
+-- breaking ties between files changed in the same instant.
+
+CREATE INDEX idx_files_user_changes ON files(user_id, updated_at, id);
@@ -0,0 +1,11 @@
+-- Create file_properties table
+-- Stores WebDAV dead properties set with PROPPATCH, keyed by the namespace
+-- URI and local name of the property
+
+CREATE TABLE file_properties (
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    namespace VARCHAR(1024) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (file_id, namespace, name)
+);
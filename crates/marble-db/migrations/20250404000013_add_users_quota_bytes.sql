@@ -0,0 +1,5 @@
+-- Add an optional storage quota to users
+-- NULL means the user may store any amount; otherwise writes that would take
+-- the total size of the user's live files above the quota are refused.
+
+ALTER TABLE users ADD COLUMN quota_bytes BIGINT;
@@ -0,0 +1,14 @@
+-- Create file_versions table
+-- Records the content of a file after every write, so earlier contents stay
+-- listable and restorable. Content is shared by hash, so a version costs a row.
+
+CREATE TABLE file_versions (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    content_hash VARCHAR(64) NOT NULL,
+    size INTEGER NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_file_versions_file ON file_versions(file_id, id);
@@ -0,0 +1,40 @@
+-- Record every directory in the folders table
+-- Directories were tracked in two other places: rows in the directories table
+-- under the implicit strategy, and `.dir` placeholder files otherwise. Both
+-- are backfilled into folders, along with their ancestors, and the
+-- directories table is dropped. Placeholders stay as the marker files of the
+-- placeholder strategy.
+
+WITH RECURSIVE recorded AS (
+    SELECT user_id, path, created_at
+    FROM directories
+    WHERE path <> '/'
+    UNION
+    SELECT user_id, left(path, length(path) - length('/.dir')), created_at
+    FROM files
+    WHERE path LIKE '%/.dir'
+      AND content_type = 'application/vnd.marble.directory'
+      AND is_deleted = false
+      AND path <> '/.dir'
+), ancestors AS (
+    SELECT user_id, path, created_at FROM recorded
+    UNION
+    SELECT user_id, regexp_replace(path, '/[^/]+$', ''), created_at
+    FROM ancestors
+    WHERE path ~ '^/.+/'
+)
+INSERT INTO folders (user_id, path, created_at, updated_at, is_deleted)
+SELECT user_id, path, MIN(created_at), MIN(created_at), false
+FROM ancestors
+GROUP BY user_id, path
+ON CONFLICT (user_id, path) DO UPDATE SET is_deleted = false;
+
+-- Link each folder to the folder of its parent path
+UPDATE folders child
+SET parent_id = parent.id
+FROM folders parent
+WHERE child.parent_id IS NULL
+  AND parent.user_id = child.user_id
+  AND parent.path = regexp_replace(child.path, '/[^/]+$', '');
+
+DROP TABLE directories;
@@ -0,0 +1,12 @@
+-- Create file_search_index table
+-- Holds the searchable text of markdown files, so searching does not have to
+-- fetch every file's content from the hash store. The `simple` configuration
+-- does no stemming, as notes may be in any language.
+
+CREATE TABLE file_search_index (
+    file_id INTEGER PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+    document TSVECTOR NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_file_search_index_document ON file_search_index USING GIN(document);
@@ -0,0 +1,12 @@
+-- Record the current content of every file written before versioning
+-- Versions were only recorded from the first write after file_versions was
+-- created, so older files had no history to list or restore. Each of them
+-- gets its current content as its first version. Aliases have no content of
+-- their own and directory placeholders are not versioned.
+
+INSERT INTO file_versions (file_id, content_hash, size, content_type, created_at)
+SELECT f.id, f.content_hash, f.size, f.content_type, f.updated_at
+FROM files f
+WHERE f.alias_target IS NULL
+  AND f.content_type <> 'application/vnd.marble.directory'
+  AND NOT EXISTS (SELECT 1 FROM file_versions v WHERE v.file_id = f.id);
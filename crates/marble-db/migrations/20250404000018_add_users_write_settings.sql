@@ -0,0 +1,7 @@
+-- Add per-tenant write settings to users
+-- default_content_type is used for uploads that declare no content type and
+-- whose path does not suggest one. normalize_newlines turns CRLF rewriting
+-- of text uploads on or off for the tenant. NULL keeps the storage defaults.
+
+ALTER TABLE users ADD COLUMN default_content_type VARCHAR(255);
+ALTER TABLE users ADD COLUMN normalize_newlines BOOLEAN;
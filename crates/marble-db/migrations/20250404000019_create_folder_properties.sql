@@ -0,0 +1,11 @@
+-- Create folder_properties table
+-- Stores WebDAV dead properties of collections set with PROPPATCH, the way
+-- file_properties does for files. They are removed when the folder is deleted.
+
+CREATE TABLE folder_properties (
+    folder_id INTEGER NOT NULL REFERENCES folders(id) ON DELETE CASCADE,
+    namespace VARCHAR(1024) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (folder_id, namespace, name)
+);
@@ -23,6 +23,11 @@ pub trait DatabaseApi: Send + Sync + 'static {
 
     /// Check if the database is healthy
     async fn health_check(&self) -> Result<()>;
+
+    /// Whether the database currently answers queries, for readiness probes
+    async fn healthy(&self) -> bool {
+        self.health_check().await.is_ok()
+    }
 }
 
 /// Database implementation that wraps a connection pool
@@ -38,6 +43,15 @@ impl Database {
             pool: Arc::new(pool),
         }
     }
+
+    /// Run a trivial query to check that the pool can reach the database
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1")
+            .execute(self.pool.as_ref())
+            .await
+            .map(|_| ())
+            .map_err(Error::QueryFailed)
+    }
 }
 
 #[async_trait::async_trait]
@@ -51,10 +65,6 @@ impl DatabaseApi for Database {
     }
 
     async fn health_check(&self) -> Result<()> {
-        sqlx::query("SELECT 1")
-            .execute(self.pool.as_ref())
-            .await
-            .map(|_| ())
-            .map_err(Error::QueryFailed)
+        self.ping().await
     }
 }
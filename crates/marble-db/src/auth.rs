@@ -1,16 +1,26 @@
 //! Authentication services for database users
 //!
 //! This module provides authentication-related functionality for users
-//! in the database, including password verification.
+//! in the database, including password verification. Passwords are stored as
+//! Argon2id hashes in PHC string format.
 
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
 use uuid::Uuid;
 use std::sync::Arc;
 use async_trait::async_trait;
+use chrono::Duration;
+use marble_core::clock::{system_clock, ClockRef};
 use sqlx::PgPool;
 
 use crate::error::Error;
-use crate::repositories::{SqlxUserRepository, Repository, UserRepository};
+use crate::repositories::{BaseRepository, SqlxUserRepository, Repository, UserRepository};
 use crate::models::User;
+use crate::totp;
+
+/// Default lifetime of a password reset token
+const DEFAULT_RESET_TOKEN_TTL_MINUTES: i64 = 60;
 
 /// Error type for authentication operations
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +44,10 @@ pub enum AuthError {
     /// Password verification error
     #[error("Password verification error: {0}")]
     PasswordVerification(String),
+
+    /// Password reset token is unknown, expired or already used
+    #[error("Invalid or expired reset token")]
+    InvalidResetToken,
 }
 
 /// Result type for authentication operations
@@ -53,12 +67,30 @@ pub trait AuthService: Send + Sync + 'static {
 /// Database-backed authentication service using SqlxUserRepository
 pub struct DatabaseAuthService {
     user_repository: SqlxUserRepository,
+    reset_token_ttl: Duration,
+    clock: ClockRef,
 }
 
 impl DatabaseAuthService {
     /// Create a new database-backed authentication service
     pub fn new(user_repository: SqlxUserRepository) -> Self {
-        Self { user_repository }
+        Self {
+            user_repository,
+            reset_token_ttl: Duration::minutes(DEFAULT_RESET_TOKEN_TTL_MINUTES),
+            clock: system_clock(),
+        }
+    }
+    
+    /// Use `clock` for reset token expiry and TOTP codes
+    pub fn with_clock(mut self, clock: ClockRef) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Set how long password reset tokens stay valid
+    pub fn with_reset_token_ttl(mut self, ttl: Duration) -> Self {
+        self.reset_token_ttl = ttl;
+        self
     }
     
     /// Create a new database-backed authentication service from a pool
@@ -66,6 +98,228 @@ impl DatabaseAuthService {
         let user_repository = SqlxUserRepository::new(pool);
         Self::new(user_repository)
     }
+    
+    /// Create a password reset token for a user
+    ///
+    /// Only a hash of the token is stored, so the returned token must be
+    /// delivered to the user out of band; it cannot be recovered later.
+    pub async fn create_reset_token(&self, username: &str) -> AuthResult<String> {
+        let user = self.user_repository
+            .find_by_username(username)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        
+        let token = generate_reset_token();
+        let expires_at = self.clock.now() + self.reset_token_ttl;
+        
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) 
+             VALUES ($1, $2, $3)"
+        )
+        .bind(user.id)
+        .bind(hash_reset_token(&token))
+        .bind(expires_at)
+        .execute(self.user_repository.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(token)
+    }
+    
+    /// Reset a password using a token from `create_reset_token`
+    ///
+    /// The token is consumed, and every other outstanding token of the user is
+    /// invalidated along with it.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> AuthResult<()> {
+        let mut tx = self.user_repository
+            .pool()
+            .begin()
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        // Claim the token atomically so it can only be used once
+        let now = self.clock.now();
+        let user_id = sqlx::query_scalar::<_, i32>(
+            "UPDATE password_reset_tokens 
+             SET used_at = $2 
+             WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2 
+             RETURNING user_id"
+        )
+        .bind(hash_reset_token(token))
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(Error::QueryFailed)?
+        .ok_or(AuthError::InvalidResetToken)?;
+        
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(hash_password(new_password)?)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        sqlx::query(
+            "UPDATE password_reset_tokens 
+             SET used_at = $2 
+             WHERE user_id = $1 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        tx.commit().await.map_err(Error::QueryFailed)?;
+        
+        Ok(())
+    }
+}
+
+impl DatabaseAuthService {
+    /// Enroll a user in TOTP two-factor authentication
+    ///
+    /// Returns the new base32 secret for the user's authenticator app. Once
+    /// enrolled, the user authenticates with the current 6-digit code appended
+    /// to their password.
+    pub async fn enroll_totp(&self, username: &str) -> AuthResult<String> {
+        let secret = totp::generate_secret();
+        self.set_totp_secret(username, Some(&secret)).await?;
+        Ok(secret)
+    }
+    
+    /// Disable TOTP two-factor authentication for a user
+    pub async fn disable_totp(&self, username: &str) -> AuthResult<()> {
+        self.set_totp_secret(username, None).await
+    }
+    
+    /// Check a TOTP code for a user, e.g. to confirm an enrollment
+    ///
+    /// Returns false if the user is not enrolled.
+    pub async fn verify_totp(&self, username: &str, code: &str) -> AuthResult<bool> {
+        let user = self.user_repository
+            .find_by_username(username)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        
+        Ok(match self.totp_secret(user.id).await? {
+            Some(secret) => totp::verify_at(&secret, code, self.unix_now()),
+            None => false,
+        })
+    }
+    
+    /// Current unix time in seconds
+    fn unix_now(&self) -> u64 {
+        self.clock.now().timestamp().max(0) as u64
+    }
+    
+    /// Store or clear a user's TOTP secret
+    async fn set_totp_secret(&self, username: &str, secret: Option<&str>) -> AuthResult<()> {
+        let result = sqlx::query("UPDATE users SET totp_secret = $1 WHERE username = $2")
+            .bind(secret)
+            .bind(username)
+            .execute(self.user_repository.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        if result.rows_affected() == 0 {
+            return Err(AuthError::UserNotFound);
+        }
+        Ok(())
+    }
+    
+    /// Load a user's TOTP secret, if enrolled
+    async fn totp_secret(&self, user_id: i32) -> AuthResult<Option<String>> {
+        let secret = sqlx::query_scalar::<_, Option<String>>("SELECT totp_secret FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(self.user_repository.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        Ok(secret)
+    }
+}
+
+/// Split a password into the password itself and a trailing TOTP code
+fn split_totp_code(password: &str) -> Option<(&str, &str)> {
+    let split = password.len().checked_sub(totp::TOTP_DIGITS)?;
+    if !password.is_char_boundary(split) {
+        return None;
+    }
+    Some(password.split_at(split))
+}
+
+
+/// Generate a random password reset token
+fn generate_reset_token() -> String {
+    // Two v4 UUIDs give 244 random bits from the OS random source
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Hash a password reset token for storage
+fn hash_reset_token(token: &str) -> String {
+    blake2b_simd::Params::new()
+        .hash_length(32)
+        .hash(token.as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+/// Hash a password for storage as an Argon2id PHC string
+pub fn hash_password(password: &str) -> crate::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::PasswordHashing(e.to_string()))
+}
+
+/// Whether a stored password is already an Argon2 PHC string
+pub fn is_password_hash(value: &str) -> bool {
+    value.starts_with("$argon2") && PasswordHash::new(value).is_ok()
+}
+
+/// Scheme of a stored password hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordScheme {
+    /// Argon2 PHC string, the current scheme
+    Argon2,
+    /// bcrypt hash from older deployments
+    Bcrypt,
+    /// Password stored as-is by older deployments
+    Plaintext,
+}
+
+impl PasswordScheme {
+    /// Detect the scheme of a stored password hash by its prefix
+    pub fn detect(password_hash: &str) -> Self {
+        if password_hash.starts_with("$argon2") {
+            PasswordScheme::Argon2
+        } else if ["$2a$", "$2b$", "$2y$"].iter().any(|prefix| password_hash.starts_with(prefix)) {
+            PasswordScheme::Bcrypt
+        } else {
+            PasswordScheme::Plaintext
+        }
+    }
+}
+
+/// Verify a password against a stored hash of any supported scheme
+fn verify_password_hash(password: &str, password_hash: &str) -> AuthResult<bool> {
+    match PasswordScheme::detect(password_hash) {
+        PasswordScheme::Argon2 => {
+            let parsed = PasswordHash::new(password_hash)
+                .map_err(|e| AuthError::PasswordVerification(e.to_string()))?;
+            
+            match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+                Ok(()) => Ok(true),
+                Err(argon2::password_hash::Error::Password) => Ok(false),
+                Err(e) => Err(AuthError::PasswordVerification(e.to_string())),
+            }
+        }
+        PasswordScheme::Bcrypt => bcrypt::verify(password, password_hash)
+            .map_err(|e| AuthError::PasswordVerification(e.to_string())),
+        PasswordScheme::Plaintext => Ok(password == password_hash),
+    }
 }
 
 #[async_trait]
@@ -77,11 +331,31 @@ impl AuthService for DatabaseAuthService {
             .await?
             .ok_or(AuthError::UserNotFound)?;
         
+        // Users enrolled in two-factor authentication append their code to the password
+        let password = match self.totp_secret(user.id).await? {
+            Some(secret) => {
+                let (password, code) = split_totp_code(password).ok_or(AuthError::InvalidCredentials)?;
+                if !totp::verify_at(&secret, code, self.unix_now()) {
+                    return Err(AuthError::InvalidCredentials);
+                }
+                password
+            }
+            None => password,
+        };
+        
         // Verify password
         if !self.verify_password(password, &user.password_hash).await? {
             return Err(AuthError::InvalidCredentials);
         }
         
+        // Upgrade legacy hashes now that the plaintext password is known
+        // (ignoring errors, as authentication still succeeded)
+        if PasswordScheme::detect(&user.password_hash) != PasswordScheme::Argon2 {
+            if let Ok(password_hash) = hash_password(password) {
+                let _ = self.user_repository.set_password_hash(user.id, &password_hash).await;
+            }
+        }
+        
         // Record login (ignoring errors, as authentication still succeeded)
         let _ = self.user_repository.record_login(user.id).await;
         
@@ -89,10 +363,7 @@ impl AuthService for DatabaseAuthService {
     }
     
     async fn verify_password(&self, password: &str, password_hash: &str) -> AuthResult<bool> {
-        // TODO: Implement proper password verification with a hashing library
-        // For now, we just do a simple string comparison as a placeholder
-        // In production, this should use a secure password hashing algorithm like bcrypt or Argon2
-        Ok(password == password_hash)
+        verify_password_hash(password, password_hash)
     }
 }
 
@@ -134,9 +405,12 @@ mod tests {
         // Create a user repository
         let user_repository = SqlxUserRepository::new(pool.clone());
         
-        // Create a test user
-        let user = User::new("testuser".to_string(), "password123".to_string());
+        // Create a test user with a hashed password
+        let password_hash = hash_password("password123").unwrap();
+        assert!(password_hash.starts_with("$argon2id$"));
+        let user = User::new("testuser".to_string(), password_hash.clone());
         let created = user_repository.create(&user).await.unwrap();
+        assert_eq!(created.password_hash, password_hash);
         
         // Create the auth service
         let auth_service = DatabaseAuthService::new(user_repository);
@@ -147,10 +421,242 @@ mod tests {
         
         // Test failed authentication with wrong password
         let result = auth_service.authenticate_user("testuser", "wrongpassword").await;
-        assert!(result.is_err());
+        assert!(matches!(result, Err(AuthError::InvalidCredentials)));
         
         // Test failed authentication with wrong username
         let result = auth_service.authenticate_user("nonexistent", "password123").await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+    
+    /// Create a user for the reset token and TOTP tests, removing any leftover one
+    async fn create_auth_test_user(pool: &Arc<PgPool>, username: &str) -> User {
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1")
+            .bind(username)
+            .execute(&**pool)
+            .await;
+        
+        SqlxUserRepository::new(pool.clone())
+            .create(&User::new(username.to_string(), "old-password".to_string()))
+            .await
+            .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_password_reset() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping password reset test - no test database available");
+                return;
+            }
+        };
+        
+        let user = create_auth_test_user(&pool, "reset_test_user").await;
+        let auth_service = DatabaseAuthService::from_pool(pool.clone());
+        
+        // Unknown users cannot request a token
+        let result = auth_service.create_reset_token("reset_test_nobody").await;
+        assert!(matches!(result, Err(AuthError::UserNotFound)));
+        
+        let token = auth_service.create_reset_token("reset_test_user").await.unwrap();
+        let other_token = auth_service.create_reset_token("reset_test_user").await.unwrap();
+        
+        // Only the hash is stored
+        let stored: Vec<String> = sqlx::query_scalar("SELECT token_hash FROM password_reset_tokens WHERE user_id = $1")
+            .bind(user.id)
+            .fetch_all(&*pool)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(!stored.contains(&token));
+        
+        // A valid token rotates the password
+        auth_service.reset_password(&token, "new-password").await.unwrap();
+        assert_eq!(auth_service.authenticate_user("reset_test_user", "new-password").await.unwrap(), user.uuid);
+        assert!(auth_service.authenticate_user("reset_test_user", "old-password").await.is_err());
+        
+        // The token cannot be reused, and other outstanding tokens are invalidated
+        let result = auth_service.reset_password(&token, "another-password").await;
+        assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+        let result = auth_service.reset_password(&other_token, "another-password").await;
+        assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+        
+        // Unknown tokens are rejected
+        let result = auth_service.reset_password("not-a-token", "another-password").await;
+        assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+        
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user.id).execute(&*pool).await;
+    }
+    
+    #[tokio::test]
+    async fn test_password_reset_expired_token() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping password reset test - no test database available");
+                return;
+            }
+        };
+        
+        let user = create_auth_test_user(&pool, "reset_expired_user").await;
+        let auth_service = DatabaseAuthService::from_pool(pool.clone())
+            .with_reset_token_ttl(chrono::Duration::seconds(-1));
+        
+        let token = auth_service.create_reset_token("reset_expired_user").await.unwrap();
+        let result = auth_service.reset_password(&token, "new-password").await;
+        assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+        
+        // The password is unchanged
+        assert_eq!(auth_service.authenticate_user("reset_expired_user", "old-password").await.unwrap(), user.uuid);
+        
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user.id).execute(&*pool).await;
+    }
+    
+    #[tokio::test]
+    async fn test_reset_token_expires_at_ttl() {
+        use marble_core::clock::MockClock;
+        
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping password reset test - no test database available");
+                return;
+            }
+        };
+        
+        let user = create_auth_test_user(&pool, "reset_clock_user").await;
+        let start = chrono::DateTime::from_timestamp(chrono::Utc::now().timestamp(), 0).unwrap();
+        let clock = Arc::new(MockClock::new(start));
+        let auth_service = DatabaseAuthService::from_pool(pool.clone())
+            .with_reset_token_ttl(chrono::Duration::minutes(10))
+            .with_clock(clock.clone());
+        
+        let token = auth_service.create_reset_token("reset_clock_user").await.unwrap();
+        
+        // Rejected at the deadline, without consuming the token
+        clock.advance(chrono::Duration::minutes(10));
+        let result = auth_service.reset_password(&token, "new-password").await;
+        assert!(matches!(result, Err(AuthError::InvalidResetToken)));
+        
+        // Accepted a second before it
+        clock.set(start + chrono::Duration::minutes(10) - chrono::Duration::seconds(1));
+        auth_service.reset_password(&token, "new-password").await.unwrap();
+        assert_eq!(auth_service.authenticate_user("reset_clock_user", "new-password").await.unwrap(), user.uuid);
+        
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user.id).execute(&*pool).await;
+    }
+    
+    #[tokio::test]
+    async fn test_totp_authentication() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping TOTP test - no test database available");
+                return;
+            }
+        };
+        
+        let enrolled = create_auth_test_user(&pool, "totp_enrolled_user").await;
+        let plain = create_auth_test_user(&pool, "totp_plain_user").await;
+        let auth_service = DatabaseAuthService::from_pool(pool.clone());
+        
+        let secret = auth_service.enroll_totp("totp_enrolled_user").await.unwrap();
+        let now = auth_service.unix_now();
+        let code = totp::code_at(&secret, now).unwrap();
+        assert!(auth_service.verify_totp("totp_enrolled_user", &code).await.unwrap());
+        
+        // A correct code appended to the password succeeds
+        let uuid = auth_service
+            .authenticate_user("totp_enrolled_user", &format!("old-password{}", code))
+            .await
+            .unwrap();
+        assert_eq!(uuid, enrolled.uuid);
+        
+        // The password alone, or with a code outside the window, fails
+        assert!(auth_service.authenticate_user("totp_enrolled_user", "old-password").await.is_err());
+        let stale = totp::code_at(&secret, now - 10 * totp::TOTP_STEP_SECONDS).unwrap();
+        let result = auth_service.authenticate_user("totp_enrolled_user", &format!("old-password{}", stale)).await;
+        assert!(matches!(result, Err(AuthError::InvalidCredentials)));
+        
+        // Non-enrolled users authenticate as before
+        assert_eq!(auth_service.authenticate_user("totp_plain_user", "old-password").await.unwrap(), plain.uuid);
+        assert!(!auth_service.verify_totp("totp_plain_user", &code).await.unwrap());
+        
+        // Disabling restores plain password authentication
+        auth_service.disable_totp("totp_enrolled_user").await.unwrap();
+        assert_eq!(auth_service.authenticate_user("totp_enrolled_user", "old-password").await.unwrap(), enrolled.uuid);
+        
+        let _ = sqlx::query("DELETE FROM users WHERE id IN ($1, $2)")
+            .bind(enrolled.id)
+            .bind(plain.id)
+            .execute(&*pool)
+            .await;
+    }
+    
+    const ARGON2_FIXTURE: &str = "$argon2id$v=19$m=19456,t=2,p=1$t+I7TXe2KYkogqPQyaRbqA$foJuLfhE+H27ojV5sEkdF3mLe6EXCbqIr30URsYYq6E";
+    const BCRYPT_FIXTURE: &str = "$2b$04$YvB31bDOh0.MXxQcMpV.1uWaa8kf5MuO/C4e0Jc2I/iqkVesjtdHq";
+    
+    #[test]
+    fn test_password_scheme_detection() {
+        assert_eq!(PasswordScheme::detect(ARGON2_FIXTURE), PasswordScheme::Argon2);
+        assert_eq!(PasswordScheme::detect(BCRYPT_FIXTURE), PasswordScheme::Bcrypt);
+        assert_eq!(PasswordScheme::detect("fixture-password"), PasswordScheme::Plaintext);
+    }
+    
+    #[test]
+    fn test_verify_password_hash_schemes() {
+        assert!(verify_password_hash("fixture-password", ARGON2_FIXTURE).unwrap());
+        assert!(!verify_password_hash("wrong-password", ARGON2_FIXTURE).unwrap());
+        
+        assert!(verify_password_hash("fixture-password", BCRYPT_FIXTURE).unwrap());
+        assert!(!verify_password_hash("wrong-password", BCRYPT_FIXTURE).unwrap());
+        
+        assert!(verify_password_hash("fixture-password", "fixture-password").unwrap());
+        assert!(!verify_password_hash("wrong-password", "fixture-password").unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_legacy_hash_rehashed_on_login() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping auth test - no test database available");
+                return;
+            }
+        };
+        
+        let auth_service = DatabaseAuthService::new(SqlxUserRepository::new(pool.clone()));
+        
+        for (username, legacy_hash) in [("legacy_plain_user", "fixture-password"), ("legacy_bcrypt_user", BCRYPT_FIXTURE)] {
+            let user = create_auth_test_user(&pool, username).await;
+            
+            // Store the legacy value directly, as the repository would hash it
+            sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+                .bind(legacy_hash)
+                .bind(user.id)
+                .execute(&*pool)
+                .await
+                .unwrap();
+            
+            let result = auth_service.authenticate_user(username, "wrong-password").await;
+            assert!(matches!(result, Err(AuthError::InvalidCredentials)));
+            
+            let uuid = auth_service.authenticate_user(username, "fixture-password").await.unwrap();
+            assert_eq!(uuid, user.uuid);
+            
+            // The stored hash was upgraded and still authenticates
+            let stored: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+                .bind(user.id)
+                .fetch_one(&*pool)
+                .await
+                .unwrap();
+            assert_eq!(PasswordScheme::detect(&stored), PasswordScheme::Argon2);
+            assert_eq!(auth_service.authenticate_user(username, "fixture-password").await.unwrap(), user.uuid);
+            
+            let _ = sqlx::query("DELETE FROM users WHERE id = $1")
+                .bind(user.id)
+                .execute(&*pool)
+                .await;
+        }
+    }
+}
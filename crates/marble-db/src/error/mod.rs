@@ -22,6 +22,42 @@ pub enum Error {
     /// Failed to convert database row
     #[error("Failed to convert database row: {0}")]
     RowConversionFailed(#[source] sqlx::Error),
+
+    /// The record an operation works on does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The record an operation would create already exists
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+
+    /// A folder would be moved into its own subtree
+    #[error("Cannot move a folder below itself: {0}")]
+    MoveIntoSelf(String),
+
+    /// Failed to hash a password
+    #[error("Failed to hash password: {0}")]
+    PasswordHashing(String),
+}
+
+impl Error {
+    /// Whether the error means the database could not be reached, rather than
+    /// that it rejected the operation
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            Error::ConnectionFailed(_) => true,
+            Error::QueryFailed(e) | Error::RowConversionFailed(e) => is_connection_error(e),
+            _ => false,
+        }
+    }
+}
+
+/// Whether a sqlx error is a failure to reach the database
+pub fn is_connection_error(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) | sqlx::Error::Tls(_)
+    )
 }
 
 impl From<sqlx::Error> for Error {
@@ -21,13 +21,18 @@ pub use auth::{AuthService, DatabaseAuthService, AuthError, AuthResult};
 pub mod api;
 pub mod config;
 pub mod models;
+pub mod pool_stats;
 pub mod repositories;
+pub mod totp;
+pub mod usage;
 
 #[cfg(test)]
 mod tests;
 
 pub use api::{Database, DatabaseApi};
 pub use config::DatabaseConfig;
+pub use pool_stats::{pool_stats, PoolStats};
+pub use usage::{usage_report, TenantUsage};
 
 /// Static migrator for database schema migrations
 pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!();
@@ -60,6 +65,37 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     Ok(())
 }
 
+/// List the versions of migrations that have not been applied yet
+///
+/// A database that has never been migrated reports every migration as pending.
+pub async fn pending_migrations(pool: &PgPool) -> Result<Vec<i64>> {
+    let tracked: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM information_schema.tables
+             WHERE table_schema = current_schema() AND table_name = '_sqlx_migrations'
+         )"
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(Error::QueryFailed)?;
+
+    let applied: Vec<i64> = if tracked {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = true")
+            .fetch_all(pool)
+            .await
+            .map_err(Error::QueryFailed)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
 /// Create and initialize a new Database instance
 pub async fn connect(config: DatabaseConfig) -> Result<Database> {
     let pool = create_pool(config).await?;
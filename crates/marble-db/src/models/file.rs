@@ -13,8 +13,10 @@ pub struct File {
     pub id: i32,
     /// Foreign key to the user who owns this file
     pub user_id: i32,
-    /// Path relative to the user's root folder
+    /// Path relative to the user's root folder, used as the lookup key
     pub path: String,
+    /// Path as originally spelled by the user, shown in listings
+    pub display_path: String,
     /// Content-addressable hash of file contents
     pub content_hash: String,
     /// MIME type of the file
@@ -27,6 +29,12 @@ pub struct File {
     pub updated_at: DateTime<Utc>,
     /// Soft deletion flag
     pub is_deleted: bool,
+    /// Lookup path of the file this alias follows, `None` for regular files
+    pub alias_target: Option<String>,
+    /// When the file was last read, if access tracking is enabled
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    /// Content type pinned by the user, used for writes that declare none
+    pub content_type_override: Option<String>,
 }
 
 impl File {
@@ -42,6 +50,7 @@ impl File {
         Self {
             id: 0, // Will be assigned by database
             user_id,
+            display_path: path.clone(),
             path,
             content_hash,
             content_type,
@@ -49,9 +58,27 @@ impl File {
             created_at: now,
             updated_at: now,
             is_deleted: false,
+            alias_target: None,
+            last_accessed_at: None,
+            content_type_override: None,
         }
     }
     
+    /// Create an alias that follows the file at `target_path`
+    ///
+    /// Aliases have no content of their own, so the hash is empty and the
+    /// size zero; reads and writes go to the target.
+    pub fn new_alias(user_id: i32, path: String, target_path: String, content_type: String) -> Self {
+        let mut file = Self::new(user_id, path, String::new(), content_type, 0);
+        file.alias_target = Some(target_path);
+        file
+    }
+    
+    /// Check if this file is an alias of another file
+    pub fn is_alias(&self) -> bool {
+        self.alias_target.is_some()
+    }
+    
     /// Get the filename from the path
     pub fn name(&self) -> String {
         Path::new(&self.path)
@@ -130,6 +157,7 @@ mod tests {
         assert_eq!(file.id, 0);
         assert_eq!(file.user_id, 1);
         assert_eq!(file.path, "/documents/notes.md");
+        assert_eq!(file.display_path, "/documents/notes.md");
         assert_eq!(file.content_hash, "abcdef1234567890");
         assert_eq!(file.content_type, "text/markdown");
         assert_eq!(file.size, 1024);
@@ -262,4 +290,20 @@ mod tests {
         assert!(canvas_file_by_ext.is_canvas());
         assert!(!not_canvas_file.is_canvas());
     }
+
+    #[test]
+    fn test_new_alias() {
+        let alias = File::new_alias(
+            1, 
+            "/links/notes.md".to_string(), 
+            "/documents/notes.md".to_string(), 
+            "text/markdown".to_string()
+        );
+        
+        assert!(alias.is_alias());
+        assert_eq!(alias.alias_target.as_deref(), Some("/documents/notes.md"));
+        assert_eq!(alias.content_hash, "");
+        assert_eq!(alias.size, 0);
+        assert!(!File::new(1, "/a.md".to_string(), "abc".to_string(), "text/markdown".to_string(), 1).is_alias());
+    }
 }
@@ -0,0 +1,18 @@
+//! File property model for WebDAV dead properties
+//!
+//! This module defines the FileProperty struct stored by PROPPATCH.
+
+use serde::{Deserialize, Serialize};
+
+/// Represents a dead property of a file in the database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileProperty {
+    /// Foreign key to the file this property belongs to
+    pub file_id: i32,
+    /// Namespace URI of the property, empty for no namespace
+    pub namespace: String,
+    /// Local name of the property
+    pub name: String,
+    /// Text value of the property
+    pub value: String,
+}
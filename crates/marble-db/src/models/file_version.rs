@@ -0,0 +1,23 @@
+//! File version model for the history of file contents
+//!
+//! This module defines the FileVersion struct recorded on every write.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Represents one recorded content of a file in the database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileVersion {
+    /// Primary key
+    pub id: i32,
+    /// Foreign key to the file this version belongs to
+    pub file_id: i32,
+    /// Hash of the content, pointing into hash storage
+    pub content_hash: String,
+    /// Size of the content in bytes
+    pub size: i32,
+    /// MIME type of the content
+    pub content_type: String,
+    /// When the version was recorded
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,18 @@
+//! Folder property model for WebDAV dead properties of collections
+//!
+//! This module defines the FolderProperty struct stored by PROPPATCH.
+
+use serde::{Deserialize, Serialize};
+
+/// Represents a dead property of a folder in the database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderProperty {
+    /// Foreign key to the folder this property belongs to
+    pub folder_id: i32,
+    /// Namespace URI of the property, empty for no namespace
+    pub namespace: String,
+    /// Local name of the property
+    pub name: String,
+    /// Text value of the property
+    pub value: String,
+}
@@ -5,7 +5,13 @@
 mod user;
 mod folder;
 mod file;
+mod file_property;
+mod folder_property;
+mod file_version;
 
 pub use user::User;
 pub use folder::Folder;
 pub use file::File;
+pub use file_property::FileProperty;
+pub use folder_property::FolderProperty;
+pub use file_version::FileVersion;
@@ -22,6 +22,12 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     /// Most recent login timestamp, if any
     pub last_login: Option<DateTime<Utc>>,
+    /// Maximum total size of the user's live files in bytes, unlimited if `None`
+    pub quota_bytes: Option<i64>,
+    /// Content type for uploads without a declared or guessable type, storage default if `None`
+    pub default_content_type: Option<String>,
+    /// Whether CRLF in text uploads is rewritten to LF, storage default if `None`
+    pub normalize_newlines: Option<bool>,
 }
 
 impl User {
@@ -34,6 +40,9 @@ impl User {
             password_hash,
             created_at: Utc::now(),
             last_login: None,
+            quota_bytes: None,
+            default_content_type: None,
+            normalize_newlines: None,
         }
     }
 
@@ -0,0 +1,228 @@
+//! Repository for WebDAV dead properties
+//!
+//! This module provides the FilePropertyRepository trait and its SQLx implementation.
+//! Properties of files and of folders are kept in separate tables.
+
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::{FromRow, Row};
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::models::{FileProperty, FolderProperty};
+use crate::Result;
+use crate::Error;
+use super::{Repository, BaseRepository, TransactionSupport};
+
+/// A change to the dead properties of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyChange {
+    /// Set a property, replacing any previous value
+    Set {
+        namespace: String,
+        name: String,
+        value: String,
+    },
+
+    /// Remove a property; removing a missing property is not an error
+    Remove {
+        namespace: String,
+        name: String,
+    },
+}
+
+/// Repository trait for dead properties
+#[async_trait]
+pub trait FilePropertyRepository: Repository + BaseRepository + Send + Sync {
+    /// List the properties of a file, ordered by namespace and name
+    async fn list_by_file(&self, file_id: i32) -> Result<Vec<FileProperty>>;
+
+    /// List the properties of many files at once, ordered by file, namespace and name
+    async fn list_by_files(&self, file_ids: &[i32]) -> Result<Vec<FileProperty>>;
+
+    /// Apply changes to the properties of a file in order, all or nothing
+    ///
+    /// With `content_type`, the file's content type and its pin, `(type, pin)`,
+    /// are updated in the same transaction.
+    async fn apply(&self, file_id: i32, changes: &[PropertyChange], content_type: Option<(&str, Option<&str>)>) -> Result<()>;
+
+    /// Remove every property of a file, returning how many were removed
+    async fn delete_by_file(&self, file_id: i32) -> Result<u64>;
+
+    /// List the properties of a folder, ordered by namespace and name
+    async fn list_by_folder(&self, folder_id: i32) -> Result<Vec<FolderProperty>>;
+
+    /// List the properties of many folders at once, ordered by folder, namespace and name
+    async fn list_by_folders(&self, folder_ids: &[i32]) -> Result<Vec<FolderProperty>>;
+
+    /// Apply changes to the properties of a folder in order, all or nothing
+    async fn apply_to_folder(&self, folder_id: i32, changes: &[PropertyChange]) -> Result<()>;
+}
+
+/// SQLx implementation of the FilePropertyRepository
+pub struct SqlxFilePropertyRepository {
+    pool: Arc<PgPool>,
+}
+
+impl Repository for SqlxFilePropertyRepository {
+    fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+impl BaseRepository for SqlxFilePropertyRepository {
+    fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+impl SqlxFilePropertyRepository {
+    /// Apply changes to the rows of `table` keyed by `owner_column` inside a transaction
+    async fn apply_changes(
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        table: &str,
+        owner_column: &str,
+        owner_id: i32,
+        changes: &[PropertyChange],
+    ) -> Result<()> {
+        for change in changes {
+            match change {
+                PropertyChange::Set { namespace, name, value } => {
+                    sqlx::query(&format!(
+                        "INSERT INTO {table} ({owner_column}, namespace, name, value)
+                         VALUES ($1, $2, $3, $4)
+                         ON CONFLICT ({owner_column}, namespace, name) DO UPDATE SET value = EXCLUDED.value"
+                    ))
+                    .bind(owner_id)
+                    .bind(namespace)
+                    .bind(name)
+                    .bind(value)
+                    .execute(&mut **transaction)
+                    .await
+                    .map_err(Error::QueryFailed)?;
+                }
+                PropertyChange::Remove { namespace, name } => {
+                    sqlx::query(&format!(
+                        "DELETE FROM {table}
+                         WHERE {owner_column} = $1 AND namespace = $2 AND name = $3"
+                    ))
+                    .bind(owner_id)
+                    .bind(namespace)
+                    .bind(name)
+                    .execute(&mut **transaction)
+                    .await
+                    .map_err(Error::QueryFailed)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for FileProperty {
+    fn from_row(row: &PgRow) -> std::result::Result<Self, sqlx::Error> {
+        Ok(FileProperty {
+            file_id: row.try_get("file_id")?,
+            namespace: row.try_get("namespace")?,
+            name: row.try_get("name")?,
+            value: row.try_get("value")?,
+        })
+    }
+}
+
+impl FromRow<'_, PgRow> for FolderProperty {
+    fn from_row(row: &PgRow) -> std::result::Result<Self, sqlx::Error> {
+        Ok(FolderProperty {
+            folder_id: row.try_get("folder_id")?,
+            namespace: row.try_get("namespace")?,
+            name: row.try_get("name")?,
+            value: row.try_get("value")?,
+        })
+    }
+}
+
+#[async_trait]
+impl FilePropertyRepository for SqlxFilePropertyRepository {
+    async fn list_by_file(&self, file_id: i32) -> Result<Vec<FileProperty>> {
+        self.list_by_files(&[file_id]).await
+    }
+
+    async fn list_by_files(&self, file_ids: &[i32]) -> Result<Vec<FileProperty>> {
+        if file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let properties = sqlx::query_as::<_, FileProperty>(
+            "SELECT file_id, namespace, name, value
+             FROM file_properties
+             WHERE file_id = ANY($1)
+             ORDER BY file_id, namespace, name"
+        )
+        .bind(file_ids)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+
+        Ok(properties)
+    }
+
+    async fn apply(&self, file_id: i32, changes: &[PropertyChange], content_type: Option<(&str, Option<&str>)>) -> Result<()> {
+        let mut transaction = self.begin_transaction().await?;
+
+        Self::apply_changes(&mut transaction, "file_properties", "file_id", file_id, changes).await?;
+
+        if let Some((content_type, content_type_override)) = content_type {
+            sqlx::query(
+                "UPDATE files SET content_type = $1, content_type_override = $2, updated_at = $3 WHERE id = $4"
+            )
+            .bind(content_type)
+            .bind(content_type_override)
+            .bind(chrono::Utc::now())
+            .bind(file_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(Error::QueryFailed)?;
+        }
+
+        Self::commit_transaction(transaction).await
+    }
+
+    async fn delete_by_file(&self, file_id: i32) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM file_properties WHERE file_id = $1")
+            .bind(file_id)
+            .execute(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn list_by_folder(&self, folder_id: i32) -> Result<Vec<FolderProperty>> {
+        self.list_by_folders(&[folder_id]).await
+    }
+
+    async fn list_by_folders(&self, folder_ids: &[i32]) -> Result<Vec<FolderProperty>> {
+        if folder_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let properties = sqlx::query_as::<_, FolderProperty>(
+            "SELECT folder_id, namespace, name, value
+             FROM folder_properties
+             WHERE folder_id = ANY($1)
+             ORDER BY folder_id, namespace, name"
+        )
+        .bind(folder_ids)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+
+        Ok(properties)
+    }
+
+    async fn apply_to_folder(&self, folder_id: i32, changes: &[PropertyChange]) -> Result<()> {
+        let mut transaction = self.begin_transaction().await?;
+        Self::apply_changes(&mut transaction, "folder_properties", "folder_id", folder_id, changes).await?;
+        Self::commit_transaction(transaction).await
+    }
+}
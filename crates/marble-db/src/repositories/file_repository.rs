@@ -6,11 +6,90 @@ use sqlx::postgres::{PgPool, PgRow};
 use sqlx::{FromRow, Row};
 use std::sync::Arc;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
 
 use crate::models::File;
 use crate::Result;
 use crate::Error;
-use super::{Repository, BaseRepository};
+use super::file_version_repository::insert_version;
+use super::{escape_like, Repository, BaseRepository, TransactionSupport};
+
+/// Sort order for folder listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListOrder {
+    /// Alphabetical by path
+    #[default]
+    Path,
+    
+    /// Most recently modified first
+    ModifiedDesc,
+    
+    /// Largest files first
+    SizeDesc,
+}
+
+impl ListOrder {
+    /// SQL ORDER BY clause for this sort order
+    ///
+    /// Path is always used as a tie-breaker so the result is deterministic.
+    fn order_by_clause(&self) -> &'static str {
+        match self {
+            ListOrder::Path => "ORDER BY path",
+            ListOrder::ModifiedDesc => "ORDER BY updated_at DESC, path",
+            ListOrder::SizeDesc => "ORDER BY size DESC, path",
+        }
+    }
+}
+
+/// How to resolve a path that exists for both users when reassigning files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Leave the source's file with the source user
+    Skip,
+    
+    /// Replace the target's file with the source's file
+    Overwrite,
+    
+    /// Move the source's file to a free path such as `notes (2).md`
+    Rename,
+}
+
+/// Path for the `n`th renamed copy of a file, numbering before the extension
+fn numbered_path(path: &str, n: u32) -> String {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{} ({}){}", &path[..dot], n, &path[dot..])
+        }
+        _ => format!("{} ({})", path, n),
+    }
+}
+
+/// Position in a user's change feed
+///
+/// The feed is ordered by `(updated_at, id)`, so files sharing a timestamp
+/// still have a fixed order and paging neither skips nor repeats them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeCursor {
+    /// Modification time of the last file seen
+    pub updated_at: DateTime<Utc>,
+    
+    /// ID of the last file seen, breaking ties between equal timestamps
+    pub id: i32,
+}
+
+impl ChangeCursor {
+    /// The cursor positioned right after a file
+    pub fn after(file: &File) -> Self {
+        Self {
+            updated_at: file.updated_at,
+            id: file.id,
+        }
+    }
+}
 
 /// Repository trait for file operations
 #[async_trait]
@@ -21,9 +100,31 @@ pub trait FileRepository: Repository + BaseRepository + Send + Sync {
     /// Find a file by user ID and path
     async fn find_by_path(&self, user_id: i32, path: &str) -> Result<Option<File>>;
     
+    /// Content type of the live file at a path, without loading the rest of its row
+    async fn find_content_type(&self, user_id: i32, path: &str) -> Result<Option<String>>;
+    
+    /// Whether any live file lies below a folder
+    async fn has_files_below(&self, user_id: i32, folder_path: &str) -> Result<bool>;
+    
     /// Find files by content hash
     async fn find_by_content_hash(&self, content_hash: &str) -> Result<Vec<File>>;
     
+    /// Find up to `limit` files of any user whose content hash starts with `prefix`, ordered by hash
+    ///
+    /// Meant for admin and deduplication tooling; deleted files are included.
+    async fn find_by_content_hash_prefix(&self, prefix: &str, limit: i64) -> Result<Vec<File>>;
+    
+    /// Find the tenants with a live file referencing a content hash
+    async fn tenants_referencing(&self, content_hash: &str) -> Result<Vec<Uuid>>;
+    
+    /// Whether a user has a live file referencing a content hash
+    async fn references_content(&self, user_id: i32, content_hash: &str) -> Result<bool>;
+    
+    /// Find the files at any of the given paths for a user, in one query
+    ///
+    /// Paths without a file are skipped, so the result may be shorter than `paths`.
+    async fn find_by_paths(&self, user_id: i32, paths: &[String], include_deleted: bool) -> Result<Vec<File>>;
+    
     /// List files in a folder path for a user
     async fn list_by_folder_path(
         &self, 
@@ -32,39 +133,208 @@ pub trait FileRepository: Repository + BaseRepository + Send + Sync {
         include_deleted: bool
     ) -> Result<Vec<File>>;
     
+    /// List files in a folder path for a user using the given sort order
+    async fn list_by_folder_path_sorted(
+        &self, 
+        user_id: i32, 
+        folder_path: &str, 
+        include_deleted: bool,
+        order: ListOrder
+    ) -> Result<Vec<File>>;
+    
     /// Create a new file
     async fn create(&self, file: &File) -> Result<File>;
     
     /// Update an existing file
     async fn update(&self, file: &File) -> Result<File>;
     
+    /// Create a new file and record its content as its first version, in one transaction
+    async fn create_versioned(&self, file: &File) -> Result<File>;
+    
+    /// Update an existing file and record its content as a new version, in one transaction
+    async fn update_versioned(&self, file: &File) -> Result<File>;
+    
+    /// Move a file to a new path, keeping its content hash and timestamps
+    async fn move_file(&self, id: i32, new_path: &str) -> Result<File>;
+    
+    /// Rename a live file of a user in place
+    ///
+    /// A single UPDATE of the path, so the id, content hash and creation time
+    /// are kept. Fails with `NotFound` when no live file is at `from_path` and
+    /// with `AlreadyExists` when any file, deleted or not, is at `to_path`.
+    async fn rename(&self, user_id: i32, from_path: &str, to_path: &str) -> Result<File>;
+    
     /// Mark a file as deleted
     async fn mark_deleted(&self, id: i32) -> Result<bool>;
     
+    /// Mark many files as deleted in one transaction, returning whether each was marked
+    async fn mark_deleted_many(&self, ids: &[i32]) -> Result<Vec<bool>>;
+    
+    /// Mark every live file below a folder as deleted, returning how many were marked
+    async fn mark_deleted_by_prefix(&self, user_id: i32, folder_path: &str) -> Result<u64>;
+    
+    /// List a user's deleted files, most recently deleted first
+    async fn list_deleted(&self, user_id: i32) -> Result<Vec<File>>;
+    
     /// Restore a deleted file
     async fn restore(&self, id: i32) -> Result<bool>;
     
+    /// Record that a file was just read
+    async fn touch_access(&self, id: i32) -> Result<bool>;
+    
     /// Delete a file permanently (use with caution)
     async fn delete_permanently(&self, id: i32) -> Result<bool>;
     
     /// Count files by user ID
     async fn count_by_user(&self, user_id: i32, include_deleted: bool) -> Result<i64>;
     
+    /// Total size in bytes of a user's files, deleted ones only if `include_deleted`
+    async fn total_size_by_user(&self, user_id: i32, include_deleted: bool) -> Result<i64>;
+    
+    /// Reassign all files of `from_user` to `to_user`, e.g. when merging accounts
+    ///
+    /// Runs in a single transaction. Paths both users have are resolved per
+    /// `on_conflict`; deleted files count as taken paths. Content is shared by
+    /// hash, so no blobs move. Returns the number of files reassigned.
+    ///
+    /// Folders are merged into the target's; a source folder is only kept
+    /// while files skipped on conflict remain below it. Aliases follow their
+    /// target file: they point at its new path when it was renamed, and stay
+    /// with the source when it was skipped. Aliases of the source that stay
+    /// behind may point at files that moved.
+    async fn reassign_files(&self, from_user: i32, to_user: i32, on_conflict: ConflictPolicy) -> Result<u64>;
+    
     /// Find all markdown files for a user
     async fn find_markdown_files(&self, user_id: i32, include_deleted: bool) -> Result<Vec<File>>;
     
     /// Find all canvas files for a user
     async fn find_canvas_files(&self, user_id: i32, include_deleted: bool) -> Result<Vec<File>>;
+    
+    /// Replace the searchable text of a file
+    async fn index_content(&self, file_id: i32, text: &str) -> Result<()>;
+    
+    /// Drop a file from the search index, returning whether it was indexed
+    async fn remove_from_index(&self, file_id: i32) -> Result<bool>;
+    
+    /// Find a user's live files whose indexed text matches a plain-text query, best matches first
+    async fn search(&self, user_id: i32, query: &str) -> Result<Vec<File>>;
+    
+    /// List up to `limit` files of a user changed after the cursor, oldest change first
+    ///
+    /// Deleted files are included so that deletions reach the client. Returns
+    /// the cursor to pass for the next page, which stays at `cursor` when
+    /// nothing changed.
+    async fn list_changed_since(
+        &self,
+        user_id: i32,
+        cursor: Option<ChangeCursor>,
+        limit: i64
+    ) -> Result<(Vec<File>, Option<ChangeCursor>)>;
 }
 
 /// SQLx implementation of the FileRepository
+///
+/// Paths are stored twice: `path` is the key used for uniqueness and lookups,
+/// `display_path` keeps the spelling the file was created with. By default the
+/// key is the path as given. With case-insensitive paths enabled the key is
+/// NFC normalized and lowercased, so `/Notes.md` and `/notes.md` address the
+/// same file while listings still show `/Notes.md`.
 pub struct SqlxFileRepository {
     pool: Arc<PgPool>,
+    case_insensitive: bool,
 }
 
 impl Repository for SqlxFileRepository {
     fn new(pool: Arc<PgPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            case_insensitive: false,
+        }
+    }
+}
+
+impl SqlxFileRepository {
+    /// Enable or disable case-insensitive path lookups
+    pub fn with_case_insensitive_paths(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+    
+    /// Lookup key stored in the `path` column for a path
+    pub fn path_key(&self, path: &str) -> String {
+        if self.case_insensitive {
+            path.nfc().collect::<String>().to_lowercase()
+        } else {
+            path.to_string()
+        }
+    }
+    
+    /// `LIKE` pattern matching every path below a folder, for use with `ESCAPE '\'`
+    fn folder_pattern(&self, folder_path: &str) -> String {
+        let folder_key = escape_like(&self.path_key(folder_path));
+        if folder_key.ends_with('/') {
+            format!("{}%", folder_key)
+        } else {
+            format!("{}/%", folder_key)
+        }
+    }
+    
+    /// Insert a file row through any executor
+    async fn insert_row<'e, E>(&self, executor: E, file: &File) -> Result<File>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let now = chrono::Utc::now();
+        let created_file = sqlx::query_as::<_, File>(
+            "INSERT INTO files (user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, content_type_override) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) 
+             RETURNING id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override"
+        )
+        .bind(file.user_id)
+        .bind(self.path_key(&file.path))
+        .bind(&file.display_path)
+        .bind(&file.content_hash)
+        .bind(&file.content_type)
+        .bind(file.size)
+        .bind(now)
+        .bind(now)
+        .bind(file.is_deleted)
+        .bind(&file.alias_target)
+        .bind(&file.content_type_override)
+        .fetch_one(executor)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(created_file)
+    }
+    
+    /// Update a file row through any executor
+    async fn update_row<'e, E>(&self, executor: E, file: &File) -> Result<File>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let now = chrono::Utc::now();
+        let updated_file = sqlx::query_as::<_, File>(
+            "UPDATE files 
+             SET path = $1, display_path = $2, content_hash = $3, content_type = $4, size = $5, updated_at = $6, is_deleted = $7, alias_target = $8, content_type_override = $9 
+             WHERE id = $10 
+             RETURNING id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override"
+        )
+        .bind(self.path_key(&file.path))
+        .bind(&file.display_path)
+        .bind(&file.content_hash)
+        .bind(&file.content_type)
+        .bind(file.size)
+        .bind(now)
+        .bind(file.is_deleted)
+        .bind(&file.alias_target)
+        .bind(&file.content_type_override)
+        .bind(file.id)
+        .fetch_one(executor)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(updated_file)
     }
 }
 
@@ -80,12 +350,16 @@ impl FromRow<'_, PgRow> for File {
             id: row.try_get("id")?,
             user_id: row.try_get("user_id")?,
             path: row.try_get("path")?,
+            display_path: row.try_get("display_path")?,
             content_hash: row.try_get("content_hash")?,
             content_type: row.try_get("content_type")?,
             size: row.try_get("size")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
             is_deleted: row.try_get("is_deleted")?,
+            alias_target: row.try_get("alias_target")?,
+            last_accessed_at: row.try_get("last_accessed_at")?,
+            content_type_override: row.try_get("content_type_override")?,
         })
     }
 }
@@ -94,7 +368,7 @@ impl FromRow<'_, PgRow> for File {
 impl FileRepository for SqlxFileRepository {
     async fn find_by_id(&self, id: i32) -> Result<Option<File>> {
         let file = sqlx::query_as::<_, File>(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
              FROM files 
              WHERE id = $1"
         )
@@ -108,12 +382,12 @@ impl FileRepository for SqlxFileRepository {
     
     async fn find_by_path(&self, user_id: i32, path: &str) -> Result<Option<File>> {
         let file = sqlx::query_as::<_, File>(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
              FROM files 
              WHERE user_id = $1 AND path = $2"
         )
         .bind(user_id)
-        .bind(path)
+        .bind(self.path_key(path))
         .fetch_optional(self.pool())
         .await
         .map_err(Error::QueryFailed)?;
@@ -121,9 +395,35 @@ impl FileRepository for SqlxFileRepository {
         Ok(file)
     }
     
+    async fn find_content_type(&self, user_id: i32, path: &str) -> Result<Option<String>> {
+        let content_type = sqlx::query_scalar::<_, String>(
+            "SELECT content_type FROM files WHERE user_id = $1 AND path = $2 AND is_deleted = false"
+        )
+        .bind(user_id)
+        .bind(self.path_key(path))
+        .fetch_optional(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(content_type)
+    }
+    
+    async fn has_files_below(&self, user_id: i32, folder_path: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM files WHERE user_id = $1 AND path LIKE $2 ESCAPE '\\' AND is_deleted = false)"
+        )
+        .bind(user_id)
+        .bind(self.folder_pattern(folder_path))
+        .fetch_one(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(exists)
+    }
+    
     async fn find_by_content_hash(&self, content_hash: &str) -> Result<Vec<File>> {
         let files = sqlx::query_as::<_, File>(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
              FROM files 
              WHERE content_hash = $1"
         )
@@ -135,29 +435,105 @@ impl FileRepository for SqlxFileRepository {
         Ok(files)
     }
     
+    async fn find_by_content_hash_prefix(&self, prefix: &str, limit: i64) -> Result<Vec<File>> {
+        let files = sqlx::query_as::<_, File>(
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE content_hash LIKE $1 ESCAPE '\\' 
+             ORDER BY content_hash, id 
+             LIMIT $2"
+        )
+        .bind(format!("{}%", escape_like(prefix)))
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(files)
+    }
+    
+    async fn tenants_referencing(&self, content_hash: &str) -> Result<Vec<Uuid>> {
+        let tenants = sqlx::query_scalar::<_, Uuid>(
+            "SELECT DISTINCT u.uuid 
+             FROM files f 
+             JOIN users u ON u.id = f.user_id 
+             WHERE f.content_hash = $1 AND f.is_deleted = false 
+             ORDER BY u.uuid"
+        )
+        .bind(content_hash)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(tenants)
+    }
+    
+    async fn references_content(&self, user_id: i32, content_hash: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM files WHERE user_id = $1 AND content_hash = $2 AND is_deleted = false)"
+        )
+        .bind(user_id)
+        .bind(content_hash)
+        .fetch_one(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(exists)
+    }
+    
+    async fn find_by_paths(&self, user_id: i32, paths: &[String], include_deleted: bool) -> Result<Vec<File>> {
+        let query = if include_deleted {
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE user_id = $1 AND path = ANY($2)"
+        } else {
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE user_id = $1 AND path = ANY($2) AND is_deleted = false"
+        };
+        
+        let keys: Vec<String> = paths.iter().map(|path| self.path_key(path)).collect();
+        
+        let files = sqlx::query_as::<_, File>(query)
+            .bind(user_id)
+            .bind(keys)
+            .fetch_all(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        Ok(files)
+    }
+    
     async fn list_by_folder_path(
         &self, 
         user_id: i32, 
         folder_path: &str, 
         include_deleted: bool
     ) -> Result<Vec<File>> {
-        let path_pattern = if folder_path.ends_with('/') {
-            format!("{}%", folder_path)
-        } else {
-            format!("{}/%", folder_path)
-        };
+        self.list_by_folder_path_sorted(user_id, folder_path, include_deleted, ListOrder::Path)
+            .await
+    }
+    
+    async fn list_by_folder_path_sorted(
+        &self, 
+        user_id: i32, 
+        folder_path: &str, 
+        include_deleted: bool,
+        order: ListOrder
+    ) -> Result<Vec<File>> {
+        let path_pattern = self.folder_pattern(folder_path);
         
         let mut query = String::from(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
              FROM files 
-             WHERE user_id = $1 AND path LIKE $2 "
+             WHERE user_id = $1 AND path LIKE $2 ESCAPE '\\' "
         );
         
         if !include_deleted {
             query.push_str("AND is_deleted = false ");
         }
         
-        query.push_str("ORDER BY path");
+        query.push_str(order.order_by_clause());
         
         let files = sqlx::query_as::<_, File>(&query)
             .bind(user_id)
@@ -170,47 +546,73 @@ impl FileRepository for SqlxFileRepository {
     }
     
     async fn create(&self, file: &File) -> Result<File> {
-        let now = chrono::Utc::now();
-        let created_file = sqlx::query_as::<_, File>(
-            "INSERT INTO files (user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) 
-             RETURNING id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted"
+        self.insert_row(self.pool(), file).await
+    }
+    
+    async fn update(&self, file: &File) -> Result<File> {
+        self.update_row(self.pool(), file).await
+    }
+    
+    async fn create_versioned(&self, file: &File) -> Result<File> {
+        let mut transaction = self.begin_transaction().await?;
+        
+        let created_file = self.insert_row(&mut *transaction, file).await?;
+        insert_version(&mut *transaction, &created_file).await?;
+        
+        Self::commit_transaction(transaction).await?;
+        
+        Ok(created_file)
+    }
+    
+    async fn update_versioned(&self, file: &File) -> Result<File> {
+        let mut transaction = self.begin_transaction().await?;
+        
+        let updated_file = self.update_row(&mut *transaction, file).await?;
+        insert_version(&mut *transaction, &updated_file).await?;
+        
+        Self::commit_transaction(transaction).await?;
+        
+        Ok(updated_file)
+    }
+    
+    async fn move_file(&self, id: i32, new_path: &str) -> Result<File> {
+        let moved_file = sqlx::query_as::<_, File>(
+            "UPDATE files 
+             SET path = $1, display_path = $2 
+             WHERE id = $3 
+             RETURNING id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override"
         )
-        .bind(file.user_id)
-        .bind(&file.path)
-        .bind(&file.content_hash)
-        .bind(&file.content_type)
-        .bind(file.size)
-        .bind(now)
-        .bind(now)
-        .bind(file.is_deleted)
+        .bind(self.path_key(new_path))
+        .bind(new_path)
+        .bind(id)
         .fetch_one(self.pool())
         .await
         .map_err(Error::QueryFailed)?;
         
-        Ok(created_file)
+        Ok(moved_file)
     }
     
-    async fn update(&self, file: &File) -> Result<File> {
-        let now = chrono::Utc::now();
-        let updated_file = sqlx::query_as::<_, File>(
+    async fn rename(&self, user_id: i32, from_path: &str, to_path: &str) -> Result<File> {
+        let renamed = sqlx::query_as::<_, File>(
             "UPDATE files 
-             SET path = $1, content_hash = $2, content_type = $3, size = $4, updated_at = $5, is_deleted = $6 
-             WHERE id = $7 
-             RETURNING id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted"
+             SET path = $1, display_path = $2 
+             WHERE user_id = $3 AND path = $4 AND is_deleted = false 
+             RETURNING id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override"
         )
-        .bind(&file.path)
-        .bind(&file.content_hash)
-        .bind(&file.content_type)
-        .bind(file.size)
-        .bind(now)
-        .bind(file.is_deleted)
-        .bind(file.id)
-        .fetch_one(self.pool())
+        .bind(self.path_key(to_path))
+        .bind(to_path)
+        .bind(user_id)
+        .bind(self.path_key(from_path))
+        .fetch_optional(self.pool())
         .await
-        .map_err(Error::QueryFailed)?;
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                Error::AlreadyExists(format!("File already exists: {}", to_path))
+            }
+            e => Error::QueryFailed(e),
+        })?;
         
-        Ok(updated_file)
+        renamed.ok_or_else(|| Error::NotFound(format!("File not found: {}", from_path)))
     }
     
     async fn mark_deleted(&self, id: i32) -> Result<bool> {
@@ -229,6 +631,62 @@ impl FileRepository for SqlxFileRepository {
         Ok(result.rows_affected() > 0)
     }
     
+    async fn mark_deleted_many(&self, ids: &[i32]) -> Result<Vec<bool>> {
+        let now = chrono::Utc::now();
+        let mut transaction = self.begin_transaction().await?;
+        
+        let mut marked = Vec::with_capacity(ids.len());
+        for id in ids {
+            let result = sqlx::query(
+                "UPDATE files 
+                 SET is_deleted = true, updated_at = $1 
+                 WHERE id = $2"
+            )
+            .bind(now)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(Error::QueryFailed)?;
+            
+            marked.push(result.rows_affected() > 0);
+        }
+        
+        Self::commit_transaction(transaction).await?;
+        Ok(marked)
+    }
+    
+    async fn mark_deleted_by_prefix(&self, user_id: i32, folder_path: &str) -> Result<u64> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            "UPDATE files 
+             SET is_deleted = true, updated_at = $1 
+             WHERE user_id = $2 AND path LIKE $3 ESCAPE '\\' AND is_deleted = false"
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(self.folder_pattern(folder_path))
+        .execute(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    async fn list_deleted(&self, user_id: i32) -> Result<Vec<File>> {
+        let files = sqlx::query_as::<_, File>(
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE user_id = $1 AND is_deleted = true 
+             ORDER BY updated_at DESC, path"
+        )
+        .bind(user_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(files)
+    }
+    
     async fn restore(&self, id: i32) -> Result<bool> {
         let now = chrono::Utc::now();
         let result = sqlx::query(
@@ -245,6 +703,17 @@ impl FileRepository for SqlxFileRepository {
         Ok(result.rows_affected() > 0)
     }
     
+    async fn touch_access(&self, id: i32) -> Result<bool> {
+        let result = sqlx::query("UPDATE files SET last_accessed_at = $1 WHERE id = $2")
+            .bind(chrono::Utc::now())
+            .bind(id)
+            .execute(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+            
+        Ok(result.rows_affected() > 0)
+    }
+    
     async fn delete_permanently(&self, id: i32) -> Result<bool> {
         let result = sqlx::query("DELETE FROM files WHERE id = $1")
             .bind(id)
@@ -271,15 +740,156 @@ impl FileRepository for SqlxFileRepository {
         Ok(count)
     }
     
-    async fn find_markdown_files(&self, user_id: i32, include_deleted: bool) -> Result<Vec<File>> {
-        let mut query = String::from(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
-             FROM files 
-             WHERE user_id = $1 
-             AND (content_type = 'text/markdown' OR path LIKE '%.md' OR path LIKE '%.markdown') "
-        );
+    async fn total_size_by_user(&self, user_id: i32, include_deleted: bool) -> Result<i64> {
+        let query = if include_deleted {
+            "SELECT COALESCE(SUM(size), 0)::BIGINT FROM files WHERE user_id = $1"
+        } else {
+            "SELECT COALESCE(SUM(size), 0)::BIGINT FROM files WHERE user_id = $1 AND is_deleted = false"
+        };
         
-        if !include_deleted {
+        let total: i64 = sqlx::query_scalar(query)
+            .bind(user_id)
+            .fetch_one(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        Ok(total)
+    }
+    
+    async fn reassign_files(&self, from_user: i32, to_user: i32, on_conflict: ConflictPolicy) -> Result<u64> {
+        let mut transaction = self.begin_transaction().await?;
+        
+        // Aliases come last, so their targets are resolved before them
+        let files: Vec<(i32, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, path, display_path, alias_target FROM files WHERE user_id = $1 
+             ORDER BY alias_target IS NOT NULL, path FOR UPDATE"
+        )
+        .bind(from_user)
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        let mut reassigned = 0;
+        let mut renamed = std::collections::HashMap::new();
+        let mut skipped = std::collections::HashSet::new();
+        for (id, path, display_path, alias_target) in files {
+            if alias_target.as_ref().is_some_and(|target| skipped.contains(target)) {
+                skipped.insert(path);
+                continue;
+            }
+            let alias_target = alias_target.map(|target| renamed.get(&target).cloned().unwrap_or(target));
+            
+            let existing: Option<i32> = sqlx::query_scalar(
+                "SELECT id FROM files WHERE user_id = $1 AND path = $2"
+            )
+            .bind(to_user)
+            .bind(&path)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(Error::QueryFailed)?;
+            
+            let (new_path, new_display_path) = match (existing, on_conflict) {
+                (None, _) => (path.clone(), display_path),
+                (Some(_), ConflictPolicy::Skip) => {
+                    skipped.insert(path);
+                    continue;
+                }
+                (Some(existing_id), ConflictPolicy::Overwrite) => {
+                    sqlx::query("DELETE FROM files WHERE id = $1")
+                        .bind(existing_id)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(Error::QueryFailed)?;
+                    (path.clone(), display_path)
+                }
+                (Some(_), ConflictPolicy::Rename) => {
+                    let mut n = 2;
+                    loop {
+                        let candidate = numbered_path(&display_path, n);
+                        let taken: bool = sqlx::query_scalar(
+                            "SELECT EXISTS(SELECT 1 FROM files WHERE user_id = $1 AND path = $2)"
+                        )
+                        .bind(to_user)
+                        .bind(self.path_key(&candidate))
+                        .fetch_one(&mut *transaction)
+                        .await
+                        .map_err(Error::QueryFailed)?;
+                        
+                        if !taken {
+                            break (self.path_key(&candidate), candidate);
+                        }
+                        n += 1;
+                    }
+                }
+            };
+            
+            sqlx::query("UPDATE files SET user_id = $1, path = $2, display_path = $3, alias_target = $4 WHERE id = $5")
+                .bind(to_user)
+                .bind(&new_path)
+                .bind(new_display_path)
+                .bind(alias_target)
+                .bind(id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(Error::QueryFailed)?;
+            if new_path != path {
+                renamed.insert(path, new_path);
+            }
+            reassigned += 1;
+        }
+        
+        // Merge the folder tree, a folder is live if it is live for either user
+        sqlx::query(
+            "INSERT INTO folders (user_id, path, created_at, updated_at, is_deleted) 
+             SELECT $2, path, created_at, updated_at, is_deleted FROM folders WHERE user_id = $1 
+             ON CONFLICT (user_id, path) DO UPDATE SET is_deleted = folders.is_deleted AND EXCLUDED.is_deleted"
+        )
+        .bind(from_user)
+        .bind(to_user)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        sqlx::query(
+            "UPDATE folders child 
+             SET parent_id = parent.id 
+             FROM folders parent 
+             WHERE child.user_id = $1 AND child.parent_id IS NULL 
+               AND parent.user_id = child.user_id 
+               AND parent.path = regexp_replace(child.path, '/[^/]+$', '')"
+        )
+        .bind(to_user)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        // The source keeps only the folders its remaining files are in
+        sqlx::query(
+            "DELETE FROM folders folder 
+             WHERE folder.user_id = $1 
+               AND NOT EXISTS (
+                   SELECT 1 FROM files 
+                   WHERE files.user_id = $1 AND left(files.path, length(folder.path) + 1) = folder.path || '/'
+               )"
+        )
+        .bind(from_user)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Self::commit_transaction(transaction).await?;
+        Ok(reassigned)
+    }
+    
+    async fn find_markdown_files(&self, user_id: i32, include_deleted: bool) -> Result<Vec<File>> {
+        let mut query = String::from(
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE user_id = $1 
+             AND (content_type = 'text/markdown' OR path LIKE '%.md' OR path LIKE '%.markdown') "
+        );
+        
+        if !include_deleted {
             query.push_str("AND is_deleted = false ");
         }
         
@@ -296,7 +906,7 @@ impl FileRepository for SqlxFileRepository {
     
     async fn find_canvas_files(&self, user_id: i32, include_deleted: bool) -> Result<Vec<File>> {
         let mut query = String::from(
-            "SELECT id, user_id, path, content_hash, content_type, size, created_at, updated_at, is_deleted 
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
              FROM files 
              WHERE user_id = $1 
              AND (content_type = 'application/obsidian-canvas' OR path LIKE '%.canvas') "
@@ -316,6 +926,87 @@ impl FileRepository for SqlxFileRepository {
         
         Ok(files)
     }
+    
+    async fn index_content(&self, file_id: i32, text: &str) -> Result<()> {
+        // Postgres text cannot hold NUL bytes
+        let text = text.replace('\0', " ");
+        sqlx::query(
+            "INSERT INTO file_search_index (file_id, document, updated_at) 
+             VALUES ($1, to_tsvector('simple', $2), $3) 
+             ON CONFLICT (file_id) DO UPDATE SET document = EXCLUDED.document, updated_at = EXCLUDED.updated_at"
+        )
+        .bind(file_id)
+        .bind(&text)
+        .bind(chrono::Utc::now())
+        .execute(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(())
+    }
+    
+    async fn remove_from_index(&self, file_id: i32) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM file_search_index WHERE file_id = $1")
+            .bind(file_id)
+            .execute(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    async fn search(&self, user_id: i32, query: &str) -> Result<Vec<File>> {
+        let files = sqlx::query_as::<_, File>(
+            "SELECT f.id, f.user_id, f.path, f.display_path, f.content_hash, f.content_type, f.size, f.created_at, f.updated_at, f.is_deleted, f.alias_target, f.last_accessed_at, f.content_type_override 
+             FROM files f 
+             JOIN file_search_index i ON i.file_id = f.id 
+             WHERE f.user_id = $1 AND f.is_deleted = false 
+             AND i.document @@ plainto_tsquery('simple', $2) 
+             ORDER BY ts_rank(i.document, plainto_tsquery('simple', $2)) DESC, f.path"
+        )
+        .bind(user_id)
+        .bind(query)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(files)
+    }
+    
+    async fn list_changed_since(
+        &self,
+        user_id: i32,
+        cursor: Option<ChangeCursor>,
+        limit: i64
+    ) -> Result<(Vec<File>, Option<ChangeCursor>)> {
+        let mut query = String::from(
+            "SELECT id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override 
+             FROM files 
+             WHERE user_id = $1 "
+        );
+        
+        // Compare the pair, not the timestamp alone, so ties are neither skipped nor repeated
+        if cursor.is_some() {
+            query.push_str("AND (updated_at, id) > ($3, $4) ");
+        }
+        
+        query.push_str("ORDER BY updated_at, id LIMIT $2");
+        
+        let mut files_query = sqlx::query_as::<_, File>(&query)
+            .bind(user_id)
+            .bind(limit);
+        if let Some(cursor) = cursor {
+            files_query = files_query.bind(cursor.updated_at).bind(cursor.id);
+        }
+        
+        let files = files_query
+            .fetch_all(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        let next = files.last().map(ChangeCursor::after).or(cursor);
+        Ok((files, next))
+    }
 }
 
 #[cfg(test)]
@@ -450,6 +1141,12 @@ mod tests {
         assert_eq!(updated.content_hash, "updated-hash");
         assert_eq!(updated.size, 2048);
         
+        // Test recording access
+        assert!(updated.last_accessed_at.is_none());
+        assert!(repo.touch_access(created_file.id).await.unwrap());
+        let found = repo.find_by_id(created_file.id).await.unwrap().unwrap();
+        assert!(found.last_accessed_at.is_some());
+        
         // Test marking as deleted
         let result = repo.mark_deleted(created_file.id).await.unwrap();
         assert!(result);
@@ -478,8 +1175,764 @@ mod tests {
         let not_found = repo.find_by_id(created_file.id).await.unwrap();
         assert!(not_found.is_none());
         
+        // Test moving a file keeps its content hash and timestamps
+        let moved = repo.move_file(created_canvas.id, "/renamed.canvas").await.unwrap();
+        assert_eq!(moved.id, created_canvas.id);
+        assert_eq!(moved.path, "/renamed.canvas");
+        assert_eq!(moved.content_hash, created_canvas.content_hash);
+        assert_eq!(moved.updated_at, created_canvas.updated_at);
+        assert!(repo.find_by_path(user_id, "/diagram.canvas").await.unwrap().is_none());
+        
         // Clean up
         let _ = repo.delete_permanently(created_canvas.id).await;
         let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
     }
+    
+    #[tokio::test]
+    async fn test_tenants_referencing() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let usernames = ["file_refs_test_user_a", "file_refs_test_user_b"];
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = ANY($1))")
+            .bind(&usernames[..])
+            .execute(&*pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = ANY($1)")
+            .bind(&usernames[..])
+            .execute(&*pool)
+            .await;
+        
+        let mut users = Vec::new();
+        for username in usernames {
+            let tenant = Uuid::new_v4();
+            let user_id: i32 = sqlx::query_scalar(
+                "INSERT INTO users (username, password_hash, created_at, uuid) 
+                 VALUES ($1, $2, $3, $4) 
+                 RETURNING id"
+            )
+            .bind(username)
+            .bind("test_password_hash")
+            .bind(chrono::Utc::now())
+            .bind(tenant)
+            .fetch_one(&*pool)
+            .await
+            .expect("Failed to create test user");
+            users.push((user_id, tenant));
+        }
+        
+        let repo = SqlxFileRepository::new(pool);
+        
+        // Both tenants store identical content
+        let content_hash = format!("shared-{}", Uuid::new_v4());
+        let mut files = Vec::new();
+        for (user_id, _) in &users {
+            let file = File::new(
+                *user_id,
+                "/shared.md".to_string(),
+                content_hash.clone(),
+                "text/markdown".to_string(),
+                6
+            );
+            files.push(repo.create(&file).await.unwrap());
+        }
+        
+        let mut expected: Vec<Uuid> = users.iter().map(|(_, tenant)| *tenant).collect();
+        expected.sort();
+        assert_eq!(repo.tenants_referencing(&content_hash).await.unwrap(), expected);
+        assert!(repo.references_content(users[0].0, &content_hash).await.unwrap());
+        
+        // Deleted files are not live references
+        repo.mark_deleted(files[0].id).await.unwrap();
+        assert_eq!(repo.tenants_referencing(&content_hash).await.unwrap(), vec![users[1].1]);
+        assert!(!repo.references_content(users[0].0, &content_hash).await.unwrap());
+        assert!(repo.references_content(users[1].0, &content_hash).await.unwrap());
+        
+        assert!(repo.tenants_referencing("unknown-hash").await.unwrap().is_empty());
+        
+        // Clean up
+        for ((user_id, _), file) in users.iter().zip(&files) {
+            let _ = repo.delete_permanently(file.id).await;
+            let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_list_by_folder_path_sorted() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = 'file_sort_test_user')").execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = 'file_sort_test_user'").execute(&*pool).await;
+        
+        let user_id: i32 = match sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind("file_sort_test_user")
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        {
+            Ok(id) => id,
+            Err(_) => {
+                println!("Failed to create test user");
+                return;
+            }
+        };
+        
+        let repo = SqlxFileRepository::new(pool);
+        
+        // (path, size, minutes since the reference time)
+        let fixtures = [
+            ("/sorted/a.md", 300, 10),
+            ("/sorted/b.md", 100, 30),
+            ("/sorted/c.md", 200, 20),
+        ];
+        
+        let base = chrono::Utc::now() - chrono::Duration::hours(1);
+        for (path, size, minutes) in fixtures {
+            let file = File::new(
+                user_id,
+                path.to_string(),
+                format!("hash-{}", path),
+                "text/markdown".to_string(),
+                size
+            );
+            let created = repo.create(&file).await.unwrap();
+            
+            sqlx::query("UPDATE files SET updated_at = $1 WHERE id = $2")
+                .bind(base + chrono::Duration::minutes(minutes))
+                .bind(created.id)
+                .execute(repo.pool())
+                .await
+                .unwrap();
+        }
+        
+        let paths = |files: Vec<File>| files.into_iter().map(|f| f.path).collect::<Vec<_>>();
+        
+        let by_path = repo
+            .list_by_folder_path_sorted(user_id, "/sorted", false, ListOrder::Path)
+            .await
+            .unwrap();
+        assert_eq!(paths(by_path), vec!["/sorted/a.md", "/sorted/b.md", "/sorted/c.md"]);
+        
+        let by_modified = repo
+            .list_by_folder_path_sorted(user_id, "/sorted", false, ListOrder::ModifiedDesc)
+            .await
+            .unwrap();
+        assert_eq!(paths(by_modified), vec!["/sorted/b.md", "/sorted/c.md", "/sorted/a.md"]);
+        
+        let by_size = repo
+            .list_by_folder_path_sorted(user_id, "/sorted", false, ListOrder::SizeDesc)
+            .await
+            .unwrap();
+        assert_eq!(paths(by_size), vec!["/sorted/a.md", "/sorted/c.md", "/sorted/b.md"]);
+        
+        // The unsorted variant keeps the path order
+        let default_order = repo.list_by_folder_path(user_id, "/sorted", false).await.unwrap();
+        assert_eq!(paths(default_order), vec!["/sorted/a.md", "/sorted/b.md", "/sorted/c.md"]);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_case_insensitive_paths() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = 'file_case_test_user')").execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = 'file_case_test_user'").execute(&*pool).await;
+        
+        let user_id: i32 = match sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind("file_case_test_user")
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        {
+            Ok(id) => id,
+            Err(_) => {
+                println!("Failed to create test user");
+                return;
+            }
+        };
+        
+        let repo = SqlxFileRepository::new(pool.clone()).with_case_insensitive_paths(true);
+        assert_eq!(repo.path_key("/Cafe\u{301}/Notes.md"), "/caf\u{e9}/notes.md");
+        
+        let file = File::new(
+            user_id,
+            "/Docs/Notes.md".to_string(),
+            "hash-notes".to_string(),
+            "text/markdown".to_string(),
+            10
+        );
+        let created = repo.create(&file).await.unwrap();
+        assert_eq!(created.path, "/docs/notes.md");
+        assert_eq!(created.display_path, "/Docs/Notes.md");
+        
+        // Any casing finds the file, which keeps its original spelling
+        let found = repo.find_by_path(user_id, "/docs/notes.md").await.unwrap().unwrap();
+        assert_eq!(found.id, created.id);
+        assert_eq!(found.display_path, "/Docs/Notes.md");
+        assert!(repo.find_by_path(user_id, "/DOCS/NOTES.MD").await.unwrap().is_some());
+        
+        let listed = repo.list_by_folder_path(user_id, "/DOCS", false).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].display_path, "/Docs/Notes.md");
+        
+        // The lookup key is unique, so another casing cannot be created
+        let duplicate = File::new(
+            user_id,
+            "/docs/NOTES.md".to_string(),
+            "hash-other".to_string(),
+            "text/markdown".to_string(),
+            10
+        );
+        assert!(repo.create(&duplicate).await.is_err());
+        
+        // Moving updates both the key and the display spelling
+        let moved = repo.move_file(created.id, "/Docs/Renamed.md").await.unwrap();
+        assert_eq!(moved.path, "/docs/renamed.md");
+        assert_eq!(moved.display_path, "/Docs/Renamed.md");
+        
+        // Case-sensitive repositories look up the path as given
+        let exact = SqlxFileRepository::new(pool);
+        assert!(exact.find_by_path(user_id, "/docs/renamed.md").await.unwrap().is_some());
+        assert!(exact.find_by_path(user_id, "/Docs/Renamed.md").await.unwrap().is_none());
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_list_changed_since_pages_through_ties() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = 'file_changes_test_user')").execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = 'file_changes_test_user'").execute(&*pool).await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind("file_changes_test_user")
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        .expect("Failed to create test user");
+        
+        let repo = SqlxFileRepository::new(pool);
+        
+        // Seven files changed in the same instant, plus one later change
+        let instant = chrono::Utc::now() - chrono::Duration::hours(1);
+        let mut expected = Vec::new();
+        for i in 0..8 {
+            let file = File::new(
+                user_id,
+                format!("/changes/{}.md", i),
+                format!("hash-{}", i),
+                "text/markdown".to_string(),
+                i
+            );
+            let created = repo.create(&file).await.unwrap();
+            let updated_at = if i == 7 { instant + chrono::Duration::seconds(1) } else { instant };
+            sqlx::query("UPDATE files SET updated_at = $1 WHERE id = $2")
+                .bind(updated_at)
+                .bind(created.id)
+                .execute(repo.pool())
+                .await
+                .unwrap();
+            expected.push(created.id);
+        }
+        
+        // Page through the feed three files at a time
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (files, next) = repo.list_changed_since(user_id, cursor, 3).await.unwrap();
+            if files.is_empty() {
+                assert_eq!(next, cursor, "An empty page keeps the cursor");
+                break;
+            }
+            seen.extend(files.iter().map(|f| f.id));
+            cursor = next;
+        }
+        
+        // Every file appears exactly once, ties ordered by id
+        assert_eq!(seen, expected);
+        
+        // Changes after the cursor show up on the next request, deletions included
+        repo.mark_deleted(expected[0]).await.unwrap();
+        let (files, _) = repo.list_changed_since(user_id, cursor, 3).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].id, expected[0]);
+        assert!(files[0].is_deleted);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    /// Create a fresh user with the given files, removing leftovers of earlier runs
+    async fn setup_merge_user(repo: &SqlxFileRepository, username: &str, paths: &[&str]) -> i32 {
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1").bind(username).execute(repo.pool()).await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind(username)
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(repo.pool())
+        .await
+        .expect("Failed to create test user");
+        
+        for path in paths {
+            let file = File::new(
+                user_id,
+                path.to_string(),
+                format!("hash-{}-{}", username, path),
+                "text/markdown".to_string(),
+                1
+            );
+            repo.create(&file).await.unwrap();
+        }
+        user_id
+    }
+    
+    #[test]
+    fn test_numbered_path() {
+        assert_eq!(numbered_path("/notes.md", 2), "/notes (2).md");
+        assert_eq!(numbered_path("/a.b/README", 3), "/a.b/README (3)");
+        assert_eq!(numbered_path("/.hidden", 2), "/.hidden (2)");
+    }
+    
+    #[tokio::test]
+    async fn test_reassign_files() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let hash_at = |user_id: i32, path: &'static str| {
+            let repo = &repo;
+            async move { repo.find_by_path(user_id, path).await.unwrap().map(|f| f.content_hash) }
+        };
+        
+        // Without collisions every file moves over
+        let from = setup_merge_user(&repo, "merge_from_user", &["/a.md", "/docs/b.md"]).await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/c.md"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Skip).await.unwrap(), 2);
+        assert_eq!(repo.count_by_user(from, true).await.unwrap(), 0);
+        assert_eq!(repo.count_by_user(to, true).await.unwrap(), 3);
+        
+        // Skip leaves colliding files with the source
+        let from = setup_merge_user(&repo, "merge_from_user", &["/a.md", "/b.md"]).await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/a.md"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Skip).await.unwrap(), 1);
+        assert_eq!(hash_at(from, "/a.md").await.as_deref(), Some("hash-merge_from_user-/a.md"));
+        assert_eq!(hash_at(to, "/a.md").await.as_deref(), Some("hash-merge_to_user-/a.md"));
+        assert!(hash_at(to, "/b.md").await.is_some());
+        
+        // Overwrite replaces the target's file
+        let from = setup_merge_user(&repo, "merge_from_user", &["/a.md"]).await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/a.md"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Overwrite).await.unwrap(), 1);
+        assert_eq!(hash_at(to, "/a.md").await.as_deref(), Some("hash-merge_from_user-/a.md"));
+        assert_eq!(repo.count_by_user(to, true).await.unwrap(), 1);
+        
+        // Rename keeps both, numbering past paths already taken
+        let from = setup_merge_user(&repo, "merge_from_user", &["/a.md"]).await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/a.md", "/a (2).md"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Rename).await.unwrap(), 1);
+        assert_eq!(hash_at(to, "/a.md").await.as_deref(), Some("hash-merge_to_user-/a.md"));
+        assert_eq!(hash_at(to, "/a (3).md").await.as_deref(), Some("hash-merge_from_user-/a.md"));
+        
+        // Clean up
+        for user_id in [from, to] {
+            let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+            let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_reassign_files_folders_and_aliases() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let add_alias = |user_id: i32, path: &'static str, target: &'static str| {
+            let repo = &repo;
+            async move {
+                let mut alias = File::new(user_id, path.to_string(), String::new(), "text/markdown".to_string(), 0);
+                alias.alias_target = Some(target.to_string());
+                repo.create(&alias).await.unwrap();
+            }
+        };
+        let add_folders = |user_id: i32, paths: &'static [&'static str]| {
+            let repo = &repo;
+            async move {
+                for path in paths {
+                    sqlx::query("INSERT INTO folders (user_id, path) VALUES ($1, $2)")
+                        .bind(user_id)
+                        .bind(path)
+                        .execute(repo.pool())
+                        .await
+                        .unwrap();
+                }
+            }
+        };
+        let folders_of = |user_id: i32| {
+            let repo = &repo;
+            async move {
+                let folders: Vec<(String, Option<String>)> = sqlx::query_as(
+                    "SELECT child.path, parent.path FROM folders child 
+                     LEFT JOIN folders parent ON parent.id = child.parent_id 
+                     WHERE child.user_id = $1 ORDER BY child.path"
+                )
+                .bind(user_id)
+                .fetch_all(repo.pool())
+                .await
+                .unwrap();
+                folders
+            }
+        };
+        let alias_target_of = |user_id: i32, path: &'static str| {
+            let repo = &repo;
+            async move { repo.find_by_path(user_id, path).await.unwrap().and_then(|f| f.alias_target) }
+        };
+        
+        // Renamed files take their aliases along, folders merge into the target's
+        let from = setup_merge_user(&repo, "merge_from_user", &["/docs/a.md", "/docs/sub/b.md"]).await;
+        add_folders(from, &["/docs", "/docs/sub"]).await;
+        add_alias(from, "/link.md", "/docs/a.md").await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/docs/a.md"]).await;
+        add_folders(to, &["/docs"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Rename).await.unwrap(), 3);
+        assert_eq!(alias_target_of(to, "/link.md").await.as_deref(), Some("/docs/a (2).md"));
+        assert_eq!(folders_of(to).await, vec![
+            ("/docs".to_string(), None),
+            ("/docs/sub".to_string(), Some("/docs".to_string())),
+        ]);
+        assert!(folders_of(from).await.is_empty());
+        
+        // Skipped files keep their aliases and folders with the source
+        let from = setup_merge_user(&repo, "merge_from_user", &["/docs/a.md", "/docs/sub/b.md"]).await;
+        add_folders(from, &["/docs", "/docs/sub"]).await;
+        add_alias(from, "/link.md", "/docs/a.md").await;
+        let to = setup_merge_user(&repo, "merge_to_user", &["/docs/a.md"]).await;
+        assert_eq!(repo.reassign_files(from, to, ConflictPolicy::Skip).await.unwrap(), 1);
+        assert_eq!(alias_target_of(from, "/link.md").await.as_deref(), Some("/docs/a.md"));
+        assert!(repo.find_by_path(to, "/link.md").await.unwrap().is_none());
+        assert_eq!(folders_of(from).await, vec![("/docs".to_string(), None)]);
+        assert_eq!(folders_of(to).await.len(), 2);
+        
+        // Clean up
+        for user_id in [from, to] {
+            let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+            let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+            let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_rename() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "rename_user", &["/a.md", "/b.md"]).await;
+        let original = repo.find_by_path(user_id, "/a.md").await.unwrap().unwrap();
+        
+        // The row moves as is
+        let renamed = repo.rename(user_id, "/a.md", "/docs/c.md").await.unwrap();
+        assert_eq!(renamed.id, original.id);
+        assert_eq!(renamed.path, "/docs/c.md");
+        assert_eq!(renamed.content_hash, original.content_hash);
+        assert_eq!(renamed.created_at, original.created_at);
+        assert!(repo.find_by_path(user_id, "/a.md").await.unwrap().is_none());
+        
+        // An existing destination is left untouched
+        let result = repo.rename(user_id, "/docs/c.md", "/b.md").await;
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        let b = repo.find_by_path(user_id, "/b.md").await.unwrap().unwrap();
+        assert_eq!(b.content_hash, "hash-rename_user-/b.md");
+        
+        // So is a missing or deleted source
+        let result = repo.rename(user_id, "/missing.md", "/d.md").await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        repo.mark_deleted(b.id).await.unwrap();
+        let result = repo.rename(user_id, "/b.md", "/d.md").await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_versioned_writes() {
+        use crate::repositories::{FileVersionRepository, SqlxFileVersionRepository};
+        
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool.clone());
+        let version_repo = SqlxFileVersionRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "versioned_user", &[]).await;
+        
+        // Creating a file records its first version
+        let file = File::new(user_id, "/a.md".to_string(), "hash-1".to_string(), "text/markdown".to_string(), 1);
+        let mut file = repo.create_versioned(&file).await.unwrap();
+        let versions = version_repo.list_versions(file.id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].content_hash, "hash-1");
+        
+        // Each update adds the new content
+        file.update_content("hash-2".to_string(), "text/markdown".to_string(), 2);
+        let updated = repo.update_versioned(&file).await.unwrap();
+        assert_eq!(updated.content_hash, "hash-2");
+        let versions = version_repo.list_versions(file.id).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].content_hash, "hash-2");
+        assert_eq!(versions[0].size, 2);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_list_deleted_and_restore() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "trash_user", &["/a.md", "/b.md", "/c.md"]).await;
+        let a = repo.find_by_path(user_id, "/a.md").await.unwrap().unwrap();
+        let b = repo.find_by_path(user_id, "/b.md").await.unwrap().unwrap();
+        assert!(repo.list_deleted(user_id).await.unwrap().is_empty());
+        
+        // Deleted files show up in the trash, the latest first
+        repo.mark_deleted(a.id).await.unwrap();
+        repo.mark_deleted(b.id).await.unwrap();
+        let trash: Vec<String> = repo.list_deleted(user_id).await.unwrap()
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+        assert_eq!(trash, vec!["/b.md", "/a.md"]);
+        
+        // A restored file leaves the trash and is live again
+        assert!(repo.restore(a.id).await.unwrap());
+        let trash = repo.list_deleted(user_id).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].path, "/b.md");
+        assert!(!repo.find_by_path(user_id, "/a.md").await.unwrap().unwrap().is_deleted);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_search() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "search_user", &["/garden.md", "/kitchen.md"]).await;
+        let garden = repo.find_by_path(user_id, "/garden.md").await.unwrap().unwrap();
+        let kitchen = repo.find_by_path(user_id, "/kitchen.md").await.unwrap().unwrap();
+        repo.index_content(garden.id, "# Garden\nPlant the tomatoes in spring").await.unwrap();
+        repo.index_content(kitchen.id, "# Kitchen\nBuy tomatoes and basil").await.unwrap();
+        
+        // A keyword unique to one note finds only that note
+        let found = repo.search(user_id, "spring").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, garden.id);
+        
+        // Shared words find both, and reindexing replaces the old text
+        assert_eq!(repo.search(user_id, "tomatoes").await.unwrap().len(), 2);
+        repo.index_content(garden.id, "# Garden\nPlant beans in autumn").await.unwrap();
+        assert!(repo.search(user_id, "spring").await.unwrap().is_empty());
+        
+        // Deleted files are not found
+        repo.mark_deleted(kitchen.id).await.unwrap();
+        assert!(repo.search(user_id, "basil").await.unwrap().is_empty());
+        
+        // Files dropped from the index are no longer found
+        assert!(repo.remove_from_index(garden.id).await.unwrap());
+        assert!(repo.search(user_id, "autumn").await.unwrap().is_empty());
+        assert!(!repo.remove_from_index(garden.id).await.unwrap());
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_find_by_content_hash_prefix() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "hash_prefix_user", &[]).await;
+        for (path, hash) in [
+            ("/b.md", "prefixtest-ab02"),
+            ("/a.md", "prefixtest-ab01"),
+            ("/c.md", "prefixtest-ac01"),
+            ("/d.md", "prefixtest-a_01"),
+        ] {
+            let file = File::new(user_id, path.to_string(), hash.to_string(), "text/markdown".to_string(), 1);
+            repo.create(&file).await.unwrap();
+        }
+        
+        // Only matching hashes are returned, ordered by hash
+        let found = repo.find_by_content_hash_prefix("prefixtest-ab", 10).await.unwrap();
+        let hashes: Vec<&str> = found.iter().map(|f| f.content_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["prefixtest-ab01", "prefixtest-ab02"]);
+        
+        // The limit caps the result
+        assert_eq!(repo.find_by_content_hash_prefix("prefixtest-a", 2).await.unwrap().len(), 2);
+        
+        // Wildcards in the prefix match literally
+        let found = repo.find_by_content_hash_prefix("prefixtest-a_", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "/d.md");
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_list_by_folder_path_escapes_wildcards() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "like_escape_user", &["/a_b/file.md", "/axb/file.md", "/a%/file.md", "/abc/file.md"]).await;
+        
+        // `_` and `%` in the folder path match only themselves
+        let files = repo.list_by_folder_path(user_id, "/a_b", false).await.unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["/a_b/file.md"]);
+        
+        let files = repo.list_by_folder_path(user_id, "/a%", false).await.unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["/a%/file.md"]);
+        
+        assert!(repo.has_files_below(user_id, "/a_b").await.unwrap());
+        assert!(!repo.has_files_below(user_id, "/a_c").await.unwrap());
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_total_size_by_user() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        let repo = SqlxFileRepository::new(pool);
+        
+        let user_id = setup_merge_user(&repo, "total_size_user", &[]).await;
+        assert_eq!(repo.total_size_by_user(user_id, false).await.unwrap(), 0);
+        
+        let mut ids = Vec::new();
+        for (path, size) in [("/a.md", 100), ("/b.md", 250), ("/c.md", 4000)] {
+            let file = File::new(user_id, path.to_string(), format!("hash-size-{}", path), "text/markdown".to_string(), size);
+            ids.push(repo.create(&file).await.unwrap().id);
+        }
+        repo.mark_deleted(ids[2]).await.unwrap();
+        
+        assert_eq!(repo.total_size_by_user(user_id, false).await.unwrap(), 350);
+        assert_eq!(repo.total_size_by_user(user_id, true).await.unwrap(), 4350);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
 }
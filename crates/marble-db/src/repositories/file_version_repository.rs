@@ -0,0 +1,153 @@
+//! Repository for the version history of files
+//!
+//! This module provides the FileVersionRepository trait and its SQLx implementation.
+
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::{FromRow, Row};
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use crate::models::{File, FileVersion};
+use crate::Result;
+use crate::Error;
+use super::{Repository, BaseRepository, TransactionSupport};
+
+/// Repository trait for file versions
+#[async_trait]
+pub trait FileVersionRepository: Repository + BaseRepository + Send + Sync {
+    /// Record the current content of a file as its newest version
+    async fn record(&self, file: &File) -> Result<FileVersion>;
+
+    /// List the versions of a file, newest first
+    async fn list_versions(&self, file_id: i32) -> Result<Vec<FileVersion>>;
+
+    /// Make the content of an earlier version current again
+    ///
+    /// The restored content is recorded as a new version, so restoring never
+    /// loses history. Fails with `NotFound` when the version does not belong
+    /// to the file.
+    async fn restore_version(&self, file_id: i32, version_id: i32) -> Result<File>;
+}
+
+/// SQLx implementation of the FileVersionRepository
+pub struct SqlxFileVersionRepository {
+    pool: Arc<PgPool>,
+}
+
+impl Repository for SqlxFileVersionRepository {
+    fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+impl BaseRepository for SqlxFileVersionRepository {
+    fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+impl FromRow<'_, PgRow> for FileVersion {
+    fn from_row(row: &PgRow) -> std::result::Result<Self, sqlx::Error> {
+        Ok(FileVersion {
+            id: row.try_get("id")?,
+            file_id: row.try_get("file_id")?,
+            content_hash: row.try_get("content_hash")?,
+            size: row.try_get("size")?,
+            content_type: row.try_get("content_type")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// Record the current content of a file as its newest version, through any executor
+///
+/// Lets a file write and its version share a transaction.
+pub(crate) async fn insert_version<'e, E>(executor: E, file: &File) -> Result<FileVersion>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_as::<_, FileVersion>(
+        "INSERT INTO file_versions (file_id, content_hash, size, content_type, created_at)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id, file_id, content_hash, size, content_type, created_at"
+    )
+    .bind(file.id)
+    .bind(&file.content_hash)
+    .bind(file.size)
+    .bind(&file.content_type)
+    .bind(chrono::Utc::now())
+    .fetch_one(executor)
+    .await
+    .map_err(Error::QueryFailed)
+}
+
+#[async_trait]
+impl FileVersionRepository for SqlxFileVersionRepository {
+    async fn record(&self, file: &File) -> Result<FileVersion> {
+        insert_version(self.pool(), file).await
+    }
+
+    async fn list_versions(&self, file_id: i32) -> Result<Vec<FileVersion>> {
+        let versions = sqlx::query_as::<_, FileVersion>(
+            "SELECT id, file_id, content_hash, size, content_type, created_at
+             FROM file_versions
+             WHERE file_id = $1
+             ORDER BY id DESC"
+        )
+        .bind(file_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+
+        Ok(versions)
+    }
+
+    async fn restore_version(&self, file_id: i32, version_id: i32) -> Result<File> {
+        let mut transaction = self.begin_transaction().await?;
+
+        let version = sqlx::query_as::<_, FileVersion>(
+            "SELECT id, file_id, content_hash, size, content_type, created_at
+             FROM file_versions
+             WHERE id = $1 AND file_id = $2"
+        )
+        .bind(version_id)
+        .bind(file_id)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?
+        .ok_or_else(|| Error::NotFound(format!("Version {} of file {}", version_id, file_id)))?;
+
+        let now = chrono::Utc::now();
+        let file = sqlx::query_as::<_, File>(
+            "UPDATE files
+             SET content_hash = $1, size = $2, content_type = $3, updated_at = $4
+             WHERE id = $5
+             RETURNING id, user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted, alias_target, last_accessed_at, content_type_override"
+        )
+        .bind(&version.content_hash)
+        .bind(version.size)
+        .bind(&version.content_type)
+        .bind(now)
+        .bind(file_id)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+
+        sqlx::query(
+            "INSERT INTO file_versions (file_id, content_hash, size, content_type, created_at)
+             VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(file_id)
+        .bind(&version.content_hash)
+        .bind(version.size)
+        .bind(&version.content_type)
+        .bind(now)
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+
+        Self::commit_transaction(transaction).await?;
+
+        Ok(file)
+    }
+}
@@ -4,13 +4,26 @@
 
 use sqlx::postgres::{PgPool, PgRow};
 use sqlx::{FromRow, Row};
+use std::path::Path;
 use std::sync::Arc;
 use async_trait::async_trait;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::models::Folder;
 use crate::Result;
 use crate::Error;
-use super::{Repository, BaseRepository};
+use super::{escape_like, Repository, BaseRepository, TransactionSupport};
+
+/// A marker file that `create_path` writes into each folder without files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FolderPlaceholder<'a> {
+    /// File name of the marker inside the folder
+    pub name: &'a str,
+    /// Content hash of the marker
+    pub content_hash: &'a str,
+    /// Content type identifying the marker
+    pub content_type: &'a str,
+}
 
 /// Repository trait for folder operations
 #[async_trait]
@@ -21,6 +34,9 @@ pub trait FolderRepository: Repository + BaseRepository + Send + Sync {
     /// Find a folder by user ID and path
     async fn find_by_path(&self, user_id: i32, path: &str) -> Result<Option<Folder>>;
     
+    /// Find the live folders of a user at any of the given paths
+    async fn find_by_paths(&self, user_id: i32, paths: &[String]) -> Result<Vec<Folder>>;
+    
     /// List folders for a user (optionally with a parent ID)
     async fn list_by_user(
         &self, 
@@ -29,13 +45,21 @@ pub trait FolderRepository: Repository + BaseRepository + Send + Sync {
         include_deleted: bool
     ) -> Result<Vec<Folder>>;
     
+    /// List every folder below a folder path for a user, ordered by path
+    async fn list_by_folder_path(
+        &self,
+        user_id: i32,
+        folder_path: &str,
+        include_deleted: bool
+    ) -> Result<Vec<Folder>>;
+    
     /// Create a new folder
     async fn create(&self, folder: &Folder) -> Result<Folder>;
     
     /// Update an existing folder
     async fn update(&self, folder: &Folder) -> Result<Folder>;
     
-    /// Mark a folder as deleted
+    /// Mark a folder as deleted, removing its dead properties
     async fn mark_deleted(&self, id: i32) -> Result<bool>;
     
     /// Restore a deleted folder
@@ -49,16 +73,59 @@ pub trait FolderRepository: Repository + BaseRepository + Send + Sync {
     
     /// Delete a folder permanently (use with caution)
     async fn delete_permanently(&self, id: i32) -> Result<bool>;
+    
+    /// Rename a folder along with everything below it
+    ///
+    /// Rewrites the paths of the folder, its descendant folders and the files
+    /// beneath them in one transaction. Descendants keep their ids, so their
+    /// parent links stay valid; the folder itself is relinked to the folder at
+    /// its new parent path, if there is one. Moving a folder below itself is
+    /// refused, as it would leave the subtree without a root.
+    async fn rename_folder(&self, user_id: i32, from_path: &str, to_path: &str) -> Result<Folder>;
+    
+    /// Create a folder and any missing ancestors in one transaction
+    ///
+    /// Each folder is linked to its parent, and deleted folders on the way are
+    /// restored. With a placeholder, every folder on the path that has no live
+    /// files below it also gets the marker file, in the same transaction.
+    /// Returns the folders from the top down; the root has no row.
+    async fn create_path(
+        &self,
+        user_id: i32,
+        path: &str,
+        placeholder: Option<FolderPlaceholder<'_>>
+    ) -> Result<Vec<Folder>>;
 }
 
 /// SQLx implementation of the FolderRepository
 pub struct SqlxFolderRepository {
     pool: Arc<PgPool>,
+    case_insensitive: bool,
 }
 
 impl Repository for SqlxFolderRepository {
     fn new(pool: Arc<PgPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            case_insensitive: false,
+        }
+    }
+}
+
+impl SqlxFolderRepository {
+    /// Enable or disable case-insensitive path lookups, matching the file repository
+    pub fn with_case_insensitive_paths(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+    
+    /// Lookup key stored in the `path` column for a path
+    pub fn path_key(&self, path: &str) -> String {
+        if self.case_insensitive {
+            path.nfc().collect::<String>().to_lowercase()
+        } else {
+            path.to_string()
+        }
     }
 }
 
@@ -105,7 +172,7 @@ impl FolderRepository for SqlxFolderRepository {
              WHERE user_id = $1 AND path = $2"
         )
         .bind(user_id)
-        .bind(path)
+        .bind(self.path_key(path))
         .fetch_optional(self.pool())
         .await
         .map_err(Error::QueryFailed)?;
@@ -113,6 +180,22 @@ impl FolderRepository for SqlxFolderRepository {
         Ok(folder)
     }
     
+    async fn find_by_paths(&self, user_id: i32, paths: &[String]) -> Result<Vec<Folder>> {
+        let keys: Vec<String> = paths.iter().map(|path| self.path_key(path)).collect();
+        let folders = sqlx::query_as::<_, Folder>(
+            "SELECT id, user_id, path, parent_id, created_at, updated_at, is_deleted 
+             FROM folders 
+             WHERE user_id = $1 AND path = ANY($2) AND is_deleted = false"
+        )
+        .bind(user_id)
+        .bind(&keys)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(folders)
+    }
+    
     async fn list_by_user(
         &self, 
         user_id: i32, 
@@ -151,6 +234,41 @@ impl FolderRepository for SqlxFolderRepository {
         Ok(folders)
     }
     
+    async fn list_by_folder_path(
+        &self,
+        user_id: i32,
+        folder_path: &str,
+        include_deleted: bool
+    ) -> Result<Vec<Folder>> {
+        let folder_key = escape_like(&self.path_key(folder_path));
+        let path_pattern = if folder_key.ends_with('/') {
+            format!("{}%", folder_key)
+        } else {
+            format!("{}/%", folder_key)
+        };
+        
+        let mut query = String::from(
+            "SELECT id, user_id, path, parent_id, created_at, updated_at, is_deleted 
+             FROM folders 
+             WHERE user_id = $1 AND path LIKE $2 ESCAPE '\\' "
+        );
+        
+        if !include_deleted {
+            query.push_str("AND is_deleted = false ");
+        }
+        
+        query.push_str("ORDER BY path");
+        
+        let folders = sqlx::query_as::<_, Folder>(&query)
+            .bind(user_id)
+            .bind(path_pattern)
+            .fetch_all(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        Ok(folders)
+    }
+    
     async fn create(&self, folder: &Folder) -> Result<Folder> {
         let now = chrono::Utc::now();
         let created_folder = sqlx::query_as::<_, Folder>(
@@ -193,8 +311,10 @@ impl FolderRepository for SqlxFolderRepository {
     
     async fn mark_deleted(&self, id: i32) -> Result<bool> {
         let now = chrono::Utc::now();
+        // Dead properties do not outlive the folder they were set on
         let result = sqlx::query(
-            "UPDATE folders 
+            "WITH removed AS (DELETE FROM folder_properties WHERE folder_id = $2) 
+             UPDATE folders 
              SET is_deleted = true, updated_at = $1 
              WHERE id = $2"
         )
@@ -274,12 +394,166 @@ impl FolderRepository for SqlxFolderRepository {
             
         Ok(result.rows_affected() > 0)
     }
+    
+    async fn rename_folder(&self, user_id: i32, from_path: &str, to_path: &str) -> Result<Folder> {
+        let from_key = self.path_key(from_path);
+        let to_key = self.path_key(to_path);
+        
+        if to_key.starts_with(&format!("{}/", from_key.trim_end_matches('/'))) {
+            return Err(Error::MoveIntoSelf(format!("{} -> {}", from_path, to_path)));
+        }
+        
+        let mut transaction = self.begin_transaction().await?;
+        
+        let taken: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM folders WHERE user_id = $1 AND path = $2)"
+        )
+        .bind(user_id)
+        .bind(&to_key)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        if taken {
+            return Err(Error::AlreadyExists(format!("Folder already exists: {}", to_path)));
+        }
+        
+        let parent_path = Path::new(to_path).parent().map(|p| p.to_string_lossy().to_string());
+        let parent_id: Option<i32> = match parent_path {
+            Some(parent_path) => sqlx::query_scalar(
+                "SELECT id FROM folders WHERE user_id = $1 AND path = $2"
+            )
+            .bind(user_id)
+            .bind(self.path_key(&parent_path))
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(Error::QueryFailed)?,
+            None => None,
+        };
+        
+        let now = chrono::Utc::now();
+        let renamed = sqlx::query_as::<_, Folder>(
+            "UPDATE folders 
+             SET path = $1, parent_id = $2, updated_at = $3 
+             WHERE user_id = $4 AND path = $5 
+             RETURNING id, user_id, path, parent_id, created_at, updated_at, is_deleted"
+        )
+        .bind(&to_key)
+        .bind(parent_id)
+        .bind(now)
+        .bind(user_id)
+        .bind(&from_key)
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?
+        .ok_or_else(|| Error::NotFound(format!("Folder not found: {}", from_path)))?;
+        
+        // Descendants swap the old prefix for the new one. The prefix is cut by
+        // segment rather than by length: a display path can differ in length
+        // from its key, as lowercasing and NFC composition change the count
+        let prefix_segments = from_key.trim_end_matches('/').split('/').count() as i32;
+        sqlx::query(
+            "UPDATE folders 
+             SET path = $1 || '/' || array_to_string((string_to_array(path, '/'))[$2 + 1:], '/'), updated_at = $3 
+             WHERE user_id = $4 AND path LIKE $5 || '/%' ESCAPE '\\'"
+        )
+        .bind(&to_key)
+        .bind(prefix_segments)
+        .bind(now)
+        .bind(user_id)
+        .bind(escape_like(&from_key))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        sqlx::query(
+            "UPDATE files 
+             SET path = $1 || '/' || array_to_string((string_to_array(path, '/'))[$3 + 1:], '/'), 
+                 display_path = $2 || '/' || array_to_string((string_to_array(display_path, '/'))[$3 + 1:], '/') 
+             WHERE user_id = $4 AND path LIKE $5 || '/%' ESCAPE '\\'"
+        )
+        .bind(&to_key)
+        .bind(to_path)
+        .bind(prefix_segments)
+        .bind(user_id)
+        .bind(escape_like(&from_key))
+        .execute(&mut *transaction)
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Self::commit_transaction(transaction).await?;
+        
+        Ok(renamed)
+    }
+    
+    async fn create_path(
+        &self,
+        user_id: i32,
+        path: &str,
+        placeholder: Option<FolderPlaceholder<'_>>
+    ) -> Result<Vec<Folder>> {
+        let mut transaction = self.begin_transaction().await?;
+        let now = chrono::Utc::now();
+        
+        let mut folders: Vec<Folder> = Vec::new();
+        let mut current = String::new();
+        for part in path.split('/').filter(|part| !part.is_empty()) {
+            current.push('/');
+            current.push_str(part);
+            let key = self.path_key(&current);
+            
+            let folder = sqlx::query_as::<_, Folder>(
+                "INSERT INTO folders (user_id, path, parent_id, created_at, updated_at, is_deleted) 
+                 VALUES ($1, $2, $3, $4, $4, false) 
+                 ON CONFLICT (user_id, path) DO UPDATE SET is_deleted = false 
+                 RETURNING id, user_id, path, parent_id, created_at, updated_at, is_deleted"
+            )
+            .bind(user_id)
+            .bind(&key)
+            .bind(folders.last().map(|parent| parent.id))
+            .bind(now)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(Error::QueryFailed)?;
+            
+            if let Some(placeholder) = placeholder {
+                sqlx::query(
+                    "INSERT INTO files (user_id, path, display_path, content_hash, content_type, size, created_at, updated_at, is_deleted) 
+                     SELECT $1, $2, $3, $4, $5, 0, $6, $6, false 
+                     WHERE NOT EXISTS (
+                         SELECT 1 FROM files WHERE user_id = $1 AND path LIKE $7 ESCAPE '\\' AND is_deleted = false
+                     ) 
+                     ON CONFLICT (user_id, path) DO UPDATE 
+                     SET content_hash = EXCLUDED.content_hash, content_type = EXCLUDED.content_type, 
+                         size = 0, updated_at = EXCLUDED.updated_at, is_deleted = false"
+                )
+                .bind(user_id)
+                .bind(format!("{}/{}", key, placeholder.name))
+                .bind(format!("{}/{}", current, placeholder.name))
+                .bind(placeholder.content_hash)
+                .bind(placeholder.content_type)
+                .bind(now)
+                .bind(format!("{}/%", escape_like(&key)))
+                .execute(&mut *transaction)
+                .await
+                .map_err(Error::QueryFailed)?;
+            }
+            
+            folders.push(folder);
+        }
+        
+        Self::commit_transaction(transaction).await?;
+        
+        Ok(folders)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::DatabaseConfig;
+    use crate::models::File;
+    use crate::repositories::{FileRepository, SqlxFileRepository};
     use sqlx::postgres::PgPoolOptions;
     use std::time::Duration;
     
@@ -325,8 +599,8 @@ mod tests {
             }
         };
         
-        // Clear the folders table
-        let _ = sqlx::query("DELETE FROM folders").execute(&*pool).await;
+        // Clear folders left by earlier runs
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = 'folder_test_user')").execute(&*pool).await;
         let _ = sqlx::query("DELETE FROM users WHERE username = 'folder_test_user'").execute(&*pool).await;
         
         // Create a test user
@@ -407,4 +681,176 @@ mod tests {
         let _ = repo.delete_permanently(created_root.id).await;
         let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
     }
+    
+    #[tokio::test]
+    async fn test_rename_folder_rewrites_descendants() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let username = "folder_rename_user";
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1").bind(username).execute(&*pool).await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind(username)
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        .expect("Failed to create test user");
+        
+        let repo = SqlxFolderRepository::new(pool.clone());
+        let files = SqlxFileRepository::new(pool);
+        
+        let a = repo.create(&Folder::new(user_id, "/a".to_string(), None)).await.unwrap();
+        let b = repo.create(&Folder::new(user_id, "/a/b".to_string(), Some(a.id))).await.unwrap();
+        let c = repo.create(&Folder::new(user_id, "/a/b/c".to_string(), Some(b.id))).await.unwrap();
+        let other = repo.create(&Folder::new(user_id, "/ab".to_string(), None)).await.unwrap();
+        let note = File::new(user_id, "/a/b/c/note.md".to_string(), "hash".to_string(), "text/markdown".to_string(), 1);
+        files.create(&note).await.unwrap();
+        
+        let renamed = repo.rename_folder(user_id, "/a", "/x").await.unwrap();
+        assert_eq!(renamed.id, a.id);
+        assert_eq!(renamed.path, "/x");
+        assert_eq!(renamed.parent_id, None);
+        
+        let x_b = repo.find_by_id(b.id).await.unwrap().unwrap();
+        assert_eq!(x_b.path, "/x/b");
+        assert_eq!(x_b.parent_id, Some(a.id));
+        let x_b_c = repo.find_by_id(c.id).await.unwrap().unwrap();
+        assert_eq!(x_b_c.path, "/x/b/c");
+        assert_eq!(x_b_c.parent_id, Some(b.id));
+        
+        // Folders merely sharing the prefix are left alone
+        assert_eq!(repo.find_by_id(other.id).await.unwrap().unwrap().path, "/ab");
+        assert!(repo.find_by_path(user_id, "/a").await.unwrap().is_none());
+        
+        let moved = files.find_by_path(user_id, "/x/b/c/note.md").await.unwrap().unwrap();
+        assert_eq!(moved.display_path, "/x/b/c/note.md");
+        
+        // Renaming onto an existing folder changes nothing
+        let result = repo.rename_folder(user_id, "/x/b", "/ab").await;
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert_eq!(repo.find_by_id(b.id).await.unwrap().unwrap().path, "/x/b");
+        
+        // Moving below another folder relinks the renamed folder only
+        let renamed = repo.rename_folder(user_id, "/x/b", "/ab/b").await.unwrap();
+        assert_eq!(renamed.parent_id, Some(other.id));
+        let moved_c = repo.find_by_id(c.id).await.unwrap().unwrap();
+        assert_eq!(moved_c.path, "/ab/b/c");
+        assert_eq!(moved_c.parent_id, Some(b.id));
+        
+        let result = repo.rename_folder(user_id, "/missing", "/y").await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_rename_folder_keeps_display_paths_of_different_length() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let username = "folder_rename_display_user";
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1").bind(username).execute(&*pool).await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind(username)
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        .expect("Failed to create test user");
+        
+        let repo = SqlxFolderRepository::new(pool.clone()).with_case_insensitive_paths(true);
+        let files = SqlxFileRepository::new(pool).with_case_insensitive_paths(true);
+        
+        // `İ` lowercases to two characters, so the key is longer than the display path
+        repo.create(&Folder::new(user_id, repo.path_key("/İİ"), None)).await.unwrap();
+        let note = File::new(user_id, "/İİ/Note.md".to_string(), "hash".to_string(), "text/markdown".to_string(), 1);
+        files.create(&note).await.unwrap();
+        
+        repo.rename_folder(user_id, "/İİ", "/Renamed").await.unwrap();
+        
+        let moved = files.find_by_path(user_id, "/renamed/note.md").await.unwrap().unwrap();
+        assert_eq!(moved.display_path, "/Renamed/Note.md");
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_rename_folder_into_own_subtree() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let username = "folder_rename_cycle_user";
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = $1)").bind(username).execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1").bind(username).execute(&*pool).await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind(username)
+        .bind("test_password_hash")
+        .bind(chrono::Utc::now())
+        .fetch_one(&*pool)
+        .await
+        .expect("Failed to create test user");
+        
+        let repo = SqlxFolderRepository::new(pool);
+        
+        let a = repo.create(&Folder::new(user_id, "/a".to_string(), None)).await.unwrap();
+        let b = repo.create(&Folder::new(user_id, "/a/b".to_string(), Some(a.id))).await.unwrap();
+        
+        // Neither into a child nor into a deeper, not yet existing path
+        let result = repo.rename_folder(user_id, "/a", "/a/b/a").await;
+        assert!(matches!(result, Err(Error::MoveIntoSelf(_))));
+        let result = repo.rename_folder(user_id, "/a", "/a/new").await;
+        assert!(matches!(result, Err(Error::MoveIntoSelf(_))));
+        
+        assert_eq!(repo.find_by_id(a.id).await.unwrap().unwrap().path, "/a");
+        assert_eq!(repo.find_by_id(b.id).await.unwrap().unwrap().path, "/a/b");
+        
+        // A sibling sharing the prefix is a different folder
+        let renamed = repo.rename_folder(user_id, "/a/b", "/ab").await.unwrap();
+        assert_eq!(renamed.path, "/ab");
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1").bind(user_id).execute(repo.pool()).await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(repo.pool()).await;
+    }
 }
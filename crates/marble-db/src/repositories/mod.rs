@@ -6,10 +6,14 @@
 mod user_repository;
 mod folder_repository;
 mod file_repository;
+mod file_property_repository;
+mod file_version_repository;
 
-pub use user_repository::{UserRepository, SqlxUserRepository};
-pub use folder_repository::{FolderRepository, SqlxFolderRepository};
-pub use file_repository::{FileRepository, SqlxFileRepository};
+pub use user_repository::{UserRepository, SqlxUserRepository, TenantCursor, UserChangeHook};
+pub use folder_repository::{FolderPlaceholder, FolderRepository, SqlxFolderRepository};
+pub use file_repository::{FileRepository, SqlxFileRepository, ListOrder, ChangeCursor, ConflictPolicy};
+pub use file_property_repository::{FilePropertyRepository, SqlxFilePropertyRepository, PropertyChange};
+pub use file_version_repository::{FileVersionRepository, SqlxFileVersionRepository};
 
 use sqlx::postgres::PgPool;
 use std::sync::Arc;
@@ -61,6 +65,20 @@ where
     }
 }
 
+/// Escape `%`, `_` and `\` so a value matches itself literally in a `LIKE` pattern
+///
+/// Queries using the result should spell out `ESCAPE '\'`.
+pub(crate) fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 /// A trait for repositories that have a pool reference
 pub trait BaseRepository {
     /// Get a reference to the database pool
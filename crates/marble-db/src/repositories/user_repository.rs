@@ -2,16 +2,32 @@
 //!
 //! This module provides the UserRepository trait and its SQLx implementation.
 
+use chrono::{DateTime, Utc};
 use sqlx::postgres::{PgPool, PgRow};
 use sqlx::{FromRow, Row};
 use std::sync::Arc;
 use async_trait::async_trait;
+use uuid::Uuid;
 
 use crate::models::User;
 use crate::Result;
 use crate::Error;
+use crate::auth::{hash_password, is_password_hash};
 use super::{Repository, BaseRepository};
 
+/// Position in the tenant enumeration
+///
+/// Carries the sort key of the last tenant seen, `(created_at, id)`, so the
+/// next page is found even when that tenant has since been deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantCursor {
+    /// Creation time of the last tenant seen
+    pub created_at: DateTime<Utc>,
+    
+    /// ID of the last tenant seen, breaking ties between equal timestamps
+    pub id: i32,
+}
+
 /// Repository trait for user operations
 #[async_trait]
 pub trait UserRepository: Repository + BaseRepository + Send + Sync {
@@ -33,18 +49,57 @@ pub trait UserRepository: Repository + BaseRepository + Send + Sync {
     /// Record a login for a user
     async fn record_login(&self, id: i32) -> Result<bool>;
     
+    /// Replace a user's password hash, leaving the rest of the row untouched
+    async fn set_password_hash(&self, id: i32, password_hash: &str) -> Result<bool>;
+    
     /// List all users (with optional pagination)
     async fn list(&self, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<User>>;
+    
+    /// List up to `limit` tenant UUIDs after the cursor, oldest tenant first
+    ///
+    /// Returns the cursor to pass for the next page, which stays at `cursor`
+    /// when there are no more tenants. Every tenant is visited exactly once,
+    /// even while users are added or deleted.
+    async fn list_tenant_uuids(
+        &self,
+        limit: i64,
+        cursor: Option<TenantCursor>
+    ) -> Result<(Vec<Uuid>, Option<TenantCursor>)>;
 }
 
+/// Callback invoked with a tenant UUID after its user row is created, updated or deleted
+///
+/// Used to invalidate caches keyed by tenant UUID, such as the UUID to user id
+/// cache in the storage layer.
+pub type UserChangeHook = Arc<dyn Fn(Uuid) + Send + Sync>;
+
 /// SQLx implementation of the UserRepository
 pub struct SqlxUserRepository {
     pool: Arc<PgPool>,
+    change_hooks: Vec<UserChangeHook>,
 }
 
 impl Repository for SqlxUserRepository {
     fn new(pool: Arc<PgPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            change_hooks: Vec::new(),
+        }
+    }
+}
+
+impl SqlxUserRepository {
+    /// Register a hook called after a user row changes
+    pub fn with_change_hook(mut self, hook: UserChangeHook) -> Self {
+        self.change_hooks.push(hook);
+        self
+    }
+    
+    /// Notify all registered hooks that a tenant changed
+    fn notify_changed(&self, uuid: Uuid) {
+        for hook in &self.change_hooks {
+            hook(uuid);
+        }
     }
 }
 
@@ -63,6 +118,9 @@ impl FromRow<'_, PgRow> for User {
             password_hash: row.try_get("password_hash")?,
             created_at: row.try_get("created_at")?,
             last_login: row.try_get("last_login")?,
+            quota_bytes: row.try_get("quota_bytes")?,
+            default_content_type: row.try_get("default_content_type")?,
+            normalize_newlines: row.try_get("normalize_newlines")?,
         })
     }
 }
@@ -71,7 +129,7 @@ impl FromRow<'_, PgRow> for User {
 impl UserRepository for SqlxUserRepository {
     async fn find_by_id(&self, id: i32) -> Result<Option<User>> {
         let user = sqlx::query_as::<_, User>(
-            "SELECT id, uuid, username, password_hash, created_at, last_login 
+            "SELECT id, uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines 
              FROM users 
              WHERE id = $1"
         )
@@ -85,7 +143,7 @@ impl UserRepository for SqlxUserRepository {
     
     async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
         let user = sqlx::query_as::<_, User>(
-            "SELECT id, uuid, username, password_hash, created_at, last_login 
+            "SELECT id, uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines 
              FROM users 
              WHERE username = $1"
         )
@@ -98,49 +156,74 @@ impl UserRepository for SqlxUserRepository {
     }
     
     async fn create(&self, user: &User) -> Result<User> {
+        // Plain passwords are hashed; values that are already hashes are kept
+        let password_hash = if is_password_hash(&user.password_hash) {
+            user.password_hash.clone()
+        } else {
+            hash_password(&user.password_hash)?
+        };
+        
         let created_user = sqlx::query_as::<_, User>(
-            "INSERT INTO users (uuid, username, password_hash, created_at, last_login) 
-             VALUES ($1, $2, $3, $4, $5) 
-             RETURNING id, uuid, username, password_hash, created_at, last_login"
+            "INSERT INTO users (uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) 
+             RETURNING id, uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines"
         )
         .bind(user.uuid)
         .bind(&user.username)
-        .bind(&user.password_hash)
+        .bind(&password_hash)
         .bind(user.created_at)
         .bind(user.last_login)
+        .bind(user.quota_bytes)
+        .bind(&user.default_content_type)
+        .bind(user.normalize_newlines)
         .fetch_one(self.pool())
         .await
         .map_err(Error::QueryFailed)?;
         
+        // A re-created tenant gets a new id under the same UUID
+        self.notify_changed(created_user.uuid);
+        
         Ok(created_user)
     }
     
     async fn update(&self, user: &User) -> Result<User> {
         let updated_user = sqlx::query_as::<_, User>(
             "UPDATE users 
-             SET username = $1, password_hash = $2, last_login = $3 
-             WHERE id = $4 
-             RETURNING id, uuid, username, password_hash, created_at, last_login"
+             SET username = $1, password_hash = $2, last_login = $3, quota_bytes = $4, 
+                 default_content_type = $5, normalize_newlines = $6 
+             WHERE id = $7 
+             RETURNING id, uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines"
         )
         .bind(&user.username)
         .bind(&user.password_hash)
         .bind(user.last_login)
+        .bind(user.quota_bytes)
+        .bind(&user.default_content_type)
+        .bind(user.normalize_newlines)
         .bind(user.id)
         .fetch_one(self.pool())
         .await
         .map_err(Error::QueryFailed)?;
         
+        self.notify_changed(updated_user.uuid);
+        
         Ok(updated_user)
     }
     
     async fn delete(&self, id: i32) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        let deleted_uuid = sqlx::query_scalar::<_, Uuid>("DELETE FROM users WHERE id = $1 RETURNING uuid")
             .bind(id)
-            .execute(self.pool())
+            .fetch_optional(self.pool())
             .await
             .map_err(Error::QueryFailed)?;
-            
-        Ok(result.rows_affected() > 0)
+        
+        match deleted_uuid {
+            Some(uuid) => {
+                self.notify_changed(uuid);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
     
     async fn record_login(&self, id: i32) -> Result<bool> {
@@ -159,12 +242,27 @@ impl UserRepository for SqlxUserRepository {
         Ok(result.rows_affected() > 0)
     }
     
+    async fn set_password_hash(&self, id: i32, password_hash: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE users 
+             SET password_hash = $1 
+             WHERE id = $2"
+        )
+        .bind(password_hash)
+        .bind(id)
+        .execute(self.pool())
+        .await
+        .map_err(Error::QueryFailed)?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
     async fn list(&self, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<User>> {
         let limit = limit.unwrap_or(100);
         let offset = offset.unwrap_or(0);
         
         let users = sqlx::query_as::<_, User>(
-            "SELECT id, uuid, username, password_hash, created_at, last_login 
+            "SELECT id, uuid, username, password_hash, created_at, last_login, quota_bytes, default_content_type, normalize_newlines 
              FROM users 
              ORDER BY id 
              LIMIT $1 OFFSET $2"
@@ -177,6 +275,37 @@ impl UserRepository for SqlxUserRepository {
         
         Ok(users)
     }
+    
+    async fn list_tenant_uuids(
+        &self,
+        limit: i64,
+        cursor: Option<TenantCursor>
+    ) -> Result<(Vec<Uuid>, Option<TenantCursor>)> {
+        let mut query = String::from("SELECT uuid, created_at, id FROM users ");
+        
+        // Compare the pair, not the timestamp alone, so ties are neither skipped nor repeated
+        if cursor.is_some() {
+            query.push_str("WHERE (created_at, id) > ($2, $3) ");
+        }
+        
+        query.push_str("ORDER BY created_at, id LIMIT $1");
+        
+        let mut users_query = sqlx::query_as::<_, (Uuid, DateTime<Utc>, i32)>(&query).bind(limit);
+        if let Some(cursor) = cursor {
+            users_query = users_query.bind(cursor.created_at).bind(cursor.id);
+        }
+        
+        let rows = users_query
+            .fetch_all(self.pool())
+            .await
+            .map_err(Error::QueryFailed)?;
+        
+        let next = rows
+            .last()
+            .map(|&(_, created_at, id)| TenantCursor { created_at, id })
+            .or(cursor);
+        Ok((rows.into_iter().map(|(uuid, _, _)| uuid).collect(), next))
+    }
 }
 
 #[cfg(test)]
@@ -250,4 +379,124 @@ mod tests {
         let not_found = repo.find_by_id(created.id).await.unwrap();
         assert!(not_found.is_none());
     }
+    
+    #[tokio::test]
+    async fn test_set_password_hash() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM users WHERE username = 'password_hash_user'").execute(&*pool).await;
+        
+        let repo = SqlxUserRepository::new(pool.clone());
+        let user = repo
+            .create(&User::new("password_hash_user".to_string(), "old-hash".to_string()))
+            .await
+            .unwrap();
+        
+        // A change made after the row was read survives the new hash
+        sqlx::query("UPDATE users SET quota_bytes = 1000 WHERE id = $1")
+            .bind(user.id)
+            .execute(&*pool)
+            .await
+            .unwrap();
+        assert!(repo.set_password_hash(user.id, "new-hash").await.unwrap());
+        
+        let updated = repo.find_by_id(user.id).await.unwrap().unwrap();
+        assert_eq!(updated.password_hash, "new-hash");
+        assert_eq!(updated.quota_bytes, Some(1000));
+        
+        assert!(!repo.set_password_hash(-1, "new-hash").await.unwrap());
+        
+        repo.delete(user.id).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_list_tenant_uuids() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM users WHERE username LIKE 'tenant_page_user_%'").execute(&*pool).await;
+        
+        let repo = SqlxUserRepository::new(pool);
+        
+        let mut created = Vec::new();
+        for i in 0..5 {
+            let user = User::new(format!("tenant_page_user_{}", i), "passwordhash".to_string());
+            created.push(repo.create(&user).await.unwrap().uuid);
+        }
+        
+        // Page through all tenants two at a time
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = repo.list_tenant_uuids(2, cursor).await.unwrap();
+            if page.is_empty() {
+                assert_eq!(next, cursor, "An empty page keeps the cursor");
+                break;
+            }
+            assert!(page.len() <= 2);
+            cursor = next;
+            seen.extend(page);
+        }
+        
+        // Every created tenant is visited exactly once
+        for uuid in &created {
+            assert_eq!(seen.iter().filter(|seen_uuid| *seen_uuid == uuid).count(), 1);
+        }
+        
+        let mut deduped = seen.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), seen.len());
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM users WHERE username LIKE 'tenant_page_user_%'").execute(repo.pool()).await;
+    }
+    
+    #[tokio::test]
+    async fn test_list_tenant_uuids_after_deleted_tenant() {
+        let pool = match create_test_pool().await {
+            Ok(pool) => Arc::new(pool),
+            Err(_) => {
+                println!("Skipping repository test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM users WHERE username LIKE 'tenant_cursor_user_%'").execute(&*pool).await;
+        
+        let repo = SqlxUserRepository::new(pool);
+        
+        let mut created = Vec::new();
+        for i in 0..3 {
+            let user = User::new(format!("tenant_cursor_user_{}", i), "passwordhash".to_string());
+            created.push(repo.create(&user).await.unwrap());
+        }
+        
+        // Start right at the first created tenant, then delete it
+        let cursor = TenantCursor {
+            created_at: created[0].created_at,
+            id: created[0].id,
+        };
+        repo.delete(created[0].id).await.unwrap();
+        
+        // The rest are still listed after it
+        let (page, _) = repo.list_tenant_uuids(i64::MAX, Some(cursor)).await.unwrap();
+        assert!(page.contains(&created[1].uuid));
+        assert!(page.contains(&created[2].uuid));
+        assert!(!page.contains(&created[0].uuid));
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM users WHERE username LIKE 'tenant_cursor_user_%'").execute(repo.pool()).await;
+    }
 }
@@ -90,4 +90,9 @@ async fn test_combined_repositories() {
     let matching_users_final = users_final.iter().filter(|u| u.username == unique_username).count();
     println!("Final number of users with our test username: {}", matching_users_final);
     assert_eq!(matching_users_final, 1, "Should still have exactly one user with our test username");
+    
+    // Clean up
+    let _ = sqlx::query("DELETE FROM files WHERE user_id = $1").bind(user.id).execute(&*pool).await;
+    let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1").bind(user.id).execute(&*pool).await;
+    let _ = sqlx::query("DELETE FROM users WHERE id = $1").bind(user.id).execute(&*pool).await;
 }
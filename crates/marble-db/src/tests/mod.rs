@@ -1,5 +1,6 @@
 //! Test modules for marble-db
 
 // Module containing tests for database migrations
+mod api_tests;
 mod combined_repository_tests;
 mod migration_tests;
@@ -15,7 +15,8 @@ pub trait MarbleStorage: Send + Sync + 'static {
     /// Get a raw storage operator for a specific user.
     /// 
     /// This operator provides access to the user's files with their original
-    /// paths and structure.
+    /// paths and structure. It is wrapped with the retry, logging and metrics
+    /// layers enabled in the storage configuration.
     /// 
     /// # Arguments
     /// * `user_id` - The UUID of the user
@@ -40,4 +41,4 @@ pub type MarbleStorageRef = Arc<dyn MarbleStorage>;
 
 /// Tenant-isolated storage module
 pub mod tenant;
-pub use tenant::{TenantStorage, TenantStorageRef, FileMetadata};
\ No newline at end of file
+pub use tenant::{TenantStorage, TenantStorageRef, FileMetadata, DeadProperty, DedupOutcome, EntryKind, ListOrder, PropertyChange, StorageUsage, VersionInfo};
\ No newline at end of file
@@ -1,8 +1,13 @@
 use std::sync::Arc;
 use uuid::Uuid;
 use async_trait::async_trait;
+use bytes::Bytes;
+use sqlx::types::chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
 
-use crate::error::StorageResult;
+use crate::error::{StorageError, StorageResult};
+
+pub use marble_db::repositories::{ListOrder, PropertyChange};
 
 /// TenantStorage provides tenant-isolated storage operations.
 ///
@@ -22,6 +27,22 @@ pub trait TenantStorage: Send + Sync + 'static {
     /// * The file contents as a byte vector
     async fn read(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<u8>>;
     
+    /// Read a file by path for a specific tenant as a stream of chunks
+    ///
+    /// The default implementation reads the whole file and yields it as one
+    /// chunk; implementations backed by large stores should override it.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * A stream of the file contents
+    async fn read_stream(&self, tenant_id: &Uuid, path: &str) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+        let content = self.read(tenant_id, path).await?;
+        Ok(stream::once(async move { Ok(Bytes::from(content)) }).boxed())
+    }
+    
     /// Create a directory for a specific tenant
     ///
     /// # Arguments
@@ -41,8 +62,38 @@ pub trait TenantStorage: Send + Sync + 'static {
     /// * `content_type` - Optional MIME type of the content
     ///
     /// # Returns
-    /// * Ok(()) if the write was successful
-    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, content_type: Option<&str>) -> StorageResult<()>;
+    /// * Whether the tenant already held the content ([`DedupOutcome::Hit`])
+    ///   or not ([`DedupOutcome::Miss`])
+    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, content_type: Option<&str>) -> StorageResult<DedupOutcome>;
+    
+    /// Append data to a file for a specific tenant, creating it if missing
+    ///
+    /// Content is addressed by its hash, so the combined content is stored under
+    /// a new hash and the file record is updated to point at it.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file, relative to the tenant's root
+    /// * `data` - The bytes to append
+    /// * `content_type` - Optional MIME type; defaults to the existing file's type
+    ///
+    /// # Returns
+    /// * Ok(()) if the append was successful
+    async fn append(&self, tenant_id: &Uuid, path: &str, data: Vec<u8>, content_type: Option<&str>) -> StorageResult<()>;
+    
+    /// Truncate or extend a file for a specific tenant to `len` bytes
+    ///
+    /// Longer files are cut off and shorter files are padded with zero bytes.
+    /// Truncating to 0 leaves an empty file, not a directory.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file, relative to the tenant's root
+    /// * `len` - The new length of the file in bytes
+    ///
+    /// # Returns
+    /// * Ok(()) if the file was resized
+    async fn truncate(&self, tenant_id: &Uuid, path: &str, len: u64) -> StorageResult<()>;
     
     /// Check if a file exists for a tenant
     ///
@@ -64,6 +115,37 @@ pub trait TenantStorage: Send + Sync + 'static {
     /// * Ok(()) if the delete was successful
     async fn delete(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()>;
     
+    /// Delete a directory and everything below it for a tenant
+    ///
+    /// Returns the number of files deleted.
+    async fn delete_directory(&self, tenant_id: &Uuid, path: &str) -> StorageResult<u64>;
+    
+    /// Rename a file for a tenant without copying its content
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `from` - The current path of the file, relative to the tenant's root
+    /// * `to` - The new path of the file, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * Ok(()) if the file was renamed; the content hash and timestamps are preserved
+    async fn rename(&self, tenant_id: &Uuid, from: &str, to: &str) -> StorageResult<()>;
+    
+    /// Create a live alias of a file for a tenant
+    ///
+    /// Unlike a copy, the alias keeps following the target: reading it returns
+    /// the target's current content and writing it updates the target.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path of the alias, relative to the tenant's root
+    /// * `target` - The path of the file the alias follows, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * Ok(()) if the alias was created
+    /// * `StorageError::NotFound` if the target does not exist
+    async fn create_alias(&self, tenant_id: &Uuid, path: &str, target: &str) -> StorageResult<()>;
+    
     /// List files for a tenant in a directory
     ///
     /// # Arguments
@@ -71,9 +153,27 @@ pub trait TenantStorage: Send + Sync + 'static {
     /// * `dir_path` - The directory path, relative to the tenant's root
     ///
     /// # Returns
-    /// * A list of file paths in the directory
+    /// * A list of file paths in the directory, empty for an empty directory
+    /// * `StorageError::NotFound` if the directory does not exist
+    /// * `StorageError::Validation` if the path is a file
     async fn list(&self, tenant_id: &Uuid, dir_path: &str) -> StorageResult<Vec<String>>;
     
+    /// List files for a tenant in a directory together with their metadata
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `dir_path` - The directory path, relative to the tenant's root
+    /// * `order` - The order in which entries are returned
+    ///
+    /// # Returns
+    /// * Metadata for each entry in the directory, sorted by `order`
+    async fn list_with_metadata(
+        &self,
+        tenant_id: &Uuid,
+        dir_path: &str,
+        order: ListOrder,
+    ) -> StorageResult<Vec<FileMetadata>>;
+    
     /// Get metadata for a file for a tenant
     ///
     /// # Arguments
@@ -83,9 +183,203 @@ pub trait TenantStorage: Send + Sync + 'static {
     /// # Returns
     /// * File metadata including size, content type, etc.
     async fn metadata(&self, tenant_id: &Uuid, path: &str) -> StorageResult<FileMetadata>;
+    
+    /// Get metadata for many files for a tenant at once
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `paths` - The paths to the files, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * Metadata aligned with `paths`, with `None` for paths that don't exist
+    async fn metadata_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<Option<FileMetadata>>>;
+    
+    /// Tell whether a path is a file or a directory for a tenant
+    ///
+    /// Cheaper than `metadata` when only the kind of entry matters; the
+    /// default implementation falls back to it.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to check, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * The kind of entry at the path, or `None` if nothing is there
+    async fn entry_kind(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Option<EntryKind>> {
+        match self.metadata(tenant_id, path).await {
+            Ok(metadata) if metadata.is_directory => Ok(Some(EntryKind::Directory)),
+            Ok(_) => Ok(Some(EntryKind::File)),
+            Err(StorageError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Get the storage a tenant uses and its quota
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    ///
+    /// # Returns
+    /// * The bytes taken by the tenant's live files and its quota, if any
+    async fn usage(&self, tenant_id: &Uuid) -> StorageResult<StorageUsage>;
+    
+    /// Pin the content type of a file for a tenant
+    ///
+    /// The pinned type is reported in place of the guessed one and used by
+    /// later writes that declare no content type of their own.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file, relative to the tenant's root
+    /// * `content_type` - The type to pin, or `None` to go back to guessing
+    ///
+    /// # Returns
+    /// * Ok(()) if the content type was set
+    /// * `StorageError::NotFound` if the file does not exist
+    async fn set_content_type_override(&self, tenant_id: &Uuid, path: &str, content_type: Option<&str>) -> StorageResult<()>;
+    
+    /// Get the dead properties of a file or directory for a tenant
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file or directory, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * The properties ordered by namespace and name
+    /// * `StorageError::NotFound` if the path does not exist
+    async fn properties(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<DeadProperty>>;
+    
+    /// Get the dead properties of many files for a tenant at once
+    ///
+    /// The default implementation looks up each path on its own.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `paths` - The paths to the files, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * Properties aligned with `paths`, empty for paths that don't exist
+    async fn properties_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<Vec<DeadProperty>>> {
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.properties(tenant_id, path).await {
+                Ok(properties) => results.push(properties),
+                Err(crate::error::StorageError::NotFound(_)) => results.push(Vec::new()),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+    
+    /// Change the dead properties of a file or directory for a tenant
+    ///
+    /// Changes are applied in order and either all of them take effect or none,
+    /// including the content type pin.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `path` - The path to the file or directory, relative to the tenant's root
+    /// * `changes` - The properties to set and remove
+    /// * `content_type` - A content type to pin as with `set_content_type_override`,
+    ///   `Some(None)` to unpin, `None` to leave it alone
+    ///
+    /// # Returns
+    /// * Ok(()) if every change was applied
+    /// * `StorageError::NotFound` if the path does not exist
+    /// * `StorageError::Validation` if a content type is given for a directory
+    async fn patch_properties(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        changes: &[PropertyChange],
+        content_type: Option<Option<&str>>,
+    ) -> StorageResult<()>;
+    
+    /// Read content by its hash for a tenant
+    ///
+    /// Content is shared between tenants, so it is only returned if one of the
+    /// tenant's live files references the hash.
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `content_hash` - The content hash
+    ///
+    /// # Returns
+    /// * The content, or an authorization error if the tenant does not reference it
+    async fn read_by_hash(&self, tenant_id: &Uuid, content_hash: &str) -> StorageResult<Vec<u8>>;
+    
+    /// Delete many files for a specific tenant at once
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `paths` - The paths to delete, relative to the tenant's root
+    ///
+    /// # Returns
+    /// * Whether each file was deleted, aligned with `paths`; missing files yield `false`
+    async fn delete_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<bool>>;
+    
+    /// Find a tenant's markdown files whose content matches a plain-text query
+    ///
+    /// # Arguments
+    /// * `tenant_id` - The UUID of the tenant
+    /// * `query` - Words that must all occur in the file
+    ///
+    /// # Returns
+    /// * Metadata of the live files found, best matches first
+    async fn search(&self, tenant_id: &Uuid, query: &str) -> StorageResult<Vec<FileMetadata>>;
+    
+    /// Release caches and connections at shutdown
+    ///
+    /// Operations after shutdown fail. Implementations without resources to
+    /// release need not override this.
+    async fn shutdown(&self) {}
+}
+
+/// Whether the tenant already held the content of a write
+///
+/// Content is addressed by its hash, so identical content is stored once no
+/// matter how many files or tenants hold it. The outcome only reflects the
+/// writing tenant's own files, so it reveals nothing about other tenants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupOutcome {
+    /// A live file of the tenant already had the same content
+    Hit,
+    
+    /// The tenant held no file with this content
+    Miss,
+}
+
+impl DedupOutcome {
+    /// Outcome of a write, given whether the tenant already referenced the content
+    pub fn from_referenced(referenced: bool) -> Self {
+        if referenced {
+            DedupOutcome::Hit
+        } else {
+            DedupOutcome::Miss
+        }
+    }
+    
+    /// Lowercase name, `hit` or `miss`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DedupOutcome::Hit => "hit",
+            DedupOutcome::Miss => "miss",
+        }
+    }
+}
+
+/// Kind of entry found at a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// A file, including an alias of one
+    File,
+    
+    /// A directory
+    Directory,
 }
 
 /// Metadata for a file
+#[derive(Debug, Clone)]
 pub struct FileMetadata {
     /// Path to the file
     pub path: String,
@@ -102,9 +396,141 @@ pub struct FileMetadata {
     /// Last modified time in milliseconds since epoch
     pub last_modified: Option<u64>,
     
+    /// Last read time in milliseconds since epoch, if access tracking is enabled
+    pub last_accessed: Option<u64>,
+    
     /// Content hash for verification
     pub content_hash: Option<String>,
 }
 
+/// A WebDAV dead property of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadProperty {
+    /// Namespace URI of the property, empty for no namespace
+    pub namespace: String,
+    
+    /// Local name of the property
+    pub name: String,
+    
+    /// Text value of the property
+    pub value: String,
+}
+
+impl From<marble_db::models::FileProperty> for DeadProperty {
+    fn from(property: marble_db::models::FileProperty) -> Self {
+        Self {
+            namespace: property.namespace,
+            name: property.name,
+            value: property.value,
+        }
+    }
+}
+
+impl From<marble_db::models::FolderProperty> for DeadProperty {
+    fn from(property: marble_db::models::FolderProperty) -> Self {
+        Self {
+            namespace: property.namespace,
+            name: property.name,
+            value: property.value,
+        }
+    }
+}
+
+/// Storage taken by a tenant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Bytes taken by the tenant's live files
+    pub used_bytes: u64,
+    
+    /// Storage quota of the tenant in bytes, unlimited if `None`
+    pub quota_bytes: Option<u64>,
+}
+
+impl StorageUsage {
+    /// Bytes left before the quota is reached, unlimited if `None`
+    pub fn available_bytes(&self) -> Option<u64> {
+        self.quota_bytes.map(|quota| quota.saturating_sub(self.used_bytes))
+    }
+}
+
+/// A recorded content of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// Identifier to pass when restoring the version
+    pub id: i32,
+    
+    /// Hash of the content
+    pub content_hash: String,
+    
+    /// Size of the content in bytes
+    pub size: u64,
+    
+    /// Content type (MIME type) of the content
+    pub content_type: String,
+    
+    /// When the version was recorded, in milliseconds since epoch
+    pub created: u64,
+}
+
+impl From<marble_db::models::FileVersion> for VersionInfo {
+    fn from(version: marble_db::models::FileVersion) -> Self {
+        Self {
+            id: version.id,
+            content_hash: version.content_hash,
+            size: version.size as u64,
+            content_type: version.content_type,
+            created: epoch_millis(&version.created_at),
+        }
+    }
+}
+
+/// Convert a database timestamp to milliseconds since epoch
+///
+/// Times before the epoch cannot be represented and are reported as the epoch
+/// itself, so a stored timestamp always yields a value.
+pub(crate) fn epoch_millis(time: &DateTime<Utc>) -> u64 {
+    u64::try_from(time.timestamp_millis()).unwrap_or(0)
+}
+
+/// Sort metadata entries in place according to a listing order
+///
+/// Used by implementations that cannot push the ordering down to the database.
+/// Path is the tie-breaker for every order, matching the database ordering.
+pub fn sort_metadata(entries: &mut [FileMetadata], order: ListOrder) {
+    match order {
+        ListOrder::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+        ListOrder::ModifiedDesc => entries.sort_by(|a, b| {
+            b.last_modified.cmp(&a.last_modified).then_with(|| a.path.cmp(&b.path))
+        }),
+        ListOrder::SizeDesc => entries.sort_by(|a, b| {
+            b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))
+        }),
+    }
+}
+
 /// Type alias for a boxed TenantStorage trait object
-pub type TenantStorageRef = Arc<dyn TenantStorage>;
\ No newline at end of file
+pub type TenantStorageRef = Arc<dyn TenantStorage>;
+/// Apply property changes in order to an in-memory property list
+///
+/// Used by implementations without a database; the list is kept sorted by
+/// namespace and name, matching the database ordering.
+pub fn apply_property_changes(properties: &mut Vec<DeadProperty>, changes: &[PropertyChange]) {
+    for change in changes {
+        match change {
+            PropertyChange::Set { namespace, name, value } => {
+                match properties.iter_mut().find(|p| &p.namespace == namespace && &p.name == name) {
+                    Some(existing) => existing.value = value.clone(),
+                    None => properties.push(DeadProperty {
+                        namespace: namespace.clone(),
+                        name: name.clone(),
+                        value: value.clone(),
+                    }),
+                }
+            }
+            PropertyChange::Remove { namespace, name } => {
+                properties.retain(|p| &p.namespace != namespace || &p.name != name);
+            }
+        }
+    }
+    properties.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+}
@@ -1,11 +1,25 @@
 use std::path::PathBuf;
 
-use opendal::services::{Fs, S3};
+use bytes::Bytes;
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use opendal::services::{Azblob, Fs, S3};
 use opendal::Operator;
 
-use crate::config::{StorageBackend, StorageConfig};
+use crate::config::{Compression, StorageBackend, StorageConfig};
 use crate::error::{StorageError, StorageResult};
-use crate::hash::hash_to_path;
+use crate::hash::{hash_content, hash_to_path, path_to_hash};
+
+/// Leading bytes of every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Skippable zstd frame written ahead of every compressed object
+///
+/// It tells compressed objects apart from content stored as is without
+/// reading them whole, and zstd decoders skip it.
+const COMPRESSED_MARKER: [u8; 12] = [0x50, 0x2A, 0x4D, 0x18, 4, 0, 0, 0, b'M', b'R', b'B', b'L'];
+
+/// Size of the buffer each decoded chunk is written to
+const DECODE_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Creates a hash-based storage operator based on the configuration
 pub fn create_hash_storage(config: &StorageConfig) -> StorageResult<Operator> {
@@ -41,6 +55,37 @@ pub fn create_hash_storage(config: &StorageConfig) -> StorageResult<Operator> {
                 builder.secret_access_key(secret_key);
             }
             
+            // Build the operator
+            let operator_builder = Operator::new(builder)?;
+            Ok(operator_builder.finish())
+        }
+        StorageBackend::Azure(azure_config) => {
+            let mut builder = Azblob::default();
+            
+            // Set the required options
+            builder.container(&azure_config.container);
+            builder.account_name(&azure_config.account);
+            
+            match azure_config.endpoint {
+                Some(ref endpoint) => builder.endpoint(endpoint),
+                None => builder.endpoint(&format!("https://{}.blob.core.windows.net", azure_config.account)),
+            };
+            
+            if let Some(ref prefix) = azure_config.prefix {
+                let hash_prefix = format!("{}/hash", prefix);
+                builder.root(&hash_prefix);
+            } else {
+                builder.root("/hash");
+            }
+            
+            if let Some(ref account_key) = azure_config.account_key {
+                builder.account_key(account_key);
+            }
+            
+            if let Some(ref sas_token) = azure_config.sas_token {
+                builder.sas_token(sas_token);
+            }
+            
             // Build the operator
             let operator_builder = Operator::new(builder)?;
             Ok(operator_builder.finish())
@@ -76,42 +121,169 @@ fn create_fs_hash_storage(base_path: PathBuf) -> StorageResult<Operator> {
 // Permission layer implementation removed for simplicity
 // We'll add a proper Layer implementation in a future phase if needed
 
-/// Put content into hash storage with a given hash
-pub async fn put_content_by_hash(
+/// Put content into hash storage with a given hash, unless it is already there
+///
+/// The content is compressed first if `compression` is set; the hash stays
+/// that of the uncompressed content. Returns `true` if the content was written
+/// and `false` if a blob with the hash already existed (deduplication).
+pub async fn put_content_if_absent(
     op: &Operator,
     hash: &str,
     content: Vec<u8>,
-) -> StorageResult<()> {
+    compression: Option<Compression>,
+) -> StorageResult<bool> {
     let path = hash_to_path(hash);
     
     // Check if content already exists (deduplication)
     if op.is_exist(&path).await? {
         // Content already exists, no need to write it again
-        return Ok(());
+        return Ok(false);
     }
     
     // Write the content
+    let content = match compression {
+        Some(Compression::Zstd { level }) => {
+            let mut compressed = COMPRESSED_MARKER.to_vec();
+            zstd::stream::copy_encode(content.as_slice(), &mut compressed, level)
+                .map_err(|e| StorageError::Storage(format!("Failed to compress {}: {}", hash, e)))?;
+            compressed
+        }
+        None => content,
+    };
     op.write(&path, content).await?;
-    Ok(())
+    Ok(true)
 }
 
-/// Get content from hash storage by hash
+/// Undo the compression of a stored object
+///
+/// Compressed objects start with the marker frame; objects compressed before
+/// it was introduced are bare zstd frames. An object stored as is that happens
+/// to be a zstd frame itself, such as a `.zst` upload stored before compression
+/// was enabled, already hashes to its key and is returned unchanged.
+fn decompress(hash: &str, stored: Vec<u8>) -> StorageResult<Vec<u8>> {
+    let marked = stored.starts_with(&COMPRESSED_MARKER);
+    if !marked && (!stored.starts_with(&ZSTD_MAGIC) || hash_content(&stored)? == hash) {
+        return Ok(stored);
+    }
+    
+    zstd::stream::decode_all(stored.as_slice())
+        .map_err(|e| StorageError::Storage(format!("Failed to decompress {}: {}", hash, e)))
+}
+
+/// Get content from hash storage by hash, decompressing it if needed
 pub async fn get_content_by_hash(
     op: &Operator,
     hash: &str,
 ) -> StorageResult<Vec<u8>> {
     let path = hash_to_path(hash);
     let content = op.read(&path).await?;
-    Ok(content)
+    decompress(hash, content)
 }
 
-/// Get content from hash storage by path
+/// Stream content from hash storage by hash, reading ahead `chunk_size` bytes at a time
+///
+/// Compressed objects are decoded chunk by chunk as they are read. Bare zstd
+/// frames, which may be compressed or stored as is, can only be told apart by
+/// their hash, so they are read whole.
+pub async fn stream_content_by_hash(
+    op: &Operator,
+    hash: &str,
+    chunk_size: usize,
+) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+    let path = hash_to_path(hash);
+    let mut reader = op.reader_with(&path).buffer(chunk_size).await?.map_err(StorageError::from).boxed();
+    
+    // Enough of the head to recognize the marker, even with tiny chunks
+    let mut head = Vec::new();
+    while head.len() < COMPRESSED_MARKER.len() {
+        match reader.next().await {
+            Some(chunk) => head.extend_from_slice(&chunk?),
+            None => break,
+        }
+    }
+    if head.is_empty() {
+        return Ok(futures::stream::empty().boxed());
+    }
+    let head = Bytes::from(head);
+    
+    if head.starts_with(&COMPRESSED_MARKER) {
+        let input = futures::stream::once(async { Ok(head) }).chain(reader).boxed();
+        return Ok(decode_stream(hash.to_string(), input));
+    }
+    
+    if !head.starts_with(&ZSTD_MAGIC) {
+        return Ok(futures::stream::once(async { Ok(head) }).chain(reader).boxed());
+    }
+    
+    let mut stored = head.to_vec();
+    while let Some(chunk) = reader.next().await {
+        stored.extend_from_slice(&chunk?);
+    }
+    let content = Bytes::from(decompress(hash, stored)?);
+    Ok(futures::stream::once(async { Ok(content) }).boxed())
+}
+
+/// Decode a stream of compressed chunks into a stream of content chunks
+///
+/// Fails at the end of the input if the last frame is incomplete.
+fn decode_stream(
+    hash: String,
+    input: BoxStream<'static, StorageResult<Bytes>>,
+) -> BoxStream<'static, StorageResult<Bytes>> {
+    use zstd::stream::raw::{Decoder, Operation};
+    
+    let decoder = match Decoder::new() {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            let error = StorageError::Storage(format!("Failed to decompress {}: {}", hash, e));
+            return futures::stream::once(async { Err(error) }).boxed();
+        }
+    };
+    
+    let stream = futures::stream::try_unfold((decoder, input, true), move |(mut decoder, mut input, mut frame_done)| {
+        let hash = hash.clone();
+        async move {
+            let decode_error = |e: std::io::Error| StorageError::Storage(format!("Failed to decompress {}: {}", hash, e));
+            let mut buffer = vec![0; DECODE_BUFFER_SIZE];
+            
+            while let Some(chunk) = input.next().await {
+                let chunk = chunk?;
+                let mut decoded = Vec::new();
+                let mut consumed = 0;
+                loop {
+                    let status = decoder.run_on_buffers(&chunk[consumed..], &mut buffer).map_err(decode_error)?;
+                    consumed += status.bytes_read;
+                    decoded.extend_from_slice(&buffer[..status.bytes_written]);
+                    frame_done = status.remaining == 0;
+                    
+                    // A full buffer may leave decoded bytes behind in the decoder
+                    if consumed == chunk.len() && status.bytes_written < buffer.len() {
+                        break;
+                    }
+                }
+                
+                if !decoded.is_empty() {
+                    return Ok(Some((Bytes::from(decoded), (decoder, input, frame_done))));
+                }
+            }
+            
+            if !frame_done {
+                return Err(decode_error(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated frame")));
+            }
+            Ok(None)
+        }
+    });
+    stream.boxed()
+}
+
+/// Get content from hash storage by path, decompressing it if needed
 pub async fn get_content_by_path(
     op: &Operator,
     path: &str,
 ) -> StorageResult<Vec<u8>> {
+    let hash = path_to_hash(path)?;
     let content = op.read(path).await?;
-    Ok(content)
+    decompress(&hash, content)
 }
 
 /// Check if content exists in hash storage
@@ -139,7 +311,6 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     use tokio::test;
-    use crate::hash::hash_content;
 
     async fn setup_test_storage() -> (Operator, tempfile::TempDir) {
         // Create a temporary directory
@@ -163,7 +334,7 @@ mod tests {
         let hash = hash_content(content).expect("Failed to hash content");
         
         // Store the content
-        put_content_by_hash(&storage, &hash, content.to_vec())
+        put_content_if_absent(&storage, &hash, content.to_vec(), None)
             .await
             .expect("Failed to store content");
         
@@ -193,7 +364,7 @@ mod tests {
         assert!(!exists_before, "Content should not exist before storing");
         
         // Store the content
-        put_content_by_hash(&storage, &hash, content.to_vec())
+        put_content_if_absent(&storage, &hash, content.to_vec(), None)
             .await
             .expect("Failed to store content");
         
@@ -215,11 +386,11 @@ mod tests {
         let hash = hash_content(content).expect("Failed to hash content");
         
         // Store the content twice
-        put_content_by_hash(&storage, &hash, content.to_vec())
+        put_content_if_absent(&storage, &hash, content.to_vec(), None)
             .await
             .expect("Failed to store content first time");
             
-        put_content_by_hash(&storage, &hash, content.to_vec())
+        put_content_if_absent(&storage, &hash, content.to_vec(), None)
             .await
             .expect("Failed to store content second time");
         
@@ -246,7 +417,7 @@ mod tests {
         let hash = hash_content(content).expect("Failed to hash content");
         
         // Store the content
-        put_content_by_hash(&storage, &hash, content.to_vec())
+        put_content_if_absent(&storage, &hash, content.to_vec(), None)
             .await
             .expect("Failed to store content");
         
@@ -267,4 +438,42 @@ mod tests {
             .expect("Failed to check existence");
         assert!(!exists_after, "Content should not exist after deletion");
     }
+
+    #[test]
+    async fn test_compressed_reads() {
+        let (storage, _temp_dir) = setup_test_storage().await;
+        
+        // Incompressible enough to span several stored chunks
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let hash = hash_content(&content).expect("Failed to hash content");
+        put_content_if_absent(&storage, &hash, content.clone(), Some(Compression::Zstd { level: 3 }))
+            .await
+            .expect("Failed to store content");
+        
+        // Reads by path decompress like reads by hash
+        let by_path = get_content_by_path(&storage, &hash_to_path(&hash))
+            .await
+            .expect("Failed to retrieve content");
+        assert_eq!(by_path, content);
+        
+        // Streams are decoded as they are read, not buffered into one chunk
+        let chunks: Vec<Bytes> = stream_content_by_hash(&storage, &hash, 1000)
+            .await
+            .expect("Failed to open stream")
+            .try_collect()
+            .await
+            .expect("Failed to read stream");
+        assert!(chunks.len() > 1, "Compressed content should stream in several chunks");
+        assert_eq!(chunks.concat(), content);
+        
+        // A truncated object fails instead of yielding partial content silently
+        let stored = storage.read(&hash_to_path(&hash)).await.unwrap();
+        storage.write(&hash_to_path(&hash), stored[..stored.len() / 2].to_vec()).await.unwrap();
+        let result: StorageResult<Vec<Bytes>> = stream_content_by_hash(&storage, &hash, 1000)
+            .await
+            .expect("Failed to open stream")
+            .try_collect()
+            .await;
+        assert!(result.is_err());
+    }
 }
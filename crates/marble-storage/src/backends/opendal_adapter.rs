@@ -4,34 +4,68 @@
 //! the RawStorageBackend to enable tenant isolation through
 //! database metadata while still using OpenDAL's operator interface.
 
+use std::fmt;
 use std::sync::Arc;
-use std::path::PathBuf;
+use std::task::{Context, Poll};
+use std::vec::IntoIter;
 
+use async_trait::async_trait;
 use opendal::{
+    Capability,
+    EntryMode,
     ErrorKind,
+    Metadata,
     Operator,
+    OperatorBuilder,
     Result as OpendalResult,
     Error as OpendalError,
-    services::Memory,
-    layers::LoggingLayer,
+    Scheme,
+    layers::{LoggingLayer, RetryLayer},
+};
+use opendal::raw::{
+    oio,
+    Accessor,
+    AccessorInfo,
+    OpCreateDir,
+    OpDelete,
+    OpList,
+    OpRead,
+    OpStat,
+    OpWrite,
+    RpCreateDir,
+    RpDelete,
+    RpList,
+    RpRead,
+    RpStat,
+    RpWrite,
 };
 use mime_guess::from_path;
+use sqlx::types::chrono::DateTime;
 
+use crate::api::{FileMetadata, ListOrder};
 use crate::backends::raw::RawStorageBackend;
+use crate::config::OperatorLayers;
+use crate::path::PathNormalizer;
 
-/// A wrapper for the RawStorageBackend that provides a simplified interface
-/// for the OpenDAL adapter.
+/// An OpenDAL accessor that delegates to a tenant's RawStorageBackend
+///
+/// Paths handed to the operator are resolved against the tenant's files in the
+/// database, so the operator only ever sees that tenant's content.
 pub struct RawStorageAdapter {
     /// The underlying storage backend
     backend: Arc<RawStorageBackend>,
-    /// The temporary directory for in-memory files (if needed)
-    temp_dir: Option<PathBuf>,
+}
+
+impl fmt::Debug for RawStorageAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawStorageAdapter").finish_non_exhaustive()
+    }
 }
 
 impl RawStorageAdapter {
     /// Create a new RawStorageAdapter with the given backend
     pub fn new(backend: Arc<RawStorageBackend>) -> Self {
-        Self { backend, temp_dir: None }
+        Self { backend }
     }
 
     /// Helper to convert our storage errors to OpenDAL errors
@@ -51,19 +85,10 @@ impl RawStorageAdapter {
     }
 
     /// Helper to normalize paths for OpenDAL
-    pub fn normalize_path(path: &str) -> String {
-        let path = if path.starts_with('/') {
-            path.to_string()
-        } else {
-            format!("/{}", path)
-        };
-
-        // Remove trailing slash unless it's the root path
-        if path.len() > 1 && path.ends_with('/') {
-            path[0..path.len() - 1].to_string()
-        } else {
-            path
-        }
+    ///
+    /// Paths that climb above the root are rejected.
+    pub fn normalize_path(path: &str) -> crate::error::StorageResult<String> {
+        PathNormalizer::new().try_normalize(path)
     }
     
     /// Guess the content type based on file extension
@@ -73,37 +98,222 @@ impl RawStorageAdapter {
             None => "application/octet-stream".to_string(),
         }
     }
+    
+    /// Convert our file metadata to OpenDAL metadata
+    fn convert_metadata(metadata: &FileMetadata) -> Metadata {
+        if metadata.is_directory {
+            return Metadata::new(EntryMode::DIR);
+        }
+        
+        let mut converted = Metadata::new(EntryMode::FILE)
+            .with_content_length(metadata.size)
+            .with_content_type(metadata.content_type.clone());
+        if let Some(last_modified) = metadata.last_modified.and_then(|ms| DateTime::from_timestamp_millis(ms as i64)) {
+            converted = converted.with_last_modified(last_modified);
+        }
+        if let Some(hash) = &metadata.content_hash {
+            converted = converted.with_etag(hash.clone());
+        }
+        converted
+    }
+    
+    /// Relative OpenDAL path for a listed entry, with a trailing slash for directories
+    fn entry_path(metadata: &FileMetadata) -> String {
+        let path = metadata.path.trim_start_matches('/');
+        if metadata.is_directory {
+            format!("{}/", path.trim_end_matches('/'))
+        } else {
+            path.to_string()
+        }
+    }
+}
+
+#[async_trait]
+impl Accessor for RawStorageAdapter {
+    type Reader = oio::Cursor;
+    type Writer = oio::OneShotWriter<RawStorageWriter>;
+    type Lister = oio::HierarchyLister<RawStorageLister>;
+    type BlockingReader = ();
+    type BlockingWriter = ();
+    type BlockingLister = ();
+    
+    fn info(&self) -> AccessorInfo {
+        let mut info = AccessorInfo::default();
+        info.set_scheme(Scheme::Custom("marble"))
+            .set_root("/")
+            .set_native_capability(Capability {
+                stat: true,
+                read: true,
+                read_can_seek: true,
+                read_can_next: true,
+                read_with_range: true,
+                write: true,
+                write_can_empty: true,
+                create_dir: true,
+                delete: true,
+                list: true,
+                list_with_recursive: true,
+                ..Default::default()
+            });
+        info
+    }
+    
+    async fn create_dir(&self, path: &str, _: OpCreateDir) -> OpendalResult<RpCreateDir> {
+        self.backend
+            .create_directory(&Self::normalize_path(path).map_err(Self::convert_error)?)
+            .await
+            .map_err(Self::convert_error)?;
+        Ok(RpCreateDir::default())
+    }
+    
+    async fn stat(&self, path: &str, _: OpStat) -> OpendalResult<RpStat> {
+        let normalized = Self::normalize_path(path).map_err(Self::convert_error)?;
+        
+        // Directory paths end with a slash; the root always exists
+        if path.ends_with('/') {
+            return if self.backend.directory_exists(&normalized).await.map_err(Self::convert_error)? {
+                Ok(RpStat::new(Metadata::new(EntryMode::DIR)))
+            } else {
+                Err(OpendalError::new(ErrorKind::NotFound, &format!("Directory not found: {}", normalized)))
+            };
+        }
+        
+        let metadata = self.backend
+            .get_file_metadata(&normalized)
+            .await
+            .map_err(Self::convert_error)?;
+        Ok(RpStat::new(Self::convert_metadata(&metadata)))
+    }
+    
+    async fn read(&self, path: &str, args: OpRead) -> OpendalResult<(RpRead, Self::Reader)> {
+        let mut content = self.backend
+            .read_file(&Self::normalize_path(path).map_err(Self::convert_error)?)
+            .await
+            .map_err(Self::convert_error)?;
+        
+        // Hash storage holds whole files, so ranges are cut from the full content
+        let range = args.range();
+        let len = content.len() as u64;
+        let (start, end) = match (range.offset(), range.size()) {
+            (Some(offset), Some(size)) => (offset.min(len), offset.saturating_add(size).min(len)),
+            (Some(offset), None) => (offset.min(len), len),
+            (None, Some(size)) => (len.saturating_sub(size), len),
+            (None, None) => (0, len),
+        };
+        content.truncate(end as usize);
+        content.drain(..start as usize);
+        
+        Ok((RpRead::new(), oio::Cursor::from(content)))
+    }
+    
+    async fn write(&self, path: &str, args: OpWrite) -> OpendalResult<(RpWrite, Self::Writer)> {
+        let path = Self::normalize_path(path).map_err(Self::convert_error)?;
+        let content_type = args
+            .content_type()
+            .map(|ct| ct.to_string())
+            .unwrap_or_else(|| Self::guess_content_type(&path));
+        
+        let writer = RawStorageWriter {
+            backend: self.backend.clone(),
+            path,
+            content_type,
+        };
+        Ok((RpWrite::new(), oio::OneShotWriter::new(writer)))
+    }
+    
+    async fn delete(&self, path: &str, _: OpDelete) -> OpendalResult<RpDelete> {
+        // Deleting a missing path succeeds, as OpenDAL expects
+        match self.backend.delete_file(&Self::normalize_path(path).map_err(Self::convert_error)?).await {
+            Ok(()) | Err(crate::error::StorageError::NotFound(_)) => Ok(RpDelete::default()),
+            Err(e) => Err(Self::convert_error(e)),
+        }
+    }
+    
+    async fn list(&self, path: &str, args: OpList) -> OpendalResult<(RpList, Self::Lister)> {
+        // The backend lists everything below the directory; the hierarchy
+        // lister folds nested entries into their first-level directory
+        let entries = self.backend
+            .list_files_with_metadata(&Self::normalize_path(path).map_err(Self::convert_error)?, ListOrder::Path)
+            .await
+            .map_err(Self::convert_error)?
+            .iter()
+            .map(|metadata| oio::Entry::new(&Self::entry_path(metadata), Self::convert_metadata(metadata)))
+            .collect::<Vec<_>>();
+        
+        let lister = RawStorageLister { entries: entries.into_iter() };
+        Ok((RpList::default(), oio::HierarchyLister::new(lister, path, args.recursive())))
+    }
+}
+
+/// Writer that stores the buffered content through the backend on close
+pub struct RawStorageWriter {
+    backend: Arc<RawStorageBackend>,
+    path: String,
+    content_type: String,
+}
+
+#[async_trait]
+impl oio::OneShotWrite for RawStorageWriter {
+    async fn write_once(&self, bs: &dyn oio::WriteBuf) -> OpendalResult<()> {
+        let content = bs.bytes(bs.remaining()).to_vec();
+        self.backend
+            .write_file(&self.path, content, &self.content_type)
+            .await
+            .map(|_| ())
+            .map_err(RawStorageAdapter::convert_error)
+    }
+}
+
+/// Lister over entries fetched from the backend in one query
+pub struct RawStorageLister {
+    entries: IntoIter<oio::Entry>,
+}
+
+impl oio::List for RawStorageLister {
+    fn poll_next(&mut self, _: &mut Context<'_>) -> Poll<OpendalResult<Option<oio::Entry>>> {
+        Poll::Ready(Ok(self.entries.next()))
+    }
 }
 
 /// Create an OpenDAL operator from a RawStorageBackend
 ///
-/// This function creates a new OpenDAL operator that integrates with
-/// our RawStorageBackend to provide tenant isolation.
+/// Reads, writes, stats, deletes and listings go through the backend, so the
+/// operator is scoped to the backend's tenant.
 ///
-/// Currently, this is a placeholder implementation that returns a Memory-backed
-/// OpenDAL operator. In a real implementation, we would need to:
-///
-/// 1. Create a custom OpenDAL service or layer that intercepts operations
-/// 2. Redirect these operations to our RawStorageBackend
-/// 3. Handle metadata appropriately
+/// The operator is wrapped with the enabled `layers`.
+pub fn create_raw_operator(
+    backend: Arc<RawStorageBackend>,
+    layers: &OperatorLayers,
+) -> Operator {
+    let op = OperatorBuilder::new(RawStorageAdapter::new(backend)).finish();
+    apply_layers(op, layers)
+}
+
+/// Wrap an operator with the enabled layers
 ///
-/// However, implementing a full custom OpenDAL adapter requires deep knowledge
-/// of the OpenDAL internals and is beyond the scope of this initial implementation.
-pub fn create_raw_operator(backend: Arc<RawStorageBackend>) -> OpendalResult<Operator> {
-    // Create an adapter wrapping the backend
-    let _adapter = RawStorageAdapter::new(backend);
-    
-    // For now, use a Memory backend since custom adapters are complex
-    let memory = Memory::default();
-    let op = Operator::new(memory)?.finish();
-    
-    // Add logging layer for debugging
-    #[cfg(debug_assertions)]
-    let op = op.layer(LoggingLayer::default());
-    
-    // This is a placeholder - in a real implementation, we'd create a custom
-    // adapter that delegates operations to the RawStorageBackend
-    Ok(op)
+/// Retries are innermost so that logging and metrics see every attempt's
+/// final outcome once.
+fn apply_layers(op: Operator, layers: &OperatorLayers) -> Operator {
+    let op = if layers.retry {
+        op.layer(RetryLayer::new())
+    } else {
+        op
+    };
+    
+    let op = if layers.logging {
+        op.layer(LoggingLayer::default())
+    } else {
+        op
+    };
+    
+    #[cfg(feature = "metrics")]
+    let op = if layers.metrics {
+        op.layer(opendal::layers::MetricsLayer)
+    } else {
+        op
+    };
+    
+    op
 }
 
 #[cfg(test)]
@@ -128,7 +338,7 @@ mod tests {
             .acquire_timeout(Duration::from_secs(3))
             .connect(&db_url)
             .await
-            .map_err(|e| crate::error::StorageError::Database(e))?;
+            .map_err(crate::error::StorageError::Database)?;
             
         Ok(Arc::new(pool))
     }
@@ -145,19 +355,21 @@ mod tests {
         .bind(Utc::now())
         .fetch_one(pool)
         .await
-        .map_err(|e| crate::error::StorageError::Database(e))?;
+        .map_err(crate::error::StorageError::Database)?;
         
         Ok(user_id)
     }
     
     #[test]
     async fn test_path_normalization() {
-        assert_eq!(RawStorageAdapter::normalize_path("test.md"), "/test.md");
-        assert_eq!(RawStorageAdapter::normalize_path("/test.md"), "/test.md");
-        assert_eq!(RawStorageAdapter::normalize_path("/path/"), "/path");
-        assert_eq!(RawStorageAdapter::normalize_path("path/"), "/path");
-        assert_eq!(RawStorageAdapter::normalize_path("/"), "/");
-        assert_eq!(RawStorageAdapter::normalize_path(""), "/");
+        assert_eq!(RawStorageAdapter::normalize_path("test.md").unwrap(), "/test.md");
+        assert_eq!(RawStorageAdapter::normalize_path("/test.md").unwrap(), "/test.md");
+        assert_eq!(RawStorageAdapter::normalize_path("/path/").unwrap(), "/path");
+        assert_eq!(RawStorageAdapter::normalize_path("path/").unwrap(), "/path");
+        assert_eq!(RawStorageAdapter::normalize_path("/").unwrap(), "/");
+        assert_eq!(RawStorageAdapter::normalize_path("").unwrap(), "/");
+        assert_eq!(RawStorageAdapter::normalize_path("/a/./b//c.md").unwrap(), "/a/b/c.md");
+        assert!(RawStorageAdapter::normalize_path("/a/../../b").is_err());
     }
     
     #[test]
@@ -196,11 +408,11 @@ mod tests {
         ));
         
         // Create an operator from the backend
-        let operator = create_raw_operator(backend).expect("Failed to create operator");
+        let operator = create_raw_operator(backend, &OperatorLayers::default());
         
-        // Verify the operator was created
+        // Verify the operator is backed by the adapter
         let info = operator.info();
-        assert_eq!(info.scheme().to_string(), "memory", "Default placeholder operator should use memory scheme");
+        assert_eq!(info.scheme().to_string(), "marble", "Operator should use the raw storage adapter");
         
         // Clean up
         let _ = sqlx::query("DELETE FROM users WHERE id = $1")
@@ -208,4 +420,145 @@ mod tests {
             .execute(&*db_pool)
             .await;
     }
+    
+    /// Create an operator for a fresh user, returning the user id for cleanup
+    async fn setup_test_operator(
+        pool: &Arc<sqlx::PgPool>,
+        username: &str,
+    ) -> (i32, Operator, tempfile::TempDir) {
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = $1)")
+            .bind(username)
+            .execute(&**pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1")
+            .bind(username)
+            .execute(&**pool)
+            .await;
+        
+        let user_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, password_hash, created_at) 
+             VALUES ($1, $2, $3) 
+             RETURNING id"
+        )
+        .bind(username)
+        .bind("test_password_hash")
+        .bind(Utc::now())
+        .fetch_one(&**pool)
+        .await
+        .expect("Failed to create test user");
+        
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let content_hasher = ContentHasher::new(
+            create_hash_storage(&StorageConfig::new_fs(temp_dir.path().to_path_buf())).unwrap()
+        );
+        let backend = Arc::new(RawStorageBackend::new(user_id, pool.clone(), content_hasher));
+        let operator = create_raw_operator(backend, &OperatorLayers::default());
+        
+        (user_id, operator, temp_dir)
+    }
+    
+    async fn cleanup_test_users(pool: &Arc<sqlx::PgPool>, user_ids: &[i32]) {
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = ANY($1)")
+            .bind(user_ids)
+            .execute(&**pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(user_ids)
+            .execute(&**pool)
+            .await;
+    }
+    
+    #[test]
+    async fn test_raw_operator_round_trip() {
+        let db_pool = match setup_test_db().await {
+            Ok(pool) => pool,
+            Err(_) => {
+                println!("Skipping test - no test database available");
+                return;
+            }
+        };
+        
+        let (user_id, operator, _temp_dir) = setup_test_operator(&db_pool, "adapter_round_trip_user").await;
+        
+        // Write and read back through the operator
+        operator.write("/a.md", b"# A".to_vec()).await.expect("Failed to write");
+        let content = operator.read("/a.md").await.expect("Failed to read");
+        assert_eq!(content, b"# A");
+        
+        // The content lands in the tenant's file rows
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE user_id = $1 AND NOT is_deleted")
+            .bind(user_id)
+            .fetch_one(&*db_pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        
+        // Ranged reads are cut from the stored content
+        let range = operator.read_with("/a.md").range(2..3).await.expect("Failed to read range");
+        assert_eq!(range, b"A");
+        
+        // Stat reports the stored metadata
+        let metadata = operator.stat("/a.md").await.expect("Failed to stat");
+        assert!(metadata.is_file());
+        assert_eq!(metadata.content_length(), 3);
+        assert_eq!(metadata.content_type(), Some("text/markdown"));
+        
+        // Delete removes the file and tolerates missing paths
+        operator.delete("/a.md").await.expect("Failed to delete");
+        assert!(!operator.is_exist("/a.md").await.unwrap());
+        operator.delete("/a.md").await.expect("Deleting a missing file should succeed");
+        let result = operator.read("/a.md").await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+        
+        cleanup_test_users(&db_pool, &[user_id]).await;
+    }
+    
+    #[test]
+    async fn test_raw_operator_list_and_isolation() {
+        let db_pool = match setup_test_db().await {
+            Ok(pool) => pool,
+            Err(_) => {
+                println!("Skipping test - no test database available");
+                return;
+            }
+        };
+        
+        let (user_id, operator, _temp_dir) = setup_test_operator(&db_pool, "adapter_list_user").await;
+        let (other_id, other_operator, _other_temp_dir) = setup_test_operator(&db_pool, "adapter_list_other_user").await;
+        
+        operator.write("notes/today.md", b"today".to_vec()).await.expect("Failed to write");
+        operator.write("notes/archive/old.md", b"old".to_vec()).await.expect("Failed to write");
+        operator.write("top.md", b"top".to_vec()).await.expect("Failed to write");
+        
+        // Listing shows direct children, folding nested files into directories
+        let mut names: Vec<String> = operator
+            .list("notes/")
+            .await
+            .expect("Failed to list")
+            .into_iter()
+            .map(|entry| entry.path().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["notes/archive/", "notes/today.md"]);
+        
+        // Recursive listing reaches nested files
+        let recursive: Vec<String> = operator
+            .list_with("notes/")
+            .recursive(true)
+            .await
+            .expect("Failed to list recursively")
+            .into_iter()
+            .map(|entry| entry.path().to_string())
+            .collect();
+        assert!(recursive.contains(&"notes/archive/old.md".to_string()));
+        
+        // Directories stat as directories
+        assert!(operator.stat("notes/").await.unwrap().is_dir());
+        
+        // The other tenant sees none of it
+        assert!(!other_operator.is_exist("top.md").await.unwrap());
+        assert!(other_operator.list("/").await.unwrap().is_empty());
+        
+        cleanup_test_users(&db_pool, &[user_id, other_id]).await;
+    }
 }
\ No newline at end of file
@@ -3,18 +3,54 @@
 //! This module provides a raw storage backend that uses the database to map
 //! file paths to content hashes, enforcing tenant isolation.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use marble_db::models::File;
-use marble_db::repositories::{FileRepository, SqlxFileRepository, Repository};
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use marble_db::models::{File, Folder};
+use marble_db::Error as DbError;
+use marble_db::repositories::{
+    FilePropertyRepository, FileRepository, FileVersionRepository, FolderPlaceholder,
+    FolderRepository, ListOrder, PropertyChange, Repository, SqlxFilePropertyRepository,
+    SqlxFileRepository, SqlxFileVersionRepository, SqlxFolderRepository, SqlxUserRepository,
+    UserRepository,
+};
 use sqlx::postgres::PgPool;
+use tracing::warn;
 
-use crate::api::tenant::FileMetadata;
+use crate::api::tenant::{epoch_millis, DeadProperty, DedupOutcome, EntryKind, FileMetadata, VersionInfo};
 
+use crate::config::DirectoryStrategy;
 use crate::error::{StorageError, StorageResult};
 use crate::hash::hash_content;
+use crate::services::access::AccessTracker;
 use crate::services::hasher::ContentHasher;
 
+/// Name of the file marking a directory under the placeholder strategy
+const PLACEHOLDER_NAME: &str = ".dir";
+
+/// Directory a `.dir` placeholder stands for, `None` for regular files
+fn placeholder_directory(file: &File) -> Option<&str> {
+    if file.content_type != "application/vnd.marble.directory" {
+        return None;
+    }
+    file.display_path
+        .strip_suffix(PLACEHOLDER_NAME)
+        .and_then(|directory| directory.strip_suffix('/'))
+}
+
+/// Most bytes of a file's content that are indexed for search
+///
+/// Postgres refuses a tsvector above 1 MiB, and the start of a note is what
+/// searches are after.
+pub const MAX_INDEXED_BYTES: usize = 256 * 1024;
+
+/// Text of a file as indexed for search, cut to [`MAX_INDEXED_BYTES`]
+pub(crate) fn searchable_text(content: &[u8]) -> String {
+    String::from_utf8_lossy(&content[..content.len().min(MAX_INDEXED_BYTES)]).into_owned()
+}
+
 /// Raw storage backend that integrates with the database
 pub struct RawStorageBackend {
     /// User ID for tenant isolation
@@ -28,6 +64,21 @@ pub struct RawStorageBackend {
     
     /// Content hasher for hash computation and storage
     content_hasher: ContentHasher,
+    
+    /// Repository for the folder tree, which records every directory
+    folder_repo: Arc<SqlxFolderRepository>,
+    
+    /// Repository for dead properties set with PROPPATCH
+    property_repo: Arc<SqlxFilePropertyRepository>,
+    
+    /// Repository for the contents recorded on every write
+    version_repo: Arc<SqlxFileVersionRepository>,
+    
+    /// How empty directories are represented
+    directory_strategy: DirectoryStrategy,
+    
+    /// Records reads as accesses when access tracking is enabled
+    access_tracker: Option<Arc<AccessTracker>>,
 }
 
 impl RawStorageBackend {
@@ -38,62 +89,218 @@ impl RawStorageBackend {
         content_hasher: ContentHasher,
     ) -> Self {
         let file_repo = Arc::new(SqlxFileRepository::new(db_pool.clone()));
+        let folder_repo = Arc::new(SqlxFolderRepository::new(db_pool.clone()));
+        let property_repo = Arc::new(SqlxFilePropertyRepository::new(db_pool.clone()));
+        let version_repo = Arc::new(SqlxFileVersionRepository::new(db_pool.clone()));
         
         Self {
             user_id,
             db_pool,
             file_repo,
             content_hasher,
+            folder_repo,
+            property_repo,
+            version_repo,
+            directory_strategy: DirectoryStrategy::default(),
+            access_tracker: None,
         }
     }
     
+    /// Choose how empty directories are represented
+    pub fn with_directory_strategy(mut self, strategy: DirectoryStrategy) -> Self {
+        self.directory_strategy = strategy;
+        self
+    }
+    
+    /// Record reads in `last_accessed_at`, throttled by the tracker
+    pub fn with_access_tracker(mut self, tracker: Arc<AccessTracker>) -> Self {
+        self.access_tracker = Some(tracker);
+        self
+    }
+    
+    /// Enable or disable case-insensitive path lookups
+    pub fn with_case_insensitive_paths(mut self, enabled: bool) -> Self {
+        self.file_repo = Arc::new(
+            SqlxFileRepository::new(self.db_pool.clone()).with_case_insensitive_paths(enabled),
+        );
+        self.folder_repo = Arc::new(
+            SqlxFolderRepository::new(self.db_pool.clone()).with_case_insensitive_paths(enabled),
+        );
+        self
+    }
+    
     /// Get a file by path from the database
     async fn get_file_by_path(&self, path: &str) -> StorageResult<Option<File>> {
         match self.file_repo.find_by_path(self.user_id, path).await {
             Ok(file) => Ok(file),
-            Err(e) => Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Key of a directory in the `folders` table: no trailing slash, `/` for the root
+    fn directory_key(path: &str) -> String {
+        let trimmed = path.trim_end_matches('/');
+        if trimmed.is_empty() {
+            "/".to_string()
+        } else if trimmed.starts_with('/') {
+            trimmed.to_string()
+        } else {
+            format!("/{}", trimmed)
+        }
+    }
+    
+    /// Get a tracked directory by path
+    ///
+    /// Folder rows are the only record of directories that is read, under
+    /// either strategy; `.dir` placeholders are written but never consulted.
+    async fn get_tracked_directory(&self, path: &str) -> StorageResult<Option<Folder>> {
+        match self.folder_repo.find_by_path(self.user_id, &Self::directory_key(path)).await {
+            Ok(folder) => Ok(folder.filter(|folder| !folder.is_deleted)),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Metadata of the tracked directory at `path`
+    async fn get_directory_metadata(&self, path: &str) -> StorageResult<Option<FileMetadata>> {
+        Ok(self
+            .get_tracked_directory(path)
+            .await?
+            .map(Self::directory_to_metadata))
+    }
+    
+    /// List tracked directories below a directory
+    async fn list_tracked_directories(&self, dir_path: &str) -> StorageResult<Vec<Folder>> {
+        match self.folder_repo.list_by_folder_path(self.user_id, dir_path, false).await {
+            Ok(folders) => Ok(folders),
+            Err(e) => Err(StorageError::from(e)),
         }
     }
     
     /// Get metadata for a file
+    ///
+    /// Tracked directories are found as well.
     pub async fn get_file_metadata(&self, path: &str) -> StorageResult<FileMetadata> {
-        use crate::api::tenant::FileMetadata;
-        
         // Look up the file in the database
-        let file = self.get_file_by_path(path).await?
-            .ok_or_else(|| StorageError::NotFound(format!("File not found: {}", path)))?;
-            
-        // Check if the file is marked as deleted
-        if file.is_deleted {
-            return Err(StorageError::NotFound(format!("File is deleted: {}", path)));
+        let file = self.get_file_by_path(path).await?;
+        
+        match file {
+            Some(file) if !file.is_deleted => self.file_to_resolved_metadata(file).await,
+            file => match self.get_directory_metadata(path).await? {
+                Some(directory) => Ok(directory),
+                None if file.is_some() => {
+                    Err(StorageError::NotFound(format!("File is deleted: {}", path)))
+                }
+                None => Err(StorageError::NotFound(format!("File not found: {}", path))),
+            },
         }
+    }
+    
+    /// Get metadata for many files in one query, aligned with `paths`
+    ///
+    /// Missing and deleted files yield `None` at their position.
+    pub async fn get_files_metadata(&self, paths: &[String]) -> StorageResult<Vec<Option<FileMetadata>>> {
+        let files = match self.file_repo.find_by_paths(self.user_id, paths, false).await {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
         
+        let by_key: HashMap<String, File> = files
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect();
+        
+        // Paths may repeat, so clone rather than take from the map
+        let mut metadata: Vec<Option<FileMetadata>> = paths
+            .iter()
+            .map(|path| {
+                by_key
+                    .get(&self.file_repo.path_key(path))
+                    .cloned()
+                    .map(Self::file_to_metadata)
+            })
+            .collect();
+        
+        for (entry, path) in metadata.iter_mut().zip(paths) {
+            match by_key.get(&self.file_repo.path_key(path)) {
+                Some(file) if file.is_alias() => {
+                    *entry = Some(self.listed_metadata(file.clone()).await?);
+                }
+                Some(_) => {}
+                None => {
+                    *entry = self.get_directory_metadata(path).await?;
+                }
+            }
+        }
+        
+        Ok(metadata)
+    }
+    
+    /// Build metadata from a database file record
+    fn file_to_metadata(file: File) -> FileMetadata {
         // Determine if it's a directory based on the content type
         let is_directory = 
             file.content_type == "application/vnd.marble.directory" || 
-            path.ends_with('/') || 
-            path == "/";
-            
-        // Get the last modified time from the database
-        let last_modified = file.updated_at
-            .timestamp_millis()
-            .try_into()
-            .ok();
+            file.path.ends_with('/') || 
+            file.path == "/";
             
-        // Create the metadata
-        let metadata = FileMetadata {
-            path: file.path,
+        // The last modified time is the record's updated_at, written on every change
+        FileMetadata {
+            path: file.display_path,
             size: file.size as u64,
             content_type: file.content_type,
             is_directory,
-            last_modified,
+            last_modified: Some(epoch_millis(&file.updated_at)),
+            last_accessed: file.last_accessed_at.as_ref().map(epoch_millis),
             content_hash: Some(file.content_hash),
+        }
+    }
+    
+    /// Follow an alias to the file it points at; other files are returned as is
+    async fn resolve_alias(&self, file: File) -> StorageResult<File> {
+        let Some(target_path) = file.alias_target else {
+            return Ok(file);
         };
         
+        match self.get_file_by_path(&target_path).await? {
+            Some(target) if !target.is_deleted => Ok(target),
+            _ => Err(StorageError::NotFound(format!("Alias target not found: {}", target_path))),
+        }
+    }
+    
+    /// Build metadata for a file, reporting an alias's target under the alias's own path
+    async fn file_to_resolved_metadata(&self, file: File) -> StorageResult<FileMetadata> {
+        let display_path = file.display_path.clone();
+        let mut metadata = Self::file_to_metadata(self.resolve_alias(file).await?);
+        metadata.path = display_path;
         Ok(metadata)
     }
     
-    /// Create a new file in the database
+    /// Metadata for a listed file; an alias whose target is gone is listed as it is
+    async fn listed_metadata(&self, file: File) -> StorageResult<FileMetadata> {
+        if !file.is_alias() {
+            return Ok(Self::file_to_metadata(file));
+        }
+        
+        match self.file_to_resolved_metadata(file.clone()).await {
+            Err(StorageError::NotFound(_)) => Ok(Self::file_to_metadata(file)),
+            result => result,
+        }
+    }
+    
+    /// Build metadata from a tracked directory record
+    fn directory_to_metadata(directory: Folder) -> FileMetadata {
+        FileMetadata {
+            path: directory.path,
+            size: 0,
+            content_type: "application/vnd.marble.directory".to_string(),
+            is_directory: true,
+            last_modified: Some(epoch_millis(&directory.created_at)),
+            last_accessed: None,
+            content_hash: None,
+        }
+    }
+    
+    /// Create a new file in the database, recording its content as the first version
     async fn create_file(
         &self,
         path: &str,
@@ -109,9 +316,9 @@ impl RawStorageBackend {
             size,
         );
         
-        match self.file_repo.create(&file).await {
+        match self.file_repo.create_versioned(&file).await {
             Ok(file) => Ok(file),
-            Err(e) => Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => Err(StorageError::from(e)),
         }
     }
     
@@ -131,13 +338,134 @@ impl RawStorageBackend {
         
         match self.file_repo.update(file).await {
             Ok(file) => Ok(file),
-            Err(e) => Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => Err(StorageError::from(e)),
         }
     }
     
-    /// Read a file from raw storage
-    pub async fn read_file(&self, path: &str) -> StorageResult<Vec<u8>> {
-        // First, lookup the file in the database to get the content hash
+    /// Update an existing file in the database, recording its new content as a version
+    async fn update_file_versioned(
+        &self,
+        file: &mut File,
+        content_hash: &str,
+        content_type: &str,
+        size: i32,
+    ) -> StorageResult<File> {
+        file.update_content(
+            content_hash.to_string(),
+            content_type.to_string(),
+            size,
+        );
+        
+        match self.file_repo.update_versioned(file).await {
+            Ok(file) => Ok(file),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Whether a live file of this user references the content hash
+    pub async fn references_content(&self, content_hash: &str) -> StorageResult<bool> {
+        let files = match self.file_repo.find_by_content_hash(content_hash).await {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        Ok(files.iter().any(|f| f.user_id == self.user_id && !f.is_deleted))
+    }
+    
+    /// Count the live files of this user, directory placeholders included
+    pub async fn file_count(&self) -> StorageResult<i64> {
+        match self.file_repo.count_by_user(self.user_id, false).await {
+            Ok(count) => Ok(count),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Total size in bytes of the user's live files
+    pub async fn total_size(&self) -> StorageResult<i64> {
+        self.file_repo
+            .total_size_by_user(self.user_id, false)
+            .await
+            .map_err(StorageError::from)
+    }
+    
+    /// Size of the live file a write to a path replaces, 0 if there is none
+    ///
+    /// Alias rows count as zero bytes, as in `total_size`; a write through a
+    /// live alias replaces its target, so the target's size is returned.
+    pub async fn live_file_size(&self, path: &str) -> StorageResult<i64> {
+        let file = match self.get_file_by_path(path).await? {
+            Some(file) if !file.is_deleted => file,
+            _ => return Ok(0),
+        };
+        
+        match self.resolve_alias(file).await {
+            Ok(file) => Ok(i64::from(file.size)),
+            Err(StorageError::NotFound(_)) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Storage quota of the user in bytes, unlimited if `None`
+    pub async fn quota_bytes(&self) -> StorageResult<Option<i64>> {
+        let user = SqlxUserRepository::new(self.db_pool.clone())
+            .find_by_id(self.user_id)
+            .await
+            .map_err(StorageError::from)?;
+        Ok(user.and_then(|user| user.quota_bytes))
+    }
+    
+    /// Write settings of the user: default content type and newline normalization
+    ///
+    /// `None` in either position means the storage-wide default applies.
+    pub async fn write_settings(&self) -> StorageResult<(Option<String>, Option<bool>)> {
+        let user = SqlxUserRepository::new(self.db_pool.clone())
+            .find_by_id(self.user_id)
+            .await
+            .map_err(StorageError::from)?;
+        Ok(user.map_or((None, None), |user| (user.default_content_type, user.normalize_newlines)))
+    }
+    
+    /// Recompute size and hash of every live file from its stored content
+    ///
+    /// Rows whose size or hash disagree with the content are updated; content
+    /// whose actual hash differs from its key is stored again under that hash.
+    /// Directory placeholders and files with missing content are skipped.
+    /// Returns the number of rows fixed.
+    pub async fn refresh_metadata(&self) -> StorageResult<u64> {
+        let files = match self.file_repo.list_by_folder_path(self.user_id, "/", false).await {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        let mut fixed = 0;
+        for mut file in files {
+            if file.content_type == "application/vnd.marble.directory"
+                || !self.content_hasher.content_exists(&file.content_hash).await?
+            {
+                continue;
+            }
+            
+            let content = self.content_hasher.get_content(&file.content_hash).await?;
+            let content_hash = self.content_hasher.compute_hash(&content)?;
+            let size = content.len() as i32;
+            if content_hash == file.content_hash && size == file.size {
+                continue;
+            }
+            
+            if content_hash != file.content_hash {
+                self.content_hasher.store_content(&content).await?;
+            }
+            
+            let content_type = file.content_type.clone();
+            self.update_file(&mut file, &content_hash, &content_type, size).await?;
+            fixed += 1;
+        }
+        
+        Ok(fixed)
+    }
+    
+    /// Look up the hash of the content a live file, or an alias's target, holds
+    async fn content_hash_of(&self, path: &str) -> StorageResult<String> {
         let file = self.get_file_by_path(path).await?
             .ok_or_else(|| StorageError::NotFound(format!("File not found: {}", path)))?;
         
@@ -145,9 +473,34 @@ impl RawStorageBackend {
         if file.is_deleted {
             return Err(StorageError::NotFound(format!("File is deleted: {}", path)));
         }
-            
-        // Now get the content using the hash
-        self.content_hasher.get_content(&file.content_hash).await
+        
+        // Aliases read their target's content
+        let file = self.resolve_alias(file).await?;
+        self.record_access(file.id).await;
+        Ok(file.content_hash)
+    }
+    
+    /// Record a read of a file if tracking is on and its throttle window has passed
+    ///
+    /// Failing to record an access never fails the read.
+    async fn record_access(&self, file_id: i32) {
+        if let Some(tracker) = &self.access_tracker {
+            if tracker.should_record(file_id) {
+                let _ = self.file_repo.touch_access(file_id).await;
+            }
+        }
+    }
+    
+    /// Read a file from raw storage
+    pub async fn read_file(&self, path: &str) -> StorageResult<Vec<u8>> {
+        let content_hash = self.content_hash_of(path).await?;
+        self.content_hasher.get_content(&content_hash).await
+    }
+    
+    /// Stream a file from raw storage in chunks
+    pub async fn read_file_stream(&self, path: &str) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+        let content_hash = self.content_hash_of(path).await?;
+        self.content_hasher.stream_content(&content_hash).await
     }
     
     /// Write a file to raw storage
@@ -156,39 +509,429 @@ impl RawStorageBackend {
         path: &str,
         content: Vec<u8>,
         content_type: &str,
-    ) -> StorageResult<()> {
+    ) -> StorageResult<DedupOutcome> {
         // Hash the content
         let content_hash = hash_content(&content)?;
         let size = content.len() as i32;
         
+        // Only this tenant's own files count, so the outcome reveals nothing about others
+        let referenced = self.file_repo
+            .references_content(self.user_id, &content_hash)
+            .await
+            .map_err(StorageError::from)?;
+        
         // Store the content using the content hasher (which ensures deduplication)
-        self.content_hasher.store_content(&content).await?;
+        self.content_hasher.store_content_if_absent(&content).await?;
         
-        // Check if the file already exists in the database
-        let existing_file = self.get_file_by_path(path).await?;
+        // Check if the file already exists in the database; writes through a
+        // live alias update its target, while a deleted alias becomes a plain file
+        let existing_file = match self.get_file_by_path(path).await? {
+            Some(file) if file.is_alias() && !file.is_deleted => Some(self.resolve_alias(file).await?),
+            Some(mut file) => {
+                file.alias_target = None;
+                // A pin and properties do not outlive the file they were set on
+                if file.is_deleted {
+                    file.content_type_override = None;
+                    if let Err(e) = self.property_repo.delete_by_file(file.id).await {
+                        return Err(StorageError::from(e));
+                    }
+                }
+                Some(file)
+            }
+            None => None,
+        };
         
-        // Update or create the file metadata in the database
-        if let Some(mut file) = existing_file {
-            self.update_file(&mut file, &content_hash, content_type, size)
-                .await?;
+        // Update or create the file metadata in the database; every write
+        // becomes a version in the same transaction, so earlier contents stay restorable
+        let file = if let Some(mut file) = existing_file {
+            self.update_file_versioned(&mut file, &content_hash, content_type, size)
+                .await?
         } else {
             self.create_file(path, &content_hash, content_type, size)
-                .await?;
+                .await?
+        };
+        self.update_search_index(&file, &content).await;
+        
+        Ok(DedupOutcome::from_referenced(referenced))
+    }
+    
+    /// Keep the search index of a file in step with its content
+    ///
+    /// Markdown files are indexed and any other file is dropped from the index.
+    /// The index is derived data, so a failure to update it is logged rather
+    /// than failing the change that triggered it.
+    async fn update_search_index(&self, file: &File, content: &[u8]) {
+        let result = if file.is_markdown() {
+            self.file_repo.index_content(file.id, &searchable_text(content)).await
+        } else {
+            self.file_repo.remove_from_index(file.id).await.map(|_| ())
+        };
+        
+        if let Err(e) = result {
+            warn!("Failed to update the search index of {}: {}", file.path, e);
+        }
+    }
+    
+    /// Update the search index of a file whose content is not at hand
+    async fn reindex(&self, file: &File) {
+        if file.is_alias() {
+            return;
+        }
+        if !file.is_markdown() {
+            return self.update_search_index(file, &[]).await;
+        }
+        
+        match self.content_hasher.get_content(&file.content_hash).await {
+            Ok(content) => self.update_search_index(file, &content).await,
+            Err(e) => warn!("Failed to read {} for the search index: {}", file.path, e),
         }
+    }
+    
+    /// Find live markdown files whose content matches a plain-text query, best matches first
+    pub async fn search(&self, query: &str) -> StorageResult<Vec<FileMetadata>> {
+        match self.file_repo.search(self.user_id, query).await {
+            Ok(files) => Ok(files.into_iter().map(Self::file_to_metadata).collect()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Get the live file at a path, following an alias to its target
+    async fn get_live_file(&self, path: &str) -> StorageResult<File> {
+        match self.get_file_by_path(path).await? {
+            Some(file) if !file.is_deleted => self.resolve_alias(file).await,
+            _ => Err(StorageError::NotFound(format!("File not found: {}", path))),
+        }
+    }
+    
+    /// List the recorded versions of a file, newest first
+    pub async fn list_versions(&self, path: &str) -> StorageResult<Vec<VersionInfo>> {
+        let file = self.get_live_file(path).await?;
+        
+        match self.version_repo.list_versions(file.id).await {
+            Ok(versions) => Ok(versions.into_iter().map(VersionInfo::from).collect()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Make the content of an earlier version of a file current again
+    pub async fn restore_version(&self, path: &str, version_id: i32) -> StorageResult<()> {
+        let file = self.get_live_file(path).await?;
+        
+        let restored = match self.version_repo.restore_version(file.id, version_id).await {
+            Ok(restored) => restored,
+            Err(DbError::NotFound(_)) => return Err(StorageError::NotFound(format!("Version {} of {} not found", version_id, path))),
+            Err(e) => return Err(StorageError::from(e)),
+        };
         
+        self.reindex(&restored).await;
         Ok(())
     }
     
+    /// Create an alias at `path` that follows the file at `target`
+    ///
+    /// Reads, metadata and writes of the alias go to the target, so the two
+    /// paths stay in sync. An alias of an alias follows the final target.
+    pub async fn create_alias(&self, path: &str, target: &str) -> StorageResult<()> {
+        let target_file = match self.get_file_by_path(target).await? {
+            Some(file) if !file.is_deleted => self.resolve_alias(file).await?,
+            _ => return Err(StorageError::NotFound(format!("Alias target not found: {}", target))),
+        };
+        
+        if target_file.content_type == "application/vnd.marble.directory" {
+            return Err(StorageError::Validation(format!("Cannot alias a directory: {}", target)));
+        }
+        
+        let alias = File::new_alias(
+            self.user_id,
+            path.to_string(),
+            target_file.path.clone(),
+            target_file.content_type.clone(),
+        );
+        
+        // Replace a deleted row at the alias path, but never a live file
+        let result = match self.get_file_by_path(path).await? {
+            Some(existing) if !existing.is_deleted => {
+                return Err(StorageError::Validation(format!("Destination already exists: {}", path)));
+            }
+            Some(existing) => {
+                let mut replaced = alias;
+                replaced.id = existing.id;
+                self.file_repo.update(&replaced).await
+            }
+            None => self.file_repo.create(&alias).await,
+        };
+        
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// The content type pinned on a live file, or an alias's target
+    pub async fn content_type_override(&self, path: &str) -> StorageResult<Option<String>> {
+        match self.get_file_by_path(path).await? {
+            Some(file) if !file.is_deleted => Ok(self.resolve_alias(file).await?.content_type_override),
+            _ => Ok(None),
+        }
+    }
+    
+    /// Pin the content type of a live file, or clear the pin with `None`
+    ///
+    /// The reported content type follows the pin right away; clearing it
+    /// goes back to `guessed_type`.
+    pub async fn set_content_type_override(
+        &self,
+        path: &str,
+        content_type: Option<&str>,
+        guessed_type: &str,
+    ) -> StorageResult<()> {
+        let mut file = match self.get_file_by_path(path).await? {
+            Some(file) if !file.is_deleted => self.resolve_alias(file).await?,
+            _ => return Err(StorageError::NotFound(format!("File not found: {}", path))),
+        };
+        
+        if file.content_type == "application/vnd.marble.directory" {
+            return Err(StorageError::Validation(format!("Cannot set the content type of a directory: {}", path)));
+        }
+        
+        file.content_type_override = content_type.map(str::to_string);
+        file.content_type = content_type.unwrap_or(guessed_type).to_string();
+        
+        match self.file_repo.update(&file).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// The live file at a path, following aliases to their target
+    async fn live_file(&self, path: &str) -> StorageResult<File> {
+        match self.get_file_by_path(path).await? {
+            Some(file) if !file.is_deleted => self.resolve_alias(file).await,
+            _ => Err(StorageError::NotFound(format!("File not found: {}", path))),
+        }
+    }
+    
+    /// Get the dead properties of a file or directory
+    ///
+    /// Directories without a folder row have no properties; missing files are
+    /// not found.
+    pub async fn get_file_properties(&self, path: &str) -> StorageResult<Vec<DeadProperty>> {
+        let file = match self.live_file(path).await {
+            Ok(file) => file,
+            Err(StorageError::NotFound(_)) if self.directory_exists(path).await? => {
+                let Some(folder) = self.get_tracked_directory(path).await? else {
+                    return Ok(Vec::new());
+                };
+                return match self.property_repo.list_by_folder(folder.id).await {
+                    Ok(properties) => Ok(properties.into_iter().map(DeadProperty::from).collect()),
+                    Err(e) => Err(StorageError::from(e)),
+                };
+            }
+            Err(e) => return Err(e),
+        };
+        
+        match self.property_repo.list_by_file(file.id).await {
+            Ok(properties) => Ok(properties.into_iter().map(DeadProperty::from).collect()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Get the dead properties of many files and directories, aligned with `paths`
+    ///
+    /// Takes two queries for the files and two for the directories. Missing
+    /// paths yield no properties.
+    pub async fn get_files_properties(&self, paths: &[String]) -> StorageResult<Vec<Vec<DeadProperty>>> {
+        let files = match self.file_repo.find_by_paths(self.user_id, paths, false).await {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        // Properties of an alias belong to its target
+        let mut file_ids: HashMap<String, i32> = HashMap::new();
+        for file in files {
+            let key = file.path.clone();
+            match self.resolve_alias(file).await {
+                Ok(file) => {
+                    file_ids.insert(key, file.id);
+                }
+                Err(StorageError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        
+        let ids: Vec<i32> = file_ids.values().copied().collect();
+        let properties = match self.property_repo.list_by_files(&ids).await {
+            Ok(properties) => properties,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        let mut by_file: HashMap<i32, Vec<DeadProperty>> = HashMap::new();
+        for property in properties {
+            by_file.entry(property.file_id).or_default().push(DeadProperty::from(property));
+        }
+        
+        // Paths that are not files may be directories
+        let directory_keys: Vec<String> = paths
+            .iter()
+            .filter(|path| !file_ids.contains_key(&self.file_repo.path_key(path)))
+            .map(|path| Self::directory_key(path))
+            .collect();
+        let folders = match self.folder_repo.find_by_paths(self.user_id, &directory_keys).await {
+            Ok(folders) => folders,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        let folder_ids: HashMap<String, i32> = folders.into_iter().map(|folder| (folder.path, folder.id)).collect();
+        
+        let ids: Vec<i32> = folder_ids.values().copied().collect();
+        let properties = match self.property_repo.list_by_folders(&ids).await {
+            Ok(properties) => properties,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        let mut by_folder: HashMap<i32, Vec<DeadProperty>> = HashMap::new();
+        for property in properties {
+            by_folder.entry(property.folder_id).or_default().push(DeadProperty::from(property));
+        }
+        
+        Ok(paths
+            .iter()
+            .map(|path| {
+                let properties = match file_ids.get(&self.file_repo.path_key(path)) {
+                    Some(id) => by_file.get(id),
+                    None => folder_ids
+                        .get(&self.folder_repo.path_key(&Self::directory_key(path)))
+                        .and_then(|id| by_folder.get(id)),
+                };
+                properties.cloned().unwrap_or_default()
+            })
+            .collect())
+    }
+    
+    /// Apply changes to the dead properties of a file or directory, all or nothing
+    ///
+    /// `content_type` is `(type, pin)` as for `set_content_type_override` and
+    /// is applied in the same transaction; directories have no content type.
+    /// A directory implied by the files below it gets its folder row first.
+    pub async fn patch_file_properties(
+        &self,
+        path: &str,
+        changes: &[PropertyChange],
+        content_type: Option<(&str, Option<&str>)>,
+    ) -> StorageResult<()> {
+        let file = match self.live_file(path).await {
+            Err(StorageError::NotFound(_)) if self.directory_exists(path).await? => {
+                return self.patch_directory_properties(path, changes, content_type.is_some()).await;
+            }
+            result => result?,
+        };
+        
+        if file.content_type == "application/vnd.marble.directory" {
+            return Err(StorageError::Validation(format!("Cannot set properties of a directory: {}", path)));
+        }
+        
+        match self.property_repo.apply(file.id, changes, content_type).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Apply changes to the dead properties of a directory, all or nothing
+    async fn patch_directory_properties(&self, path: &str, changes: &[PropertyChange], sets_content_type: bool) -> StorageResult<()> {
+        let key = Self::directory_key(path);
+        if sets_content_type || key == "/" {
+            return Err(StorageError::Validation(format!("Cannot set this property of a directory: {}", path)));
+        }
+        
+        let folder = match self.get_tracked_directory(&key).await? {
+            Some(folder) => folder,
+            None => {
+                self.create_folders(&key, None).await?;
+                self.get_tracked_directory(&key)
+                    .await?
+                    .ok_or_else(|| StorageError::NotFound(format!("Directory not found: {}", path)))?
+            }
+        };
+        
+        match self.property_repo.apply_to_folder(folder.id, changes).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
     /// Check if a file exists
     pub async fn file_exists(&self, path: &str) -> StorageResult<bool> {
         let file = self.get_file_by_path(path).await?;
         
         // The file exists if it's in the database and not marked as deleted
-        Ok(file.map(|f| !f.is_deleted).unwrap_or(false))
+        if file.map(|f| !f.is_deleted).unwrap_or(false) {
+            return Ok(true);
+        }
+        
+        Ok(self.get_directory_metadata(path).await?.is_some())
+    }
+    
+    /// Tell whether a path is a file or a directory without loading its metadata
+    ///
+    /// Directories are recognised the way `directory_exists` does, so one
+    /// implied by the files below it counts.
+    pub async fn entry_kind(&self, path: &str) -> StorageResult<Option<EntryKind>> {
+        if path.trim_end_matches('/').is_empty() {
+            return Ok(Some(EntryKind::Directory));
+        }
+        
+        match self.file_repo.find_content_type(self.user_id, path).await {
+            Ok(Some(content_type)) if content_type == "application/vnd.marble.directory" => {
+                return Ok(Some(EntryKind::Directory));
+            }
+            Ok(Some(_)) => return Ok(Some(EntryKind::File)),
+            Ok(None) => {}
+            Err(e) => return Err(StorageError::from(e)),
+        }
+        
+        if self.directory_exists(path).await? {
+            Ok(Some(EntryKind::Directory))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    /// Check if a directory exists
+    ///
+    /// The root always exists. Other directories exist if they are tracked or
+    /// have live files below them.
+    pub async fn directory_exists(&self, dir_path: &str) -> StorageResult<bool> {
+        let dir_path = dir_path.trim_end_matches('/');
+        if dir_path.is_empty() {
+            return Ok(true);
+        }
+        
+        if self.get_tracked_directory(dir_path).await?.is_some() {
+            return Ok(true);
+        }
+        
+        match self.file_repo.has_files_below(self.user_id, dir_path).await {
+            Ok(exists) => Ok(exists),
+            Err(e) => Err(StorageError::from(e)),
+        }
     }
     
     /// Delete a file
     pub async fn delete_file(&self, path: &str) -> StorageResult<()> {
+        // A directory goes with its placeholder, if it has one
+        if let Some(directory) = self.get_tracked_directory(path).await? {
+            if let Err(e) = self.folder_repo.mark_deleted(directory.id).await {
+                return Err(StorageError::from(e));
+            }
+            
+            let placeholder_path = format!("{}/{}", directory.path, PLACEHOLDER_NAME);
+            if let Some(placeholder) = self.get_file_by_path(&placeholder_path).await?.filter(|file| !file.is_deleted) {
+                if let Err(e) = self.file_repo.mark_deleted(placeholder.id).await {
+                    return Err(StorageError::from(e));
+                }
+            }
+            return Ok(());
+        }
+        
         // First, lookup the file in the database
         let file = self.get_file_by_path(path).await?
             .ok_or_else(|| StorageError::NotFound(format!("File not found: {}", path)))?;
@@ -196,7 +939,7 @@ impl RawStorageBackend {
         // Mark the file as deleted in the database
         match self.file_repo.mark_deleted(file.id).await {
             Ok(_) => {},
-            Err(e) => return Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => return Err(StorageError::from(e)),
         }
         
         // Note: We don't delete the actual content from hash storage since other files
@@ -205,11 +948,159 @@ impl RawStorageBackend {
         Ok(())
     }
     
+    /// List the deleted files of the user, most recently deleted first
+    pub async fn list_trash(&self) -> StorageResult<Vec<FileMetadata>> {
+        // Directory placeholders are not user files and are never listed
+        match self.file_repo.list_deleted(self.user_id).await {
+            Ok(files) => Ok(files
+                .into_iter()
+                .filter(|file| placeholder_directory(file).is_none())
+                .map(Self::file_to_metadata)
+                .collect()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// The deleted file at a path, `None` if the path is not in the trash
+    async fn get_trashed_file(&self, path: &str) -> StorageResult<Option<File>> {
+        Ok(self
+            .get_file_by_path(path)
+            .await?
+            .filter(|file| file.is_deleted && placeholder_directory(file).is_none()))
+    }
+    
+    /// Size of the deleted file at a path, 0 if the path is not in the trash
+    pub async fn trashed_file_size(&self, path: &str) -> StorageResult<i64> {
+        Ok(self
+            .get_trashed_file(path)
+            .await?
+            .map_or(0, |file| i64::from(file.size)))
+    }
+    
+    /// Bring a deleted file back at its original path
+    pub async fn restore_file(&self, path: &str) -> StorageResult<()> {
+        let Some(file) = self.get_trashed_file(path).await? else {
+            return Err(StorageError::NotFound(format!("File not in trash: {}", path)));
+        };
+        
+        match self.file_repo.restore(file.id).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
+    /// Delete many files in one transaction
+    ///
+    /// Returns, aligned with `paths`, whether each file was deleted; missing
+    /// and already deleted files yield `false` without affecting the others.
+    pub async fn delete_files(&self, paths: &[String]) -> StorageResult<Vec<bool>> {
+        let files = match self.file_repo.find_by_paths(self.user_id, paths, false).await {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        let ids: HashMap<String, i32> = files
+            .into_iter()
+            .map(|file| (file.path, file.id))
+            .collect();
+        
+        // A path listed twice is only deleted once
+        let mut pending = Vec::new();
+        let mut positions = Vec::with_capacity(paths.len());
+        for path in paths {
+            let position = ids.get(&self.file_repo.path_key(path)).map(|id| {
+                pending.iter().position(|p| p == id).unwrap_or_else(|| {
+                    pending.push(*id);
+                    pending.len() - 1
+                })
+            });
+            positions.push(position);
+        }
+        
+        let marked = match self.file_repo.mark_deleted_many(&pending).await {
+            Ok(marked) => marked,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        Ok(positions
+            .into_iter()
+            .map(|position| position.is_some_and(|i| marked[i]))
+            .collect())
+    }
+    
+    /// Delete a directory and everything below it
+    ///
+    /// Marks every live file below the directory deleted in one query, and the
+    /// folder rows of the directory and below it as well. Returns the number of
+    /// files deleted.
+    pub async fn delete_directory(&self, dir_path: &str) -> StorageResult<u64> {
+        let dir_path = Self::directory_key(dir_path);
+        
+        let deleted = match self.file_repo.mark_deleted_by_prefix(self.user_id, &dir_path).await {
+            Ok(deleted) => deleted,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        // Folder rows are kept under either strategy, so they go regardless
+        let mut folders = match self.folder_repo.list_by_folder_path(self.user_id, &dir_path, false).await {
+            Ok(folders) => folders,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        match self.folder_repo.find_by_path(self.user_id, &dir_path).await {
+            Ok(folder) => folders.extend(folder),
+            Err(e) => return Err(StorageError::from(e)),
+        }
+        for folder in folders.into_iter().filter(|folder| !folder.is_deleted) {
+            if let Err(e) = self.folder_repo.mark_deleted(folder.id).await {
+                return Err(StorageError::from(e));
+            }
+        }
+        
+        Ok(deleted)
+    }
+    
+    /// Move a file to a new path
+    ///
+    /// This is a single metadata update: the content hash and timestamps are kept,
+    /// and no content is read or written. A soft-deleted record at the destination
+    /// is removed first so it does not block the move.
+    pub async fn move_file(&self, from: &str, to: &str) -> StorageResult<()> {
+        let file = self.get_file_by_path(from).await?
+            .filter(|f| !f.is_deleted)
+            .ok_or_else(|| StorageError::NotFound(format!("File not found: {}", from)))?;
+        
+        // With case-insensitive paths a move may only change the casing
+        if let Some(existing) = self.get_file_by_path(to).await?.filter(|f| f.id != file.id) {
+            if !existing.is_deleted {
+                return Err(StorageError::Validation(format!("Destination already exists: {}", to)));
+            }
+            
+            if let Err(e) = self.file_repo.delete_permanently(existing.id).await {
+                return Err(StorageError::from(e));
+            }
+        }
+        
+        // Renaming in place keeps the file's id, hash and creation time
+        match self.file_repo.rename(self.user_id, &file.path, to).await {
+            // A new extension can make a file markdown or stop it being one
+            Ok(moved) => {
+                if moved.is_markdown() != file.is_markdown() {
+                    self.reindex(&moved).await;
+                }
+                Ok(())
+            }
+            Err(DbError::NotFound(_)) => Err(StorageError::NotFound(format!("File not found: {}", from))),
+            Err(DbError::AlreadyExists(_)) => Err(StorageError::Validation(format!("Destination already exists: {}", to))),
+            Err(e) => Err(StorageError::from(e)),
+        }
+    }
+    
     /// Create a directory
     ///
-    /// Creates an empty directory by adding a special placeholder file to the database.
-    /// Since we don't actually have physical directories (only files), this is
-    /// represented as a metadata-only entry in the database with a special content type.
+    /// The directory and its ancestors get rows in the `folders` table, which
+    /// is what lookups and listings read. Under the placeholder strategy an
+    /// empty directory also gets a `.dir` marker file, kept internal to this
+    /// backend.
     pub async fn create_directory(&self, dir_path: &str) -> StorageResult<()> {
         // Normalize the directory path to ensure it ends with a slash
         let normalized_dir = if dir_path.ends_with('/') || dir_path == "" {
@@ -221,67 +1112,42 @@ impl RawStorageBackend {
         // Check if the directory already exists by checking for any files with this prefix
         let files = match self.file_repo.list_by_folder_path(self.user_id, &normalized_dir, false).await {
             Ok(files) => files,
-            Err(e) => return Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => return Err(StorageError::from(e)),
         };
         
         // If there are already files with this prefix, the directory "exists"
+        // and only its folder rows may be missing
         if !files.is_empty() {
-            return Ok(());
+            return self.create_folders(&normalized_dir, None).await;
         }
         
-        // Create the parent directories if needed
-        let path_parts: Vec<&str> = normalized_dir
-            .trim_matches('/')
-            .split('/')
-            .collect();
-            
-        // Create parent directories (if needed)
-        if path_parts.len() > 1 {
-            let mut parent_path = String::from("/");
-            for i in 0..path_parts.len() - 1 {
-                parent_path.push_str(path_parts[i]);
-                parent_path.push('/');
-                
-                // Check if this parent directory exists
-                let parent_files = match self.file_repo.list_by_folder_path(self.user_id, &parent_path, false).await {
-                    Ok(files) => files,
-                    Err(e) => return Err(StorageError::Storage(format!("Database error: {}", e))),
-                };
-                
-                // If it doesn't exist, create a placeholder
-                if parent_files.is_empty() {
-                    let placeholder_path = format!("{}/.dir", parent_path.trim_end_matches('/'));
-                    let content_hash = hash_content(&[])?;
-                    self.create_file(
-                        &placeholder_path,
-                        &content_hash,
-                        "application/vnd.marble.directory",
-                        0,
-                    ).await?;
-                }
-            }
+        // Under the implicit strategy the folder rows alone record the directory
+        if self.directory_strategy == DirectoryStrategy::Implicit {
+            return self.create_folders(&normalized_dir, None).await;
         }
         
-        // Create an empty directory placeholder
-        let placeholder_path = if normalized_dir == "/" {
-            "/.dir".to_string()
-        } else {
-            format!("{}/.dir", normalized_dir.trim_end_matches('/'))
-        };
-        
-        // Create a zero-length file with a special content type to mark it as a directory
+        // Placeholders mark the directory and each empty parent, written in
+        // the same transaction as their folder rows
         let content_hash = hash_content(&[])?;
-        self.create_file(
-            &placeholder_path,
-            &content_hash,
-            "application/vnd.marble.directory",
-            0,
-        ).await?;
-        
-        Ok(())
+        let placeholder = FolderPlaceholder {
+            name: PLACEHOLDER_NAME,
+            content_hash: &content_hash,
+            content_type: "application/vnd.marble.directory",
+        };
+        self.create_folders(&normalized_dir, Some(placeholder)).await
+    }
+    
+    /// Record a directory and its ancestors in the `folders` table
+    async fn create_folders(&self, dir_path: &str, placeholder: Option<FolderPlaceholder<'_>>) -> StorageResult<()> {
+        match self.folder_repo.create_path(self.user_id, dir_path, placeholder).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(StorageError::from(e)),
+        }
     }
     
     /// List files in a directory
+    ///
+    /// Tracked subdirectories are listed as well; `.dir` placeholders never are.
     pub async fn list_files(&self, dir_path: &str) -> StorageResult<Vec<String>> {
         // Normalize the directory path
         let normalized_dir = if !dir_path.ends_with('/') && !dir_path.is_empty() {
@@ -293,17 +1159,62 @@ impl RawStorageBackend {
         // List files from the database
         let files = match self.file_repo.list_by_folder_path(self.user_id, &normalized_dir, false).await {
             Ok(files) => files,
-            Err(e) => return Err(StorageError::Storage(format!("Database error: {}", e))),
+            Err(e) => return Err(StorageError::from(e)),
         };
         
-        // Extract just the filenames
-        let file_paths = files
+        // Extract just the filenames; directories come from the folder rows
+        let mut file_paths: Vec<String> = files
             .into_iter()
-            .map(|file| file.path)
+            .filter(|file| placeholder_directory(file).is_none())
+            .map(|file| file.display_path)
             .collect();
         
+        file_paths.extend(
+            self.list_tracked_directories(&normalized_dir)
+                .await?
+                .into_iter()
+                .map(|directory| directory.path),
+        );
+        
         Ok(file_paths)
     }
+    
+    /// List files in a directory with their metadata, in the given order
+    ///
+    /// Tracked directories follow the files; `.dir` placeholders are never
+    /// listed.
+    pub async fn list_files_with_metadata(
+        &self,
+        dir_path: &str,
+        order: ListOrder,
+    ) -> StorageResult<Vec<FileMetadata>> {
+        let normalized_dir = if !dir_path.ends_with('/') && !dir_path.is_empty() {
+            format!("{}/", dir_path)
+        } else {
+            dir_path.to_string()
+        };
+        
+        let files = match self.file_repo
+            .list_by_folder_path_sorted(self.user_id, &normalized_dir, false, order)
+            .await
+        {
+            Ok(files) => files,
+            Err(e) => return Err(StorageError::from(e)),
+        };
+        
+        let mut metadata = Vec::with_capacity(files.len());
+        for file in files.into_iter().filter(|file| placeholder_directory(file).is_none()) {
+            metadata.push(self.listed_metadata(file).await?);
+        }
+        metadata.extend(
+            self.list_tracked_directories(&normalized_dir)
+                .await?
+                .into_iter()
+                .map(Self::directory_to_metadata),
+        );
+        
+        Ok(metadata)
+    }
 }
 
 #[cfg(test)]
@@ -315,7 +1226,6 @@ mod tests {
     use tempfile::tempdir;
     use crate::backends::hash::create_hash_storage;
     use crate::config::StorageConfig;
-    use crate::api::tenant::FileMetadata;
     
     async fn setup_test_db() -> Result<Arc<PgPool>, StorageError> {
         // This should be skipped if no test database is available
@@ -327,7 +1237,7 @@ mod tests {
             .acquire_timeout(Duration::from_secs(3))
             .connect(&db_url)
             .await
-            .map_err(|e| StorageError::Database(e))?;
+            .map_err(StorageError::Database)?;
             
         Ok(Arc::new(pool))
     }
@@ -344,7 +1254,7 @@ mod tests {
         .bind(Utc::now())
         .fetch_one(pool)
         .await
-        .map_err(|e| StorageError::Database(e))?;
+        .map_err(StorageError::Database)?;
         
         Ok(user_id)
     }
@@ -361,6 +1271,8 @@ mod tests {
         
         // Clear the files and users table
         let _ = sqlx::query("DELETE FROM files").execute(&*pool).await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id IN (SELECT id FROM users WHERE username = 'raw_storage_test_user')")
+            .execute(&*pool).await;
         let _ = sqlx::query("DELETE FROM users WHERE username = 'raw_storage_test_user'")
             .execute(&*pool).await;
         
@@ -431,7 +1343,8 @@ mod tests {
         // Test listing files in a directory
         let files = backend.list_files("/parent/child").await.expect("Failed to list directory");
         assert!(files.contains(&"/parent/child/file.txt".to_string()), "Directory listing should include the file");
-        assert!(files.contains(&"/parent/child/grandchild/.dir".to_string()), "Directory listing should include subdirectory placeholder");
+        assert!(files.contains(&"/parent/child/grandchild".to_string()), "Directory listing should include the subdirectory");
+        assert!(!files.iter().any(|path| path.ends_with("/.dir")), "Directory listing should not include placeholders");
         
         // Test getting metadata
         let metadata = backend.get_file_metadata("/parent/child/.dir").await.expect("Failed to get directory metadata");
@@ -444,6 +1357,10 @@ mod tests {
             .bind(user_id)
             .execute(&*backend.db_pool)
             .await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
     }
     
     #[tokio::test]
@@ -485,6 +1402,61 @@ mod tests {
             .bind(user_id)
             .execute(&*backend.db_pool)
             .await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
+    }
+    
+    #[tokio::test]
+    async fn test_last_modified_from_updated_at() {
+        // Setup the test environment
+        let (backend, user_id, _temp_dir) = match setup_test_backend().await {
+            Ok(setup) => setup,
+            Err(_) => {
+                // Skip the test if setup fails
+                return;
+            }
+        };
+        
+        backend.write_file("/dated.md", b"First".to_vec(), "text/markdown")
+            .await
+            .expect("Failed to write file");
+        let set_updated_at = |time: sqlx::types::chrono::DateTime<Utc>| {
+            sqlx::query("UPDATE files SET updated_at = $1 WHERE user_id = $2 AND path = '/dated.md'")
+                .bind(time)
+                .bind(user_id)
+                .execute(&*backend.db_pool)
+        };
+        
+        // The stored time is reported to the millisecond
+        let written_at = sqlx::types::chrono::DateTime::parse_from_rfc3339("2024-03-01T12:00:00.123Z").unwrap().to_utc();
+        set_updated_at(written_at).await.expect("Failed to set updated_at");
+        let metadata = backend.get_file_metadata("/dated.md").await.expect("Failed to get metadata");
+        assert_eq!(metadata.last_modified, Some(1_709_294_400_123));
+        
+        // Times before the epoch are reported as the epoch rather than dropped
+        let before_epoch = sqlx::types::chrono::DateTime::parse_from_rfc3339("1960-01-01T00:00:00Z").unwrap().to_utc();
+        set_updated_at(before_epoch).await.expect("Failed to set updated_at");
+        let metadata = backend.get_file_metadata("/dated.md").await.expect("Failed to get metadata");
+        assert_eq!(metadata.last_modified, Some(0));
+        
+        // Rewriting the file moves the time forward
+        backend.write_file("/dated.md", b"Second".to_vec(), "text/markdown")
+            .await
+            .expect("Failed to rewrite file");
+        let metadata = backend.get_file_metadata("/dated.md").await.expect("Failed to get metadata");
+        assert!(metadata.last_modified.unwrap() > 1_709_294_400_123);
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
     }
     
     #[tokio::test]
@@ -549,9 +1521,62 @@ mod tests {
             .bind(user_id)
             .execute(&*backend.db_pool)
             .await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
         let _ = sqlx::query("DELETE FROM users WHERE id = $1")
             .bind(user_id)
             .execute(&*backend.db_pool)
             .await;
     }
+    
+    #[tokio::test]
+    async fn test_move_file_preserves_metadata() {
+        // Setup the test environment
+        let (backend, user_id, _temp_dir) = match setup_test_backend().await {
+            Ok(setup) => setup,
+            Err(_) => {
+                // Skip the test if setup fails
+                return;
+            }
+        };
+        
+        let content = b"Content that should survive a rename".to_vec();
+        backend.write_file("/notes/draft.md", content.clone(), "text/markdown")
+            .await
+            .expect("Failed to write file");
+        
+        let before = backend.get_file_metadata("/notes/draft.md").await.expect("Failed to get metadata");
+        
+        // Rename in place
+        backend.move_file("/notes/draft.md", "/notes/final.md").await.expect("Failed to move file");
+        
+        // The old path is gone and the new path has the same hash and timestamp
+        assert!(!backend.file_exists("/notes/draft.md").await.unwrap());
+        let after = backend.get_file_metadata("/notes/final.md").await.expect("Failed to get metadata");
+        assert_eq!(after.content_hash, before.content_hash);
+        assert_eq!(after.last_modified, before.last_modified);
+        assert_eq!(after.size, before.size);
+        
+        let read_content = backend.read_file("/notes/final.md").await.expect("Failed to read file");
+        assert_eq!(read_content, content);
+        
+        // Moving onto an existing file is rejected
+        backend.write_file("/notes/other.md", b"Other".to_vec(), "text/markdown")
+            .await
+            .expect("Failed to write file");
+        let result = backend.move_file("/notes/final.md", "/notes/other.md").await;
+        assert!(matches!(result, Err(StorageError::Validation(_))));
+        
+        // Clean up
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM folders WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*backend.db_pool)
+            .await;
+    }
 }
@@ -3,10 +3,11 @@
 //! This module provides utilities for working with user IDs, including
 //! conversion between UUID and database ID.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use marble_db::repositories::UserChangeHook;
 use sqlx::postgres::PgPool;
-use sqlx::types::chrono::Utc;
 use uuid::Uuid;
 
 use crate::error::{StorageError, StorageResult};
@@ -33,10 +34,101 @@ pub async fn uuid_to_db_id(
     }
 }
 
+/// Cache of UUID to database user ID lookups
+///
+/// Entries must be invalidated when a user is updated, deleted or re-created,
+/// otherwise a stale id could route writes to the wrong tenant. Every
+/// invalidation bumps a generation counter; a lookup only populates the cache
+/// if no invalidation happened while it was querying the database, so an
+/// in-flight stale result can never overwrite a newer invalidation.
+#[derive(Debug, Default)]
+pub struct UserIdCache {
+    state: RwLock<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    ids: HashMap<Uuid, i32>,
+    generation: u64,
+}
+
+impl UserIdCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Look up the database ID for a UUID, querying the database on a miss
+    pub async fn get(&self, pool: &PgPool, uuid: Uuid) -> StorageResult<i32> {
+        let generation = {
+            let state = self.state.read().unwrap();
+            if let Some(id) = state.ids.get(&uuid) {
+                return Ok(*id);
+            }
+            state.generation
+        };
+        
+        let id = uuid_to_db_id(pool, uuid).await?;
+        self.populate(uuid, id, generation);
+        
+        Ok(id)
+    }
+    
+    /// Drop the cached ID for a UUID
+    pub fn invalidate(&self, uuid: Uuid) {
+        let mut state = self.state.write().unwrap();
+        state.ids.remove(&uuid);
+        state.generation += 1;
+    }
+    
+    /// Drop all cached IDs
+    pub fn clear(&self) {
+        let mut state = self.state.write().unwrap();
+        state.ids.clear();
+        state.generation += 1;
+    }
+    
+    /// Number of cached IDs
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().ids.len()
+    }
+    
+    /// Whether no IDs are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Create a hook that invalidates this cache when a user changes
+    ///
+    /// Register it with `SqlxUserRepository::with_change_hook` so user updates
+    /// and deletes keep the cache consistent.
+    pub fn invalidation_hook(self: &Arc<Self>) -> UserChangeHook {
+        let cache = Arc::downgrade(self);
+        Arc::new(move |uuid| {
+            if let Some(cache) = cache.upgrade() {
+                cache.invalidate(uuid);
+            }
+        })
+    }
+    
+    /// Store a looked-up ID unless the cache was invalidated since `generation`
+    fn populate(&self, uuid: Uuid, id: i32, generation: u64) -> bool {
+        let mut state = self.state.write().unwrap();
+        if state.generation != generation {
+            return false;
+        }
+        state.ids.insert(uuid, id);
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use marble_db::models::User;
+    use marble_db::repositories::{Repository, SqlxUserRepository, UserRepository};
     use sqlx::postgres::PgPoolOptions;
+    use sqlx::types::chrono::Utc;
     use std::time::Duration;
     
     async fn setup_test_db() -> Result<Arc<PgPool>, StorageError> {
@@ -49,7 +141,7 @@ mod tests {
             .acquire_timeout(Duration::from_secs(3))
             .connect(&db_url)
             .await
-            .map_err(|e| StorageError::Database(e))?;
+            .map_err(StorageError::Database)?;
             
         Ok(Arc::new(pool))
     }
@@ -99,4 +191,57 @@ mod tests {
             .execute(&*pool)
             .await;
     }
+    
+    #[test]
+    fn test_stale_populate_is_discarded() {
+        let cache = UserIdCache::new();
+        let uuid = Uuid::new_v4();
+        
+        // A lookup starts, then the user is invalidated before it completes
+        let generation = cache.state.read().unwrap().generation;
+        cache.invalidate(uuid);
+        
+        // The in-flight result must not be cached
+        assert!(!cache.populate(uuid, 1, generation));
+        assert!(!cache.state.read().unwrap().ids.contains_key(&uuid));
+        
+        // A lookup started after the invalidation is cached
+        let generation = cache.state.read().unwrap().generation;
+        assert!(cache.populate(uuid, 2, generation));
+        assert_eq!(cache.state.read().unwrap().ids.get(&uuid), Some(&2));
+    }
+    
+    #[tokio::test]
+    async fn test_cache_invalidation_after_recreate() {
+        let pool = match setup_test_db().await {
+            Ok(pool) => pool,
+            Err(_) => {
+                println!("Skipping test - no test database available");
+                return;
+            }
+        };
+        
+        let _ = sqlx::query("DELETE FROM users WHERE username = 'uuid_cache_user'")
+            .execute(&*pool)
+            .await;
+        
+        let cache = Arc::new(UserIdCache::new());
+        let repo = SqlxUserRepository::new(pool.clone()).with_change_hook(cache.invalidation_hook());
+        
+        let mut user = User::new("uuid_cache_user".to_string(), "hash".to_string());
+        let original = repo.create(&user).await.unwrap();
+        assert_eq!(cache.get(&pool, original.uuid).await.unwrap(), original.id);
+        
+        // Re-create the tenant under the same UUID
+        repo.delete(original.id).await.unwrap();
+        user.uuid = original.uuid;
+        let recreated = repo.create(&user).await.unwrap();
+        assert_ne!(recreated.id, original.id);
+        
+        // The repository invalidated the cache, so the new id is resolved
+        assert_eq!(cache.get(&pool, original.uuid).await.unwrap(), recreated.id);
+        
+        // Clean up
+        let _ = repo.delete(recreated.id).await;
+    }
 }
@@ -1,3 +1,4 @@
+use std::env;
 use std::path::PathBuf;
 
 use crate::error::{StorageError, StorageResult};
@@ -24,6 +25,28 @@ pub struct S3Config {
     pub secret_key: Option<String>,
 }
 
+/// Configuration for Azure Blob Storage backend
+#[derive(Clone, Debug)]
+pub struct AzureConfig {
+    /// Storage account name
+    pub account: String,
+    
+    /// Blob container name
+    pub container: String,
+    
+    /// Blob endpoint (optional, defaults to `https://<account>.blob.core.windows.net`)
+    pub endpoint: Option<String>,
+    
+    /// Path prefix for storage within the container
+    pub prefix: Option<String>,
+    
+    /// Shared account key (if not using a SAS token or environment credentials)
+    pub account_key: Option<String>,
+    
+    /// Shared access signature token (if not using an account key)
+    pub sas_token: Option<String>,
+}
+
 /// Configuration for local filesystem storage backend (used for development/testing)
 #[derive(Clone, Debug)]
 pub struct FileSystemConfig {
@@ -37,15 +60,95 @@ pub enum StorageBackend {
     /// S3 storage backend
     S3(S3Config),
     
+    /// Azure Blob Storage backend
+    Azure(AzureConfig),
+    
     /// Local filesystem storage backend (development/testing)
     FileSystem(FileSystemConfig),
 }
 
+/// How directories without files are represented
+///
+/// Every directory has a row in the `folders` table, and storage reads
+/// directories from there alone. The strategies only differ in whether a
+/// marker file is written as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DirectoryStrategy {
+    /// An empty directory also gets a zero-length `.dir` file row, which is
+    /// internal to storage and never listed, trashed or restored
+    #[default]
+    Placeholder,
+    
+    /// The `folders` table alone records directories, keeping `files` free
+    /// of placeholder rows
+    Implicit,
+}
+
+/// Compression applied to content objects in hash storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Zstandard at the given level; 3 is zstd's default, higher levels
+    /// compress better and more slowly
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// Parse `zstd` or `zstd:<level>`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.split_once(':') {
+            None if value == "zstd" => Some(Compression::Zstd { level: 3 }),
+            Some(("zstd", level)) => level.trim().parse().ok().map(|level| Compression::Zstd { level }),
+            _ => None,
+        }
+    }
+}
+
+/// OpenDAL layers wrapped around raw storage operators
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OperatorLayers {
+    /// Retry temporary failures with exponential backoff
+    pub retry: bool,
+    
+    /// Log every operation
+    pub logging: bool,
+    
+    /// Record operation metrics through the `metrics` facade; requires the
+    /// `metrics` feature
+    pub metrics: bool,
+}
+
+impl Default for OperatorLayers {
+    fn default() -> Self {
+        Self {
+            retry: true,
+            logging: true,
+            metrics: false,
+        }
+    }
+}
+
 /// Configuration for all storage aspects
 #[derive(Clone, Debug)]
 pub struct StorageConfig {
     /// Storage backend configuration
     pub backend: StorageBackend,
+    
+    /// Layers applied to raw storage operators
+    pub layers: OperatorLayers,
+    
+    /// Maximum number of content writes in flight at once; unbounded if unset
+    pub max_concurrent_writes: Option<usize>,
+    
+    /// Maximum number of content reads in flight at once; unbounded if unset
+    pub max_concurrent_reads: Option<usize>,
+    
+    /// Bytes read ahead per chunk when streaming content,
+    /// [`DEFAULT_READ_CHUNK_SIZE`](crate::services::hasher::DEFAULT_READ_CHUNK_SIZE) if unset
+    pub read_chunk_size: Option<usize>,
+    
+    /// Compression of newly stored content; content is stored as is if unset
+    pub compression: Option<Compression>,
 }
 
 impl StorageConfig {
@@ -67,6 +170,37 @@ impl StorageConfig {
                 access_key,
                 secret_key,
             }),
+            layers: OperatorLayers::default(),
+            max_concurrent_writes: None,
+            max_concurrent_reads: None,
+            read_chunk_size: None,
+            compression: None,
+        }
+    }
+
+    /// Create a new configuration for Azure Blob Storage
+    pub fn new_azure(
+        account: String,
+        container: String,
+        endpoint: Option<String>,
+        prefix: Option<String>,
+        account_key: Option<String>,
+        sas_token: Option<String>,
+    ) -> Self {
+        Self {
+            backend: StorageBackend::Azure(AzureConfig {
+                account,
+                container,
+                endpoint,
+                prefix,
+                account_key,
+                sas_token,
+            }),
+            layers: OperatorLayers::default(),
+            max_concurrent_writes: None,
+            max_concurrent_reads: None,
+            read_chunk_size: None,
+            compression: None,
         }
     }
 
@@ -74,11 +208,125 @@ impl StorageConfig {
     pub fn new_fs(hash_base_path: PathBuf) -> Self {
         Self {
             backend: StorageBackend::FileSystem(FileSystemConfig { hash_base_path }),
+            layers: OperatorLayers::default(),
+            max_concurrent_writes: None,
+            max_concurrent_reads: None,
+            read_chunk_size: None,
+            compression: None,
+        }
+    }
+
+    /// Set the layers applied to raw storage operators
+    pub fn with_layers(mut self, layers: OperatorLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Read ahead `chunk_size` bytes at a time when streaming content
+    pub fn with_read_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.read_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Compress content before storing it
+    ///
+    /// Objects stay keyed by the hash of the uncompressed content, so
+    /// deduplication is unaffected, and content stored before compression was
+    /// enabled remains readable.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Bound the number of content writes and reads in flight at once
+    pub fn with_max_concurrency(mut self, writes: Option<usize>, reads: Option<usize>) -> Self {
+        self.max_concurrent_writes = writes;
+        self.max_concurrent_reads = reads;
+        self
+    }
+
+    /// Create a configuration from environment variables
+    ///
+    /// Uses S3 when `STORAGE_S3_BUCKET` is set (with `STORAGE_S3_REGION`,
+    /// `STORAGE_S3_ENDPOINT`, `STORAGE_S3_PREFIX`, `STORAGE_S3_ACCESS_KEY` and
+    /// `STORAGE_S3_SECRET_KEY`), then Azure when `STORAGE_AZURE_CONTAINER` is
+    /// set (with `STORAGE_AZURE_ACCOUNT`, `STORAGE_AZURE_ENDPOINT`,
+    /// `STORAGE_AZURE_PREFIX`, `STORAGE_AZURE_ACCOUNT_KEY` and
+    /// `STORAGE_AZURE_SAS_TOKEN`), otherwise the filesystem at `STORAGE_PATH`
+    /// (default `./data`). `STORAGE_MAX_CONCURRENT_WRITES` and
+    /// `STORAGE_MAX_CONCURRENT_READS` bound content operations in flight,
+    /// `STORAGE_READ_CHUNK_SIZE` sets the streaming read-ahead in bytes, and
+    /// `STORAGE_COMPRESSION` (`zstd` or `zstd:<level>`) compresses new content.
+    pub fn from_env() -> Self {
+        let config = match (env::var("STORAGE_S3_BUCKET"), env::var("STORAGE_AZURE_CONTAINER")) {
+            (Ok(bucket), _) => Self::new_s3(
+                env::var("STORAGE_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                bucket,
+                env::var("STORAGE_S3_ENDPOINT").ok(),
+                env::var("STORAGE_S3_PREFIX").ok(),
+                env::var("STORAGE_S3_ACCESS_KEY").ok(),
+                env::var("STORAGE_S3_SECRET_KEY").ok(),
+            ),
+            (Err(_), Ok(container)) => Self::new_azure(
+                env::var("STORAGE_AZURE_ACCOUNT").unwrap_or_default(),
+                container,
+                env::var("STORAGE_AZURE_ENDPOINT").ok(),
+                env::var("STORAGE_AZURE_PREFIX").ok(),
+                env::var("STORAGE_AZURE_ACCOUNT_KEY").ok(),
+                env::var("STORAGE_AZURE_SAS_TOKEN").ok(),
+            ),
+            (Err(_), Err(_)) => Self::new_fs(
+                env::var("STORAGE_PATH")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| PathBuf::from("./data")),
+            ),
+        };
+        
+        let config = config.with_max_concurrency(
+            env::var("STORAGE_MAX_CONCURRENT_WRITES").ok().and_then(|s| s.trim().parse().ok()),
+            env::var("STORAGE_MAX_CONCURRENT_READS").ok().and_then(|s| s.trim().parse().ok()),
+        );
+        
+        let config = match env::var("STORAGE_READ_CHUNK_SIZE").ok().and_then(|s| s.trim().parse().ok()) {
+            Some(chunk_size) => config.with_read_chunk_size(chunk_size),
+            None => config,
+        };
+        
+        match env::var("STORAGE_COMPRESSION").ok().and_then(|s| Compression::parse(&s)) {
+            Some(compression) => config.with_compression(compression),
+            None => config,
         }
     }
 
     /// Validate the configuration
     pub fn validate(&self) -> StorageResult<()> {
+        if self.layers.metrics && !cfg!(feature = "metrics") {
+            return Err(StorageError::Configuration(
+                "Metrics layer requires the `metrics` feature".to_string(),
+            ));
+        }
+        
+        if self.max_concurrent_writes == Some(0) || self.max_concurrent_reads == Some(0) {
+            return Err(StorageError::Configuration(
+                "Concurrency limits must allow at least one operation".to_string(),
+            ));
+        }
+        
+        if self.read_chunk_size == Some(0) {
+            return Err(StorageError::Configuration(
+                "Read chunk size must be at least one byte".to_string(),
+            ));
+        }
+        
+        if let Some(Compression::Zstd { level }) = self.compression {
+            if !zstd::compression_level_range().contains(&level) {
+                return Err(StorageError::Configuration(format!(
+                    "Unsupported zstd compression level: {}",
+                    level
+                )));
+            }
+        }
+        
         match &self.backend {
             StorageBackend::S3(config) => {
                 if config.bucket.is_empty() {
@@ -93,6 +341,19 @@ impl StorageConfig {
                 }
                 Ok(())
             }
+            StorageBackend::Azure(config) => {
+                if config.container.is_empty() {
+                    return Err(StorageError::Configuration(
+                        "Azure container name cannot be empty".to_string(),
+                    ));
+                }
+                if config.account.is_empty() {
+                    return Err(StorageError::Configuration(
+                        "Azure account name cannot be empty".to_string(),
+                    ));
+                }
+                Ok(())
+            }
             StorageBackend::FileSystem(config) => {
                 // Check if base path exists and is a directory
                 if !config.hash_base_path.exists() {
@@ -112,3 +373,47 @@ impl StorageConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn azure(account: &str, container: &str) -> StorageConfig {
+        StorageConfig::new_azure(
+            account.to_string(),
+            container.to_string(),
+            None,
+            Some("marble".to_string()),
+            Some("a2V5".to_string()),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_validate_azure() {
+        assert!(azure("marbleaccount", "notes").validate().is_ok());
+        assert!(matches!(azure("marbleaccount", "").validate(), Err(StorageError::Configuration(_))));
+        assert!(matches!(azure("", "notes").validate(), Err(StorageError::Configuration(_))));
+    }
+
+    #[test]
+    fn test_compression_parse_and_validate() {
+        assert_eq!(Compression::parse("zstd"), Some(Compression::Zstd { level: 3 }));
+        assert_eq!(Compression::parse(" ZSTD:19 "), Some(Compression::Zstd { level: 19 }));
+        assert_eq!(Compression::parse("gzip"), None);
+        assert_eq!(Compression::parse("zstd:high"), None);
+
+        let config = azure("marbleaccount", "notes").with_compression(Compression::Zstd { level: 19 });
+        assert!(config.validate().is_ok());
+        let config = azure("marbleaccount", "notes").with_compression(Compression::Zstd { level: 99 });
+        assert!(matches!(config.validate(), Err(StorageError::Configuration(_))));
+    }
+
+    #[test]
+    fn test_azure_hash_storage_under_prefix() {
+        let operator = crate::backends::hash::create_hash_storage(&azure("marbleaccount", "notes"))
+            .expect("Failed to build Azure operator");
+        assert_eq!(operator.info().name(), "notes");
+        assert_eq!(operator.info().root(), "/marble/hash/");
+    }
+}
@@ -34,11 +34,64 @@ pub enum StorageError {
     /// Validation errors
     #[error("validation error: {0}")]
     Validation(String),
+
+    /// Creating a file would exceed the tenant's file count limit
+    #[error("file limit exceeded: at most {0} files allowed")]
+    FileLimitExceeded(i64),
+
+    /// A file operation was aimed at a directory
+    #[error("is a directory: {0}")]
+    IsDirectory(String),
+
+    /// The content type policy refuses the content
+    #[error("content type not allowed: {0}")]
+    ContentTypeNotAllowed(String),
+
+    /// A file would be larger than the size limit
+    #[error("file too large: at most {0} bytes allowed")]
+    FileTooLarge(u64),
+
+    /// Writing would take the tenant above its storage quota
+    #[error("storage quota exceeded: at most {0} bytes allowed")]
+    QuotaExceeded(i64),
+
+    /// The parent directory of a path does not exist
+    #[error("parent directory does not exist: {0}")]
+    ParentNotFound(String),
+
+    /// The metadata database could not be reached
+    #[error("database unavailable: {0}")]
+    Unavailable(String),
+
+    /// The storage was shut down
+    #[error("storage is shut down")]
+    Closed,
 }
 
 /// Result type for storage operations
 pub type StorageResult<T> = Result<T, StorageError>;
 
+impl StorageError {
+    /// Whether the error means the metadata database could not be reached
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            StorageError::Unavailable(_) => true,
+            StorageError::Database(e) => marble_db::error::is_connection_error(e),
+            _ => false,
+        }
+    }
+}
+
+impl From<marble_db::Error> for StorageError {
+    fn from(error: marble_db::Error) -> Self {
+        if error.is_unavailable() {
+            StorageError::Unavailable(error.to_string())
+        } else {
+            StorageError::Storage(format!("Database error: {}", error))
+        }
+    }
+}
+
 impl From<std::io::Error> for StorageError {
     fn from(error: std::io::Error) -> Self {
         if error.kind() == std::io::ErrorKind::NotFound {
@@ -24,6 +24,15 @@ pub fn hash_content(content: &[u8]) -> StorageResult<String> {
     Ok(encoded)
 }
 
+/// Whether a string is a well-formed content hash
+///
+/// Checks the encoding and length only; the hash need not refer to any content.
+pub fn is_valid_hash(hash: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(hash)
+        .is_ok_and(|bytes| bytes.len() == HASH_BYTES_LENGTH)
+}
+
 /// Converts a content hash to a storage path
 ///
 /// Format: /.hash/{hash}
@@ -72,6 +81,16 @@ mod tests {
         assert_ne!(hash, hash3);
     }
 
+    #[test]
+    fn test_is_valid_hash() {
+        assert!(is_valid_hash(&hash_content(b"Hello, world!").unwrap()));
+        
+        assert!(!is_valid_hash(""));
+        assert!(!is_valid_hash("abcdef123456"));
+        assert!(!is_valid_hash("11111111-1111-1111-1111-11111111111g"));
+        assert!(!is_valid_hash("not a hash!"));
+    }
+
     #[test]
     fn test_hash_to_path() {
         let hash = "abcdef123456";
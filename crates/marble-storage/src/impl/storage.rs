@@ -40,7 +40,7 @@ impl MarbleStorageImpl {
         let hash_operator = create_hash_storage(&config)?;
         
         // Create the content hasher
-        let content_hasher = ContentHasher::new(hash_operator.clone());
+        let content_hasher = ContentHasher::for_config(hash_operator.clone(), &config);
         
         Ok(Self {
             config,
@@ -62,7 +62,7 @@ impl MarbleStorageImpl {
         let hash_operator = create_hash_storage(&config)?;
         
         // Create the content hasher
-        let content_hasher = ContentHasher::new(hash_operator.clone());
+        let content_hasher = ContentHasher::for_config(hash_operator.clone(), &config);
         
         Ok(Self {
             config,
@@ -115,12 +115,7 @@ impl MarbleStorage for MarbleStorageImpl {
         ));
         
         // Create an OpenDAL operator from the backend using our adapter
-        match create_raw_operator(backend) {
-            Ok(operator) => Ok(operator),
-            Err(e) => Err(StorageError::Storage(format!(
-                "Failed to create OpenDAL operator: {}", e
-            ))),
-        }
+        Ok(create_raw_operator(backend, &self.config.layers))
     }
     
     /// Get the hash-based storage operator
@@ -148,6 +143,7 @@ pub async fn create_storage_with_db(
 mod tests {
     use super::*;
     use tempfile::tempdir;
+    use crate::config::OperatorLayers;
     use tokio::test;
     use sqlx::postgres::PgPoolOptions;
     use std::time::Duration;
@@ -203,27 +199,37 @@ mod tests {
             .acquire_timeout(Duration::from_secs(3))
             .connect(&db_url)
             .await
-            .map_err(|e| StorageError::Database(e))?;
+            .map_err(StorageError::Database)?;
             
         Ok(Arc::new(pool))
     }
     
-    async fn setup_test_user(pool: &PgPool) -> Result<(i32, Uuid), StorageError> {
+    async fn setup_test_user(pool: &PgPool, username: &str) -> Result<(i32, Uuid), StorageError> {
         let test_uuid = Uuid::new_v4();
         
+        // Remove leftovers from an earlier failed run
+        let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username = $1)")
+            .bind(username)
+            .execute(pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE username = $1")
+            .bind(username)
+            .execute(pool)
+            .await;
+        
         // Create a test user first
         let user_id: i32 = sqlx::query_scalar(
             "INSERT INTO users (username, password_hash, created_at, uuid) 
              VALUES ($1, $2, $3, $4) 
              RETURNING id"
         )
-        .bind("raw_storage_impl_test_user")
+        .bind(username)
         .bind("test_password_hash")
         .bind(Utc::now())
         .bind(test_uuid)
         .fetch_one(pool)
         .await
-        .map_err(|e| StorageError::Database(e))?;
+        .map_err(StorageError::Database)?;
         
         Ok((user_id, test_uuid))
     }
@@ -243,36 +249,65 @@ mod tests {
             }
         };
         
-        // Create a test user
-        let (user_id, user_uuid) = match setup_test_user(&db_pool).await {
-            Ok(user) => user,
-            Err(_) => {
-                println!("Failed to create test user");
-                return;
-            }
-        };
+        // Create two test users
+        let (user_id, user_uuid) = setup_test_user(&db_pool, "raw_storage_impl_test_user")
+            .await
+            .expect("Failed to create test user");
+        let (other_id, other_uuid) = setup_test_user(&db_pool, "raw_storage_impl_other_user")
+            .await
+            .expect("Failed to create test user");
         
         // Create the storage with database connection
         let storage_impl = MarbleStorageImpl::new_with_db(config, db_pool.clone())
             .await
             .expect("Failed to create storage with DB");
         
-        // Try to get raw storage
-        let result = storage_impl.raw_storage(user_uuid).await;
+        // The operator round-trips content for its tenant
+        let operator = storage_impl.raw_storage(user_uuid).await.expect("Failed to get raw storage");
+        operator.write("notes/today.md", b"# Today".to_vec()).await.expect("Failed to write");
+        let content = operator.read("notes/today.md").await.expect("Failed to read");
+        assert_eq!(content, b"# Today");
         
-        // Currently we expect an error because our create_raw_operator returns an Unsupported error
-        // Once fully implemented, this would be a passing test
-        assert!(result.is_err(), "Raw storage operator creation should return an error until fully implemented");
-        assert!(result.unwrap_err().to_string().contains("Failed to create OpenDAL operator"), 
-                "Error should be about OpenDAL operator creation");
+        // Another tenant does not see it
+        let other_operator = storage_impl.raw_storage(other_uuid).await.expect("Failed to get raw storage");
+        assert!(!other_operator.is_exist("notes/today.md").await.unwrap());
         
         // Clean up
-        let _ = sqlx::query("DELETE FROM users WHERE id = $1")
-            .bind(user_id)
+        let _ = sqlx::query("DELETE FROM files WHERE user_id = ANY($1)")
+            .bind(vec![user_id, other_id])
+            .execute(&*db_pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM users WHERE id = ANY($1)")
+            .bind(vec![user_id, other_id])
             .execute(&*db_pool)
             .await;
     }
     
+    #[cfg(not(feature = "metrics"))]
+    #[test]
+    async fn test_metrics_layer_requires_feature() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let config = StorageConfig::new_fs(temp_dir.path().to_path_buf())
+            .with_layers(OperatorLayers { metrics: true, ..OperatorLayers::default() });
+        
+        // Without the feature the layer cannot be built, so the config is refused
+        match MarbleStorageImpl::new(config).await {
+            Err(StorageError::Configuration(message)) => assert!(message.contains("metrics")),
+            Err(e) => panic!("Expected a configuration error, got: {}", e),
+            Ok(_) => panic!("Expected the metrics layer to be rejected"),
+        }
+    }
+    
+    #[cfg(feature = "metrics")]
+    #[test]
+    async fn test_metrics_layer_enabled() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let config = StorageConfig::new_fs(temp_dir.path().to_path_buf())
+            .with_layers(OperatorLayers { metrics: true, ..OperatorLayers::default() });
+        
+        MarbleStorageImpl::new(config).await.expect("Failed to create storage with the metrics layer");
+    }
+    
     #[test]
     async fn test_raw_storage_without_db() {
         // Create a temporary directory
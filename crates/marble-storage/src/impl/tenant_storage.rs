@@ -1,16 +1,27 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
 use mime_guess::from_path;
 use sqlx::postgres::PgPool;
 use uuid::Uuid;
 
-use crate::api::tenant::{FileMetadata, TenantStorage};
+use crate::api::tenant::{DeadProperty, DedupOutcome, EntryKind, FileMetadata, ListOrder, PropertyChange, StorageUsage, TenantStorage, VersionInfo};
 use crate::backends::raw::RawStorageBackend;
-use crate::backends::user::uuid_to_db_id;
+use crate::backends::user::{uuid_to_db_id, UserIdCache};
+use crate::config::DirectoryStrategy;
 use crate::error::{StorageError, StorageResult};
+use crate::path::PathNormalizer;
+use crate::services::access::AccessTracker;
+use crate::services::content_policy::ContentTypePolicy;
 use crate::services::hasher::ContentHasher;
 
+/// Default size limit of a single file
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+
 /// Implementation of the TenantStorage trait
 ///
 /// This implementation uses the existing RawStorageBackend and ContentHasher
@@ -21,6 +32,39 @@ pub struct MarbleTenantStorage {
     
     /// Content hasher for deduplication and storage
     content_hasher: ContentHasher,
+    
+    /// Policy restricting which content types may be written
+    content_type_policy: ContentTypePolicy,
+    
+    /// Normalizer producing the canonical form of every path
+    path_normalizer: PathNormalizer,
+    
+    /// Cache of tenant UUID to database user ID lookups, off if `None`
+    user_ids: Option<Arc<UserIdCache>>,
+    
+    /// Maximum number of live files per tenant, unlimited if `None`
+    max_file_count: Option<i64>,
+    
+    /// Maximum size of a single file in bytes
+    max_file_size: u64,
+    
+    /// Whether paths are looked up case-insensitively
+    case_insensitive_paths: bool,
+    
+    /// How empty directories are represented
+    directory_strategy: DirectoryStrategy,
+    
+    /// Whether writes fail when the parent directory does not exist
+    require_existing_parent: bool,
+    
+    /// Throttles recording reads in `last_accessed_at`, off if `None`
+    access_tracker: Option<Arc<AccessTracker>>,
+    
+    /// Whether `\r\n` line endings in text content are stored as `\n`
+    normalize_newlines: bool,
+    
+    /// Set once the storage has been shut down
+    closed: AtomicBool,
 }
 
 impl MarbleTenantStorage {
@@ -29,43 +73,262 @@ impl MarbleTenantStorage {
         Self {
             db_pool,
             content_hasher,
+            content_type_policy: ContentTypePolicy::default(),
+            path_normalizer: PathNormalizer::new(),
+            user_ids: None,
+            max_file_count: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            case_insensitive_paths: false,
+            directory_strategy: DirectoryStrategy::default(),
+            require_existing_parent: false,
+            access_tracker: None,
+            normalize_newlines: false,
+            closed: AtomicBool::new(false),
         }
     }
     
+    /// Restrict uploads with a content-type policy
+    pub fn with_content_type_policy(mut self, policy: ContentTypePolicy) -> Self {
+        self.content_type_policy = policy;
+        self
+    }
+    
+    /// Use a custom path normalizer
+    pub fn with_path_normalizer(mut self, path_normalizer: PathNormalizer) -> Self {
+        self.path_normalizer = path_normalizer;
+        self
+    }
+    
+    /// Cache tenant UUID to database user ID lookups
+    ///
+    /// Off by default: every operation looks the tenant up. A cached id goes
+    /// stale when its tenant is deleted and re-created, so register
+    /// [`UserIdCache::invalidation_hook`] with every `SqlxUserRepository` that
+    /// changes users, and only enable the cache when all such changes happen
+    /// in this process.
+    pub fn with_user_id_cache(mut self, user_ids: Arc<UserIdCache>) -> Self {
+        self.user_ids = Some(user_ids);
+        self
+    }
+    
+    /// Limit the number of live files per tenant
+    ///
+    /// Directory placeholders count as files. Updating an existing file never
+    /// counts against the limit.
+    pub fn with_max_file_count(mut self, max_file_count: i64) -> Self {
+        self.max_file_count = Some(max_file_count);
+        self
+    }
+    
+    /// Limit the size of a single file
+    ///
+    /// Writes, appends, truncations and restores that would produce a larger
+    /// file are refused before anything is stored.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+    
+    /// Look up paths case-insensitively while keeping their original casing
+    ///
+    /// Requires a database where the `files.path` keys were written in this mode.
+    pub fn with_case_insensitive_paths(mut self, enabled: bool) -> Self {
+        self.case_insensitive_paths = enabled;
+        self
+    }
+    
+    /// Choose how empty directories are represented
+    ///
+    /// Either way every created directory is recorded in the `folders` table,
+    /// which is all lookups and listings read;
+    /// [`DirectoryStrategy::Placeholder`] also writes internal `.dir` markers.
+    pub fn with_directory_strategy(mut self, strategy: DirectoryStrategy) -> Self {
+        self.directory_strategy = strategy;
+        self
+    }
+    
+    /// Fail writes whose parent directory does not exist
+    ///
+    /// By default parent directories are implied by the files below them.
+    /// Strict WebDAV clients expect a PUT into a missing collection to fail with
+    /// [`StorageError::ParentNotFound`] instead.
+    pub fn with_require_existing_parent(mut self, enabled: bool) -> Self {
+        self.require_existing_parent = enabled;
+        self
+    }
+    
+    /// Record when files are read, at most once per file per `window`
+    ///
+    /// Off by default: tracking turns reads into occasional writes. Reads of
+    /// a file within the window after a recorded one are not recorded, so
+    /// `last_accessed` may lag behind by up to the window.
+    pub fn with_access_tracking(mut self, window: Duration) -> Self {
+        self.access_tracker = Some(Arc::new(AccessTracker::new(window)));
+        self
+    }
+    
+    /// Store text content with `\n` line endings
+    ///
+    /// Applies to `text/*` content types, markdown included, and rewrites
+    /// `\r\n` before the content is hashed, so notes edited on different
+    /// platforms deduplicate. Binary content is never touched.
+    pub fn with_normalize_newlines(mut self, enabled: bool) -> Self {
+        self.normalize_newlines = enabled;
+        self
+    }
+    
+    /// The user ID cache, if enabled
+    pub fn user_id_cache(&self) -> Option<&Arc<UserIdCache>> {
+        self.user_ids.as_ref()
+    }
+    
+    /// Repair the file metadata of a user from the stored content
+    ///
+    /// Returns the number of files whose size or hash was corrected.
+    pub async fn refresh_metadata(&self, user_id: i32) -> StorageResult<u64> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(StorageError::Closed);
+        }
+        
+        RawStorageBackend::new(user_id, self.db_pool.clone(), self.content_hasher.clone())
+            .with_case_insensitive_paths(self.case_insensitive_paths)
+            .refresh_metadata()
+            .await
+    }
+    
+    /// List the recorded versions of a tenant's file, newest first
+    pub async fn list_versions(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<VersionInfo>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let path = self.normalize_path(path)?;
+        backend.list_versions(&path).await
+    }
+    
+    /// Make an earlier version of a tenant's file current again
+    ///
+    /// The restored content is recorded as the newest version. Restoring counts
+    /// against the storage quota like writing the content again.
+    pub async fn restore_version(&self, tenant_id: &Uuid, path: &str, version_id: i32) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let path = self.normalize_path(path)?;
+        
+        let versions = backend.list_versions(&path).await?;
+        if let Some(version) = versions.iter().find(|version| version.id == version_id) {
+            let size = usize::try_from(version.size).unwrap_or(usize::MAX);
+            self.check_quota(&backend, &path, size).await?;
+        }
+        
+        backend.restore_version(&path, version_id).await
+    }
+    
+    /// List a tenant's deleted files, most recently deleted first
+    pub async fn list_trash(&self, tenant_id: &Uuid) -> StorageResult<Vec<FileMetadata>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        backend.list_trash().await
+    }
+    
+    /// Bring a tenant's deleted file back at its original path
+    ///
+    /// The restored file counts against the file limit and the storage quota
+    /// like a new one.
+    pub async fn restore(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let path = self.normalize_path(path)?;
+        self.check_file_limit(&backend).await?;
+        
+        let size = usize::try_from(backend.trashed_file_size(&path).await?).unwrap_or(usize::MAX);
+        self.check_quota(&backend, &path, size).await?;
+        
+        backend.restore_file(&path).await
+    }
+    
     /// Helper to create a RawStorageBackend for a specific tenant
     async fn get_backend_for_tenant(&self, tenant_id: &Uuid) -> StorageResult<RawStorageBackend> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(StorageError::Closed);
+        }
+        
         // Convert UUID to database ID
-        let db_user_id = uuid_to_db_id(&self.db_pool, *tenant_id).await?;
+        let db_user_id = match &self.user_ids {
+            Some(user_ids) => user_ids.get(&self.db_pool, *tenant_id).await?,
+            None => uuid_to_db_id(&self.db_pool, *tenant_id).await?,
+        };
         
         // Create and return the backend
-        Ok(RawStorageBackend::new(
+        let backend = RawStorageBackend::new(
             db_user_id,
             self.db_pool.clone(),
             self.content_hasher.clone(),
-        ))
+        )
+        .with_case_insensitive_paths(self.case_insensitive_paths)
+        .with_directory_strategy(self.directory_strategy);
+        
+        Ok(match &self.access_tracker {
+            Some(tracker) => backend.with_access_tracker(tracker.clone()),
+            None => backend,
+        })
     }
     
-    /// Helper to normalize paths
-    fn normalize_path(path: &str) -> String {
-        let path = if path.starts_with('/') {
-            path.to_string()
-        } else {
-            format!("/{}", path)
+    /// Normalize a client path, rejecting paths that climb above the tenant root
+    fn normalize_path(&self, path: &str) -> StorageResult<String> {
+        self.path_normalizer.try_normalize(path)
+    }
+    
+    /// Reject creating a new file when the tenant is at its file limit
+    async fn check_file_limit(&self, backend: &RawStorageBackend) -> StorageResult<()> {
+        if let Some(limit) = self.max_file_count {
+            if backend.file_count().await? >= limit {
+                return Err(StorageError::FileLimitExceeded(limit));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Reject storing `new_size` bytes at a path when it is above the size
+    /// limit or would take the tenant above its storage quota
+    ///
+    /// Replacing a file only counts the difference in size against the quota,
+    /// so shrinking or rewriting a file within the size limit is always allowed.
+    async fn check_quota(&self, backend: &RawStorageBackend, path: &str, new_size: usize) -> StorageResult<()> {
+        if new_size as u64 > self.max_file_size {
+            return Err(StorageError::FileTooLarge(self.max_file_size));
+        }
+        
+        let Some(quota) = backend.quota_bytes().await? else {
+            return Ok(());
         };
-
-        // Remove trailing slash unless it's the root path
-        if path.len() > 1 && path.ends_with('/') {
-            path[0..path.len() - 1].to_string()
-        } else {
-            path
+        
+        let replaced = backend.live_file_size(path).await?;
+        let new_size = i64::try_from(new_size).unwrap_or(i64::MAX);
+        if new_size > replaced && backend.total_size().await? - replaced + new_size > quota {
+            return Err(StorageError::QuotaExceeded(quota));
         }
+        Ok(())
     }
     
-    /// Helper to guess content type from path
-    fn guess_content_type(path: &str) -> String {
+    /// Rewrite `\r\n` to `\n` in text content when newline normalization is on
+    ///
+    /// The tenant setting wins over the storage-wide one.
+    fn normalize_text(&self, tenant_setting: Option<bool>, content_type: &str, content: Vec<u8>) -> Vec<u8> {
+        if !tenant_setting.unwrap_or(self.normalize_newlines) || !content_type.trim_start().to_ascii_lowercase().starts_with("text/") {
+            return content;
+        }
+        
+        let mut normalized = Vec::with_capacity(content.len());
+        let mut bytes = content.into_iter().peekable();
+        while let Some(byte) = bytes.next() {
+            if byte == b'\r' && bytes.peek() == Some(&b'\n') {
+                continue;
+            }
+            normalized.push(byte);
+        }
+        normalized
+    }
+    
+    /// Helper to guess content type from path, falling back to the tenant default
+    fn guess_content_type(path: &str, tenant_default: Option<&str>) -> String {
         match from_path(path).first() {
             Some(mime) => mime.to_string(),
-            None => "application/octet-stream".to_string(),
+            None => tenant_default.unwrap_or("application/octet-stream").to_string(),
         }
     }
 }
@@ -74,37 +337,168 @@ impl MarbleTenantStorage {
 impl TenantStorage for MarbleTenantStorage {
     async fn read(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<u8>> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
         backend.read_file(&normalized_path).await
     }
     
-    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, content_type: Option<&str>) -> StorageResult<()> {
+    async fn read_stream(&self, tenant_id: &Uuid, path: &str) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        backend.read_file_stream(&normalized_path).await
+    }
+    
+    async fn write(&self, tenant_id: &Uuid, path: &str, content: Vec<u8>, content_type: Option<&str>) -> StorageResult<DedupOutcome> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
+        let (default_type, normalize_newlines) = backend.write_settings().await?;
+        
+        // Use the provided content type, then a pinned one, then guess from path
+        let content_type = match content_type {
+            Some(ct) => ct.to_string(),
+            None => backend
+                .content_type_override(&normalized_path)
+                .await?
+                .unwrap_or_else(|| Self::guess_content_type(&normalized_path, default_type.as_deref())),
+        };
+        
+        let content = self.normalize_text(normalize_newlines, &content_type, content);
+        
+        // Reject disallowed uploads before anything is stored
+        self.content_type_policy.check(&normalized_path, &content_type, &content)?;
+        
+        // A file row would shadow the directory
+        if backend.directory_exists(&normalized_path).await? {
+            return Err(StorageError::IsDirectory(path.to_string()));
+        }
+        
+        if self.require_existing_parent {
+            let parent = normalized_path.rsplit_once('/').map_or("", |(parent, _)| parent);
+            if !backend.directory_exists(parent).await? {
+                return Err(StorageError::ParentNotFound(normalized_path));
+            }
+        }
+        
+        if self.max_file_count.is_some() && !backend.file_exists(&normalized_path).await? {
+            self.check_file_limit(&backend).await?;
+        }
+        
+        self.check_quota(&backend, &normalized_path, content.len()).await?;
         
-        // Use provided content type or guess from path
+        backend.write_file(&normalized_path, content, &content_type).await
+    }
+    
+    async fn append(&self, tenant_id: &Uuid, path: &str, data: Vec<u8>, content_type: Option<&str>) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        
+        // Hash storage is immutable, so read the existing content and store the concatenation
+        let (mut content, existing_type) = match backend.get_file_metadata(&normalized_path).await {
+            Ok(metadata) if metadata.is_directory => {
+                return Err(StorageError::Validation(format!("Cannot append to a directory: {}", path)));
+            }
+            Ok(metadata) => (backend.read_file(&normalized_path).await?, Some(metadata.content_type)),
+            Err(StorageError::NotFound(_)) => {
+                self.check_file_limit(&backend).await?;
+                (Vec::new(), None)
+            }
+            Err(e) => return Err(e),
+        };
+        content.extend_from_slice(&data);
+        
+        let (default_type, normalize_newlines) = backend.write_settings().await?;
         let content_type = content_type
             .map(|ct| ct.to_string())
-            .unwrap_or_else(|| Self::guess_content_type(&normalized_path));
+            .or(existing_type)
+            .unwrap_or_else(|| Self::guess_content_type(&normalized_path, default_type.as_deref()));
+        let content = self.normalize_text(normalize_newlines, &content_type, content);
         
-        backend.write_file(&normalized_path, content, &content_type).await
+        self.content_type_policy.check(&normalized_path, &content_type, &content)?;
+        self.check_quota(&backend, &normalized_path, content.len()).await?;
+        
+        backend.write_file(&normalized_path, content, &content_type).await?;
+        Ok(())
+    }
+    
+    async fn truncate(&self, tenant_id: &Uuid, path: &str, len: u64) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        
+        let metadata = backend.get_file_metadata(&normalized_path).await?;
+        if metadata.is_directory {
+            return Err(StorageError::Validation(format!("Cannot truncate a directory: {}", path)));
+        }
+        
+        // Limits are checked against the new length before anything is allocated
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        self.check_quota(&backend, &normalized_path, len).await?;
+        let mut content = backend.read_file(&normalized_path).await?;
+        content.resize(len, 0);
+        
+        // The resized content must pass the same policy as any other write
+        self.content_type_policy.check(&normalized_path, &metadata.content_type, &content)?;
+        
+        backend.write_file(&normalized_path, content, &metadata.content_type).await?;
+        Ok(())
     }
     
     async fn exists(&self, tenant_id: &Uuid, path: &str) -> StorageResult<bool> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
         backend.file_exists(&normalized_path).await
     }
     
     async fn delete(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
         backend.delete_file(&normalized_path).await
     }
     
+    async fn delete_directory(&self, tenant_id: &Uuid, path: &str) -> StorageResult<u64> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        backend.delete_directory(&normalized_path).await
+    }
+    
+    async fn delete_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<bool>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_paths: Vec<String> = paths
+            .iter()
+            .map(|path| self.normalize_path(path))
+            .collect::<StorageResult<_>>()?;
+        
+        backend.delete_files(&normalized_paths).await
+    }
+    
+    async fn rename(&self, tenant_id: &Uuid, from: &str, to: &str) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let from = self.normalize_path(from)?;
+        let to = self.normalize_path(to)?;
+        backend.move_file(&from, &to).await
+    }
+    
+    async fn create_alias(&self, tenant_id: &Uuid, path: &str, target: &str) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let path = self.normalize_path(path)?;
+        let target = self.normalize_path(target)?;
+        
+        if self.max_file_count.is_some() && !backend.file_exists(&path).await? {
+            self.check_file_limit(&backend).await?;
+        }
+        
+        backend.create_alias(&path, &target).await
+    }
+    
     async fn list(&self, tenant_id: &Uuid, dir_path: &str) -> StorageResult<Vec<String>> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(dir_path);
+        let normalized_path = self.normalize_path(dir_path)?;
+        
+        // An empty listing must mean an empty directory, not a missing one
+        if !backend.directory_exists(&normalized_path).await? {
+            if backend.file_exists(&normalized_path).await? {
+                return Err(StorageError::Validation(format!("Not a directory: {}", normalized_path)));
+            }
+            return Err(StorageError::NotFound(format!("Directory not found: {}", normalized_path)));
+        }
         
         // Ensure path ends with slash for directory listing
         let dir_path = if normalized_path.ends_with('/') {
@@ -116,19 +510,148 @@ impl TenantStorage for MarbleTenantStorage {
         backend.list_files(&dir_path).await
     }
     
+    async fn list_with_metadata(
+        &self,
+        tenant_id: &Uuid,
+        dir_path: &str,
+        order: ListOrder,
+    ) -> StorageResult<Vec<FileMetadata>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(dir_path)?;
+        
+        // Ensure path ends with slash for directory listing
+        let dir_path = if normalized_path.ends_with('/') {
+            normalized_path
+        } else {
+            format!("{}/", normalized_path)
+        };
+        
+        backend.list_files_with_metadata(&dir_path, order).await
+    }
+    
     async fn create_directory(&self, tenant_id: &Uuid, path: &str) -> StorageResult<()> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
+        
+        // An existing directory is left as is, so only new ones count
+        if self.max_file_count.is_some() && !backend.directory_exists(&normalized_path).await? {
+            self.check_file_limit(&backend).await?;
+        }
+        
         backend.create_directory(&normalized_path).await
     }
     
     async fn metadata(&self, tenant_id: &Uuid, path: &str) -> StorageResult<FileMetadata> {
         let backend = self.get_backend_for_tenant(tenant_id).await?;
-        let normalized_path = Self::normalize_path(path);
+        let normalized_path = self.normalize_path(path)?;
         
         // Use the new get_file_metadata method from RawStorageBackend
         backend.get_file_metadata(&normalized_path).await
     }
+    
+    async fn metadata_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<Option<FileMetadata>>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_paths: Vec<String> = paths
+            .iter()
+            .map(|path| self.normalize_path(path))
+            .collect::<StorageResult<_>>()?;
+        
+        backend.get_files_metadata(&normalized_paths).await
+    }
+    
+    async fn entry_kind(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Option<EntryKind>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        backend.entry_kind(&normalized_path).await
+    }
+    
+    async fn usage(&self, tenant_id: &Uuid) -> StorageResult<StorageUsage> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        
+        Ok(StorageUsage {
+            used_bytes: backend.total_size().await?.max(0) as u64,
+            quota_bytes: backend.quota_bytes().await?.map(|quota| quota.max(0) as u64),
+        })
+    }
+    
+    async fn set_content_type_override(&self, tenant_id: &Uuid, path: &str, content_type: Option<&str>) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        let (default_type, _) = backend.write_settings().await?;
+        let guessed_type = Self::guess_content_type(&normalized_path, default_type.as_deref());
+        backend.set_content_type_override(&normalized_path, content_type, &guessed_type).await
+    }
+    
+    async fn properties(&self, tenant_id: &Uuid, path: &str) -> StorageResult<Vec<DeadProperty>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        backend.get_file_properties(&normalized_path).await
+    }
+    
+    async fn properties_many(&self, tenant_id: &Uuid, paths: &[String]) -> StorageResult<Vec<Vec<DeadProperty>>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_paths: Vec<String> = paths
+            .iter()
+            .map(|path| self.normalize_path(path))
+            .collect::<StorageResult<_>>()?;
+        
+        backend.get_files_properties(&normalized_paths).await
+    }
+    
+    async fn patch_properties(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        changes: &[PropertyChange],
+        content_type: Option<Option<&str>>,
+    ) -> StorageResult<()> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        let normalized_path = self.normalize_path(path)?;
+        
+        // Unpinning goes back to the type the path suggests
+        let guessed_type = match content_type {
+            Some(None) => {
+                let (default_type, _) = backend.write_settings().await?;
+                Self::guess_content_type(&normalized_path, default_type.as_deref())
+            }
+            _ => String::new(),
+        };
+        let content_type = content_type.map(|pin| (pin.unwrap_or(&guessed_type), pin));
+        
+        backend.patch_file_properties(&normalized_path, changes, content_type).await
+    }
+    
+    async fn read_by_hash(&self, tenant_id: &Uuid, content_hash: &str) -> StorageResult<Vec<u8>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        
+        if !backend.references_content(content_hash).await? {
+            return Err(StorageError::Authorization(format!(
+                "Content not referenced by tenant: {}",
+                content_hash
+            )));
+        }
+        
+        self.content_hasher.get_content(content_hash).await
+    }
+    
+    /// Only markdown files are indexed, on write; files written before the
+    /// index existed are found after running [`crate::index_unindexed`].
+    async fn search(&self, tenant_id: &Uuid, query: &str) -> StorageResult<Vec<FileMetadata>> {
+        let backend = self.get_backend_for_tenant(tenant_id).await?;
+        backend.search(query).await
+    }
+    
+    /// Clear the user ID cache and close the database pool
+    ///
+    /// Waits for connections in use to be returned. Later operations fail with
+    /// [`StorageError::Closed`].
+    async fn shutdown(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Some(user_ids) = &self.user_ids {
+            user_ids.clear();
+        }
+        self.db_pool.close().await;
+    }
 }
 
 /// Create a new TenantStorage implementation
@@ -3,11 +3,19 @@
 
 // Re-export the primary traits and types
 pub use api::{MarbleStorage, MarbleStorageRef};
-pub use api::tenant::{TenantStorage, TenantStorageRef, FileMetadata};
-pub use config::{FileSystemConfig, S3Config, StorageBackend, StorageConfig};
+pub use api::tenant::{TenantStorage, TenantStorageRef, FileMetadata, DeadProperty, EntryKind, ListOrder, PropertyChange, StorageUsage, VersionInfo};
+pub use config::{AzureConfig, Compression, DirectoryStrategy, FileSystemConfig, OperatorLayers, S3Config, StorageBackend, StorageConfig};
 pub use error::{StorageError, StorageResult};
+pub use backends::user::UserIdCache;
+pub use path::PathNormalizer;
 pub use mock::MockTenantStorage;
+pub use services::access::AccessTracker;
+pub use services::content_policy::ContentTypePolicy;
 pub use services::hasher::ContentHasher;
+pub use services::maintenance::{collect_garbage, find_dangling_refs, index_unindexed, scrub, tombstone_refs, GcReport, ScrubReport};
+pub use backends::hash::create_hash_storage;
+pub use r#impl::{create_storage, create_storage_with_db, create_tenant_storage};
+pub use r#impl::tenant_storage::MarbleTenantStorage;
 
 // Public modules
 pub mod api;
@@ -15,6 +23,7 @@ pub mod config;
 pub mod error;
 pub mod hash;
 pub mod mock;
+pub mod path;
 
 // Internal modules
 mod backends;
@@ -3,9 +3,13 @@ use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
-use crate::api::{FileMetadata, TenantStorage};
+use crate::api::{DeadProperty, DedupOutcome, FileMetadata, ListOrder, PropertyChange, StorageUsage, TenantStorage};
+use crate::api::tenant::{apply_property_changes, sort_metadata};
 use crate::StorageError;
 
+/// Dead properties by (tenant_id, path)
+type PropertyMap = HashMap<(Uuid, String), Vec<DeadProperty>>;
+
 /// Mock implementation of TenantStorage for testing
 #[derive(Default)]
 pub struct MockTenantStorage {
@@ -13,6 +17,12 @@ pub struct MockTenantStorage {
     files: Arc<RwLock<HashMap<(Uuid, String), (Vec<u8>, bool)>>>,
     // Maps (tenant_id, directory_path) -> [entry_names]
     directory_entries: Arc<RwLock<HashMap<(Uuid, String), Vec<String>>>>,
+    // Maps (tenant_id, alias_path) -> target_path
+    aliases: Arc<RwLock<HashMap<(Uuid, String), String>>>,
+    // Maps (tenant_id, path) -> pinned content type
+    content_types: Arc<RwLock<HashMap<(Uuid, String), String>>>,
+    // Maps (tenant_id, path) -> dead properties
+    properties: Arc<RwLock<PropertyMap>>,
 }
 
 impl MockTenantStorage {
@@ -21,6 +31,9 @@ impl MockTenantStorage {
         Self {
             files: Arc::new(RwLock::new(HashMap::new())),
             directory_entries: Arc::new(RwLock::new(HashMap::new())),
+            aliases: Arc::new(RwLock::new(HashMap::new())),
+            content_types: Arc::new(RwLock::new(HashMap::new())),
+            properties: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -68,6 +81,15 @@ impl MockTenantStorage {
         }
     }
     
+    /// Path an alias follows, or the path itself for other files
+    fn resolve_alias(&self, tenant_id: &Uuid, path: &str) -> String {
+        let aliases = self.aliases.read().unwrap();
+        aliases
+            .get(&(*tenant_id, path.to_string()))
+            .cloned()
+            .unwrap_or_else(|| path.to_string())
+    }
+    
     /// Get parent path of a file path
     fn get_parent_path(&self, path: &str) -> String {
         let path = path.trim_end_matches('/');
@@ -112,8 +134,9 @@ impl TenantStorage for MockTenantStorage {
     }
     
     async fn read(&self, tenant_id: &Uuid, path: &str) -> Result<Vec<u8>, StorageError> {
+        let path = self.resolve_alias(tenant_id, path);
         let files = self.files.read().unwrap();
-        match files.get(&(*tenant_id, path.to_string())) {
+        match files.get(&(*tenant_id, path.clone())) {
             Some((content, is_directory)) => {
                 if *is_directory {
                     return Err(StorageError::Validation("Cannot read a directory".to_string()));
@@ -130,7 +153,45 @@ impl TenantStorage for MockTenantStorage {
         path: &str,
         content: Vec<u8>,
         _content_type: Option<&str>,
+    ) -> Result<DedupOutcome, StorageError> {
+        if let Some((_, true)) = self.files.read().unwrap().get(&(*tenant_id, path.to_string())) {
+            return Err(StorageError::IsDirectory(path.to_string()));
+        }
+        
+        // Only the tenant's own files count, as in raw storage
+        let referenced = self
+            .files
+            .read()
+            .unwrap()
+            .iter()
+            .any(|((tenant, _), (existing, is_dir))| tenant == tenant_id && !is_dir && *existing == content);
+        
+        self.add_file(tenant_id, &self.resolve_alias(tenant_id, path), content);
+        Ok(DedupOutcome::from_referenced(referenced))
+    }
+    
+    async fn append(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        data: Vec<u8>,
+        _content_type: Option<&str>,
     ) -> Result<(), StorageError> {
+        let mut content = match self.read(tenant_id, path).await {
+            Ok(content) => content,
+            Err(StorageError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        content.extend_from_slice(&data);
+        
+        self.add_file(tenant_id, path, content);
+        Ok(())
+    }
+    
+    async fn truncate(&self, tenant_id: &Uuid, path: &str, len: u64) -> Result<(), StorageError> {
+        let mut content = self.read(tenant_id, path).await?;
+        content.resize(len as usize, 0);
+        
         self.add_file(tenant_id, path, content);
         Ok(())
     }
@@ -140,6 +201,9 @@ impl TenantStorage for MockTenantStorage {
         if files.remove(&(*tenant_id, path.to_string())).is_none() {
             return Err(StorageError::NotFound(path.to_string()));
         }
+        self.aliases.write().unwrap().remove(&(*tenant_id, path.to_string()));
+        self.content_types.write().unwrap().remove(&(*tenant_id, path.to_string()));
+        self.properties.write().unwrap().remove(&(*tenant_id, path.to_string()));
         
         // Remove from parent directory entries
         let parent_path = self.get_parent_path(path);
@@ -156,6 +220,105 @@ impl TenantStorage for MockTenantStorage {
         Ok(())
     }
     
+    async fn delete_directory(&self, tenant_id: &Uuid, path: &str) -> Result<u64, StorageError> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let below = |key: &(Uuid, String)| key.0 == *tenant_id && (key.1 == path || key.1.starts_with(&prefix));
+        
+        let mut files = self.files.write().unwrap();
+        let removed: Vec<(Uuid, String)> = files.keys().filter(|key| below(key)).cloned().collect();
+        let mut deleted = 0;
+        for key in removed {
+            if let Some((_, false)) = files.remove(&key) {
+                deleted += 1;
+            }
+            self.aliases.write().unwrap().remove(&key);
+            self.content_types.write().unwrap().remove(&key);
+            self.properties.write().unwrap().remove(&key);
+        }
+        
+        let mut directory_entries = self.directory_entries.write().unwrap();
+        directory_entries.retain(|key, _| !below(key));
+        let parent_path = self.get_parent_path(path);
+        let dir_name = self.get_file_name(path);
+        if let Some(entries) = directory_entries.get_mut(&(*tenant_id, parent_path)) {
+            entries.retain(|name| name != &dir_name);
+        }
+        
+        Ok(deleted)
+    }
+    
+    async fn delete_many(&self, tenant_id: &Uuid, paths: &[String]) -> Result<Vec<bool>, StorageError> {
+        let mut deleted = Vec::with_capacity(paths.len());
+        for path in paths {
+            deleted.push(self.delete(tenant_id, path).await.is_ok());
+        }
+        Ok(deleted)
+    }
+    
+    async fn search(&self, tenant_id: &Uuid, query: &str) -> Result<Vec<FileMetadata>, StorageError> {
+        // Markdown files containing every word, in path order
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut paths: Vec<String> = self
+            .files
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|((tenant, path), (_, is_dir))| tenant == tenant_id && !is_dir && path.ends_with(".md"))
+            .filter(|(_, (content, _))| {
+                let text = String::from_utf8_lossy(content).to_lowercase();
+                !words.is_empty() && words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .map(|((_, path), _)| path.clone())
+            .collect();
+        paths.sort();
+        
+        let mut found = Vec::with_capacity(paths.len());
+        for path in paths {
+            found.push(self.metadata(tenant_id, &path).await?);
+        }
+        Ok(found)
+    }
+    
+    async fn create_alias(&self, tenant_id: &Uuid, path: &str, target: &str) -> Result<(), StorageError> {
+        let target = self.resolve_alias(tenant_id, target);
+        match self.files.read().unwrap().get(&(*tenant_id, target.clone())) {
+            Some((_, true)) => {
+                return Err(StorageError::Validation(format!("Cannot alias a directory: {}", target)));
+            }
+            Some(_) => {}
+            None => return Err(StorageError::NotFound(format!("Alias target not found: {}", target))),
+        }
+        if self.exists(tenant_id, path).await? {
+            return Err(StorageError::Validation(format!("Destination already exists: {}", path)));
+        }
+        
+        // The alias is listed like a file but holds no content of its own
+        self.add_file(tenant_id, path, Vec::new());
+        self.aliases.write().unwrap().insert((*tenant_id, path.to_string()), target);
+        Ok(())
+    }
+    
+    async fn rename(&self, tenant_id: &Uuid, from: &str, to: &str) -> Result<(), StorageError> {
+        if self.exists(tenant_id, to).await? {
+            return Err(StorageError::Validation(format!("Destination already exists: {}", to)));
+        }
+        
+        let content = {
+            let files = self.files.read().unwrap();
+            match files.get(&(*tenant_id, from.to_string())) {
+                Some((_, true)) => {
+                    return Err(StorageError::Validation("Cannot rename a directory".to_string()));
+                }
+                Some((content, false)) => content.clone(),
+                None => return Err(StorageError::NotFound(from.to_string())),
+            }
+        };
+        
+        self.delete(tenant_id, from).await?;
+        self.add_file(tenant_id, to, content);
+        Ok(())
+    }
+    
     async fn list(&self, tenant_id: &Uuid, path: &str) -> Result<Vec<String>, StorageError> {
         
         // Check if path exists and is a directory
@@ -181,6 +344,28 @@ impl TenantStorage for MockTenantStorage {
         }
     }
     
+    async fn list_with_metadata(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        order: ListOrder,
+    ) -> Result<Vec<FileMetadata>, StorageError> {
+        let entries = self.list(tenant_id, path).await?;
+        
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let entry_path = if path == "." {
+                entry
+            } else {
+                format!("{}/{}", path.trim_end_matches('/'), entry)
+            };
+            results.push(self.metadata(tenant_id, &entry_path).await?);
+        }
+        
+        sort_metadata(&mut results, order);
+        Ok(results)
+    }
+    
     async fn create_directory(&self, tenant_id: &Uuid, path: &str) -> Result<(), StorageError> {
         self.add_directory(tenant_id, path);
         Ok(())
@@ -188,9 +373,13 @@ impl TenantStorage for MockTenantStorage {
     
     async fn metadata(&self, tenant_id: &Uuid, path: &str) -> Result<FileMetadata, StorageError> {
         let files = self.files.read().unwrap();
-        match files.get(&(*tenant_id, path.to_string())) {
+        let resolved = self.resolve_alias(tenant_id, path);
+        let pinned = self.content_types.read().unwrap().get(&(*tenant_id, resolved.clone())).cloned();
+        match files.get(&(*tenant_id, resolved)) {
             Some((content, is_directory)) => {
-                let content_type = if *is_directory {
+                let content_type = if let Some(pinned) = pinned {
+                    pinned
+                } else if *is_directory {
                     "application/x-directory".to_string()
                 } else if path.ends_with(".md") {
                     "text/markdown".to_string()
@@ -206,10 +395,101 @@ impl TenantStorage for MockTenantStorage {
                     size: content.len() as u64,
                     is_directory: *is_directory,
                     last_modified: None,
+                    last_accessed: None,
                     content_hash: None,
                 })
             }
             None => Err(StorageError::NotFound(path.to_string())),
         }
     }
+    
+    async fn metadata_many(&self, tenant_id: &Uuid, paths: &[String]) -> Result<Vec<Option<FileMetadata>>, StorageError> {
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            match self.metadata(tenant_id, path).await {
+                Ok(metadata) => results.push(Some(metadata)),
+                Err(StorageError::NotFound(_)) => results.push(None),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+    
+    async fn usage(&self, tenant_id: &Uuid) -> Result<StorageUsage, StorageError> {
+        let used_bytes = self.files
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|((file_tenant, _), (_, is_directory))| file_tenant == tenant_id && !is_directory)
+            .map(|(_, (content, _))| content.len() as u64)
+            .sum();
+        
+        Ok(StorageUsage { used_bytes, quota_bytes: None })
+    }
+    
+    async fn set_content_type_override(&self, tenant_id: &Uuid, path: &str, content_type: Option<&str>) -> Result<(), StorageError> {
+        let resolved = self.resolve_alias(tenant_id, path);
+        if !self.files.read().unwrap().contains_key(&(*tenant_id, resolved.clone())) {
+            return Err(StorageError::NotFound(path.to_string()));
+        }
+        
+        let mut content_types = self.content_types.write().unwrap();
+        match content_type {
+            Some(content_type) => content_types.insert((*tenant_id, resolved), content_type.to_string()),
+            None => content_types.remove(&(*tenant_id, resolved)),
+        };
+        Ok(())
+    }
+    
+    async fn properties(&self, tenant_id: &Uuid, path: &str) -> Result<Vec<DeadProperty>, StorageError> {
+        let resolved = self.resolve_alias(tenant_id, path);
+        if !self.files.read().unwrap().contains_key(&(*tenant_id, resolved.clone())) {
+            return Err(StorageError::NotFound(path.to_string()));
+        }
+        
+        let properties = self.properties.read().unwrap();
+        Ok(properties.get(&(*tenant_id, resolved)).cloned().unwrap_or_default())
+    }
+    
+    async fn patch_properties(
+        &self,
+        tenant_id: &Uuid,
+        path: &str,
+        changes: &[PropertyChange],
+        content_type: Option<Option<&str>>,
+    ) -> Result<(), StorageError> {
+        let resolved = self.resolve_alias(tenant_id, path);
+        match self.files.read().unwrap().get(&(*tenant_id, resolved.clone())) {
+            Some((_, true)) if content_type.is_some() => {
+                return Err(StorageError::Validation(format!("Cannot set the content type of a directory: {}", path)));
+            }
+            Some(_) => {}
+            None => return Err(StorageError::NotFound(path.to_string())),
+        }
+        
+        let mut properties = self.properties.write().unwrap();
+        apply_property_changes(properties.entry((*tenant_id, resolved.clone())).or_default(), changes);
+        
+        let mut content_types = self.content_types.write().unwrap();
+        match content_type {
+            Some(Some(content_type)) => {
+                content_types.insert((*tenant_id, resolved), content_type.to_string());
+            }
+            Some(None) => {
+                content_types.remove(&(*tenant_id, resolved));
+            }
+            None => {}
+        }
+        Ok(())
+    }
+    
+    async fn read_by_hash(&self, tenant_id: &Uuid, content_hash: &str) -> Result<Vec<u8>, StorageError> {
+        let files = self.files.read().unwrap();
+        for ((file_tenant, _), (content, is_directory)) in files.iter() {
+            if file_tenant == tenant_id && !*is_directory && crate::hash::hash_content(content)? == content_hash {
+                return Ok(content.clone());
+            }
+        }
+        Err(StorageError::Authorization(format!("Content not referenced by tenant: {}", content_hash)))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,237 @@
+//! Path normalization shared by all storage layers
+//!
+//! Every layer that accepts a path (the WebDAV handler, tenant storage and the
+//! OpenDAL adapter) must agree on one spelling of each path, otherwise the same
+//! file can be looked up under two different keys. [`PathNormalizer`] defines
+//! that spelling.
+//!
+//! # Canonical form
+//!
+//! * Paths are absolute: they always start with `/`
+//! * The root is `/`
+//! * There is no trailing slash, except for the root
+//! * Empty segments (`//`) and `.` segments are removed
+//! * `..` removes the previous segment and never climbs above the root
+//!
+//! [`PathNormalizer::normalize`] clamps `..` segments at the root, which suits
+//! paths that are only compared. Tenant storage resolves client paths with
+//! [`PathNormalizer::try_normalize`] instead, which rejects a path that tries
+//! to climb above the root rather than quietly reinterpreting it.
+//! * Optionally, segments are converted to Unicode NFC (see below)
+//!
+//! The WebDAV layer addresses resources relative to the tenant root instead,
+//! using `.` for the root. [`PathNormalizer::to_relative`] derives that form
+//! from the canonical one so both layers always agree.
+//!
+//! # Unicode normalization
+//!
+//! macOS sends filenames in NFD (`é` as `e` followed by a combining accent)
+//! while most other systems use NFC, so the same name can arrive in two byte
+//! spellings. With [`PathNormalizer::with_unicode_nfc`] enabled every path is
+//! converted to NFC so both spellings address the same file. It is disabled by
+//! default for deployments that need byte-exact paths.
+
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::error::{StorageError, StorageResult};
+
+/// Normalizes paths to the canonical form used throughout Marble
+#[derive(Debug, Clone, Default)]
+pub struct PathNormalizer {
+    /// Whether paths are converted to Unicode NFC
+    unicode_nfc: bool,
+}
+
+impl PathNormalizer {
+    /// Create a new path normalizer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable Unicode NFC normalization
+    pub fn with_unicode_nfc(mut self, enabled: bool) -> Self {
+        self.unicode_nfc = enabled;
+        self
+    }
+
+    /// Whether paths are converted to Unicode NFC
+    pub fn unicode_nfc(&self) -> bool {
+        self.unicode_nfc
+    }
+
+    /// Normalize a path to its canonical absolute form
+    pub fn normalize(&self, path: &str) -> String {
+        self.resolve(path).0
+    }
+
+    /// Normalize a path, rejecting `..` segments that climb above the root
+    ///
+    /// Such a path can only come from a client trying to escape its namespace,
+    /// so it fails with [`StorageError::Validation`].
+    pub fn try_normalize(&self, path: &str) -> StorageResult<String> {
+        match self.resolve(path) {
+            (normalized, false) => Ok(normalized),
+            (_, true) => Err(StorageError::Validation(format!("Path escapes the root: {}", path))),
+        }
+    }
+
+    /// Normalize a path, reporting whether a `..` segment was clamped at the root
+    fn resolve(&self, path: &str) -> (String, bool) {
+        if self.unicode_nfc && !is_nfc(path) {
+            let composed: String = path.nfc().collect();
+            return Self::normalize_segments(&composed);
+        }
+
+        Self::normalize_segments(path)
+    }
+
+    /// Collapse separators and dot segments
+    fn normalize_segments(path: &str) -> (String, bool) {
+        let mut segments: Vec<&str> = Vec::new();
+        let mut escaped = false;
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    escaped |= segments.pop().is_none();
+                }
+                segment => segments.push(segment),
+            }
+        }
+
+        (format!("/{}", segments.join("/")), escaped)
+    }
+
+    /// Normalize a path to the form relative to the tenant root
+    ///
+    /// This is the canonical form without the leading slash, with `.` for the root.
+    pub fn to_relative(&self, path: &str) -> String {
+        let normalized = self.normalize(path);
+
+        if normalized == "/" {
+            ".".to_string()
+        } else {
+            normalized[1..].to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_table() {
+        let normalizer = PathNormalizer::new();
+
+        let cases = [
+            // Empty and root
+            ("", "/"),
+            ("/", "/"),
+            (".", "/"),
+            ("./", "/"),
+            ("//", "/"),
+            // Leading slash
+            ("notes.md", "/notes.md"),
+            ("/notes.md", "/notes.md"),
+            ("//notes.md", "/notes.md"),
+            // Trailing slash
+            ("dir/", "/dir"),
+            ("/dir/", "/dir"),
+            ("/dir//", "/dir"),
+            // Nested and duplicate separators
+            ("a/b/c.md", "/a/b/c.md"),
+            ("/a//b///c.md", "/a/b/c.md"),
+            // Dot segments
+            ("/a/./b", "/a/b"),
+            ("./a/b", "/a/b"),
+            ("/a/b/..", "/a"),
+            ("/a/../b", "/b"),
+            ("/..", "/"),
+            ("../../a", "/a"),
+            // Names that merely contain dots are kept
+            ("/.hidden", "/.hidden"),
+            ("/a/..b/c...", "/a/..b/c..."),
+            ("/dir/.dir", "/dir/.dir"),
+            // Spaces are preserved
+            ("/My Notes/today.md", "/My Notes/today.md"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(normalizer.normalize(input), expected, "normalize({:?})", input);
+        }
+    }
+
+    #[test]
+    fn test_to_relative_table() {
+        let normalizer = PathNormalizer::new();
+
+        let cases = [
+            ("", "."),
+            ("/", "."),
+            (".", "."),
+            ("notes.md", "notes.md"),
+            ("/notes.md", "notes.md"),
+            ("/dir/", "dir"),
+            ("/a//b/./c.md", "a/b/c.md"),
+            ("/a/b/../c", "a/c"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(normalizer.to_relative(input), expected, "to_relative({:?})", input);
+        }
+    }
+
+    #[test]
+    fn test_try_normalize_rejects_escape() {
+        let normalizer = PathNormalizer::new();
+
+        // Separators and dot segments inside the root are canonicalized
+        assert_eq!(normalizer.try_normalize("/a/./b").unwrap(), "/a/b");
+        assert_eq!(normalizer.try_normalize("/a//b///c.md").unwrap(), "/a/b/c.md");
+        assert_eq!(normalizer.try_normalize("/a/b/../c").unwrap(), "/a/c");
+        assert_eq!(normalizer.try_normalize("/a/..").unwrap(), "/");
+        assert_eq!(normalizer.try_normalize("/a/..b").unwrap(), "/a/..b");
+
+        // Climbing above the root is rejected
+        for input in ["..", "/..", "../a", "/a/../../b", "//a/./../../b", "/a/b/../../.."] {
+            assert!(
+                matches!(normalizer.try_normalize(input), Err(StorageError::Validation(_))),
+                "try_normalize({:?})",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_unicode_nfc() {
+        let nfc = "/Caf\u{e9}/r\u{e9}sum\u{e9}.md";
+        let nfd = "/Cafe\u{301}/re\u{301}sume\u{301}.md";
+
+        let byte_exact = PathNormalizer::new();
+        assert!(!byte_exact.unicode_nfc());
+        assert_eq!(byte_exact.normalize(nfc), nfc);
+        assert_eq!(byte_exact.normalize(nfd), nfd);
+
+        let composing = PathNormalizer::new().with_unicode_nfc(true);
+        assert_eq!(composing.normalize(nfc), nfc);
+        assert_eq!(composing.normalize(nfd), nfc);
+        assert_eq!(composing.to_relative(nfd), &nfc[1..]);
+
+        // Structural normalization still applies
+        assert_eq!(composing.normalize("//Cafe\u{301}/./x/../"), "/Caf\u{e9}");
+    }
+
+    #[test]
+    fn test_forms_agree() {
+        let normalizer = PathNormalizer::new();
+
+        // Both forms of the same input describe the same path
+        for input in ["", "/", "a", "/a/b/", "./a//b", "/a/../b"] {
+            let absolute = normalizer.normalize(input);
+            let relative = normalizer.to_relative(input);
+            assert_eq!(normalizer.normalize(&relative), absolute);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Throttled last-access tracking
+//!
+//! Recording every read would turn reads into writes. The tracker lets at
+//! most one access record per file through per window; reads inside the
+//! window are coalesced into the record that opened it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Decides which reads are recorded as accesses
+#[derive(Debug)]
+pub struct AccessTracker {
+    /// Minimum time between two recorded accesses of the same file
+    window: Duration,
+
+    /// When each file's access was last recorded
+    recorded: Mutex<HashMap<i32, Instant>>,
+}
+
+impl AccessTracker {
+    /// Create a tracker recording each file at most once per `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            recorded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The throttle window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Check whether a read of the file should be recorded now
+    ///
+    /// Returns true for the first read of a file in each window and marks the
+    /// window as started; every other read in the window returns false.
+    pub fn should_record(&self, file_id: i32) -> bool {
+        let now = Instant::now();
+        let mut recorded = self.recorded.lock().unwrap();
+
+        if let Some(last) = recorded.get(&file_id) {
+            if now.duration_since(*last) < self.window {
+                return false;
+            }
+        }
+
+        // Forget files whose window has passed so the map stays bounded by
+        // the files read within one window
+        if recorded.len() >= 1024 {
+            recorded.retain(|_, last| now.duration_since(*last) < self.window);
+        }
+
+        recorded.insert(file_id, now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_coalesce_within_window() {
+        let tracker = AccessTracker::new(Duration::from_millis(50));
+
+        assert!(tracker.should_record(1));
+        for _ in 0..100 {
+            assert!(!tracker.should_record(1));
+        }
+
+        // Other files have windows of their own
+        assert!(tracker.should_record(2));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(tracker.should_record(1));
+        assert!(!tracker.should_record(1));
+    }
+}
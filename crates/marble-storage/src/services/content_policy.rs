@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use crate::error::{StorageError, StorageResult};
+
+/// Policy restricting which content types may be uploaded
+///
+/// Entries are either MIME types (`application/x-msdownload`), MIME type
+/// wildcards (`image/*`) or file extensions starting with a dot (`.exe`).
+/// Uploads are checked against both the declared content type and the type
+/// sniffed from the content, so renaming an executable to `.txt` does not
+/// bypass the policy.
+#[derive(Debug, Clone, Default)]
+pub enum ContentTypePolicy {
+    /// Accept every content type
+    #[default]
+    AllowAll,
+
+    /// Accept only the listed types
+    Allow(Vec<String>),
+
+    /// Reject the listed types
+    Deny(Vec<String>),
+}
+
+impl ContentTypePolicy {
+    /// Check an upload against the policy
+    ///
+    /// # Arguments
+    /// * `path` - The path the content is written to
+    /// * `declared_type` - The declared (or guessed) MIME type
+    /// * `content` - The content being written
+    ///
+    /// # Returns
+    /// * Ok(()) if the upload is allowed, `StorageError::ContentTypeNotAllowed` otherwise
+    pub fn check(&self, path: &str, declared_type: &str, content: &[u8]) -> StorageResult<()> {
+        let sniffed_type = sniff_content_type(content);
+
+        match self {
+            ContentTypePolicy::AllowAll => Ok(()),
+            ContentTypePolicy::Allow(entries) => {
+                let declared_allowed = entries.iter().any(|entry| {
+                    matches_mime(entry, declared_type) || matches_extension(entry, path)
+                });
+                let sniffed_allowed = sniffed_type
+                    .is_none_or(|sniffed| entries.iter().any(|entry| matches_mime(entry, sniffed)));
+
+                if declared_allowed && sniffed_allowed {
+                    Ok(())
+                } else {
+                    Err(not_allowed(path, declared_type, sniffed_type))
+                }
+            }
+            ContentTypePolicy::Deny(entries) => {
+                let denied = entries.iter().any(|entry| {
+                    matches_mime(entry, declared_type)
+                        || matches_extension(entry, path)
+                        || sniffed_type.is_some_and(|sniffed| matches_mime(entry, sniffed))
+                });
+
+                if denied {
+                    Err(not_allowed(path, declared_type, sniffed_type))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Sniff the content type from well-known magic bytes
+///
+/// Only formats relevant to upload policies are recognised; anything else
+/// returns `None` and is judged by its declared type alone.
+pub fn sniff_content_type(content: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"MZ", "application/x-msdownload"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"\xfe\xed\xfa\xce", "application/x-mach-binary"),
+        (b"\xfe\xed\xfa\xcf", "application/x-mach-binary"),
+        (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"#!", "text/x-shellscript"),
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| content.starts_with(magic))
+        .map(|(_, mime)| *mime)
+}
+
+/// Check whether a policy entry matches a MIME type
+fn matches_mime(entry: &str, mime: &str) -> bool {
+    if entry.starts_with('.') {
+        return false;
+    }
+
+    // Ignore parameters such as `; charset=utf-8`
+    let mime = mime.split(';').next().unwrap_or("").trim();
+
+    match entry.strip_suffix("/*") {
+        Some(prefix) => mime
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind.eq_ignore_ascii_case(prefix)),
+        None => entry.eq_ignore_ascii_case(mime),
+    }
+}
+
+/// Check whether a policy entry matches the extension of a path
+fn matches_extension(entry: &str, path: &str) -> bool {
+    match (entry.strip_prefix('.'), Path::new(path).extension()) {
+        (Some(wanted), Some(actual)) => actual.to_string_lossy().eq_ignore_ascii_case(wanted),
+        _ => false,
+    }
+}
+
+fn not_allowed(path: &str, declared_type: &str, sniffed_type: Option<&str>) -> StorageError {
+    StorageError::ContentTypeNotAllowed(format!(
+        "{}: declared {}, detected {}",
+        path,
+        declared_type,
+        sniffed_type.unwrap_or("unknown")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXECUTABLE: &[u8] = b"MZ\x90\x00\x03\x00\x00\x00";
+
+    fn deny_executables() -> ContentTypePolicy {
+        ContentTypePolicy::Deny(vec![
+            "application/x-msdownload".to_string(),
+            ".exe".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_allow_all() {
+        let policy = ContentTypePolicy::AllowAll;
+        assert!(policy.check("/tool.exe", "application/x-msdownload", EXECUTABLE).is_ok());
+    }
+
+    #[test]
+    fn test_deny_list() {
+        let policy = deny_executables();
+
+        // Allowed type succeeds
+        assert!(policy.check("/notes.md", "text/markdown", b"# Notes").is_ok());
+
+        // Rejected by declared type
+        assert!(matches!(
+            policy.check("/download", "application/x-msdownload", b"plain"),
+            Err(StorageError::ContentTypeNotAllowed(_))
+        ));
+
+        // Rejected by extension
+        assert!(policy.check("/setup.EXE", "application/octet-stream", b"plain").is_err());
+
+        // Rejected by sniffed type even when disguised as text
+        assert!(policy.check("/readme.txt", "text/plain", EXECUTABLE).is_err());
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let policy = ContentTypePolicy::Allow(vec![
+            "text/*".to_string(),
+            "image/png".to_string(),
+            ".canvas".to_string(),
+        ]);
+
+        assert!(policy.check("/notes.md", "text/markdown; charset=utf-8", b"# Notes").is_ok());
+        assert!(policy.check("/board.canvas", "application/octet-stream", b"{}").is_ok());
+        assert!(policy.check("/image.png", "image/png", b"\x89PNG\r\n\x1a\n").is_ok());
+
+        // Declared type not in the list
+        assert!(policy.check("/doc.pdf", "application/pdf", b"plain").is_err());
+
+        // Declared type allowed but sniffed type is not
+        assert!(policy.check("/notes.txt", "text/plain", EXECUTABLE).is_err());
+    }
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(sniff_content_type(EXECUTABLE), Some("application/x-msdownload"));
+        assert_eq!(sniff_content_type(b"\x7fELF\x02\x01"), Some("application/x-executable"));
+        assert_eq!(sniff_content_type(b"#!/bin/sh\n"), Some("text/x-shellscript"));
+        assert_eq!(sniff_content_type(b"# Heading"), None);
+        assert_eq!(sniff_content_type(b""), None);
+    }
+}
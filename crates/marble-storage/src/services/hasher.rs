@@ -1,20 +1,106 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, StreamExt};
 use opendal::Operator;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
-use crate::backends::hash::{exists_by_hash, get_content_by_hash, put_content_by_hash};
+use crate::backends::hash::{exists_by_hash, get_content_by_hash, put_content_if_absent, stream_content_by_hash};
+use crate::config::{Compression, StorageConfig};
 use crate::error::{StorageError, StorageResult};
 use crate::hash::hash_content;
 
+/// Bytes read ahead per chunk when streaming content, unless configured
+pub const DEFAULT_READ_CHUNK_SIZE: usize = 256 * 1024;
+
 /// Service for handling content hashing and storage
+///
+/// Writes and reads can each be bounded to a number of operations in flight,
+/// shared by every clone of the hasher, so bulk imports wait for a permit
+/// instead of overwhelming the backend.
 #[derive(Clone)]
 pub struct ContentHasher {
     /// The OpenDAL operator for the hash storage
     operator: Operator,
+    
+    /// Permits for content writes; unbounded if unset
+    write_permits: Option<Arc<Semaphore>>,
+    
+    /// Permits for content reads; unbounded if unset
+    read_permits: Option<Arc<Semaphore>>,
+    
+    /// Bytes read ahead per chunk when streaming content
+    read_chunk_size: usize,
+    
+    /// Compression of newly stored content; reads handle either form
+    compression: Option<Compression>,
 }
 
 impl ContentHasher {
     /// Create a new ContentHasher with the given operator
     pub fn new(operator: Operator) -> Self {
-        Self { operator }
+        Self {
+            operator,
+            write_permits: None,
+            read_permits: None,
+            read_chunk_size: DEFAULT_READ_CHUNK_SIZE,
+            compression: None,
+        }
+    }
+    
+    /// Create a ContentHasher applying the concurrency limits of the configuration
+    pub fn for_config(operator: Operator, config: &StorageConfig) -> Self {
+        let mut hasher = Self::new(operator);
+        if let Some(max) = config.max_concurrent_writes {
+            hasher = hasher.with_max_concurrent_writes(max);
+        }
+        if let Some(max) = config.max_concurrent_reads {
+            hasher = hasher.with_max_concurrent_reads(max);
+        }
+        if let Some(chunk_size) = config.read_chunk_size {
+            hasher = hasher.with_read_chunk_size(chunk_size);
+        }
+        if let Some(compression) = config.compression {
+            hasher = hasher.with_compression(compression);
+        }
+        hasher
+    }
+    
+    /// Allow at most `max` content writes in flight at once
+    pub fn with_max_concurrent_writes(mut self, max: usize) -> Self {
+        self.write_permits = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+    
+    /// Allow at most `max` content reads in flight at once
+    pub fn with_max_concurrent_reads(mut self, max: usize) -> Self {
+        self.read_permits = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+    
+    /// Read ahead `chunk_size` bytes at a time when streaming content
+    ///
+    /// Larger chunks mean fewer requests on high-latency backends such as S3,
+    /// at the cost of memory per open stream.
+    pub fn with_read_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.read_chunk_size = chunk_size.max(1);
+        self
+    }
+    
+    /// Compress content before storing it
+    ///
+    /// Hashes, and so deduplication, are computed on the uncompressed content,
+    /// and reads return it uncompressed whether or not it was stored compressed.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+    
+    /// Wait for a permit from a limit, if there is one
+    ///
+    /// The semaphores are never closed, so acquiring only fails without a limit.
+    async fn acquire(permits: &Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+        permits.clone()?.acquire_owned().await.ok()
     }
     
     /// Store content and return its hash
@@ -22,20 +108,43 @@ impl ContentHasher {
     /// If the content already exists (based on its hash), it won't be stored again.
     /// This provides automatic deduplication of content.
     pub async fn store_content(&self, content: &[u8]) -> StorageResult<String> {
+        let (hash, _) = self.store_content_if_absent(content).await?;
+        Ok(hash)
+    }
+    
+    /// Store content and return its hash and whether a new blob was written
+    ///
+    /// The flag is `false` when the content was already stored.
+    pub async fn store_content_if_absent(&self, content: &[u8]) -> StorageResult<(String, bool)> {
         // Generate hash for the content
         let hash = hash_content(content)?;
         
         // Store content in hash-based storage
-        put_content_by_hash(&self.operator, &hash, content.to_vec()).await?;
+        let _permit = Self::acquire(&self.write_permits).await;
+        let written = put_content_if_absent(&self.operator, &hash, content.to_vec(), self.compression).await?;
         
-        Ok(hash)
+        Ok((hash, written))
     }
     
     /// Retrieve content by its hash
     pub async fn get_content(&self, hash: &str) -> StorageResult<Vec<u8>> {
+        let _permit = Self::acquire(&self.read_permits).await;
         get_content_by_hash(&self.operator, hash).await
     }
     
+    /// Stream content by its hash without buffering it whole
+    ///
+    /// The read permit is held until the stream is dropped.
+    pub async fn stream_content(&self, hash: &str) -> StorageResult<BoxStream<'static, StorageResult<Bytes>>> {
+        let permit = Self::acquire(&self.read_permits).await;
+        let stream = stream_content_by_hash(&self.operator, hash, self.read_chunk_size).await?;
+        Ok(stream
+            .inspect(move |_| {
+                let _held = &permit;
+            })
+            .boxed())
+    }
+    
     /// Check if content with the given hash exists
     pub async fn content_exists(&self, hash: &str) -> StorageResult<bool> {
         exists_by_hash(&self.operator, hash).await
@@ -67,7 +176,8 @@ impl ContentHasher {
         }
         
         // Store the content
-        put_content_by_hash(&self.operator, &actual_hash, content.to_vec()).await?;
+        let _permit = Self::acquire(&self.write_permits).await;
+        put_content_if_absent(&self.operator, &actual_hash, content.to_vec(), self.compression).await?;
         
         Ok(actual_hash)
     }
@@ -81,10 +191,15 @@ impl ContentHasher {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::TryStreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use async_trait::async_trait;
+    use opendal::raw::{Accessor, Layer, LayeredAccessor, OpList, OpRead, OpWrite, RpList, RpRead, RpWrite};
+    use opendal::services::Memory;
     use tempfile::tempdir;
     use tokio::test;
     use crate::backends::hash::create_hash_storage;
-    use crate::config::StorageConfig;
 
     async fn setup_test_hasher() -> (ContentHasher, tempfile::TempDir) {
         // Create a temporary directory
@@ -178,4 +293,158 @@ mod tests {
         let retrieved = hasher.get_content(&hash1).await.expect("Retrieval failed");
         assert_eq!(retrieved, content);
     }
+
+    #[test]
+    async fn test_compressed_round_trip() {
+        let (hasher, _temp_dir) = setup_test_hasher().await;
+        let plain = hasher.clone();
+        let hasher = hasher.with_compression(Compression::Zstd { level: 3 });
+        
+        // Markdown compresses well and reads back byte-identical
+        let content = "# Notes\n\nSome repeated text. ".repeat(200).into_bytes();
+        let hash = hasher.store_content(&content).await.expect("Failed to store content");
+        assert_eq!(hash, hasher.compute_hash(&content).unwrap(), "Key is the hash of the uncompressed content");
+        
+        let stored = hasher.operator().read(&crate::hash::hash_to_path(&hash)).await.unwrap();
+        assert!(stored.len() < content.len() / 10, "Stored object should be compressed");
+        
+        assert_eq!(hasher.get_content(&hash).await.unwrap(), content);
+        let streamed: Vec<Bytes> = hasher.stream_content(&hash).await.unwrap().try_collect().await.unwrap();
+        assert_eq!(streamed.concat(), content);
+        
+        // Reads do not depend on the writer's setting
+        assert_eq!(plain.get_content(&hash).await.unwrap(), content);
+        
+        // Content stored as is before compression stays readable, even a zstd frame itself
+        let frame = zstd::stream::encode_all(&b"already compressed"[..], 3).unwrap();
+        let frame_hash = plain.store_content(&frame).await.unwrap();
+        let uncompressed_hash = plain.store_content(b"stored before").await.unwrap();
+        assert_eq!(hasher.get_content(&frame_hash).await.unwrap(), frame);
+        assert_eq!(hasher.get_content(&uncompressed_hash).await.unwrap(), b"stored before");
+        let streamed: Vec<Bytes> = hasher.stream_content(&frame_hash).await.unwrap().try_collect().await.unwrap();
+        assert_eq!(streamed.concat(), frame);
+    }
+
+    #[test]
+    async fn test_stream_with_custom_chunk_size() {
+        let (hasher, _temp_dir) = setup_test_hasher().await;
+        
+        // Larger than several chunks and not a multiple of the chunk size
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let hash = hasher.store_content(&content).await.expect("Failed to store content");
+        
+        for chunk_size in [1000, 4096, DEFAULT_READ_CHUNK_SIZE] {
+            let hasher = hasher.clone().with_read_chunk_size(chunk_size);
+            let chunks: Vec<Bytes> = hasher
+                .stream_content(&hash)
+                .await
+                .expect("Failed to open stream")
+                .try_collect()
+                .await
+                .expect("Failed to read stream");
+            assert_eq!(chunks.concat(), content, "chunk size {}", chunk_size);
+        }
+    }
+
+    /// Writes in flight through an operator, and the most seen at once
+    #[derive(Debug, Default)]
+    struct Concurrency {
+        in_flight: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    /// Layer recording how many writes are opened concurrently
+    struct TrackWritesLayer(Arc<Concurrency>);
+
+    impl<A: Accessor> Layer<A> for TrackWritesLayer {
+        type LayeredAccessor = TrackWritesAccessor<A>;
+
+        fn layer(&self, inner: A) -> Self::LayeredAccessor {
+            TrackWritesAccessor {
+                inner,
+                concurrency: self.0.clone(),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct TrackWritesAccessor<A> {
+        inner: A,
+        concurrency: Arc<Concurrency>,
+    }
+
+    #[async_trait]
+    impl<A: Accessor> LayeredAccessor for TrackWritesAccessor<A> {
+        type Inner = A;
+        type Reader = A::Reader;
+        type BlockingReader = A::BlockingReader;
+        type Writer = A::Writer;
+        type BlockingWriter = A::BlockingWriter;
+        type Lister = A::Lister;
+        type BlockingLister = A::BlockingLister;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn read(&self, path: &str, args: OpRead) -> opendal::Result<(RpRead, Self::Reader)> {
+            self.inner.read(path, args).await
+        }
+
+        async fn write(&self, path: &str, args: OpWrite) -> opendal::Result<(RpWrite, Self::Writer)> {
+            let in_flight = self.concurrency.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.concurrency.max.fetch_max(in_flight, Ordering::SeqCst);
+
+            // Slow enough for unbounded stores to overlap
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let result = self.inner.write(path, args).await;
+
+            self.concurrency.in_flight.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+
+        async fn list(&self, path: &str, args: OpList) -> opendal::Result<(RpList, Self::Lister)> {
+            self.inner.list(path, args).await
+        }
+
+        fn blocking_read(&self, path: &str, args: OpRead) -> opendal::Result<(RpRead, Self::BlockingReader)> {
+            self.inner.blocking_read(path, args)
+        }
+
+        fn blocking_write(&self, path: &str, args: OpWrite) -> opendal::Result<(RpWrite, Self::BlockingWriter)> {
+            self.inner.blocking_write(path, args)
+        }
+
+        fn blocking_list(&self, path: &str, args: OpList) -> opendal::Result<(RpList, Self::BlockingLister)> {
+            self.inner.blocking_list(path, args)
+        }
+    }
+
+    /// Store distinct contents all at once, returning the most writes seen in flight
+    async fn max_concurrent_stores(limit: Option<usize>, stores: usize) -> usize {
+        let concurrency = Arc::new(Concurrency::default());
+        let operator = Operator::new(Memory::default())
+            .unwrap()
+            .layer(TrackWritesLayer(concurrency.clone()))
+            .finish();
+        let hasher = match limit {
+            Some(limit) => ContentHasher::new(operator).with_max_concurrent_writes(limit),
+            None => ContentHasher::new(operator),
+        };
+
+        let contents: Vec<Vec<u8>> = (0..stores).map(|i| format!("content {}", i).into_bytes()).collect();
+        let results = futures::future::join_all(contents.iter().map(|content| hasher.store_content(content))).await;
+        assert!(results.iter().all(Result::is_ok));
+
+        concurrency.max.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    async fn test_concurrent_stores_bounded() {
+        // Without a limit the stores overlap freely
+        assert!(max_concurrent_stores(None, 8).await > 2);
+
+        assert_eq!(max_concurrent_stores(Some(2), 8).await, 2);
+        assert_eq!(max_concurrent_stores(Some(1), 4).await, 1);
+    }
 }
@@ -1,2 +1,11 @@
 // Service for content hashing and storage
 pub mod hasher;
+
+// Upload content-type policy
+pub mod content_policy;
+
+// Garbage collection and scrubbing of hash storage
+pub mod maintenance;
+
+// Throttled last-access tracking
+pub mod access;
@@ -0,0 +1,55 @@
+//! Fixed test vectors for content hashing
+//!
+//! Deduplication, ETags, integrity checks and the hash store layout all depend
+//! on `hash_content` returning the same value for the same bytes forever. These
+//! digests were computed independently (blake2b-256, base64url without
+//! padding) and must never be updated to make a failing test pass: a change
+//! here means every stored hash is invalidated.
+
+use crate::hash::{hash_content, hash_to_path, path_to_hash};
+
+/// Bytes 0 through 255 in order
+fn binary_blob() -> Vec<u8> {
+    (0..=255u8).collect()
+}
+
+/// Inputs with their expected digests
+fn vectors() -> Vec<(&'static str, Vec<u8>, &'static str)> {
+    vec![
+        ("empty", Vec::new(), "DldRwCblQ7Loqy6wYJnaodHl30d3j3eH-qtFzfEv46g"),
+        ("abc", b"abc".to_vec(), "vd2BPGNCOXIxce8_7phXm5SWTjuxyz5CcmLIwGjVIxk"),
+        ("binary", binary_blob(), "Oafrn-3BmqvINCXGdV3ZDm-dDIBJZKH0qu6juftZmDU"),
+    ]
+}
+
+#[test]
+fn test_hash_content_vectors() {
+    for (name, input, expected) in vectors() {
+        assert_eq!(hash_content(&input).unwrap(), expected, "hash of {} input changed", name);
+    }
+}
+
+#[test]
+fn test_hash_content_encoding() {
+    for (name, input, _) in vectors() {
+        let hash = hash_content(&input).unwrap();
+        
+        // 32 bytes in base64url without padding
+        assert_eq!(hash.len(), 43, "length of {} hash changed", name);
+        assert!(
+            hash.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            "{} hash is not base64url: {}",
+            name,
+            hash
+        );
+    }
+}
+
+#[test]
+fn test_hash_to_path_vectors() {
+    for (name, input, expected) in vectors() {
+        let path = hash_to_path(&hash_content(&input).unwrap());
+        assert_eq!(path, format!("/.hash/{}", expected), "path of {} hash changed", name);
+        assert_eq!(path_to_hash(&path).unwrap(), expected);
+    }
+}
@@ -1,4 +1,5 @@
 //! Integration tests for marble-storage
 
+mod hash_vectors_test;
 mod raw_storage_test;
 mod tenant_storage_test;
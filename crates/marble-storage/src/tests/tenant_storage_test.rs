@@ -3,13 +3,13 @@ use std::sync::Arc;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::types::chrono::Utc;
 use uuid::Uuid;
-use tempfile::tempdir;
+use tempfile::{tempdir, TempDir};
 
 use crate::api::tenant::TenantStorage;
 use crate::config::StorageConfig;
 use crate::backends::hash::create_hash_storage;
 use crate::services::hasher::ContentHasher;
-use crate::error::StorageResult;
+use crate::error::StorageError;
 use crate::create_tenant_storage;
 
 async fn setup_test_db() -> Result<Arc<sqlx::PgPool>, crate::error::StorageError> {
@@ -21,7 +21,7 @@ async fn setup_test_db() -> Result<Arc<sqlx::PgPool>, crate::error::StorageError
         .max_connections(5)
         .connect(&db_url)
         .await
-        .map_err(|e| crate::error::StorageError::Database(e))?;
+        .map_err(crate::error::StorageError::Database)?;
         
     Ok(Arc::new(pool))
 }
@@ -41,7 +41,7 @@ async fn setup_test_user(pool: &sqlx::PgPool, username: &str) -> Result<(i32, Uu
     .bind(test_uuid)
     .fetch_one(pool)
     .await
-    .map_err(|e| crate::error::StorageError::Database(e))?;
+    .map_err(crate::error::StorageError::Database)?;
     
     Ok((user_id, test_uuid))
 }
@@ -58,12 +58,7 @@ async fn setup_tenant_storage_test() -> Option<(Arc<dyn TenantStorage>, Uuid, Uu
     };
     
     // Clean up any existing test users
-    let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username IN ('tenant_test_user1', 'tenant_test_user2'))")
-        .execute(&*db_pool)
-        .await;
-    let _ = sqlx::query("DELETE FROM users WHERE username IN ('tenant_test_user1', 'tenant_test_user2')")
-        .execute(&*db_pool)
-        .await;
+    remove_test_users(&db_pool, &["tenant_test_user1", "tenant_test_user2"]).await;
     
     // Create two test users
     let (_, user1_uuid) = match setup_test_user(&db_pool, "tenant_test_user1").await {
@@ -82,26 +77,8 @@ async fn setup_tenant_storage_test() -> Option<(Arc<dyn TenantStorage>, Uuid, Uu
         }
     };
     
-    // Create a temp directory for hash storage
-    let temp_dir = match tempdir() {
-        Ok(dir) => dir,
-        Err(_) => {
-            println!("Failed to create temp dir");
-            return None;
-        }
-    };
-    
     // Create a content hasher
-    let config = StorageConfig::new_fs(temp_dir.path().to_path_buf());
-    let hash_operator = match create_hash_storage(&config) {
-        Ok(op) => op,
-        Err(_) => {
-            println!("Failed to create hash storage");
-            return None;
-        }
-    };
-    
-    let content_hasher = ContentHasher::new(hash_operator);
+    let (content_hasher, _temp_dir) = setup_hash_store()?;
     
     // Create the tenant storage
     let tenant_storage = match create_tenant_storage(db_pool.clone(), content_hasher).await {
@@ -117,14 +94,85 @@ async fn setup_tenant_storage_test() -> Option<(Arc<dyn TenantStorage>, Uuid, Uu
 
 /// Clean up test data
 async fn cleanup_tenant_storage_test(db_pool: &Arc<sqlx::PgPool>) {
-    let _ = sqlx::query("DELETE FROM files WHERE user_id IN (SELECT id FROM users WHERE username IN ('tenant_test_user1', 'tenant_test_user2'))")
-        .execute(&**db_pool)
+    remove_test_users(db_pool, &["tenant_test_user1", "tenant_test_user2"]).await;
+}
+
+/// Create one test user with a hash store of its own
+///
+/// For tests that configure the storage themselves. Leftovers of an earlier
+/// run are removed first. The temp dir holds the hash store and has to be
+/// kept until the test ends.
+async fn setup_tenant_user_test(
+    username: &str,
+) -> Option<(Arc<sqlx::PgPool>, i32, Uuid, ContentHasher, TempDir)> {
+    let db_pool = match setup_test_db().await {
+        Ok(pool) => pool,
+        Err(_) => {
+            println!("Skipping test - no test database available");
+            return None;
+        }
+    };
+    
+    remove_test_users(&db_pool, &[username]).await;
+    
+    let (user_id, user_uuid) = setup_test_user(&db_pool, username)
+        .await
+        .expect("Failed to create test user");
+    let (content_hasher, temp_dir) = setup_hash_store()?;
+    
+    Some((db_pool, user_id, user_uuid, content_hasher, temp_dir))
+}
+
+/// Clean up a user created by `setup_tenant_user_test`
+async fn cleanup_tenant_user_test(db_pool: &Arc<sqlx::PgPool>, user_id: i32) {
+    for table in ["files", "folders", "users"] {
+        let column = if table == "users" { "id" } else { "user_id" };
+        let _ = sqlx::query(&format!("DELETE FROM {} WHERE {} = $1", table, column))
+            .bind(user_id)
+            .execute(&**db_pool)
+            .await;
+    }
+}
+
+/// Remove test users by name, along with everything they own
+async fn remove_test_users(db_pool: &sqlx::PgPool, usernames: &[&str]) {
+    for table in ["files", "folders"] {
+        let _ = sqlx::query(&format!(
+            "DELETE FROM {} WHERE user_id IN (SELECT id FROM users WHERE username = ANY($1))",
+            table
+        ))
+        .bind(usernames)
+        .execute(db_pool)
         .await;
-    let _ = sqlx::query("DELETE FROM users WHERE username IN ('tenant_test_user1', 'tenant_test_user2')")
-        .execute(&**db_pool)
+    }
+    let _ = sqlx::query("DELETE FROM users WHERE username = ANY($1)")
+        .bind(usernames)
+        .execute(db_pool)
         .await;
 }
 
+/// Create a content hasher over a hash store in a fresh temp dir
+fn setup_hash_store() -> Option<(ContentHasher, TempDir)> {
+    let temp_dir = match tempdir() {
+        Ok(dir) => dir,
+        Err(_) => {
+            println!("Failed to create temp dir");
+            return None;
+        }
+    };
+    
+    let config = StorageConfig::new_fs(temp_dir.path().to_path_buf());
+    let hash_operator = match create_hash_storage(&config) {
+        Ok(op) => op,
+        Err(_) => {
+            println!("Failed to create hash storage");
+            return None;
+        }
+    };
+    
+    Some((ContentHasher::new(hash_operator), temp_dir))
+}
+
 /// Test basic operations with the tenant storage
 #[tokio::test]
 async fn test_tenant_storage_basic_operations() {
@@ -405,4 +453,1773 @@ async fn test_tenant_storage_list() {
     
     // Clean up
     cleanup_tenant_storage_test(&db_pool).await;
-}
\ No newline at end of file
+}
+
+/// Test that `.dir` placeholders stay out of listings but still mark directories
+#[tokio::test]
+async fn test_tenant_storage_list_hides_placeholders() {
+    let (tenant_storage, user1_uuid, _, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    tenant_storage.create_directory(&user1_uuid, "/docs")
+        .await
+        .expect("Failed to create directory");
+    tenant_storage.create_directory(&user1_uuid, "/docs/empty")
+        .await
+        .expect("Failed to create subdirectory");
+    tenant_storage.write(&user1_uuid, "/docs/readme.md", b"# Docs".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    
+    // Only the real file and the empty subdirectory are listed
+    let mut files = tenant_storage.list(&user1_uuid, "/docs")
+        .await
+        .expect("Failed to list directory");
+    files.sort();
+    assert_eq!(files, vec!["/docs/empty".to_string(), "/docs/readme.md".to_string()]);
+    
+    let listed = tenant_storage.list_with_metadata(&user1_uuid, "/docs", crate::api::ListOrder::Path)
+        .await
+        .expect("Failed to list directory with metadata");
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|entry| !entry.path.ends_with("/.dir")));
+    assert!(listed.iter().any(|entry| entry.path == "/docs/empty" && entry.is_directory));
+    
+    // The directories still exist
+    assert!(tenant_storage.exists(&user1_uuid, "/docs/empty").await.unwrap());
+    let metadata = tenant_storage.metadata(&user1_uuid, "/docs/empty")
+        .await
+        .expect("Failed to get directory metadata");
+    assert!(metadata.is_directory);
+    assert_eq!(metadata.path, "/docs/empty");
+    
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+fn metadata_fixture(path: &str, size: u64, last_modified: u64) -> crate::api::FileMetadata {
+    crate::api::FileMetadata {
+        path: path.to_string(),
+        size,
+        content_type: "text/markdown".to_string(),
+        is_directory: false,
+        last_modified: Some(last_modified),
+        last_accessed: None,
+        content_hash: None,
+    }
+}
+
+/// Test each listing order against entries with distinct timestamps and sizes
+#[test]
+fn test_sort_metadata_orders() {
+    use crate::api::tenant::sort_metadata;
+    use crate::api::ListOrder;
+    
+    let entries = || vec![
+        metadata_fixture("/b.md", 100, 3_000),
+        metadata_fixture("/c.md", 200, 2_000),
+        metadata_fixture("/a.md", 300, 1_000),
+    ];
+    let paths = |entries: &[crate::api::FileMetadata]| {
+        entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>()
+    };
+    
+    let mut by_path = entries();
+    sort_metadata(&mut by_path, ListOrder::Path);
+    assert_eq!(paths(&by_path), vec!["/a.md", "/b.md", "/c.md"]);
+    
+    let mut by_modified = entries();
+    sort_metadata(&mut by_modified, ListOrder::ModifiedDesc);
+    assert_eq!(paths(&by_modified), vec!["/b.md", "/c.md", "/a.md"]);
+    
+    let mut by_size = entries();
+    sort_metadata(&mut by_size, ListOrder::SizeDesc);
+    assert_eq!(paths(&by_size), vec!["/a.md", "/c.md", "/b.md"]);
+}
+
+/// Test that the mock storage honours the listing order
+#[tokio::test]
+async fn test_mock_list_with_metadata_order() {
+    use crate::api::ListOrder;
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_directory(&tenant_id, "docs");
+    storage.add_file(&tenant_id, "docs/small.md", vec![0; 10]);
+    storage.add_file(&tenant_id, "docs/large.md", vec![0; 30]);
+    storage.add_file(&tenant_id, "docs/medium.md", vec![0; 20]);
+    
+    let by_path = storage.list_with_metadata(&tenant_id, "docs", ListOrder::Path)
+        .await
+        .expect("Failed to list directory");
+    let paths: Vec<_> = by_path.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, vec!["docs/large.md", "docs/medium.md", "docs/small.md"]);
+    
+    let by_size = storage.list_with_metadata(&tenant_id, "docs", ListOrder::SizeDesc)
+        .await
+        .expect("Failed to list directory");
+    let sizes: Vec<_> = by_size.iter().map(|e| e.size).collect();
+    assert_eq!(sizes, vec![30, 20, 10]);
+}
+
+/// Test that the content-type policy rejects blacklisted uploads
+#[tokio::test]
+async fn test_tenant_storage_content_type_policy() {
+    use crate::{ContentTypePolicy, MarbleTenantStorage};
+    use crate::error::StorageError;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_policy_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_content_type_policy(ContentTypePolicy::Deny(vec![
+            "application/x-msdownload".to_string(),
+            ".exe".to_string(),
+        ]));
+    
+    // Allowed type succeeds
+    storage.write(&user_uuid, "/notes.md", b"# Notes".to_vec(), None)
+        .await
+        .expect("Allowed upload should succeed");
+    
+    // Blacklisted by declared type
+    let result = storage.write(&user_uuid, "/tool.bin", b"plain".to_vec(), Some("application/x-msdownload")).await;
+    assert!(matches!(result, Err(StorageError::ContentTypeNotAllowed(_))));
+    
+    // Blacklisted by sniffed type, disguised as text
+    let result = storage.write(&user_uuid, "/readme.txt", b"MZ\x90\x00".to_vec(), Some("text/plain")).await;
+    assert!(matches!(result, Err(StorageError::ContentTypeNotAllowed(_))));
+    assert!(!storage.exists(&user_uuid, "/readme.txt").await.unwrap());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that NFD and NFC spellings of a filename resolve to one file only with NFC enabled
+#[tokio::test]
+async fn test_tenant_storage_unicode_nfc() {
+    use crate::{MarbleTenantStorage, PathNormalizer};
+    
+    let db_pool = match setup_test_db().await {
+        Ok(pool) => pool,
+        Err(_) => {
+            println!("Skipping test - no test database available");
+            return;
+        }
+    };
+    
+    remove_test_users(&db_pool, &["tenant_nfc_user_on", "tenant_nfc_user_off"]).await;
+    
+    let (content_hasher, _temp_dir) = setup_hash_store().expect("Failed to create hash storage");
+    
+    // "résumé.md" precomposed and decomposed (as sent by macOS)
+    let nfc_name = "/r\u{e9}sum\u{e9}.md";
+    let nfd_name = "/re\u{301}sume\u{301}.md";
+    
+    for (username, unicode_nfc, expected_files) in [
+        ("tenant_nfc_user_on", true, 1),
+        ("tenant_nfc_user_off", false, 2),
+    ] {
+        let (user_id, user_uuid) = setup_test_user(&db_pool, username)
+            .await
+            .expect("Failed to create test user");
+        
+        let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone())
+            .with_path_normalizer(PathNormalizer::new().with_unicode_nfc(unicode_nfc));
+        
+        storage.write(&user_uuid, nfd_name, b"written from a Mac".to_vec(), None)
+            .await
+            .expect("Failed to write NFD name");
+        storage.write(&user_uuid, nfc_name, b"written from Linux".to_vec(), None)
+            .await
+            .expect("Failed to write NFC name");
+        
+        let files = storage.list(&user_uuid, "/").await.expect("Failed to list root");
+        assert_eq!(files.len(), expected_files, "unicode_nfc = {}: {:?}", unicode_nfc, files);
+        
+        if unicode_nfc {
+            // The later write replaced the earlier one, and both spellings read it
+            let content = storage.read(&user_uuid, nfd_name).await.expect("Failed to read NFD name");
+            assert_eq!(content, b"written from Linux");
+        }
+        
+        cleanup_tenant_user_test(&db_pool, user_id).await;
+    }
+}
+
+/// Test that dot segments and doubled slashes are canonicalized and traversal above the root rejected
+#[tokio::test]
+async fn test_tenant_storage_path_traversal() {
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, _, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    tenant_storage.write(&user1_uuid, "/notes//./today.md", b"today".to_vec(), Some("text/markdown"))
+        .await
+        .expect("Failed to write through dot segment");
+    
+    // Every spelling that stays inside the tenant resolves to the same file
+    for path in ["/notes/today.md", "//notes//today.md", "/notes/./today.md", "/notes/drafts/../today.md"] {
+        let content = tenant_storage.read(&user1_uuid, path).await.expect(path);
+        assert_eq!(content, b"today", "read({:?})", path);
+    }
+    
+    // Climbing above the root is rejected, not clamped
+    for path in ["/../today.md", "/notes/../../notes/today.md", "../../etc/passwd"] {
+        assert!(
+            matches!(tenant_storage.read(&user1_uuid, path).await, Err(StorageError::Validation(_))),
+            "read({:?})",
+            path
+        );
+    }
+    assert!(matches!(
+        tenant_storage.write(&user1_uuid, "/a/../../b.md", b"escape".to_vec(), None).await,
+        Err(StorageError::Validation(_))
+    ));
+    assert!(matches!(
+        tenant_storage.rename(&user1_uuid, "/notes/today.md", "/../today.md").await,
+        Err(StorageError::Validation(_))
+    ));
+    assert!(matches!(
+        tenant_storage.delete_many(&user1_uuid, &["/notes/today.md".to_string(), "/..".to_string()]).await,
+        Err(StorageError::Validation(_))
+    ));
+    
+    // Nothing was written, renamed or deleted by the rejected calls
+    assert!(tenant_storage.exists(&user1_uuid, "/notes/today.md").await.unwrap());
+    assert!(!tenant_storage.exists(&user1_uuid, "/b.md").await.unwrap());
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+/// Test appending to a file twice
+#[tokio::test]
+async fn test_tenant_storage_append() {
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, _, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    // Appending to a missing file creates it
+    tenant_storage.append(&user1_uuid, "/journal.log", b"first\n".to_vec(), Some("text/plain"))
+        .await
+        .expect("Failed to append to new file");
+    let first_hash = tenant_storage.metadata(&user1_uuid, "/journal.log").await.unwrap().content_hash;
+    
+    tenant_storage.append(&user1_uuid, "/journal.log", b"second\n".to_vec(), None)
+        .await
+        .expect("Failed to append to existing file");
+    
+    let content = tenant_storage.read(&user1_uuid, "/journal.log").await.unwrap();
+    assert_eq!(content, b"first\nsecond\n");
+    
+    // The file record points at the new content and keeps its content type
+    let metadata = tenant_storage.metadata(&user1_uuid, "/journal.log").await.unwrap();
+    assert_eq!(metadata.size, 13);
+    assert_eq!(metadata.content_type, "text/plain");
+    assert_ne!(metadata.content_hash, first_hash);
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+/// Test truncating a file to a shorter length, to zero and beyond its end
+#[tokio::test]
+async fn test_tenant_storage_truncate() {
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, _, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    tenant_storage.write(&user1_uuid, "/draft.md", b"Hello, world!".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    
+    // Truncate to a shorter length
+    tenant_storage.truncate(&user1_uuid, "/draft.md", 5).await.expect("Failed to truncate");
+    assert_eq!(tenant_storage.read(&user1_uuid, "/draft.md").await.unwrap(), b"Hello");
+    assert_eq!(tenant_storage.metadata(&user1_uuid, "/draft.md").await.unwrap().size, 5);
+    
+    // Extending pads with zero bytes
+    tenant_storage.truncate(&user1_uuid, "/draft.md", 7).await.expect("Failed to extend");
+    assert_eq!(tenant_storage.read(&user1_uuid, "/draft.md").await.unwrap(), b"Hello\0\0");
+    
+    // Truncate to zero leaves an empty file, not a directory
+    tenant_storage.truncate(&user1_uuid, "/draft.md", 0).await.expect("Failed to truncate to zero");
+    assert!(tenant_storage.read(&user1_uuid, "/draft.md").await.unwrap().is_empty());
+    let metadata = tenant_storage.metadata(&user1_uuid, "/draft.md").await.unwrap();
+    assert_eq!(metadata.size, 0);
+    assert!(!metadata.is_directory);
+    assert_eq!(metadata.content_type, "text/markdown");
+    
+    // Missing files cannot be truncated
+    let result = tenant_storage.truncate(&user1_uuid, "/missing.md", 0).await;
+    assert!(matches!(result, Err(crate::error::StorageError::NotFound(_))));
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+/// Test that truncation is held to the size limit, the quota and the content policy
+#[tokio::test]
+async fn test_tenant_storage_truncate_limits() {
+    use crate::services::content_policy::ContentTypePolicy;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_truncate_limits_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone())
+        .with_max_file_size(16);
+    
+    storage.write(&user_uuid, "/draft.md", b"Hello".to_vec(), None).await.unwrap();
+    
+    // Growing past the size limit is refused, however large the length
+    let result = storage.truncate(&user_uuid, "/draft.md", 17).await;
+    assert!(matches!(result, Err(StorageError::FileTooLarge(16))));
+    let result = storage.truncate(&user_uuid, "/draft.md", u64::MAX).await;
+    assert!(matches!(result, Err(StorageError::FileTooLarge(16))));
+    storage.truncate(&user_uuid, "/draft.md", 16).await.unwrap();
+    assert_eq!(storage.metadata(&user_uuid, "/draft.md").await.unwrap().size, 16);
+    
+    // Content that a stricter policy refuses cannot be rewritten through truncation
+    storage.write(&user_uuid, "/tool.txt", b"MZ\x90\x00".to_vec(), None).await.unwrap();
+    let strict = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_content_type_policy(ContentTypePolicy::Deny(vec!["application/x-msdownload".to_string()]));
+    let result = strict.truncate(&user_uuid, "/tool.txt", 3).await;
+    assert!(matches!(result, Err(StorageError::ContentTypeNotAllowed(_))));
+    assert_eq!(storage.read(&user_uuid, "/tool.txt").await.unwrap(), b"MZ\x90\x00");
+    
+    // Growing past the quota is refused as well
+    sqlx::query("UPDATE users SET quota_bytes = 25 WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set quota");
+    storage.write(&user_uuid, "/other.md", b"1234".to_vec(), None).await.unwrap();
+    storage.truncate(&user_uuid, "/other.md", 2).await.unwrap();
+    let result = storage.truncate(&user_uuid, "/other.md", 6).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(25))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test batch metadata lookups for existing and missing paths
+#[tokio::test]
+async fn test_tenant_storage_metadata_many() {
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, user2_uuid, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    tenant_storage.write(&user1_uuid, "/a.md", b"aaa".to_vec(), None).await.unwrap();
+    tenant_storage.write(&user1_uuid, "/dir/b.md", b"bbbbb".to_vec(), None).await.unwrap();
+    tenant_storage.write(&user2_uuid, "/other.md", b"other".to_vec(), None).await.unwrap();
+    tenant_storage.write(&user1_uuid, "/gone.md", b"gone".to_vec(), None).await.unwrap();
+    tenant_storage.delete(&user1_uuid, "/gone.md").await.unwrap();
+    
+    let paths = vec![
+        "/dir/b.md".to_string(),
+        "/missing.md".to_string(),
+        "a.md".to_string(),
+        "/other.md".to_string(),
+        "/gone.md".to_string(),
+    ];
+    let results = tenant_storage.metadata_many(&user1_uuid, &paths).await.expect("Failed to get metadata");
+    
+    // Results are aligned with the requested paths
+    assert_eq!(results.len(), paths.len());
+    
+    let b = results[0].as_ref().expect("b.md should exist");
+    assert_eq!(b.size, 5);
+    assert_eq!(b.content_hash, tenant_storage.metadata(&user1_uuid, "/dir/b.md").await.unwrap().content_hash);
+    
+    assert!(results[1].is_none());
+    
+    // Paths are normalized like single lookups
+    let a = results[2].as_ref().expect("a.md should exist");
+    assert_eq!(a.size, 3);
+    assert_eq!(a.path, "/a.md");
+    
+    // Other tenants' files and deleted files are not visible
+    assert!(results[3].is_none());
+    assert!(results[4].is_none());
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+/// Test that the file count limit rejects new files but not updates
+#[tokio::test]
+async fn test_tenant_storage_max_file_count() {
+    use crate::MarbleTenantStorage;
+    use crate::error::StorageError;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_limit_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_max_file_count(3);
+    
+    // Creating up to the limit succeeds; the directory placeholder counts
+    storage.create_directory(&user_uuid, "/notes")
+        .await
+        .expect("Directory within the limit should succeed");
+    storage.write(&user_uuid, "/a.md", b"a".to_vec(), None)
+        .await
+        .expect("File within the limit should succeed");
+    storage.write(&user_uuid, "/b.md", b"b".to_vec(), None)
+        .await
+        .expect("File at the limit should succeed");
+    
+    // Creating beyond the limit fails
+    let result = storage.write(&user_uuid, "/c.md", b"c".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::FileLimitExceeded(3))));
+    assert!(!storage.exists(&user_uuid, "/c.md").await.unwrap());
+    
+    let result = storage.create_directory(&user_uuid, "/more").await;
+    assert!(matches!(result, Err(StorageError::FileLimitExceeded(3))));
+    
+    // Updating existing entries at the limit still succeeds
+    storage.write(&user_uuid, "/a.md", b"updated".to_vec(), None)
+        .await
+        .expect("Overwrite at the limit should succeed");
+    assert_eq!(storage.read(&user_uuid, "/a.md").await.unwrap(), b"updated");
+    storage.create_directory(&user_uuid, "/notes")
+        .await
+        .expect("Existing directory at the limit should succeed");
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that writes beyond the tenant's storage quota are refused
+#[tokio::test]
+async fn test_tenant_storage_quota() {
+    use crate::MarbleTenantStorage;
+    use crate::error::StorageError;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_quota_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    sqlx::query("UPDATE users SET quota_bytes = 10 WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set quota");
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // Under the quota
+    storage.write(&user_uuid, "/a.md", b"123456".to_vec(), None)
+        .await
+        .expect("Write under the quota should succeed");
+    
+    // Exactly at the quota
+    storage.write(&user_uuid, "/b.md", b"1234".to_vec(), None)
+        .await
+        .expect("Write reaching the quota should succeed");
+    
+    // Over the quota, for new files and growing ones
+    let result = storage.write(&user_uuid, "/c.md", b"1".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(10))));
+    assert!(!storage.exists(&user_uuid, "/c.md").await.unwrap());
+    let result = storage.write(&user_uuid, "/a.md", b"1234567".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(10))));
+    let result = storage.append(&user_uuid, "/b.md", b"5".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(10))));
+    
+    // Overwrites only count the difference in size
+    storage.write(&user_uuid, "/a.md", b"abcdef".to_vec(), None)
+        .await
+        .expect("Same-size overwrite at the quota should succeed");
+    storage.write(&user_uuid, "/a.md", b"ab".to_vec(), None)
+        .await
+        .expect("Shrinking overwrite should succeed");
+    storage.write(&user_uuid, "/c.md", b"1234".to_vec(), None)
+        .await
+        .expect("Write into the freed space should succeed");
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_quota_through_alias() {
+    use crate::MarbleTenantStorage;
+    use crate::error::StorageError;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_quota_alias_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    sqlx::query("UPDATE users SET quota_bytes = 20 WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set quota");
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    storage.write(&user_uuid, "/target.md", b"original".to_vec(), None).await.unwrap();
+    storage.create_alias(&user_uuid, "/alias.md", "/target.md").await.unwrap();
+    
+    // The alias adds nothing to usage
+    assert_eq!(storage.usage(&user_uuid).await.unwrap().used_bytes, 8);
+    
+    // A write through the alias replaces the target, so only the growth counts
+    storage.write(&user_uuid, "/alias.md", b"sixteen bytes!!!".to_vec(), None)
+        .await
+        .expect("Write through the alias within the quota should succeed");
+    assert_eq!(storage.usage(&user_uuid).await.unwrap().used_bytes, 16);
+    let result = storage.write(&user_uuid, "/alias.md", vec![b'x'; 21], None).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(20))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that case-insensitive paths find files by any casing but list the original
+#[tokio::test]
+async fn test_tenant_storage_case_insensitive_paths() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_case_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_case_insensitive_paths(true);
+    
+    storage.write(&user_uuid, "/Notes.md", b"# Notes".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    
+    // Found by another casing
+    assert_eq!(storage.read(&user_uuid, "/notes.md").await.unwrap(), b"# Notes");
+    
+    // Metadata and listings show the original casing
+    let metadata = storage.metadata(&user_uuid, "/NOTES.MD").await.unwrap();
+    assert_eq!(metadata.path, "/Notes.md");
+    assert_eq!(storage.list(&user_uuid, "/").await.unwrap(), vec!["/Notes.md"]);
+    
+    // Writing another casing updates the same file
+    storage.write(&user_uuid, "/notes.md", b"updated".to_vec(), None)
+        .await
+        .expect("Failed to overwrite file");
+    assert_eq!(storage.list(&user_uuid, "/").await.unwrap(), vec!["/Notes.md"]);
+    
+    // Renaming may change only the casing
+    storage.rename(&user_uuid, "/notes.md", "/NOTES.md")
+        .await
+        .expect("Failed to rename file");
+    assert_eq!(storage.list(&user_uuid, "/").await.unwrap(), vec!["/NOTES.md"]);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that content can be read by hash only by tenants referencing it
+#[tokio::test]
+async fn test_tenant_storage_read_by_hash() {
+    use crate::error::StorageError;
+    
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, user2_uuid, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    tenant_storage.write(&user1_uuid, "/blob.md", b"blob content".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    let hash = tenant_storage.metadata(&user1_uuid, "/blob.md").await.unwrap().content_hash.unwrap();
+    
+    // The owner reads the content by hash
+    let content = tenant_storage.read_by_hash(&user1_uuid, &hash).await.unwrap();
+    assert_eq!(content, b"blob content");
+    
+    // Another tenant is denied
+    let result = tenant_storage.read_by_hash(&user2_uuid, &hash).await;
+    assert!(matches!(result, Err(StorageError::Authorization(_))));
+    
+    // Deleting the only reference revokes access
+    tenant_storage.delete(&user1_uuid, "/blob.md").await.unwrap();
+    let result = tenant_storage.read_by_hash(&user1_uuid, &hash).await;
+    assert!(matches!(result, Err(StorageError::Authorization(_))));
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_implicit_directories() {
+    use crate::config::DirectoryStrategy;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_implicit_dir_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_directory_strategy(DirectoryStrategy::Implicit);
+    
+    storage.create_directory(&user_uuid, "/projects/empty")
+        .await
+        .expect("Failed to create directory");
+    
+    // The directory and its parent exist without any file rows
+    assert!(storage.exists(&user_uuid, "/projects/empty").await.unwrap());
+    let metadata = storage.metadata(&user_uuid, "/projects/empty").await.unwrap();
+    assert!(metadata.is_directory);
+    assert!(storage.metadata(&user_uuid, "/projects").await.unwrap().is_directory);
+    
+    let file_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&*db_pool)
+        .await
+        .unwrap();
+    assert_eq!(file_rows, 0);
+    
+    // Both directories are tracked as folders
+    let folder_paths: Vec<String> = sqlx::query_scalar(
+        "SELECT path FROM folders WHERE user_id = $1 AND is_deleted = false ORDER BY path"
+    )
+    .bind(user_id)
+    .fetch_all(&*db_pool)
+    .await
+    .unwrap();
+    assert_eq!(folder_paths, vec!["/projects", "/projects/empty"]);
+    
+    // Listings show the directories but no placeholders
+    assert!(storage.list(&user_uuid, "/projects/empty").await.unwrap().is_empty());
+    storage.write(&user_uuid, "/projects/readme.md", b"# Projects".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    let listing = storage.list(&user_uuid, "/projects").await.unwrap();
+    assert!(listing.contains(&"/projects/readme.md".to_string()));
+    assert!(listing.contains(&"/projects/empty".to_string()));
+    assert!(listing.iter().all(|path| !path.ends_with(".dir")));
+    
+    // Deleting the directory stops tracking it
+    storage.delete(&user_uuid, "/projects/empty").await.expect("Failed to delete directory");
+    assert!(!storage.exists(&user_uuid, "/projects/empty").await.unwrap());
+    
+    // Creating it again revives the folder
+    storage.create_directory(&user_uuid, "/projects/empty")
+        .await
+        .expect("Failed to recreate directory");
+    assert!(storage.metadata(&user_uuid, "/projects/empty").await.unwrap().is_directory);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_refresh_metadata() {
+    use crate::hash::{hash_content, hash_to_path};
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_refresh_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone());
+    
+    storage.write(&user_uuid, "/sized.md", b"correct content".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    storage.write(&user_uuid, "/hashed.md", b"original".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    storage.write(&user_uuid, "/intact.md", b"untouched".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    
+    // Corrupt the size of one row
+    sqlx::query("UPDATE files SET size = 999 WHERE user_id = $1 AND path = '/sized.md'")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .unwrap();
+    
+    // Point another row at content stored under a key that is not its hash
+    let stale_key = hash_content(b"stale key").unwrap();
+    content_hasher.operator().write(&hash_to_path(&stale_key), b"actual blob".to_vec()).await.unwrap();
+    sqlx::query("UPDATE files SET content_hash = $2, size = 1 WHERE user_id = $1 AND path = '/hashed.md'")
+        .bind(user_id)
+        .bind(&stale_key)
+        .execute(&*db_pool)
+        .await
+        .unwrap();
+    
+    assert_eq!(storage.refresh_metadata(user_id).await.unwrap(), 2);
+    
+    let sized = storage.metadata(&user_uuid, "/sized.md").await.unwrap();
+    assert_eq!(sized.size, b"correct content".len() as u64);
+    
+    let hashed = storage.metadata(&user_uuid, "/hashed.md").await.unwrap();
+    assert_eq!(hashed.content_hash, Some(hash_content(b"actual blob").unwrap()));
+    assert_eq!(hashed.size, b"actual blob".len() as u64);
+    assert_eq!(storage.read(&user_uuid, "/hashed.md").await.unwrap(), b"actual blob");
+    
+    // A second run finds nothing left to fix
+    assert_eq!(storage.refresh_metadata(user_id).await.unwrap(), 0);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_shutdown() {
+    use crate::error::StorageError;
+    use crate::{MarbleTenantStorage, UserIdCache};
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_shutdown_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    // A pool of its own, since shutdown closes it
+    let storage_pool = setup_test_db().await.expect("Failed to connect");
+    let storage = MarbleTenantStorage::new(storage_pool.clone(), content_hasher)
+        .with_user_id_cache(Arc::new(UserIdCache::new()));
+    
+    storage.write(&user_uuid, "/note.md", b"# Note".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    assert_eq!(storage.user_id_cache().unwrap().len(), 1);
+    
+    storage.shutdown().await;
+    
+    assert!(storage.user_id_cache().unwrap().is_empty());
+    assert!(storage_pool.is_closed());
+    assert!(matches!(storage.read(&user_uuid, "/note.md").await, Err(StorageError::Closed)));
+    assert!(matches!(storage.refresh_metadata(user_id).await, Err(StorageError::Closed)));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_delete_many() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_delete_many_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    for path in ["/a.md", "/b.md", "/c.md"] {
+        storage.write(&user_uuid, path, path.as_bytes().to_vec(), None)
+            .await
+            .expect("Failed to write file");
+    }
+    
+    let paths: Vec<String> = ["/a.md", "/missing.md", "/c.md"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    let deleted = storage.delete_many(&user_uuid, &paths).await.unwrap();
+    assert_eq!(deleted, vec![true, false, true]);
+    
+    assert!(!storage.exists(&user_uuid, "/a.md").await.unwrap());
+    assert!(storage.exists(&user_uuid, "/b.md").await.unwrap());
+    assert!(!storage.exists(&user_uuid, "/c.md").await.unwrap());
+    
+    // Already deleted files are reported as not found
+    let deleted = storage.delete_many(&user_uuid, &paths[..1]).await.unwrap();
+    assert_eq!(deleted, vec![false]);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_require_existing_parent() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_strict_parent_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let strict = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone())
+        .with_require_existing_parent(true);
+    let lenient = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // Strict mode rejects a write into a missing directory
+    let result = strict.write(&user_uuid, "/missing/note.md", b"note".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::ParentNotFound(_))));
+    assert!(!strict.exists(&user_uuid, "/missing/note.md").await.unwrap());
+    
+    // Files at the root and in existing directories are accepted
+    strict.write(&user_uuid, "/root.md", b"root".to_vec(), None)
+        .await
+        .expect("Root write should succeed");
+    strict.create_directory(&user_uuid, "/docs").await.expect("Failed to create directory");
+    strict.write(&user_uuid, "/docs/note.md", b"note".to_vec(), None)
+        .await
+        .expect("Write into an existing directory should succeed");
+    
+    // Lenient mode implies the missing parent
+    lenient.write(&user_uuid, "/missing/note.md", b"note".to_vec(), None)
+        .await
+        .expect("Lenient write should succeed");
+    assert!(lenient.exists(&user_uuid, "/missing/note.md").await.unwrap());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_list_distinguishes_missing_directories() {
+    use crate::config::DirectoryStrategy;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_list_semantics_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_directory_strategy(DirectoryStrategy::Implicit);
+    
+    // An empty directory lists as empty
+    storage.create_directory(&user_uuid, "/empty").await.expect("Failed to create directory");
+    assert!(storage.list(&user_uuid, "/empty").await.unwrap().is_empty());
+    
+    // A missing directory is not found
+    let result = storage.list(&user_uuid, "/missing").await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    // A file cannot be listed as a directory
+    storage.write(&user_uuid, "/note.md", b"note".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    let result = storage.list(&user_uuid, "/note.md").await;
+    assert!(matches!(result, Err(StorageError::Validation(_))));
+    
+    // The root always lists
+    assert!(storage.list(&user_uuid, "/").await.unwrap().contains(&"/note.md".to_string()));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that the mock storage agrees with the real listing semantics
+#[tokio::test]
+async fn test_mock_list_distinguishes_missing_directories() {
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_directory(&tenant_id, "empty");
+    storage.add_file(&tenant_id, "note.md", b"note".to_vec());
+    
+    assert!(storage.list(&tenant_id, "empty").await.unwrap().is_empty());
+    assert!(matches!(storage.list(&tenant_id, "missing").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.list(&tenant_id, "note.md").await, Err(StorageError::Validation(_))));
+}
+
+#[tokio::test]
+async fn test_tenant_storage_write_onto_directory_rejected() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_write_dir_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.create_directory(&user_uuid, "/docs").await.expect("Failed to create directory");
+    storage.write(&user_uuid, "/docs/note.md", b"note".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    
+    // Writing onto the directory path is rejected
+    let result = storage.write(&user_uuid, "/docs", b"shadow".to_vec(), None).await;
+    assert!(matches!(result, Err(StorageError::IsDirectory(_))));
+    
+    // The directory and its contents are untouched
+    let listing = storage.list(&user_uuid, "/docs").await.expect("Failed to list directory");
+    assert!(listing.contains(&"/docs/note.md".to_string()));
+    let shadow_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE user_id = $1 AND path = '/docs'")
+        .bind(user_id)
+        .fetch_one(&*db_pool)
+        .await
+        .unwrap();
+    assert_eq!(shadow_rows, 0);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_aliases() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_alias_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/notes/target.md", b"original".to_vec(), None)
+        .await
+        .expect("Failed to write target");
+    storage.create_alias(&user_uuid, "/links/alias.md", "/notes/target.md")
+        .await
+        .expect("Failed to create alias");
+    storage.write(&user_uuid, "/notes/copy.md", b"original".to_vec(), None)
+        .await
+        .expect("Failed to write copy");
+    
+    // Reading the alias returns the target's content and metadata
+    assert_eq!(storage.read(&user_uuid, "/links/alias.md").await.unwrap(), b"original");
+    let metadata = storage.metadata(&user_uuid, "/links/alias.md").await.unwrap();
+    assert_eq!(metadata.path, "/links/alias.md");
+    assert_eq!(metadata.size, 8);
+    assert_eq!(metadata.content_type, "text/markdown");
+    
+    // Writing through the alias updates the target, but not the copy
+    storage.write(&user_uuid, "/links/alias.md", b"edited through alias".to_vec(), None)
+        .await
+        .expect("Failed to write through alias");
+    assert_eq!(storage.read(&user_uuid, "/notes/target.md").await.unwrap(), b"edited through alias");
+    assert_eq!(storage.read(&user_uuid, "/links/alias.md").await.unwrap(), b"edited through alias");
+    assert_eq!(storage.read(&user_uuid, "/notes/copy.md").await.unwrap(), b"original");
+    
+    // Editing the target is visible through the alias
+    storage.write(&user_uuid, "/notes/target.md", b"edited target".to_vec(), None)
+        .await
+        .expect("Failed to write target");
+    assert_eq!(storage.read(&user_uuid, "/links/alias.md").await.unwrap(), b"edited target");
+    
+    // Missing targets are rejected, and deleting the target leaves a dangling alias
+    let result = storage.create_alias(&user_uuid, "/links/missing.md", "/notes/missing.md").await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    storage.delete(&user_uuid, "/notes/target.md").await.expect("Failed to delete target");
+    let result = storage.read(&user_uuid, "/links/alias.md").await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that the mock storage follows aliases like the real storage
+#[tokio::test]
+async fn test_mock_aliases() {
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_file(&tenant_id, "target.md", b"original".to_vec());
+    storage.add_file(&tenant_id, "copy.md", b"original".to_vec());
+    storage.create_alias(&tenant_id, "alias.md", "target.md").await.expect("Failed to create alias");
+    
+    assert_eq!(storage.read(&tenant_id, "alias.md").await.unwrap(), b"original");
+    storage.write(&tenant_id, "alias.md", b"edited".to_vec(), None).await.unwrap();
+    assert_eq!(storage.read(&tenant_id, "target.md").await.unwrap(), b"edited");
+    assert_eq!(storage.read(&tenant_id, "copy.md").await.unwrap(), b"original");
+}
+
+/// Test that streamed reads yield the same content as buffered reads
+#[tokio::test]
+async fn test_tenant_storage_read_stream() {
+    use futures::TryStreamExt;
+    
+    // Setup the test environment
+    let (tenant_storage, user1_uuid, _user2_uuid, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => {
+            // Skip the test if setup fails
+            return;
+        }
+    };
+    
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    tenant_storage.write(&user1_uuid, "/big.bin", content.clone(), None)
+        .await
+        .expect("Failed to write file");
+    tenant_storage.create_alias(&user1_uuid, "/big-alias.bin", "/big.bin")
+        .await
+        .expect("Failed to create alias");
+    
+    for path in ["/big.bin", "/big-alias.bin"] {
+        let chunks: Vec<_> = tenant_storage.read_stream(&user1_uuid, path)
+            .await
+            .expect("Failed to open stream")
+            .try_collect()
+            .await
+            .expect("Failed to read stream");
+        assert_eq!(chunks.concat(), content);
+    }
+    
+    let result = tenant_storage.read_stream(&user1_uuid, "/missing.bin").await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    // Clean up
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+#[tokio::test]
+async fn test_mock_read_stream_default() {
+    use futures::TryStreamExt;
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_file(&tenant_id, "file.md", b"streamed".to_vec());
+    let chunks: Vec<_> = storage.read_stream(&tenant_id, "file.md").await.unwrap().try_collect().await.unwrap();
+    assert_eq!(chunks.concat(), b"streamed");
+}
+
+/// Test that reads record last access, coalescing reads within the throttle window
+#[tokio::test]
+async fn test_tenant_storage_access_tracking() {
+    use std::time::Duration;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_access_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let window = Duration::from_millis(300);
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_access_tracking(window);
+    
+    storage.write(&user_uuid, "/recent.md", b"recent".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    assert_eq!(storage.metadata(&user_uuid, "/recent.md").await.unwrap().last_accessed, None);
+    
+    // The first read is recorded
+    storage.read(&user_uuid, "/recent.md").await.unwrap();
+    let first = storage.metadata(&user_uuid, "/recent.md").await.unwrap().last_accessed;
+    assert!(first.is_some(), "First read should record access");
+    
+    // Rapid repeated reads are coalesced into that record
+    for _ in 0..20 {
+        storage.read(&user_uuid, "/recent.md").await.unwrap();
+    }
+    assert_eq!(storage.metadata(&user_uuid, "/recent.md").await.unwrap().last_accessed, first);
+    
+    // Once the window has passed the next read is recorded again
+    tokio::time::sleep(window + Duration::from_millis(50)).await;
+    storage.read(&user_uuid, "/recent.md").await.unwrap();
+    let second = storage.metadata(&user_uuid, "/recent.md").await.unwrap().last_accessed;
+    assert!(second > first, "Read after the window should record access");
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that a pinned content type is reported and survives content-only writes
+#[tokio::test]
+async fn test_tenant_storage_content_type_override() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_content_type_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/notes.txt", b"# Notes".to_vec(), None)
+        .await
+        .expect("Failed to write file");
+    assert_eq!(storage.metadata(&user_uuid, "/notes.txt").await.unwrap().content_type, "text/plain");
+    
+    // Pinning the type changes the reported type
+    storage.set_content_type_override(&user_uuid, "/notes.txt", Some("text/markdown"))
+        .await
+        .expect("Failed to set content type");
+    assert_eq!(storage.metadata(&user_uuid, "/notes.txt").await.unwrap().content_type, "text/markdown");
+    
+    // A content-only write keeps the pinned type
+    storage.write(&user_uuid, "/notes.txt", b"# Notes\n\nMore".to_vec(), None)
+        .await
+        .expect("Failed to rewrite file");
+    let metadata = storage.metadata(&user_uuid, "/notes.txt").await.unwrap();
+    assert_eq!(metadata.content_type, "text/markdown");
+    assert_eq!(metadata.size, 13);
+    
+    // A declared type wins over the pin for that write
+    storage.write(&user_uuid, "/notes.txt", b"plain".to_vec(), Some("text/csv"))
+        .await
+        .expect("Failed to write with a declared type");
+    assert_eq!(storage.metadata(&user_uuid, "/notes.txt").await.unwrap().content_type, "text/csv");
+    
+    // Clearing the pin goes back to guessing
+    storage.set_content_type_override(&user_uuid, "/notes.txt", None)
+        .await
+        .expect("Failed to clear content type");
+    assert_eq!(storage.metadata(&user_uuid, "/notes.txt").await.unwrap().content_type, "text/plain");
+    
+    let result = storage.set_content_type_override(&user_uuid, "/missing.txt", Some("text/markdown")).await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_mock_content_type_override() {
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_file(&tenant_id, "notes.txt", b"# Notes".to_vec());
+    storage.set_content_type_override(&tenant_id, "notes.txt", Some("text/markdown")).await.unwrap();
+    assert_eq!(storage.metadata(&tenant_id, "notes.txt").await.unwrap().content_type, "text/markdown");
+    
+    storage.write(&tenant_id, "notes.txt", b"# Notes\n\nMore".to_vec(), None).await.unwrap();
+    assert_eq!(storage.metadata(&tenant_id, "notes.txt").await.unwrap().content_type, "text/markdown");
+    
+    let result = storage.set_content_type_override(&tenant_id, "missing.txt", Some("text/markdown")).await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+}
+
+/// Test that dead properties are stored per file and dropped with it
+#[tokio::test]
+async fn test_tenant_storage_properties() {
+    use crate::api::{DeadProperty, PropertyChange};
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_properties_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/notes/todo.md", b"# Todo".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/notes/done.md", b"# Done".to_vec(), None).await.unwrap();
+    
+    let set = |name: &str, value: &str| PropertyChange::Set {
+        namespace: "urn:example".to_string(),
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    let property = |name: &str, value: &str| DeadProperty {
+        namespace: "urn:example".to_string(),
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    
+    // Changes apply in order, so a later set replaces an earlier one
+    storage.patch_properties(&user_uuid, "/notes/todo.md", &[
+        set("color", "red"),
+        set("starred", "yes"),
+        set("color", "blue"),
+    ], None).await.unwrap();
+    assert_eq!(
+        storage.properties(&user_uuid, "/notes/todo.md").await.unwrap(),
+        vec![property("color", "blue"), property("starred", "yes")]
+    );
+    
+    storage.patch_properties(&user_uuid, "/notes/todo.md", &[PropertyChange::Remove {
+        namespace: "urn:example".to_string(),
+        name: "starred".to_string(),
+    }], None).await.unwrap();
+    
+    let paths = vec!["/notes/todo.md".to_string(), "/notes/done.md".to_string(), "/notes/missing.md".to_string()];
+    assert_eq!(
+        storage.properties_many(&user_uuid, &paths).await.unwrap(),
+        vec![vec![property("color", "blue")], Vec::new(), Vec::new()]
+    );
+    
+    // Directories have no content type and missing files no properties
+    let result = storage.patch_properties(&user_uuid, "/notes", &[set("color", "red")], Some(Some("text/plain"))).await;
+    assert!(matches!(result, Err(StorageError::Validation(_))));
+    let result = storage.patch_properties(&user_uuid, "/notes/missing.md", &[set("color", "red")], None).await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    // Deleted files have no properties to report
+    storage.delete(&user_uuid, "/notes/todo.md").await.unwrap();
+    let result = storage.properties(&user_uuid, "/notes/todo.md").await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_directory_properties() {
+    use crate::api::{DeadProperty, PropertyChange};
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_dir_properties_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    storage.create_directory(&user_uuid, "/projects").await.unwrap();
+    storage.write(&user_uuid, "/projects/plan.md", b"# Plan".to_vec(), None).await.unwrap();
+    
+    let set = |name: &str, value: &str| PropertyChange::Set {
+        namespace: "urn:example".to_string(),
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    let property = |name: &str, value: &str| DeadProperty {
+        namespace: "urn:example".to_string(),
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    
+    // Collections keep properties of their own, also listed alongside files
+    storage.patch_properties(&user_uuid, "/projects/", &[set("color", "green")], None).await.unwrap();
+    assert_eq!(storage.properties(&user_uuid, "/projects").await.unwrap(), vec![property("color", "green")]);
+    let paths = vec!["/projects".to_string(), "/projects/plan.md".to_string()];
+    assert_eq!(
+        storage.properties_many(&user_uuid, &paths).await.unwrap(),
+        vec![vec![property("color", "green")], Vec::new()]
+    );
+    
+    // A failing change undoes the whole patch, content type pin included
+    let result = storage.patch_properties(
+        &user_uuid,
+        "/projects/plan.md",
+        &[set("color", "red"), set("broken", "nul \0 byte")],
+        Some(Some("text/plain")),
+    ).await;
+    assert!(result.is_err());
+    assert!(storage.properties(&user_uuid, "/projects/plan.md").await.unwrap().is_empty());
+    assert_eq!(storage.metadata(&user_uuid, "/projects/plan.md").await.unwrap().content_type, "text/markdown");
+    
+    // Properties go with the directory
+    storage.delete_directory(&user_uuid, "/projects").await.unwrap();
+    storage.create_directory(&user_uuid, "/projects").await.unwrap();
+    assert!(storage.properties(&user_uuid, "/projects").await.unwrap().is_empty());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_write_reports_dedup() {
+    use crate::api::DedupOutcome;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_dedup_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // New content is stored, identical content at another path is not
+    let outcome = storage.write(&user_uuid, "/a.md", b"# Same".to_vec(), None).await.unwrap();
+    assert_eq!(outcome, DedupOutcome::Miss);
+    let outcome = storage.write(&user_uuid, "/b.md", b"# Same".to_vec(), None).await.unwrap();
+    assert_eq!(outcome, DedupOutcome::Hit);
+    let outcome = storage.write(&user_uuid, "/a.md", b"# Changed".to_vec(), None).await.unwrap();
+    assert_eq!(outcome, DedupOutcome::Miss);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_dedup_ignores_other_tenants() {
+    use crate::api::DedupOutcome;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, owner_id, owner_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_dedup_owner").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    remove_test_users(&db_pool, &["tenant_dedup_prober"]).await;
+    let (prober_id, prober_uuid) = setup_test_user(&db_pool, "tenant_dedup_prober")
+        .await
+        .expect("Failed to create test user");
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    let content = format!("# Private {}", Uuid::new_v4()).into_bytes();
+    storage.write(&owner_uuid, "/secret.md", content.clone(), None).await.unwrap();
+    
+    // The blob is shared, but the other tenant does not learn that it exists
+    let outcome = storage.write(&prober_uuid, "/probe.md", content.clone(), None).await.unwrap();
+    assert_eq!(outcome, DedupOutcome::Miss);
+    let outcome = storage.write(&prober_uuid, "/probe-again.md", content, None).await.unwrap();
+    assert_eq!(outcome, DedupOutcome::Hit);
+    
+    for user_id in [owner_id, prober_id] {
+        cleanup_tenant_user_test(&db_pool, user_id).await;
+    }
+}
+
+#[tokio::test]
+async fn test_tenant_storage_delete_directory_cascades() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_rmdir_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    let nested = ["/notes/a.md", "/notes/sub/b.md", "/notes/sub/deep/c.md"];
+    for path in nested {
+        storage.write(&user_uuid, path, b"# Note".to_vec(), None).await.unwrap();
+    }
+    storage.write(&user_uuid, "/notesx.md", b"# Sibling".to_vec(), None).await.unwrap();
+    
+    let deleted = storage.delete_directory(&user_uuid, "/notes").await.unwrap();
+    assert_eq!(deleted, 3);
+    
+    // Nothing below the directory is left, not even the directory itself
+    for path in nested {
+        assert!(!storage.exists(&user_uuid, path).await.unwrap());
+    }
+    assert!(matches!(storage.list(&user_uuid, "/notes").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.list(&user_uuid, "/notes/sub").await, Err(StorageError::NotFound(_))));
+    
+    // A sibling sharing the name as a prefix survives
+    assert!(storage.exists(&user_uuid, "/notesx.md").await.unwrap());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_versions_restore() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_versions_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // Every write is a version, newest first
+    storage.write(&user_uuid, "/notes.md", b"# First".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/notes.md", b"# Second draft".to_vec(), None).await.unwrap();
+    let versions = storage.list_versions(&user_uuid, "/notes.md").await.unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0].size, 14);
+    assert_eq!(versions[1].size, 7);
+    
+    // Restoring the first brings its content back and adds to the history
+    storage.restore_version(&user_uuid, "/notes.md", versions[1].id).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/notes.md").await.unwrap(), b"# First");
+    let restored = storage.list_versions(&user_uuid, "/notes.md").await.unwrap();
+    assert_eq!(restored.len(), 3);
+    assert_eq!(restored[0].content_hash, versions[1].content_hash);
+    
+    // Versions of other files cannot be restored here
+    storage.write(&user_uuid, "/other.md", b"# Other".to_vec(), None).await.unwrap();
+    let other = storage.list_versions(&user_uuid, "/other.md").await.unwrap();
+    let result = storage.restore_version(&user_uuid, "/notes.md", other[0].id).await;
+    assert!(matches!(result, Err(StorageError::NotFound(_))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that restoring a larger version counts against the storage quota
+#[tokio::test]
+async fn test_tenant_storage_restore_version_respects_quota() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_restore_quota_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    sqlx::query("UPDATE users SET quota_bytes = 20 WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set quota");
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // Shrink a file, then use the freed space for another one
+    storage.write(&user_uuid, "/notes.md", b"# Long draft".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/notes.md", b"# S".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/other.md", b"# Other notes".to_vec(), None).await.unwrap();
+    let versions = storage.list_versions(&user_uuid, "/notes.md").await.unwrap();
+    assert_eq!(versions.len(), 2);
+    
+    // Bringing the long draft back would exceed the quota
+    let result = storage.restore_version(&user_uuid, "/notes.md", versions[1].id).await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(20))));
+    assert_eq!(storage.read(&user_uuid, "/notes.md").await.unwrap(), b"# S");
+    assert_eq!(storage.list_versions(&user_uuid, "/notes.md").await.unwrap().len(), 2);
+    
+    // Once there is room again, the restore goes through
+    storage.delete(&user_uuid, "/other.md").await.unwrap();
+    storage.restore_version(&user_uuid, "/notes.md", versions[1].id).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/notes.md").await.unwrap(), b"# Long draft");
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_create_directory_records_folders() {
+    use marble_db::repositories::{FileRepository, FolderRepository, Repository, SqlxFileRepository, SqlxFolderRepository};
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_folders_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.create_directory(&user_uuid, "/docs/drafts").await.expect("Failed to create directory");
+    
+    // Folder rows and placeholders agree on every level
+    let folders = SqlxFolderRepository::new(db_pool.clone());
+    let files = SqlxFileRepository::new(db_pool.clone());
+    let docs = folders.find_by_path(user_id, "/docs").await.unwrap().expect("Missing folder row");
+    let drafts = folders.find_by_path(user_id, "/docs/drafts").await.unwrap().expect("Missing folder row");
+    assert_eq!(docs.parent_id, None);
+    assert_eq!(drafts.parent_id, Some(docs.id));
+    for path in ["/docs/.dir", "/docs/drafts/.dir"] {
+        let placeholder = files.find_by_path(user_id, path).await.unwrap().expect("Missing placeholder");
+        assert!(!placeholder.is_deleted);
+    }
+    
+    // A directory that already holds files gets its folder row only
+    storage.write(&user_uuid, "/notes/a.md", b"# A".to_vec(), None).await.unwrap();
+    storage.create_directory(&user_uuid, "/notes").await.expect("Failed to create directory");
+    assert!(folders.find_by_path(user_id, "/notes").await.unwrap().is_some());
+    assert!(files.find_by_path(user_id, "/notes/.dir").await.unwrap().is_none());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that directories are read from folder rows even where placeholders are written
+#[tokio::test]
+async fn test_tenant_storage_placeholders_stay_internal() {
+    use marble_db::repositories::{FileRepository, Repository, SqlxFileRepository};
+    
+    let (tenant_storage, user1_uuid, _, db_pool) = match setup_tenant_storage_test().await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let user1_id = crate::backends::user::uuid_to_db_id(&db_pool, user1_uuid).await.unwrap();
+    
+    tenant_storage.create_directory(&user1_uuid, "/docs/empty")
+        .await
+        .expect("Failed to create directory");
+    let files = SqlxFileRepository::new(db_pool.clone());
+    assert!(files.find_by_path(user1_id, "/docs/empty/.dir").await.unwrap().is_some());
+    
+    // Deleting the directory removes its folder row and its marker together
+    tenant_storage.delete(&user1_uuid, "/docs/empty")
+        .await
+        .expect("Failed to delete directory");
+    assert!(!tenant_storage.exists(&user1_uuid, "/docs/empty").await.unwrap());
+    let placeholder = files.find_by_path(user1_id, "/docs/empty/.dir").await.unwrap().unwrap();
+    assert!(placeholder.is_deleted);
+    assert!(tenant_storage.list(&user1_uuid, "/docs").await.unwrap().is_empty());
+    
+    cleanup_tenant_storage_test(&db_pool).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_trash() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_trash_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/a.md", b"# A".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/b.md", b"# B".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/kept.md", b"# Kept".to_vec(), None).await.unwrap();
+    
+    // Deleted files are listed in the trash
+    storage.delete(&user_uuid, "/a.md").await.unwrap();
+    storage.delete(&user_uuid, "/b.md").await.unwrap();
+    let trash: Vec<String> = storage.list_trash(&user_uuid).await.unwrap()
+        .into_iter()
+        .map(|metadata| metadata.path)
+        .collect();
+    assert_eq!(trash, vec!["/b.md", "/a.md"]);
+    
+    // A restored file is readable again and leaves the trash
+    storage.restore(&user_uuid, "/a.md").await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/a.md").await.unwrap(), b"# A");
+    let trash = storage.list_trash(&user_uuid).await.unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].path, "/b.md");
+    
+    // Only files in the trash can be restored
+    assert!(matches!(storage.restore(&user_uuid, "/kept.md").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.restore(&user_uuid, "/missing.md").await, Err(StorageError::NotFound(_))));
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+/// Test that directory placeholders stay out of the trash and restores count against the quota
+#[tokio::test]
+async fn test_tenant_storage_trash_placeholders_and_quota() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_trash_quota_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    sqlx::query("UPDATE users SET quota_bytes = 20 WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set quota");
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // Deleting a directory trashes its placeholder along with its files
+    storage.create_directory(&user_uuid, "/drafts").await.unwrap();
+    storage.write(&user_uuid, "/drafts/long.md", b"# A long draft".to_vec(), None).await.unwrap();
+    storage.delete_directory(&user_uuid, "/drafts").await.unwrap();
+    let trash: Vec<String> = storage.list_trash(&user_uuid).await.unwrap()
+        .into_iter()
+        .map(|metadata| metadata.path)
+        .collect();
+    assert_eq!(trash, vec!["/drafts/long.md"]);
+    assert!(matches!(storage.restore(&user_uuid, "/drafts/.dir").await, Err(StorageError::NotFound(_))));
+    
+    // Restoring the draft would take the tenant above its quota
+    storage.write(&user_uuid, "/notes.md", b"# Other notes".to_vec(), None).await.unwrap();
+    let result = storage.restore(&user_uuid, "/drafts/long.md").await;
+    assert!(matches!(result, Err(StorageError::QuotaExceeded(20))));
+    assert_eq!(storage.list_trash(&user_uuid).await.unwrap().len(), 1);
+    
+    // Once there is room again, the restore goes through
+    storage.delete(&user_uuid, "/notes.md").await.unwrap();
+    storage.restore(&user_uuid, "/drafts/long.md").await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/drafts/long.md").await.unwrap(), b"# A long draft");
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_search() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_search_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/garden.md", b"# Garden\nPlant the tomatoes in spring".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/kitchen.md", b"# Kitchen\nBuy tomatoes and basil".to_vec(), None).await.unwrap();
+    storage.write(&user_uuid, "/plain.txt", b"spring cleaning".to_vec(), None).await.unwrap();
+    
+    // A keyword unique to one note finds only that note; other files are not indexed
+    let found = storage.search(&user_uuid, "spring").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, "/garden.md");
+    assert_eq!(storage.search(&user_uuid, "tomatoes").await.unwrap().len(), 2);
+    
+    // Rewriting a note reindexes it, and so does restoring an earlier version
+    storage.write(&user_uuid, "/garden.md", b"# Garden\nPlant beans in autumn".to_vec(), None).await.unwrap();
+    assert!(storage.search(&user_uuid, "spring").await.unwrap().is_empty());
+    let versions = storage.list_versions(&user_uuid, "/garden.md").await.unwrap();
+    storage.restore_version(&user_uuid, "/garden.md", versions[1].id).await.unwrap();
+    assert_eq!(storage.search(&user_uuid, "spring").await.unwrap().len(), 1);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_search_index_maintenance() {
+    use crate::backends::raw::MAX_INDEXED_BYTES;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_index_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    // A file that stops being markdown leaves the index
+    storage.write(&user_uuid, "/journal", b"# Journal\nWeekend hike".to_vec(), Some("text/markdown")).await.unwrap();
+    assert_eq!(storage.search(&user_uuid, "hike").await.unwrap().len(), 1);
+    storage.write(&user_uuid, "/journal", b"# Journal\nWeekend hike".to_vec(), Some("text/plain")).await.unwrap();
+    assert!(storage.search(&user_uuid, "hike").await.unwrap().is_empty());
+    let indexed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM file_search_index i JOIN files f ON f.id = i.file_id WHERE f.user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_one(&*db_pool)
+    .await
+    .unwrap();
+    assert_eq!(indexed, 0);
+    
+    // Only the start of a large note is indexed
+    let mut content = b"# Big\nopening ".to_vec();
+    content.resize(MAX_INDEXED_BYTES + 1024, b' ');
+    content.extend_from_slice(b"closing");
+    storage.write(&user_uuid, "/big.md", content, None).await.unwrap();
+    assert_eq!(storage.search(&user_uuid, "opening").await.unwrap().len(), 1);
+    assert!(storage.search(&user_uuid, "closing").await.unwrap().is_empty());
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_normalize_newlines() {
+    use crate::hash::hash_content;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_newlines_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let crlf = b"# Notes\r\nfirst\r\nsecond\r\n".to_vec();
+    let lf = b"# Notes\nfirst\nsecond\n".to_vec();
+    
+    // Off by default: content is stored as uploaded
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone());
+    storage.write(&user_uuid, "/raw.md", crlf.clone(), None).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/raw.md").await.unwrap(), crlf);
+    
+    // When enabled, markdown is stored and hashed with LF endings
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_normalize_newlines(true);
+    storage.write(&user_uuid, "/notes.md", crlf.clone(), None).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/notes.md").await.unwrap(), lf);
+    let metadata = storage.metadata(&user_uuid, "/notes.md").await.unwrap();
+    assert_eq!(metadata.size, lf.len() as u64);
+    assert_eq!(metadata.content_hash, Some(hash_content(&lf).unwrap()));
+    
+    // Binary content is never touched
+    storage.write(&user_uuid, "/blob.bin", crlf.clone(), Some("application/octet-stream")).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/blob.bin").await.unwrap(), crlf);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_tenant_write_settings() {
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_write_settings_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    sqlx::query("UPDATE users SET default_content_type = 'text/markdown', normalize_newlines = TRUE WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set write settings");
+    
+    let crlf = b"first\r\nsecond\r\n".to_vec();
+    let lf = b"first\nsecond\n".to_vec();
+    
+    // Untyped uploads get the tenant default and are normalized even though the storage default is off
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher.clone());
+    storage.write(&user_uuid, "/notes", crlf.clone(), None).await.unwrap();
+    let metadata = storage.metadata(&user_uuid, "/notes").await.unwrap();
+    assert_eq!(metadata.content_type, "text/markdown");
+    assert_eq!(storage.read(&user_uuid, "/notes").await.unwrap(), lf);
+    
+    // A guessable path still wins over the tenant default
+    storage.write(&user_uuid, "/image.png", vec![0x89, b'P', b'N', b'G'], None).await.unwrap();
+    let metadata = storage.metadata(&user_uuid, "/image.png").await.unwrap();
+    assert_eq!(metadata.content_type, "image/png");
+    
+    // A tenant that opts out keeps CRLF although the storage default is on
+    sqlx::query("UPDATE users SET normalize_newlines = FALSE WHERE id = $1")
+        .bind(user_id)
+        .execute(&*db_pool)
+        .await
+        .expect("Failed to set write settings");
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher)
+        .with_normalize_newlines(true);
+    storage.write(&user_uuid, "/raw.md", crlf.clone(), None).await.unwrap();
+    assert_eq!(storage.read(&user_uuid, "/raw.md").await.unwrap(), crlf);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_tenant_storage_entry_kind() {
+    use crate::api::tenant::EntryKind;
+    use crate::MarbleTenantStorage;
+    
+    let (db_pool, user_id, user_uuid, content_hasher, _temp_dir) = match setup_tenant_user_test("tenant_entry_kind_user").await {
+        Some(setup) => setup,
+        None => return,
+    };
+    
+    let storage = MarbleTenantStorage::new(db_pool.clone(), content_hasher);
+    
+    storage.write(&user_uuid, "/notes/a.md", b"# A".to_vec(), None).await.unwrap();
+    storage.create_directory(&user_uuid, "/empty").await.unwrap();
+    
+    assert_eq!(storage.entry_kind(&user_uuid, "/notes/a.md").await.unwrap(), Some(EntryKind::File));
+    assert_eq!(storage.entry_kind(&user_uuid, "/empty").await.unwrap(), Some(EntryKind::Directory));
+    assert_eq!(storage.entry_kind(&user_uuid, "/notes").await.unwrap(), Some(EntryKind::Directory));
+    assert_eq!(storage.entry_kind(&user_uuid, "/").await.unwrap(), Some(EntryKind::Directory));
+    assert_eq!(storage.entry_kind(&user_uuid, "/missing.md").await.unwrap(), None);
+    
+    // Deleted files are gone
+    storage.delete(&user_uuid, "/notes/a.md").await.unwrap();
+    assert_eq!(storage.entry_kind(&user_uuid, "/notes/a.md").await.unwrap(), None);
+    
+    cleanup_tenant_user_test(&db_pool, user_id).await;
+}
+
+#[tokio::test]
+async fn test_mock_entry_kind_default() {
+    use crate::api::tenant::EntryKind;
+    use crate::mock::MockTenantStorage;
+    
+    let storage = MockTenantStorage::new();
+    let tenant_id = Uuid::new_v4();
+    
+    storage.add_file(&tenant_id, "notes/a.md", b"# A".to_vec());
+    storage.create_directory(&tenant_id, "empty").await.unwrap();
+    
+    assert_eq!(storage.entry_kind(&tenant_id, "notes/a.md").await.unwrap(), Some(EntryKind::File));
+    assert_eq!(storage.entry_kind(&tenant_id, "empty").await.unwrap(), Some(EntryKind::Directory));
+    assert_eq!(storage.entry_kind(&tenant_id, "missing.md").await.unwrap(), None);
+}